@@ -0,0 +1,102 @@
+// pathfinder/c/include/pathfinder.h
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#ifndef PATHFINDER_H
+#define PATHFINDER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define PF_FILL_RULE_EVEN_ODD   0
+#define PF_FILL_RULE_WINDING    1
+
+typedef struct PFCanvas PFCanvas;
+typedef struct PFPath PFPath;
+typedef struct PFFont PFFont;
+
+// Canvases
+
+PFCanvas *pf_canvas_create(uint32_t width, uint32_t height);
+void pf_canvas_destroy(PFCanvas *canvas);
+const uint8_t *pf_canvas_pixels(const PFCanvas *canvas,
+                                uint32_t *out_width,
+                                uint32_t *out_height);
+int pf_canvas_write_png(const PFCanvas *canvas, const char *path);
+
+// State
+
+void pf_canvas_save(PFCanvas *canvas);
+void pf_canvas_restore(PFCanvas *canvas);
+void pf_canvas_set_fill_color(PFCanvas *canvas, uint8_t r, uint8_t g, uint8_t b, uint8_t a);
+void pf_canvas_set_stroke_color(PFCanvas *canvas, uint8_t r, uint8_t g, uint8_t b, uint8_t a);
+void pf_canvas_set_line_width(PFCanvas *canvas, float line_width);
+void pf_canvas_set_global_alpha(PFCanvas *canvas, float global_alpha);
+void pf_canvas_set_font(PFCanvas *canvas, const PFFont *font, float font_size);
+
+// Transforms
+
+void pf_canvas_set_transform(PFCanvas *canvas,
+                             float a,
+                             float b,
+                             float c,
+                             float d,
+                             float e,
+                             float f);
+void pf_canvas_translate(PFCanvas *canvas, float x, float y);
+void pf_canvas_scale(PFCanvas *canvas, float x, float y);
+void pf_canvas_rotate(PFCanvas *canvas, float angle);
+
+// Drawing. These return 0, or -1 if drawing failed partway.
+
+int pf_canvas_fill_rect(PFCanvas *canvas, float x, float y, float width, float height);
+int pf_canvas_stroke_rect(PFCanvas *canvas, float x, float y, float width, float height);
+int pf_canvas_clear_rect(PFCanvas *canvas, float x, float y, float width, float height);
+int pf_canvas_fill_path(PFCanvas *canvas, const PFPath *path, uint8_t fill_rule);
+int pf_canvas_stroke_path(PFCanvas *canvas, const PFPath *path);
+int pf_canvas_fill_text(PFCanvas *canvas, const char *text, size_t length, float x, float y);
+
+// Paths
+
+PFPath *pf_path_create(void);
+void pf_path_destroy(PFPath *path);
+void pf_path_move_to(PFPath *path, float x, float y);
+void pf_path_line_to(PFPath *path, float x, float y);
+void pf_path_quadratic_curve_to(PFPath *path, float ctrl_x, float ctrl_y, float x, float y);
+void pf_path_bezier_curve_to(PFPath *path,
+                             float ctrl0_x,
+                             float ctrl0_y,
+                             float ctrl1_x,
+                             float ctrl1_y,
+                             float x,
+                             float y);
+void pf_path_arc(PFPath *path,
+                 float center_x,
+                 float center_y,
+                 float radius,
+                 float start_angle,
+                 float end_angle,
+                 int anticlockwise);
+void pf_path_rect(PFPath *path, float x, float y, float width, float height);
+void pf_path_close_path(PFPath *path);
+
+// Fonts
+
+PFFont *pf_font_create_from_bytes(const uint8_t *data, size_t length, uint32_t font_index);
+void pf_font_destroy(PFFont *font);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,421 @@
+// pathfinder/c/src/lib.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! C bindings to the canvas API, so that C, C++, and Swift applications can embed Pathfinder.
+//!
+//! The declarations are in `include/pathfinder.h`. Objects are opaque pointers created by
+//! `*_create()` functions and freed by the matching `*_destroy()`, which, like `free()`, accept
+//! null. Unless noted, every other pointer passed in must be valid and non-null, and strings are
+//! UTF-8 with an explicit length.
+//!
+//! Panics never unwind into the caller. If one happens inside a call, such as on a font that
+//! fails to parse or a path that the partitioner can't handle, the call stops and returns null
+//! or -1; functions with nothing to return just return, possibly after drawing part of what was
+//! asked. The objects involved remain valid to use and to destroy.
+//!
+//! Only the software canvas is exposed: there's no GPU renderer in the Rust crates to create or
+//! present with, so the result of drawing is read back through `pf_canvas_pixels()` or written
+//! out with `pf_canvas_write_png()`, and uploading it to the screen is up to the application.
+
+extern crate euclid;
+extern crate font_kit;
+extern crate pathfinder_canvas;
+extern crate pathfinder_partitioner;
+extern crate pathfinder_rasterizer;
+
+use euclid::{Point2D, Rect, Size2D, Transform2D, Vector2D};
+use font_kit::font::Font;
+use pathfinder_canvas::png;
+use pathfinder_canvas::{CanvasRenderingContext2D, Path2D};
+use pathfinder_partitioner::FillRule;
+use pathfinder_rasterizer::paint::Paint;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::str;
+use std::sync::Arc;
+
+pub const PF_FILL_RULE_EVEN_ODD: u8 = 0;
+pub const PF_FILL_RULE_WINDING: u8 = 1;
+
+/// A drawing context and the software canvas it draws into.
+pub type PFCanvas = CanvasRenderingContext2D;
+pub type PFPath = Path2D;
+/// A font and the face of its data that it was loaded from.
+pub type PFFont = (Arc<Font>, u32);
+
+// Runs `body`, returning `on_panic` instead if it panics, so that no panic unwinds into C.
+fn guard<T, F>(on_panic: T, body: F) -> T where F: FnOnce() -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(on_panic)
+}
+
+// Canvases
+
+/// Creates a transparent canvas. Returns null if either dimension is zero.
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_create(width: u32, height: u32) -> *mut PFCanvas {
+    guard(ptr::null_mut(), || {
+        if width == 0 || height == 0 {
+            return ptr::null_mut()
+        }
+        Box::into_raw(Box::new(CanvasRenderingContext2D::new(&Size2D::new(width, height))))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_destroy(canvas: *mut PFCanvas) {
+    guard((), || {
+        if !canvas.is_null() {
+            drop(Box::from_raw(canvas))
+        }
+    })
+}
+
+/// Returns the canvas's premultiplied RGBA pixels, row by row, and stores its size. The pointer
+/// is valid until the canvas is next drawn into or destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_pixels(canvas: *const PFCanvas,
+                                          out_width: *mut u32,
+                                          out_height: *mut u32)
+                                          -> *const u8 {
+    guard(ptr::null(), || {
+        let canvas = (*canvas).canvas();
+        *out_width = canvas.size().width;
+        *out_height = canvas.size().height;
+        canvas.pixels().as_ptr()
+    })
+}
+
+/// Writes the canvas to a PNG file. `path` is a null-terminated UTF-8 string. Returns 0 on
+/// success and -1 on failure.
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_write_png(canvas: *const PFCanvas, path: *const c_char)
+                                             -> c_int {
+    guard(-1, || {
+        let path = match CStr::from_ptr(path).to_str() {
+            Err(_) => return -1,
+            Ok(path) => path,
+        };
+        match png::save_png((*canvas).canvas(), path) {
+            Ok(()) => 0,
+            Err(_) => -1,
+        }
+    })
+}
+
+// State
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_save(canvas: *mut PFCanvas) {
+    guard((), || {
+        (*canvas).save()
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_restore(canvas: *mut PFCanvas) {
+    guard((), || {
+        (*canvas).restore()
+    })
+}
+
+/// Sets the fill style to a color with components from 0 to 255, not premultiplied.
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_set_fill_color(canvas: *mut PFCanvas,
+                                                  r: u8,
+                                                  g: u8,
+                                                  b: u8,
+                                                  a: u8) {
+    guard((), || {
+        (*canvas).set_fill_style(Paint::Color([r, g, b, a]))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_set_stroke_color(canvas: *mut PFCanvas,
+                                                    r: u8,
+                                                    g: u8,
+                                                    b: u8,
+                                                    a: u8) {
+    guard((), || {
+        (*canvas).set_stroke_style(Paint::Color([r, g, b, a]))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_set_line_width(canvas: *mut PFCanvas, line_width: f32) {
+    guard((), || {
+        (*canvas).set_line_width(line_width)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_set_global_alpha(canvas: *mut PFCanvas, global_alpha: f32) {
+    guard((), || {
+        (*canvas).set_global_alpha(global_alpha)
+    })
+}
+
+/// Sets the font for `pf_canvas_fill_text()`, and its size in pixels per em. The canvas keeps
+/// its own reference to the font, so the font may be destroyed afterward.
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_set_font(canvas: *mut PFCanvas,
+                                            font: *const PFFont,
+                                            font_size: f32) {
+    guard((), || {
+        (*canvas).set_font((*font).0.clone(), (*font).1, font_size)
+    })
+}
+
+// Transforms
+
+/// Replaces the current transform with the matrix [a c e; b d f; 0 0 1], as in the HTML canvas.
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_set_transform(canvas: *mut PFCanvas,
+                                                 a: f32,
+                                                 b: f32,
+                                                 c: f32,
+                                                 d: f32,
+                                                 e: f32,
+                                                 f: f32) {
+    guard((), || {
+        (*canvas).set_transform(&Transform2D::row_major(a, b, c, d, e, f))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_translate(canvas: *mut PFCanvas, x: f32, y: f32) {
+    guard((), || {
+        (*canvas).translate(&Vector2D::new(x, y))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_scale(canvas: *mut PFCanvas, x: f32, y: f32) {
+    guard((), || {
+        (*canvas).scale(x, y)
+    })
+}
+
+/// Rotates subsequent drawing clockwise by `angle` radians.
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_rotate(canvas: *mut PFCanvas, angle: f32) {
+    guard((), || {
+        (*canvas).rotate(angle)
+    })
+}
+
+// Drawing
+//
+// These return 0, or -1 if drawing failed partway.
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_fill_rect(canvas: *mut PFCanvas,
+                                             x: f32,
+                                             y: f32,
+                                             width: f32,
+                                             height: f32)
+                                             -> c_int {
+    guard(-1, || {
+        (*canvas).fill_rect(&Rect::new(Point2D::new(x, y), Size2D::new(width, height)));
+        0
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_stroke_rect(canvas: *mut PFCanvas,
+                                               x: f32,
+                                               y: f32,
+                                               width: f32,
+                                               height: f32)
+                                               -> c_int {
+    guard(-1, || {
+        (*canvas).stroke_rect(&Rect::new(Point2D::new(x, y), Size2D::new(width, height)));
+        0
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_clear_rect(canvas: *mut PFCanvas,
+                                              x: f32,
+                                              y: f32,
+                                              width: f32,
+                                              height: f32)
+                                              -> c_int {
+    guard(-1, || {
+        (*canvas).clear_rect(&Rect::new(Point2D::new(x, y), Size2D::new(width, height)));
+        0
+    })
+}
+
+/// Fills `path` with the fill style. `fill_rule` is one of the `PF_FILL_RULE_*` constants.
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_fill_path(canvas: *mut PFCanvas,
+                                             path: *const PFPath,
+                                             fill_rule: u8)
+                                             -> c_int {
+    guard(-1, || {
+        let fill_rule = if fill_rule == PF_FILL_RULE_EVEN_ODD {
+            FillRule::EvenOdd
+        } else {
+            FillRule::Winding
+        };
+        (*canvas).fill_path(&*path, fill_rule);
+        0
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_stroke_path(canvas: *mut PFCanvas, path: *const PFPath)
+                                               -> c_int {
+    guard(-1, || {
+        (*canvas).stroke_path(&*path);
+        0
+    })
+}
+
+/// Fills `length` bytes of UTF-8 text with the fill style, with its baseline starting at
+/// `x`, `y`. Invalid UTF-8 draws nothing and returns -1.
+#[no_mangle]
+pub unsafe extern "C" fn pf_canvas_fill_text(canvas: *mut PFCanvas,
+                                             text: *const c_char,
+                                             length: usize,
+                                             x: f32,
+                                             y: f32)
+                                             -> c_int {
+    guard(-1, || {
+        match str::from_utf8(slice::from_raw_parts(text as *const u8, length)) {
+            Ok(text) => {
+                (*canvas).fill_text(text, &Point2D::new(x, y));
+                0
+            }
+            Err(_) => -1,
+        }
+    })
+}
+
+// Paths
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_path_create() -> *mut PFPath {
+    guard(ptr::null_mut(), || {
+        Box::into_raw(Box::new(Path2D::new()))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_path_destroy(path: *mut PFPath) {
+    guard((), || {
+        if !path.is_null() {
+            drop(Box::from_raw(path))
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_path_move_to(path: *mut PFPath, x: f32, y: f32) {
+    guard((), || {
+        (*path).move_to(&Point2D::new(x, y))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_path_line_to(path: *mut PFPath, x: f32, y: f32) {
+    guard((), || {
+        (*path).line_to(&Point2D::new(x, y))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_path_quadratic_curve_to(path: *mut PFPath,
+                                                    ctrl_x: f32,
+                                                    ctrl_y: f32,
+                                                    x: f32,
+                                                    y: f32) {
+    guard((), || {
+        (*path).quadratic_curve_to(&Point2D::new(ctrl_x, ctrl_y), &Point2D::new(x, y))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_path_bezier_curve_to(path: *mut PFPath,
+                                                 ctrl0_x: f32,
+                                                 ctrl0_y: f32,
+                                                 ctrl1_x: f32,
+                                                 ctrl1_y: f32,
+                                                 x: f32,
+                                                 y: f32) {
+    guard((), || {
+        (*path).bezier_curve_to(&Point2D::new(ctrl0_x, ctrl0_y),
+                                &Point2D::new(ctrl1_x, ctrl1_y),
+                                &Point2D::new(x, y))
+    })
+}
+
+/// Adds a circular arc; angles are in radians, and `anticlockwise` is 0 or 1.
+#[no_mangle]
+pub unsafe extern "C" fn pf_path_arc(path: *mut PFPath,
+                                     center_x: f32,
+                                     center_y: f32,
+                                     radius: f32,
+                                     start_angle: f32,
+                                     end_angle: f32,
+                                     anticlockwise: c_int) {
+    guard((), || {
+        (*path).arc(&Point2D::new(center_x, center_y),
+                    radius,
+                    start_angle,
+                    end_angle,
+                    anticlockwise != 0)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_path_rect(path: *mut PFPath, x: f32, y: f32, width: f32, height: f32) {
+    guard((), || {
+        (*path).rect(&Rect::new(Point2D::new(x, y), Size2D::new(width, height)))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_path_close_path(path: *mut PFPath) {
+    guard((), || {
+        (*path).close_path()
+    })
+}
+
+// Fonts
+
+/// Loads a font from `length` bytes of OpenType, TrueType, or collection data, which are
+/// copied. Returns null if the data can't be loaded.
+#[no_mangle]
+pub unsafe extern "C" fn pf_font_create_from_bytes(data: *const u8,
+                                                   length: usize,
+                                                   font_index: u32)
+                                                   -> *mut PFFont {
+    guard(ptr::null_mut(), || {
+        let data = Arc::new(slice::from_raw_parts(data, length).to_vec());
+        match Font::from_bytes(data, font_index) {
+            Ok(font) => Box::into_raw(Box::new((Arc::new(font), font_index))),
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pf_font_destroy(font: *mut PFFont) {
+    guard((), || {
+        if !font.is_null() {
+            drop(Box::from_raw(font))
+        }
+    })
+}
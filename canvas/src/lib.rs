@@ -0,0 +1,1096 @@
+// pathfinder/canvas/src/lib.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A drawing API modeled on the HTML canvas's `CanvasRenderingContext2D`, for applications that
+//! want to draw vector graphics without dealing with partitioning and meshes directly.
+//!
+//! As in the HTML canvas, points are transformed by the current transform as the path is built,
+//! and the path is filled or stroked with the state current at that time. Each fill is
+//! partitioned and drawn into a software `Canvas` from `pathfinder_rasterizer`.
+//!
+//! The `lottie`, `png`, and `svg` features, all on by default, enable the modules of the same
+//! names and the dependencies they need.
+
+extern crate euclid;
+extern crate font_kit;
+#[cfg(feature = "png")]
+extern crate image;
+extern crate lyon_path;
+extern crate pathfinder_font_utils;
+extern crate pathfinder_partitioner;
+extern crate pathfinder_path_utils;
+extern crate pathfinder_rasterizer;
+#[cfg(feature = "lottie")]
+extern crate serde_json;
+#[cfg(feature = "svg")]
+extern crate xml;
+
+use euclid::{Angle, Point2D, Rect, Size2D, Transform2D, Vector2D};
+use font_kit::font::Font;
+use font_kit::hinting::HintingOptions;
+use lyon_path::PathEvent;
+use lyon_path::builder::FlatPathBuilder;
+use lyon_path::default::Path as LyonPath;
+use lyon_path::iterator::PathIter;
+use pathfinder_font_utils::bitmap::{BitmapGlyph, BitmapGlyphs};
+use pathfinder_font_utils::colr::{ColorGlyphs, LayerColor};
+use pathfinder_font_utils::sfnt::{self, FontData};
+use pathfinder_font_utils::svg::SvgGlyphs;
+use pathfinder_partitioner::FillRule;
+use pathfinder_partitioner::batch;
+use pathfinder_partitioner::hairline::{self, MAX_HAIRLINE_WIDTH};
+use pathfinder_partitioner::mesh::Mesh;
+use pathfinder_partitioner::spans::Span;
+use pathfinder_path_utils::stroke::{LineCap, LineJoin, StrokeStyle, StrokeToFillIter};
+use pathfinder_path_utils::transform::{self, Transform2DPathIter};
+use pathfinder_rasterizer::blend::BlendMode;
+use pathfinder_rasterizer::overlay::{DebugOverlay, DebugOverlayViews};
+use pathfinder_rasterizer::paint::{Gradient, GradientGeometry, Paint, Pattern};
+use pathfinder_rasterizer::patch::CoonsPatch;
+use pathfinder_rasterizer::raster::{Canvas, Color};
+use std::f32;
+use std::mem;
+use std::sync::Arc;
+use std::time::Instant;
+
+use picture::{DrawCommand, Picture};
+use profiling::{FrameProfile, ProfileStage, Profiler};
+
+#[cfg(feature = "svg")]
+use pathfinder_font_utils::svg::SvgGlyphDocument;
+#[cfg(feature = "png")]
+use pathfinder_rasterizer::paint::PatternRepeat;
+#[cfg(feature = "svg")]
+use svg::SvgDocument;
+
+#[cfg(feature = "lottie")]
+pub mod lottie;
+pub mod pdf;
+pub mod picture;
+#[cfg(feature = "png")]
+pub mod png;
+pub mod profiling;
+#[cfg(feature = "svg")]
+pub mod svg;
+
+const BLACK: Color = [0, 0, 0, 255];
+
+/// The tolerance, in canvas pixels, that curves are flattened to when they're drawn as hairlines.
+const HAIRLINE_TOLERANCE: f32 = 0.1;
+
+/// The drawing state that `save()` and `restore()` preserve.
+#[derive(Clone)]
+struct State {
+    transform: Transform2D<f32>,
+    fill_style: Paint,
+    stroke_style: Paint,
+    line_width: f32,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    global_alpha: f32,
+    global_composite_operation: BlendMode,
+    font: Option<Arc<TextFont>>,
+    // The fonts to try, in order, for characters that `font` has no glyph for.
+    fallback_fonts: Vec<Arc<TextFont>>,
+    font_size: f32,
+}
+
+impl State {
+    fn new() -> State {
+        State {
+            transform: Transform2D::identity(),
+            fill_style: Paint::Color(BLACK),
+            stroke_style: Paint::Color(BLACK),
+            line_width: 1.0,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter(10.0),
+            global_alpha: 1.0,
+            global_composite_operation: BlendMode::SourceOver,
+            font: None,
+            fallback_fonts: vec![],
+            font_size: 10.0,
+        }
+    }
+}
+
+// A font for `fill_text()`, with what it takes to draw its color glyphs, which is read once
+// when the font is set instead of on every call.
+struct TextFont {
+    font: Arc<Font>,
+    // The face of the font's data that it was loaded from, since `font-kit` doesn't say.
+    font_index: u32,
+    // The font's data, kept only if it has SVG or bitmap glyphs, which are read from it.
+    data: Option<Arc<Vec<u8>>>,
+    colr: Option<ColorGlyphs>,
+}
+
+impl TextFont {
+    fn new(font: Arc<Font>, font_index: u32) -> TextFont {
+        let data = font.copy_font_data();
+        let (colr, keep_data) = match data.as_ref().and_then(|data| {
+            FontData::new(data, font_index).ok()
+        }) {
+            None => (None, false),
+            Some(font_data) => {
+                let colr = ColorGlyphs::from_font_data(&font_data).ok().and_then(|colr| colr);
+                let keep_data = [sfnt::SVG, sfnt::CBDT, sfnt::SBIX].iter().any(|&tag| {
+                    font_data.table(tag).is_some()
+                });
+                (colr, keep_data)
+            }
+        };
+        TextFont {
+            font: font,
+            font_index: font_index,
+            data: if keep_data { data } else { None },
+            colr: colr,
+        }
+    }
+}
+
+// The tables of a font that describe color versions of its glyphs.
+struct ColorGlyphTables<'a> {
+    #[cfg_attr(not(feature = "svg"), allow(dead_code))]
+    svg: Option<SvgGlyphs<'a>>,
+    colr: Option<&'a ColorGlyphs>,
+    #[cfg_attr(not(feature = "png"), allow(dead_code))]
+    bitmaps: Option<BitmapGlyphs<'a>>,
+}
+
+impl<'a> ColorGlyphTables<'a> {
+    // Reads the SVG and bitmap tables of the font. They only index into its data, so this is
+    // cheap. Tables that are missing or malformed are left out.
+    fn new(text_font: &'a TextFont) -> ColorGlyphTables<'a> {
+        let font_data = text_font.data.as_ref().and_then(|data| {
+            FontData::new(data, text_font.font_index).ok()
+        });
+        ColorGlyphTables {
+            svg: font_data.as_ref().and_then(|font_data| {
+                SvgGlyphs::from_font_data(font_data).ok().and_then(|svg_glyphs| svg_glyphs)
+            }),
+            colr: text_font.colr.as_ref(),
+            bitmaps: font_data.as_ref().and_then(|font_data| {
+                BitmapGlyphs::from_font_data(font_data).ok().and_then(|bitmaps| bitmaps)
+            }),
+        }
+    }
+
+    // `ppem` is the size in canvas pixels per em that bitmap strikes are chosen for.
+    fn has_color_glyph(&self, glyph_id: u32, ppem: f32) -> bool {
+        let has_svg_glyph = cfg!(feature = "svg") && self.svg.as_ref().map_or(false, |svg_glyphs| {
+            svg_glyphs.has_document(glyph_id)
+        });
+        let has_bitmap = cfg!(feature = "png") && self.bitmap(glyph_id, ppem).is_some();
+        has_svg_glyph || has_bitmap || self.colr.as_ref().map_or(false, |color_glyphs| {
+            color_glyphs.glyph_layers(glyph_id).is_some()
+        })
+    }
+
+    fn bitmap(&self, glyph_id: u32, ppem: f32) -> Option<BitmapGlyph<'a>> {
+        self.bitmaps.as_ref().and_then(|bitmaps| {
+            bitmaps.glyph(glyph_id, ppem).ok().and_then(|bitmap| bitmap)
+        })
+    }
+}
+
+/// A path under construction.
+#[derive(Clone, Debug, Default)]
+pub struct Path2D {
+    events: Vec<PathEvent>,
+    current_point: Option<Point2D<f32>>,
+    subpath_start: Option<Point2D<f32>>,
+}
+
+impl Path2D {
+    #[inline]
+    pub fn new() -> Path2D {
+        Path2D::default()
+    }
+
+    #[inline]
+    pub fn events(&self) -> &[PathEvent] {
+        &self.events
+    }
+
+    pub fn move_to(&mut self, to: &Point2D<f32>) {
+        self.events.push(PathEvent::MoveTo(*to));
+        self.current_point = Some(*to);
+        self.subpath_start = Some(*to);
+    }
+
+    /// Adds a line from the current point. If there's no current point, this starts a subpath
+    /// instead.
+    pub fn line_to(&mut self, to: &Point2D<f32>) {
+        if self.ensure_subpath(to) {
+            self.events.push(PathEvent::LineTo(*to));
+            self.current_point = Some(*to);
+        }
+    }
+
+    pub fn quadratic_curve_to(&mut self, ctrl: &Point2D<f32>, to: &Point2D<f32>) {
+        self.ensure_subpath(ctrl);
+        self.events.push(PathEvent::QuadraticTo(*ctrl, *to));
+        self.current_point = Some(*to);
+    }
+
+    pub fn bezier_curve_to(&mut self,
+                           ctrl0: &Point2D<f32>,
+                           ctrl1: &Point2D<f32>,
+                           to: &Point2D<f32>) {
+        self.ensure_subpath(ctrl0);
+        self.events.push(PathEvent::CubicTo(*ctrl0, *ctrl1, *to));
+        self.current_point = Some(*to);
+    }
+
+    /// Adds a circular arc around `center` from `start_angle` to `end_angle`, in radians, with a
+    /// line to its start from the current point.
+    pub fn arc(&mut self,
+               center: &Point2D<f32>,
+               radius: f32,
+               start_angle: f32,
+               end_angle: f32,
+               anticlockwise: bool) {
+        push_arc(self,
+                 center,
+                 radius,
+                 start_angle,
+                 end_angle,
+                 anticlockwise,
+                 &Transform2D::identity())
+    }
+
+    pub fn rect(&mut self, rect: &Rect<f32>) {
+        push_rect(self, rect, &Transform2D::identity())
+    }
+
+    pub fn close_path(&mut self) {
+        if let Some(subpath_start) = self.subpath_start {
+            self.events.push(PathEvent::Close);
+            self.current_point = Some(subpath_start);
+        }
+    }
+
+    // Starts a subpath at `point` if there isn't one. Returns true if there already was one.
+    fn ensure_subpath(&mut self, point: &Point2D<f32>) -> bool {
+        if self.current_point.is_some() {
+            return true
+        }
+        self.move_to(point);
+        false
+    }
+}
+
+/// A 2D drawing context that draws into a software canvas.
+pub struct CanvasRenderingContext2D {
+    canvas: Canvas,
+    state: State,
+    saved_states: Vec<State>,
+    // The current path, in canvas pixels.
+    path: Path2D,
+    // The pictures being recorded, innermost last.
+    recordings: Vec<Vec<DrawCommand>>,
+    debug_overlay: Option<DebugOverlay>,
+    // The profile of the current frame, if one is being profiled.
+    profiler: Option<Profiler>,
+    hairline_strokes: bool,
+}
+
+impl CanvasRenderingContext2D {
+    /// Creates a context that draws into a transparent canvas of the given size.
+    #[inline]
+    pub fn new(size: &Size2D<u32>) -> CanvasRenderingContext2D {
+        CanvasRenderingContext2D::from_canvas(Canvas::new(size))
+    }
+
+    pub fn from_canvas(canvas: Canvas) -> CanvasRenderingContext2D {
+        CanvasRenderingContext2D {
+            canvas: canvas,
+            state: State::new(),
+            saved_states: vec![],
+            path: Path2D::new(),
+            recordings: vec![],
+            debug_overlay: None,
+            profiler: None,
+            hairline_strokes: false,
+        }
+    }
+
+    #[inline]
+    pub fn canvas(&self) -> &Canvas {
+        &self.canvas
+    }
+
+    #[inline]
+    pub fn canvas_mut(&mut self) -> &mut Canvas {
+        &mut self.canvas
+    }
+
+    #[inline]
+    pub fn into_canvas(self) -> Canvas {
+        self.canvas
+    }
+
+    // State
+
+    /// Pushes the drawing state: the transform, styles, line settings, alpha, composite
+    /// operation, and font. The current path isn't part of the state.
+    #[inline]
+    pub fn save(&mut self) {
+        self.saved_states.push(self.state.clone())
+    }
+
+    /// Pops the drawing state saved by the last `save()`, if any.
+    #[inline]
+    pub fn restore(&mut self) {
+        if let Some(state) = self.saved_states.pop() {
+            self.state = state
+        }
+    }
+
+    /// Sets the paint for fills. Gradients and patterns are in the coordinate space of the
+    /// transform that's current when something is filled.
+    #[inline]
+    pub fn set_fill_style(&mut self, paint: Paint) {
+        self.state.fill_style = paint
+    }
+
+    #[inline]
+    pub fn set_stroke_style(&mut self, paint: Paint) {
+        self.state.stroke_style = paint
+    }
+
+    #[inline]
+    pub fn set_line_width(&mut self, line_width: f32) {
+        if line_width > 0.0 && line_width.is_finite() {
+            self.state.line_width = line_width
+        }
+    }
+
+    /// If true, strokes that are at most `MAX_HAIRLINE_WIDTH` pixels wide on the canvas are drawn
+    /// as hairlines, which stay visible and don't flicker as they move, instead of being
+    /// converted to fills. This is off by default.
+    ///
+    /// Hairlines have no caps or joins. Strokes drawn while recording a picture are always
+    /// converted to fills.
+    #[inline]
+    pub fn set_hairline_strokes(&mut self, hairline_strokes: bool) {
+        self.hairline_strokes = hairline_strokes
+    }
+
+    #[inline]
+    pub fn hairline_strokes(&self) -> bool {
+        self.hairline_strokes
+    }
+
+    #[inline]
+    pub fn set_line_cap(&mut self, line_cap: LineCap) {
+        self.state.line_cap = line_cap
+    }
+
+    #[inline]
+    pub fn set_line_join(&mut self, line_join: LineJoin) {
+        self.state.line_join = line_join
+    }
+
+    /// Sets the opacity that everything is drawn with, from 0 to 1.
+    #[inline]
+    pub fn set_global_alpha(&mut self, global_alpha: f32) {
+        if global_alpha >= 0.0 && global_alpha <= 1.0 {
+            self.state.global_alpha = global_alpha
+        }
+    }
+
+    #[inline]
+    pub fn set_global_composite_operation(&mut self, blend_mode: BlendMode) {
+        self.state.global_composite_operation = blend_mode
+    }
+
+    /// Sets the font for `fill_text()`, and its size in pixels per em. `font_index` is the face
+    /// of the font's data that it was loaded from, which color glyph tables are read from.
+    #[inline]
+    pub fn set_font(&mut self, font: Arc<Font>, font_index: u32, font_size: f32) {
+        self.state.font = Some(Arc::new(TextFont::new(font, font_index)));
+        self.state.font_size = font_size;
+    }
+
+    /// Sets the fonts that `fill_text()` takes glyphs from, in order, for characters the font
+    /// doesn't cover, each with the face of its data that it was loaded from.
+    pub fn set_fallback_fonts(&mut self, fallback_fonts: Vec<(Arc<Font>, u32)>) {
+        self.state.fallback_fonts = fallback_fonts.into_iter().map(|(font, font_index)| {
+            Arc::new(TextFont::new(font, font_index))
+        }).collect()
+    }
+
+    // Transforms
+
+    #[inline]
+    pub fn current_transform(&self) -> Transform2D<f32> {
+        self.state.transform
+    }
+
+    #[inline]
+    pub fn set_transform(&mut self, transform: &Transform2D<f32>) {
+        self.state.transform = *transform
+    }
+
+    /// Applies `transform` to subsequent drawing, before the current transform.
+    #[inline]
+    pub fn transform(&mut self, transform: &Transform2D<f32>) {
+        self.state.transform = self.state.transform.pre_mul(transform)
+    }
+
+    #[inline]
+    pub fn translate(&mut self, vector: &Vector2D<f32>) {
+        self.state.transform = self.state.transform.pre_translate(*vector)
+    }
+
+    #[inline]
+    pub fn scale(&mut self, x: f32, y: f32) {
+        self.state.transform = self.state.transform.pre_scale(x, y)
+    }
+
+    /// Rotates subsequent drawing clockwise by `angle` radians.
+    #[inline]
+    pub fn rotate(&mut self, angle: f32) {
+        self.state.transform = self.state.transform.pre_rotate(&Angle::radians(angle))
+    }
+
+    // Paths
+
+    #[inline]
+    pub fn begin_path(&mut self) {
+        self.path = Path2D::new()
+    }
+
+    #[inline]
+    pub fn move_to(&mut self, to: &Point2D<f32>) {
+        self.path.move_to(&self.state.transform.transform_point(to))
+    }
+
+    #[inline]
+    pub fn line_to(&mut self, to: &Point2D<f32>) {
+        self.path.line_to(&self.state.transform.transform_point(to))
+    }
+
+    #[inline]
+    pub fn quadratic_curve_to(&mut self, ctrl: &Point2D<f32>, to: &Point2D<f32>) {
+        let transform = &self.state.transform;
+        self.path.quadratic_curve_to(&transform.transform_point(ctrl),
+                                     &transform.transform_point(to))
+    }
+
+    #[inline]
+    pub fn bezier_curve_to(&mut self,
+                           ctrl0: &Point2D<f32>,
+                           ctrl1: &Point2D<f32>,
+                           to: &Point2D<f32>) {
+        let transform = &self.state.transform;
+        self.path.bezier_curve_to(&transform.transform_point(ctrl0),
+                                  &transform.transform_point(ctrl1),
+                                  &transform.transform_point(to))
+    }
+
+    #[inline]
+    pub fn arc(&mut self,
+               center: &Point2D<f32>,
+               radius: f32,
+               start_angle: f32,
+               end_angle: f32,
+               anticlockwise: bool) {
+        push_arc(&mut self.path,
+                 center,
+                 radius,
+                 start_angle,
+                 end_angle,
+                 anticlockwise,
+                 &self.state.transform)
+    }
+
+    #[inline]
+    pub fn rect(&mut self, rect: &Rect<f32>) {
+        push_rect(&mut self.path, rect, &self.state.transform)
+    }
+
+    #[inline]
+    pub fn close_path(&mut self) {
+        self.path.close_path()
+    }
+
+    // Takes the current path, in canvas pixels, leaving an empty one.
+    #[inline]
+    pub(crate) fn take_path(&mut self) -> Path2D {
+        mem::replace(&mut self.path, Path2D::new())
+    }
+
+    #[inline]
+    pub(crate) fn set_path(&mut self, path: Path2D) {
+        self.path = path
+    }
+
+    // Drawing
+
+    /// Fills the current path with the fill style.
+    pub fn fill(&mut self, fill_rule: FillRule) {
+        let events = self.path.events().to_vec();
+        let paint = self.state.fill_style.clone();
+        self.draw(&events, fill_rule, &paint)
+    }
+
+    /// Fills `path`, transformed by the current transform, with the fill style.
+    pub fn fill_path(&mut self, path: &Path2D, fill_rule: FillRule) {
+        let events: Vec<_> = Transform2DPathIter::new(path.events().iter().cloned(),
+                                                      &self.state.transform).collect();
+        let paint = self.state.fill_style.clone();
+        self.draw(&events, fill_rule, &paint)
+    }
+
+    /// Strokes the current path with the stroke style. The line width is scaled by the current
+    /// transform, as the path is.
+    pub fn stroke(&mut self) {
+        let transform = self.state.transform;
+        let device_line_width = self.state.line_width * transform::max_scale_factor(&transform);
+        if self.hairline_strokes && device_line_width <= MAX_HAIRLINE_WIDTH &&
+                self.recordings.is_empty() {
+            let spans = hairline::hairline_spans(self.path.events().iter().cloned(),
+                                                 device_line_width,
+                                                 HAIRLINE_TOLERANCE);
+            let paint = transform_paint(&self.state.stroke_style, &transform);
+            let (blend_mode, alpha) = (self.state.global_composite_operation,
+                                       self.state.global_alpha);
+            self.rasterize_spans(&spans, &paint, blend_mode, alpha);
+            return
+        }
+
+        let inverse_transform = match transform.inverse() {
+            None => return,
+            Some(inverse_transform) => inverse_transform,
+        };
+
+        // Stroke in user space, so that the transform applies to the pen as well.
+        let user_path = Transform2DPathIter::new(self.path.events().iter().cloned(),
+                                                 &inverse_transform);
+        let stroke_style = StrokeStyle {
+            width: self.state.line_width,
+            cap: self.state.line_cap,
+            join: self.state.line_join,
+        };
+        let stroke = StrokeToFillIter::new(PathIter::new(user_path), stroke_style);
+        let events: Vec<_> = Transform2DPathIter::new(stroke, &transform).collect();
+
+        let paint = self.state.stroke_style.clone();
+        self.draw(&events, FillRule::Winding, &paint)
+    }
+
+    /// Strokes `path`, transformed by the current transform, with the stroke style.
+    pub fn stroke_path(&mut self, path: &Path2D) {
+        let mut device_path = Path2D::new();
+        device_path.events = Transform2DPathIter::new(path.events().iter().cloned(),
+                                                      &self.state.transform).collect();
+        let saved_path = mem::replace(&mut self.path, device_path);
+        self.stroke();
+        self.path = saved_path;
+    }
+
+    pub fn fill_rect(&mut self, rect: &Rect<f32>) {
+        let mut path = Path2D::new();
+        push_rect(&mut path, rect, &self.state.transform);
+        let paint = self.state.fill_style.clone();
+        self.draw(path.events(), FillRule::Winding, &paint)
+    }
+
+    pub fn stroke_rect(&mut self, rect: &Rect<f32>) {
+        let mut path = Path2D::new();
+        push_rect(&mut path, rect, &self.state.transform);
+        let saved_path = mem::replace(&mut self.path, path);
+        self.stroke();
+        self.path = saved_path;
+    }
+
+    /// Erases `rect` to transparent black.
+    pub fn clear_rect(&mut self, rect: &Rect<f32>) {
+        let mut path = Path2D::new();
+        push_rect(&mut path, rect, &self.state.transform);
+        self.draw_with_blend_mode(path.events(),
+                                  FillRule::Winding,
+                                  &Paint::Color([0; 4]),
+                                  BlendMode::Clear,
+                                  1.0)
+    }
+
+    /// Fills a Coons patch, transformed by the current transform, with its own colors. This is
+    /// how gradient meshes are drawn.
+    ///
+    /// The patch is rasterized at the canvas's resolution, so a picture recorded with it doesn't
+    /// stay sharp when it's replayed at a larger scale.
+    pub fn fill_coons_patch(&mut self, patch: &CoonsPatch) {
+        let patch = patch.transform(&self.state.transform);
+        let size = self.canvas.size();
+        let canvas_rect = Rect::new(Point2D::zero(), Size2D::new(size.width as i32,
+                                                                 size.height as i32));
+        let pattern = match patch.to_pattern(&canvas_rect) {
+            None => return,
+            Some(pattern) => pattern,
+        };
+        let (blend_mode, alpha) = (self.state.global_composite_operation,
+                                   self.state.global_alpha);
+        self.submit(&patch.outline(), FillRule::Winding, Paint::Pattern(pattern), blend_mode, alpha)
+    }
+
+    /// Fills `text` with the fill style, in the current font, starting with its baseline at
+    /// `origin`. Characters the font has no glyph for are skipped.
+    ///
+    /// Glyphs that the font draws in color are drawn with their own colors instead, from
+    /// documents in the `SVG ` table if the `svg` feature is on; from layers in the `COLR`
+    /// table, in its first palette, with layers in the foreground color using the fill style; or,
+    /// if the `png` feature is on, from the `CBDT` or `sbix` strike nearest the size on the
+    /// canvas.
+    pub fn fill_text(&mut self, text: &str, origin: &Point2D<f32>) {
+        let font = match self.state.font {
+            None => return,
+            Some(ref font) => font.clone(),
+        };
+        let mut text_fonts = vec![font];
+        text_fonts.extend(self.state.fallback_fonts.iter().cloned());
+        let fonts: Vec<_> = text_fonts.iter().map(|text_font| text_font.font.clone()).collect();
+        let color_glyph_tables: Vec<_> = text_fonts.iter().map(|text_font| {
+            ColorGlyphTables::new(text_font)
+        }).collect();
+
+        let ppem = self.state.font_size * transform::max_scale_factor(&self.state.transform);
+
+        let mut events = vec![];
+        let mut pen = *origin;
+        for character in text.chars() {
+            // Glyph 0 is `.notdef`, which fonts map characters they don't support to. If no font
+            // has the character, draw the first font's `.notdef`.
+            let glyph = fonts.iter().enumerate().filter_map(|(font_index, font)| {
+                match font.glyph_for_char(character) {
+                    Some(glyph_id) if glyph_id != 0 => Some((font_index, glyph_id)),
+                    _ => None,
+                }
+            }).next().or_else(|| {
+                fonts[0].glyph_for_char(character).map(|glyph_id| (0, glyph_id))
+            });
+            let (font_index, glyph_id) = match glyph {
+                None => continue,
+                Some(glyph) => glyph,
+            };
+            let font = &fonts[font_index];
+            let scale = self.state.font_size / font.metrics().units_per_em as f32;
+
+            // Color glyphs are drawn right away, so draw the glyphs before them first.
+            let tables = &color_glyph_tables[font_index];
+            let mut drawn_in_color = false;
+            if tables.has_color_glyph(glyph_id, ppem) {
+                let paint = self.state.fill_style.clone();
+                self.draw(&events, FillRule::Winding, &paint);
+                events.clear();
+                drawn_in_color = self.fill_color_glyph(font, tables, glyph_id, &pen, scale, ppem);
+            }
+
+            if !drawn_in_color {
+                // Glyph outlines are Y up; the canvas is Y down.
+                let glyph_transform = Transform2D::create_scale(scale, -scale)
+                    .post_translate(pen.to_vector())
+                    .post_mul(&self.state.transform);
+                let mut path_builder = LyonPath::builder();
+                if font.outline(glyph_id, HintingOptions::None, &mut path_builder).is_ok() {
+                    events.extend(Transform2DPathIter::new(path_builder.build().into_iter(),
+                                                           &glyph_transform));
+                }
+            }
+
+            if let Ok(advance) = font.advance(glyph_id) {
+                pen.x += advance.x * scale
+            }
+        }
+
+        let paint = self.state.fill_style.clone();
+        self.draw(&events, FillRule::Winding, &paint)
+    }
+
+    // Draws the color version of a glyph with its origin at `pen`, in user space, and
+    // `scale` user units per font unit. `ppem` is the size on the canvas, which bitmap strikes
+    // are chosen for. Returns false if it couldn't be drawn, in which case its outline should be
+    // drawn instead.
+    #[cfg_attr(not(feature = "png"), allow(unused_variables))]
+    fn fill_color_glyph(&mut self,
+                        font: &Font,
+                        tables: &ColorGlyphTables,
+                        glyph_id: u32,
+                        pen: &Point2D<f32>,
+                        scale: f32,
+                        ppem: f32)
+                        -> bool {
+        #[cfg(feature = "svg")]
+        {
+            let document = tables.svg.as_ref().and_then(|svg_glyphs| {
+                svg_glyphs.document(glyph_id).ok().and_then(|document| document)
+            });
+            if let Some(document) = document {
+                if let Ok(svg_document) = SvgDocument::from_bytes(&document.data) {
+                    // SVG glyphs are in font units with Y down, with the origin on the baseline.
+                    self.save();
+                    self.transform(&Transform2D::create_scale(scale, scale)
+                                                .post_translate(pen.to_vector()));
+                    let drawn = svg_document.draw_element_with_id(
+                        self,
+                        &SvgGlyphDocument::element_id(glyph_id));
+                    self.restore();
+                    if drawn {
+                        return true
+                    }
+                }
+            }
+        }
+
+        let layers = tables.colr.as_ref().and_then(|color_glyphs| {
+            color_glyphs.layer_outlines(font, glyph_id, 0).ok().and_then(|layers| layers)
+        });
+        if let Some(layers) = layers {
+            // Layer outlines are Y up, like any other glyph's.
+            let glyph_transform = Transform2D::create_scale(scale, -scale)
+                .post_translate(pen.to_vector())
+                .post_mul(&self.state.transform);
+            for layer in layers {
+                let paint = match layer.color {
+                    LayerColor::Palette(color) => {
+                        Paint::Color([color.r, color.g, color.b, color.a])
+                    }
+                    LayerColor::Foreground => self.state.fill_style.clone(),
+                };
+                let events: Vec<_> = Transform2DPathIter::new(layer.outline.into_iter(),
+                                                              &glyph_transform).collect();
+                self.draw(&events, FillRule::Winding, &paint)
+            }
+            return true
+        }
+
+        #[cfg(feature = "png")]
+        {
+            let bitmap = tables.bitmap(glyph_id, ppem);
+            if let Some(bitmap) = bitmap {
+                if let Ok(image) = png::read_png(bitmap.data) {
+                    // Bitmap origins are in strike pixels with Y up.
+                    let strike_scale = bitmap.scale(self.state.font_size);
+                    let origin = *pen + Vector2D::new(bitmap.origin.x as f32,
+                                                      -bitmap.origin.y as f32) * strike_scale;
+                    let size = image.size().to_f32() * strike_scale;
+                    let pattern = Pattern {
+                        image: Arc::new(image),
+                        transform: Transform2D::create_scale(strike_scale, strike_scale)
+                                               .post_translate(origin.to_vector()),
+                        repeat: PatternRepeat::NoRepeat,
+                    };
+                    let mut path = Path2D::new();
+                    push_rect(&mut path, &Rect::new(origin, size), &self.state.transform);
+                    self.draw(path.events(), FillRule::Winding, &Paint::Pattern(pattern));
+                    return true
+                }
+            }
+        }
+
+        false
+    }
+
+    // Pictures
+
+    /// Starts recording a picture. Until the matching `end_recording()`, drawing commands are
+    /// recorded instead of drawn, with the state current when they're issued.
+    ///
+    /// Recordings nest: drawing goes to the innermost one.
+    #[inline]
+    pub fn begin_recording(&mut self) {
+        self.recordings.push(vec![])
+    }
+
+    /// Finishes the innermost recording and returns it, or `None` if nothing is being recorded.
+    #[inline]
+    pub fn end_recording(&mut self) -> Option<Picture> {
+        self.recordings.pop().map(|commands| Picture { commands: commands })
+    }
+
+    /// Draws `picture` under the current transform. Its commands keep the composite operations
+    /// they were recorded with, and their alpha is multiplied by the global alpha.
+    pub fn draw_picture(&mut self, picture: &Picture) {
+        let transform = self.state.transform;
+        let translation = picture::pixel_translation(&transform);
+        for command in &picture.commands {
+            let paint = transform_paint(&command.paint, &transform);
+            let alpha = command.alpha * self.state.global_alpha;
+            match translation {
+                Some(ref translation) if self.recordings.is_empty() => {
+                    // The recorded mesh can be reused as is.
+                    self.rasterize(&command.mesh, translation, &paint, command.blend_mode, alpha)
+                }
+                _ => {
+                    let events: Vec<_> = Transform2DPathIter::new(command.path.iter().cloned(),
+                                                                  &transform).collect();
+                    self.submit(&events, command.fill_rule, paint, command.blend_mode, alpha)
+                }
+            }
+        }
+    }
+
+    // Debugging
+
+    /// Turns the debug overlay on with the given views, or off with `None`.
+    ///
+    /// While it's on, the B-quads of every mesh drawn are collected, and `draw_debug_overlay()`
+    /// draws them over the frame.
+    pub fn set_debug_overlay(&mut self, views: Option<DebugOverlayViews>) {
+        // Keep the B-quads collected so far if the overlay is only switching views.
+        if let (Some(views), Some(debug_overlay)) = (views, self.debug_overlay.as_mut()) {
+            debug_overlay.set_views(views);
+            return
+        }
+        self.debug_overlay = views.map(DebugOverlay::new)
+    }
+
+    #[inline]
+    pub fn debug_overlay(&self) -> Option<&DebugOverlay> {
+        self.debug_overlay.as_ref()
+    }
+
+    /// Draws the debug overlay for everything drawn since the last call, if it's on. Call this
+    /// once the frame is finished and any layers have been closed.
+    pub fn draw_debug_overlay(&mut self) {
+        if let Some(ref mut debug_overlay) = self.debug_overlay {
+            debug_overlay.draw(&mut self.canvas);
+            debug_overlay.clear();
+        }
+    }
+
+    // Profiling
+
+    /// Starts profiling a frame. If a frame is already being profiled, its profile is dropped.
+    #[inline]
+    pub fn begin_frame(&mut self) {
+        self.profiler = Some(Profiler::new())
+    }
+
+    /// Finishes the frame started with `begin_frame()` and returns how long each stage took, or
+    /// `None` if no frame is being profiled.
+    #[inline]
+    pub fn end_frame(&mut self) -> Option<FrameProfile> {
+        self.profiler.take().map(Profiler::finish)
+    }
+
+    fn draw(&mut self, events: &[PathEvent], fill_rule: FillRule, paint: &Paint) {
+        let (blend_mode, alpha) = (self.state.global_composite_operation, self.state.global_alpha);
+        self.draw_with_blend_mode(events, fill_rule, paint, blend_mode, alpha)
+    }
+
+    fn draw_with_blend_mode(&mut self,
+                            events: &[PathEvent],
+                            fill_rule: FillRule,
+                            paint: &Paint,
+                            blend_mode: BlendMode,
+                            alpha: f32) {
+        let paint = transform_paint(paint, &self.state.transform);
+        self.submit(events, fill_rule, paint, blend_mode, alpha)
+    }
+
+    // Partitions a path in canvas pixels and either records or draws it.
+    fn submit(&mut self,
+              events: &[PathEvent],
+              fill_rule: FillRule,
+              paint: Paint,
+              blend_mode: BlendMode,
+              alpha: f32) {
+        if events.is_empty() {
+            return
+        }
+
+        let start = self.stage_start();
+        let mesh = batch::partition_path(events, fill_rule, None);
+        self.end_stage(ProfileStage::Partition, start);
+        if let Some(recording) = self.recordings.last_mut() {
+            recording.push(DrawCommand {
+                path: events.to_vec(),
+                fill_rule: fill_rule,
+                mesh: mesh,
+                paint: paint,
+                blend_mode: blend_mode,
+                alpha: alpha,
+            });
+            return
+        }
+        self.rasterize(&mesh, &Vector2D::zero(), &paint, blend_mode, alpha)
+    }
+
+    fn rasterize(&mut self,
+                 mesh: &Mesh,
+                 offset: &Vector2D<i32>,
+                 paint: &Paint,
+                 blend_mode: BlendMode,
+                 alpha: f32) {
+        if let Some(ref mut debug_overlay) = self.debug_overlay {
+            debug_overlay.push_mesh(mesh, offset);
+        }
+        self.composite_fill(blend_mode, alpha, |canvas| canvas.fill_mesh(mesh, offset, paint))
+    }
+
+    fn rasterize_spans(&mut self,
+                       spans: &[Span],
+                       paint: &Paint,
+                       blend_mode: BlendMode,
+                       alpha: f32) {
+        self.composite_fill(blend_mode, alpha, |canvas| {
+            canvas.fill_spans(spans, &Vector2D::zero(), paint)
+        })
+    }
+
+    // Calls `fill` to draw into the canvas with its coverage multiplied by `alpha`.
+    fn composite_fill<F>(&mut self, blend_mode: BlendMode, alpha: f32, fill: F)
+                         where F: FnOnce(&mut Canvas) {
+        self.canvas.set_blend_mode(blend_mode);
+        self.canvas.set_fill_opacity(alpha);
+
+        let start = self.stage_start();
+        fill(&mut self.canvas);
+        self.end_stage(ProfileStage::Fill, start);
+
+        self.canvas.set_fill_opacity(1.0);
+    }
+
+    // Returns the time a stage is starting at, if a frame is being profiled.
+    #[inline]
+    fn stage_start(&self) -> Option<Instant> {
+        self.profiler.as_ref().map(|_| Instant::now())
+    }
+
+    #[inline]
+    fn end_stage(&mut self, stage: ProfileStage, start: Option<Instant>) {
+        if let (Some(profiler), Some(start)) = (self.profiler.as_mut(), start) {
+            profiler.record(stage, start)
+        }
+    }
+}
+
+// Moves a paint from user space into canvas pixels.
+fn transform_paint(paint: &Paint, transform: &Transform2D<f32>) -> Paint {
+    match *paint {
+        Paint::Color(color) => Paint::Color(color),
+        Paint::Gradient(ref gradient) => {
+            let geometry = match gradient.geometry {
+                GradientGeometry::Linear { from, to } => {
+                    GradientGeometry::Linear {
+                        from: transform.transform_point(&from),
+                        to: transform.transform_point(&to),
+                    }
+                }
+                GradientGeometry::Radial { center, radius } => {
+                    GradientGeometry::Radial {
+                        center: transform.transform_point(&center),
+                        radius: radius * transform::max_scale_factor(transform),
+                    }
+                }
+                GradientGeometry::Conic { center, angle, inverse_transform } => {
+                    // Leave the sweep in gradient space and map pixels back into it instead.
+                    // A singular transform squashes the paint to nothing anyway.
+                    let inverse_transform = match transform.inverse() {
+                        Some(inverse) => inverse.post_mul(&inverse_transform),
+                        None => inverse_transform,
+                    };
+                    GradientGeometry::Conic {
+                        center: center,
+                        angle: angle,
+                        inverse_transform: inverse_transform,
+                    }
+                }
+            };
+            Paint::Gradient(Gradient {
+                geometry: geometry,
+                ..gradient.clone()
+            })
+        }
+        Paint::Pattern(ref pattern) => {
+            Paint::Pattern(Pattern {
+                transform: pattern.transform.post_mul(transform),
+                ..pattern.clone()
+            })
+        }
+    }
+}
+
+fn push_rect(path: &mut Path2D, rect: &Rect<f32>, transform: &Transform2D<f32>) {
+    path.move_to(&transform.transform_point(&rect.origin));
+    path.line_to(&transform.transform_point(&rect.top_right()));
+    path.line_to(&transform.transform_point(&rect.bottom_right()));
+    path.line_to(&transform.transform_point(&rect.bottom_left()));
+    path.close_path();
+}
+
+// Appends an arc as cubic Béziers of at most a quarter turn each, which stay Béziers under
+// `transform`.
+fn push_arc(path: &mut Path2D,
+            center: &Point2D<f32>,
+            radius: f32,
+            start_angle: f32,
+            end_angle: f32,
+            anticlockwise: bool,
+            transform: &Transform2D<f32>) {
+    let full_turn = 2.0 * f32::consts::PI;
+    let modulo = |angle: f32| angle - full_turn * (angle / full_turn).floor();
+    let sweep = if !anticlockwise {
+        let sweep = end_angle - start_angle;
+        if sweep >= full_turn { full_turn } else { modulo(sweep) }
+    } else {
+        let sweep = start_angle - end_angle;
+        -(if sweep >= full_turn { full_turn } else { modulo(sweep) })
+    };
+
+    let point_at = |angle: f32| *center + Vector2D::new(angle.cos(), angle.sin()) * radius;
+    path.line_to(&transform.transform_point(&point_at(start_angle)));
+
+    let segment_count = ((sweep.abs() / (f32::consts::PI * 0.5)).ceil() as usize).max(1);
+    let segment_sweep = sweep / segment_count as f32;
+    let handle_length = 4.0 / 3.0 * (segment_sweep * 0.25).tan() * radius;
+    for segment_index in 0..segment_count {
+        let angle0 = start_angle + segment_sweep * segment_index as f32;
+        let angle1 = angle0 + segment_sweep;
+        let (from, to) = (point_at(angle0), point_at(angle1));
+        let ctrl0 = from + Vector2D::new(-angle0.sin(), angle0.cos()) * handle_length;
+        let ctrl1 = to - Vector2D::new(-angle1.sin(), angle1.cos()) * handle_length;
+        path.bezier_curve_to(&transform.transform_point(&ctrl0),
+                             &transform.transform_point(&ctrl1),
+                             &transform.transform_point(&to))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::{Point2D, Rect, Size2D};
+    use pathfinder_rasterizer::paint::Paint;
+
+    use super::CanvasRenderingContext2D;
+
+    #[test]
+    fn global_alpha_fades_each_fill_without_a_layer() {
+        let mut context = CanvasRenderingContext2D::new(&Size2D::new(4, 1));
+        context.set_fill_style(Paint::Color([255, 0, 0, 255]));
+        context.set_global_alpha(0.5);
+        context.fill_rect(&Rect::new(Point2D::new(0.0, 0.0), Size2D::new(3.0, 1.0)));
+        context.fill_rect(&Rect::new(Point2D::new(1.0, 0.0), Size2D::new(3.0, 1.0)));
+        assert_eq!(context.canvas().layer_count(), 0);
+
+        // Pixels under one fill are half covered, those under both are three-quarters covered,
+        // and the fill opacity is reset afterward.
+        let pixels = context.canvas().pixels();
+        let expected = [[128, 0, 0, 128], [191, 0, 0, 191], [191, 0, 0, 191], [128, 0, 0, 128]];
+        for (pixel, expected) in pixels.chunks(4).zip(expected.iter()) {
+            for (&channel, &expected_channel) in pixel.iter().zip(expected.iter()) {
+                assert!((channel as i32 - expected_channel).abs() <= 1);
+            }
+        }
+        assert_eq!(context.canvas().fill_opacity(), 1.0);
+    }
+}
@@ -0,0 +1,608 @@
+// pathfinder/canvas/src/lottie.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Plays Lottie (Bodymovin) animations.
+//!
+//! An animation is parsed once from its JSON. Each frame, its keyframed properties are
+//! evaluated at that time, and the resulting paths are drawn into a canvas context, which
+//! partitions them again; that's cheap enough to do every frame.
+//!
+//! This covers shape layers and the null layers used to parent them: groups, paths, rectangles,
+//! ellipses, fills, strokes, and transforms, with linear, eased, and hold keyframes. Other layer
+//! types, masks, mattes, and effects are ignored. Opacity is applied to each fill and stroke
+//! separately, rather than to groups as a whole.
+
+use euclid::{Angle, Point2D, Rect, Size2D, Transform2D, Vector2D};
+use pathfinder_partitioner::FillRule;
+use pathfinder_path_utils::stroke::{LineCap, LineJoin};
+use pathfinder_rasterizer::paint::Paint;
+use serde_json::{self, Map, Value};
+use std::f32;
+
+use CanvasRenderingContext2D;
+
+/// The number of Newton iterations used to invert easing curves.
+const EASING_ITERATIONS: u32 = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LottieError {
+    /// The data couldn't be parsed as JSON.
+    InvalidJson,
+    /// The JSON doesn't describe an animation; it lacks a size, frame rate, or frame range.
+    NotAnAnimation,
+}
+
+/// A parsed Lottie animation.
+#[derive(Clone, Debug)]
+pub struct Animation {
+    size: Size2D<f32>,
+    frame_rate: f32,
+    in_point: f32,
+    out_point: f32,
+    // In the order they appear in the file, topmost first.
+    layers: Vec<Layer>,
+}
+
+impl Animation {
+    pub fn from_json(json: &[u8]) -> Result<Animation, LottieError> {
+        let root: Value = match serde_json::from_slice(json) {
+            Ok(root) => root,
+            Err(_) => return Err(LottieError::InvalidJson),
+        };
+        let root = match root.as_object() {
+            None => return Err(LottieError::NotAnAnimation),
+            Some(root) => root,
+        };
+
+        let number_field = |name: &str| {
+            root.get(name).and_then(number).ok_or(LottieError::NotAnAnimation)
+        };
+        let size = Size2D::new(try!(number_field("w")), try!(number_field("h")));
+        let layers = root.get("layers").and_then(Value::as_array).map(|layers| {
+            layers.iter().filter_map(Value::as_object).filter_map(Layer::parse).collect()
+        }).unwrap_or(vec![]);
+
+        Ok(Animation {
+            size: size,
+            frame_rate: try!(number_field("fr")),
+            in_point: try!(number_field("ip")),
+            out_point: try!(number_field("op")),
+            layers: layers,
+        })
+    }
+
+    /// Returns the size of the animation's canvas, in pixels.
+    #[inline]
+    pub fn size(&self) -> Size2D<f32> {
+        self.size
+    }
+
+    /// Returns the number of frames per second.
+    #[inline]
+    pub fn frame_rate(&self) -> f32 {
+        self.frame_rate
+    }
+
+    /// Returns the first frame and the frame just past the last.
+    #[inline]
+    pub fn frame_range(&self) -> (f32, f32) {
+        (self.in_point, self.out_point)
+    }
+
+    /// Returns the length of the animation, in seconds.
+    #[inline]
+    pub fn duration(&self) -> f32 {
+        (self.out_point - self.in_point) / self.frame_rate
+    }
+
+    /// Draws the animation as it is at `frame`, which may be fractional, under the context's
+    /// current transform.
+    pub fn draw_frame(&self, context: &mut CanvasRenderingContext2D, frame: f32) {
+        for layer in self.layers.iter().rev() {
+            if layer.shapes.is_empty() || frame < layer.in_point || frame >= layer.out_point {
+                continue
+            }
+
+            context.save();
+            context.transform(&self.layer_transform(layer, frame, 0));
+            let opacity = layer.transform.opacity(frame - layer.start_time);
+            draw_shapes(context, &layer.shapes, frame - layer.start_time, opacity);
+            context.restore();
+        }
+    }
+
+    // Returns the transform of `layer`, including those of its parents.
+    fn layer_transform(&self, layer: &Layer, frame: f32, depth: usize) -> Transform2D<f32> {
+        let transform = layer.transform.matrix(frame - layer.start_time);
+        let parent = layer.parent.and_then(|parent| {
+            self.layers.iter().find(|layer| layer.index == Some(parent))
+        });
+        match parent {
+            // Guard against cycles.
+            Some(parent) if depth < self.layers.len() => {
+                transform.post_mul(&self.layer_transform(parent, frame, depth + 1))
+            }
+            _ => transform,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Layer {
+    index: Option<i64>,
+    parent: Option<i64>,
+    in_point: f32,
+    out_point: f32,
+    start_time: f32,
+    transform: TransformProperties,
+    shapes: Vec<Shape>,
+}
+
+impl Layer {
+    fn parse(layer: &Map<String, Value>) -> Option<Layer> {
+        const NULL_LAYER: i64 = 3;
+        const SHAPE_LAYER: i64 = 4;
+
+        let layer_type = layer.get("ty").and_then(Value::as_i64);
+        let shapes = match layer_type {
+            Some(NULL_LAYER) => vec![],
+            Some(SHAPE_LAYER) => parse_shapes(layer.get("shapes")),
+            _ => return None,
+        };
+        let transform = layer.get("ks").and_then(Value::as_object).map(TransformProperties::parse);
+        Some(Layer {
+            index: layer.get("ind").and_then(Value::as_i64),
+            parent: layer.get("parent").and_then(Value::as_i64),
+            in_point: layer.get("ip").and_then(number).unwrap_or(f32::NEG_INFINITY),
+            out_point: layer.get("op").and_then(number).unwrap_or(f32::INFINITY),
+            start_time: layer.get("st").and_then(number).unwrap_or(0.0),
+            transform: transform.unwrap_or(TransformProperties::default()),
+            shapes: shapes,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Shape {
+    Group(Vec<Shape>),
+    Path(Property<Bezier>),
+    Rect { position: Property<Vec<f32>>, size: Property<Vec<f32>> },
+    Ellipse { position: Property<Vec<f32>>, size: Property<Vec<f32>> },
+    Fill { color: Property<Vec<f32>>, opacity: Property<Vec<f32>>, fill_rule: FillRule },
+    Stroke {
+        color: Property<Vec<f32>>,
+        opacity: Property<Vec<f32>>,
+        width: Property<Vec<f32>>,
+        cap: LineCap,
+        join: LineJoin,
+    },
+    Transform(TransformProperties),
+}
+
+fn parse_shapes(shapes: Option<&Value>) -> Vec<Shape> {
+    let shapes = match shapes.and_then(Value::as_array) {
+        None => return vec![],
+        Some(shapes) => shapes,
+    };
+    shapes.iter().filter_map(Value::as_object).filter_map(|shape| {
+        let property = |name: &str| Property::parse(shape.get(name));
+        let shape = match shape.get("ty").and_then(Value::as_str) {
+            Some("gr") => Shape::Group(parse_shapes(shape.get("it"))),
+            Some("sh") => Shape::Path(Property::parse(shape.get("ks"))),
+            Some("rc") => Shape::Rect { position: property("p"), size: property("s") },
+            Some("el") => Shape::Ellipse { position: property("p"), size: property("s") },
+            Some("fl") => {
+                Shape::Fill {
+                    color: property("c"),
+                    opacity: property("o"),
+                    fill_rule: match shape.get("r").and_then(Value::as_i64) {
+                        Some(2) => FillRule::EvenOdd,
+                        _ => FillRule::Winding,
+                    },
+                }
+            }
+            Some("st") => {
+                let miter_limit = shape.get("ml").and_then(number).unwrap_or(4.0);
+                Shape::Stroke {
+                    color: property("c"),
+                    opacity: property("o"),
+                    width: property("w"),
+                    cap: match shape.get("lc").and_then(Value::as_i64) {
+                        Some(2) => LineCap::Round,
+                        Some(3) => LineCap::Square,
+                        _ => LineCap::Butt,
+                    },
+                    join: match shape.get("lj").and_then(Value::as_i64) {
+                        Some(2) => LineJoin::Round,
+                        Some(3) => LineJoin::Bevel,
+                        _ => LineJoin::Miter(miter_limit),
+                    },
+                }
+            }
+            Some("tr") => Shape::Transform(TransformProperties::parse(shape)),
+            _ => return None,
+        };
+        Some(shape)
+    }).collect()
+}
+
+// Draws the shapes of a group. As in After Effects, each fill and stroke paints all the
+// geometry in its group, and items earlier in the list are on top.
+fn draw_shapes(context: &mut CanvasRenderingContext2D, shapes: &[Shape], frame: f32, opacity: f32) {
+    context.save();
+
+    let mut opacity = opacity;
+    if let Some(&Shape::Transform(ref transform)) = shapes.iter().find(|shape| {
+        match **shape { Shape::Transform(_) => true, _ => false }
+    }) {
+        context.transform(&transform.matrix(frame));
+        opacity *= transform.opacity(frame);
+    }
+
+    // Build the group's geometry under its transform.
+    context.begin_path();
+    for shape in shapes {
+        match *shape {
+            Shape::Path(ref bezier) => bezier.value(frame).add_to(context),
+            Shape::Rect { ref position, ref size } => {
+                let (center, size) = (point(&position.value(frame)), point(&size.value(frame)));
+                context.rect(&Rect::new(center - size.to_vector() * 0.5,
+                                        Size2D::new(size.x, size.y)));
+            }
+            Shape::Ellipse { ref position, ref size } => {
+                let (center, size) = (point(&position.value(frame)), point(&size.value(frame)));
+                context.save();
+                context.translate(&center.to_vector());
+                context.scale(size.x * 0.5, size.y * 0.5);
+                context.move_to(&Point2D::new(1.0, 0.0));
+                context.arc(&Point2D::zero(), 1.0, 0.0, 2.0 * f32::consts::PI, false);
+                context.close_path();
+                context.restore();
+            }
+            _ => {}
+        }
+    }
+
+    for shape in shapes.iter().rev() {
+        match *shape {
+            Shape::Group(ref shapes) => {
+                // Nested groups draw their own geometry, so set this group's path aside.
+                let path = context.take_path();
+                draw_shapes(context, shapes, frame, opacity);
+                context.set_path(path);
+            }
+            Shape::Fill { ref color, opacity: ref fill_opacity, fill_rule } => {
+                context.set_fill_style(Paint::Color(color_value(&color.value(frame))));
+                context.set_global_alpha(opacity * scalar(&fill_opacity.value(frame)) / 100.0);
+                context.fill(fill_rule);
+            }
+            Shape::Stroke { ref color, opacity: ref stroke_opacity, ref width, cap, join } => {
+                context.set_stroke_style(Paint::Color(color_value(&color.value(frame))));
+                context.set_global_alpha(opacity * scalar(&stroke_opacity.value(frame)) / 100.0);
+                context.set_line_width(scalar(&width.value(frame)));
+                context.set_line_cap(cap);
+                context.set_line_join(join);
+                context.stroke();
+            }
+            _ => {}
+        }
+    }
+
+    context.restore();
+}
+
+#[derive(Clone, Debug)]
+struct TransformProperties {
+    anchor: Property<Vec<f32>>,
+    position: Property<Vec<f32>>,
+    scale: Property<Vec<f32>>,
+    rotation: Property<Vec<f32>>,
+    opacity: Property<Vec<f32>>,
+}
+
+impl Default for TransformProperties {
+    fn default() -> TransformProperties {
+        TransformProperties::parse(&Map::new())
+    }
+}
+
+impl TransformProperties {
+    fn parse(transform: &Map<String, Value>) -> TransformProperties {
+        let property = |name: &str, default: Vec<f32>| {
+            match transform.get(name) {
+                None => Property::Static(default),
+                value => Property::parse(value),
+            }
+        };
+        TransformProperties {
+            anchor: property("a", vec![0.0, 0.0]),
+            position: property("p", vec![0.0, 0.0]),
+            scale: property("s", vec![100.0, 100.0]),
+            rotation: property("r", vec![0.0]),
+            opacity: property("o", vec![100.0]),
+        }
+    }
+
+    fn matrix(&self, frame: f32) -> Transform2D<f32> {
+        let (anchor, position) = (point(&self.anchor.value(frame)),
+                                  point(&self.position.value(frame)));
+        let scale = point(&self.scale.value(frame));
+        let rotation = scalar(&self.rotation.value(frame));
+        Transform2D::create_translation(-anchor.x, -anchor.y)
+            .post_scale(scale.x / 100.0, scale.y / 100.0)
+            .post_rotate(&Angle::degrees(rotation))
+            .post_translate(position.to_vector())
+    }
+
+    // Returns the opacity from 0 to 1.
+    fn opacity(&self, frame: f32) -> f32 {
+        scalar(&self.opacity.value(frame)) / 100.0
+    }
+}
+
+// A value that may be animated.
+#[derive(Clone, Debug)]
+enum Property<T> {
+    Static(T),
+    Animated(Vec<Keyframe<T>>),
+}
+
+#[derive(Clone, Debug)]
+struct Keyframe<T> {
+    frame: f32,
+    start: T,
+    end: Option<T>,
+    hold: bool,
+    // The control points of the easing curve toward the next keyframe.
+    ease_out: Point2D<f32>,
+    ease_in: Point2D<f32>,
+}
+
+impl<T> Property<T> where T: Interpolate {
+    fn parse(property: Option<&Value>) -> Property<T> {
+        let value = property.and_then(Value::as_object).and_then(|property| property.get("k"));
+        let value = match value {
+            None => return Property::Static(T::default_value()),
+            Some(value) => value,
+        };
+
+        // Animated properties are arrays of keyframe objects.
+        let keyframes = value.as_array().filter(|keyframes| {
+            keyframes.first().and_then(Value::as_object).map_or(false, |keyframe| {
+                keyframe.contains_key("t")
+            })
+        });
+        let keyframes = match keyframes {
+            None => return Property::Static(T::parse(value).unwrap_or(T::default_value())),
+            Some(keyframes) => keyframes,
+        };
+
+        let ease_point = |keyframe: &Map<String, Value>, name: &str, default: Point2D<f32>| {
+            keyframe.get(name).and_then(Value::as_object).map_or(default, |ease| {
+                let coordinate = |name: &str| {
+                    ease.get(name).and_then(|value| {
+                        value.as_array().and_then(|values| values.first()).or(Some(value))
+                    }).and_then(number)
+                };
+                Point2D::new(coordinate("x").unwrap_or(default.x),
+                             coordinate("y").unwrap_or(default.y))
+            })
+        };
+        let keyframes = keyframes.iter().filter_map(Value::as_object).filter_map(|keyframe| {
+            let frame = match keyframe.get("t").and_then(number) {
+                None => return None,
+                Some(frame) => frame,
+            };
+            // Values are wrapped in arrays, even when they're arrays themselves.
+            let value = |name: &str| {
+                keyframe.get(name).and_then(|value| {
+                    match *value {
+                        Value::Array(ref values) if T::wrapped() => values.first(),
+                        _ => Some(value),
+                    }
+                }).and_then(T::parse)
+            };
+            Some(Keyframe {
+                frame: frame,
+                start: value("s").unwrap_or(T::default_value()),
+                end: value("e"),
+                hold: keyframe.get("h").and_then(Value::as_i64) == Some(1),
+                ease_out: ease_point(keyframe, "o", Point2D::new(0.0, 0.0)),
+                ease_in: ease_point(keyframe, "i", Point2D::new(1.0, 1.0)),
+            })
+        }).collect();
+        Property::Animated(keyframes)
+    }
+
+    fn value(&self, frame: f32) -> T {
+        let keyframes = match *self {
+            Property::Static(ref value) => return value.clone(),
+            Property::Animated(ref keyframes) => keyframes,
+        };
+
+        let next_index = keyframes.iter().position(|keyframe| keyframe.frame > frame);
+        match next_index {
+            None => {
+                match keyframes.last() {
+                    None => T::default_value(),
+                    Some(keyframe) => keyframe.end.clone().unwrap_or(keyframe.start.clone()),
+                }
+            }
+            Some(0) => keyframes[0].start.clone(),
+            Some(next_index) => {
+                let (keyframe, next) = (&keyframes[next_index - 1], &keyframes[next_index]);
+                if keyframe.hold {
+                    return keyframe.start.clone()
+                }
+                let end = keyframe.end.as_ref().unwrap_or(&next.start);
+                let t = (frame - keyframe.frame) / (next.frame - keyframe.frame);
+                let t = ease(&keyframe.ease_out, &keyframe.ease_in, t);
+                keyframe.start.interpolate(end, t)
+            }
+        }
+    }
+}
+
+// A type of animated property value.
+trait Interpolate: Clone {
+    fn parse(value: &Value) -> Option<Self>;
+    fn default_value() -> Self;
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+    // Whether keyframe values are wrapped in a one-element array.
+    fn wrapped() -> bool;
+}
+
+impl Interpolate for Vec<f32> {
+    fn parse(value: &Value) -> Option<Vec<f32>> {
+        match *value {
+            Value::Array(ref values) => Some(values.iter().filter_map(number).collect()),
+            _ => number(value).map(|value| vec![value]),
+        }
+    }
+
+    fn default_value() -> Vec<f32> {
+        vec![]
+    }
+
+    fn interpolate(&self, other: &Vec<f32>, t: f32) -> Vec<f32> {
+        self.iter().zip(other.iter()).map(|(a, b)| a + (b - a) * t).collect()
+    }
+
+    fn wrapped() -> bool {
+        false
+    }
+}
+
+// A path as Lottie stores it: vertices with in and out tangents relative to them.
+#[derive(Clone, Debug, Default)]
+struct Bezier {
+    closed: bool,
+    vertices: Vec<Point2D<f32>>,
+    in_tangents: Vec<Vector2D<f32>>,
+    out_tangents: Vec<Vector2D<f32>>,
+}
+
+impl Bezier {
+    fn add_to(&self, context: &mut CanvasRenderingContext2D) {
+        let count = self.vertices.len();
+        if count == 0 || self.in_tangents.len() != count || self.out_tangents.len() != count {
+            return
+        }
+
+        context.move_to(&self.vertices[0]);
+        let segment_count = if self.closed { count } else { count - 1 };
+        for index in 0..segment_count {
+            let next_index = (index + 1) % count;
+            context.bezier_curve_to(&(self.vertices[index] + self.out_tangents[index]),
+                                    &(self.vertices[next_index] + self.in_tangents[next_index]),
+                                    &self.vertices[next_index]);
+        }
+        if self.closed {
+            context.close_path()
+        }
+    }
+}
+
+impl Interpolate for Bezier {
+    fn parse(value: &Value) -> Option<Bezier> {
+        let bezier = match value.as_object() {
+            None => return None,
+            Some(bezier) => bezier,
+        };
+        let points = |name: &str| -> Vec<Point2D<f32>> {
+            bezier.get(name).and_then(Value::as_array).map(|points| {
+                points.iter().filter_map(|value| Vec::<f32>::parse(value)).map(|coordinates| {
+                    point(&coordinates)
+                }).collect()
+            }).unwrap_or(vec![])
+        };
+        let vectors = |name: &str| -> Vec<Vector2D<f32>> {
+            points(name).into_iter().map(|point| point.to_vector()).collect()
+        };
+        Some(Bezier {
+            closed: bezier.get("c").and_then(Value::as_bool).unwrap_or(false),
+            vertices: points("v"),
+            in_tangents: vectors("i"),
+            out_tangents: vectors("o"),
+        })
+    }
+
+    fn default_value() -> Bezier {
+        Bezier::default()
+    }
+
+    fn interpolate(&self, other: &Bezier, t: f32) -> Bezier {
+        if self.vertices.len() != other.vertices.len() {
+            return if t < 1.0 { self.clone() } else { other.clone() }
+        }
+        let lerp_vectors = |a: &[Vector2D<f32>], b: &[Vector2D<f32>]| -> Vec<Vector2D<f32>> {
+            a.iter().zip(b.iter()).map(|(a, b)| a.lerp(*b, t)).collect()
+        };
+        Bezier {
+            closed: self.closed,
+            vertices: self.vertices
+                          .iter()
+                          .zip(other.vertices.iter())
+                          .map(|(a, b)| a.lerp(*b, t))
+                          .collect(),
+            in_tangents: lerp_vectors(&self.in_tangents, &other.in_tangents),
+            out_tangents: lerp_vectors(&self.out_tangents, &other.out_tangents),
+        }
+    }
+
+    fn wrapped() -> bool {
+        true
+    }
+}
+
+// Maps linear progress `t` through the easing curve from (0, 0) to (1, 1) with the given control
+// points, as CSS `cubic-bezier()` does.
+fn ease(ctrl0: &Point2D<f32>, ctrl1: &Point2D<f32>, t: f32) -> f32 {
+    let bezier = |a: f32, b: f32, t: f32| {
+        let u = 1.0 - t;
+        3.0 * u * u * t * a + 3.0 * u * t * t * b + t * t * t
+    };
+    let derivative = |a: f32, b: f32, t: f32| {
+        let u = 1.0 - t;
+        3.0 * u * u * a + 6.0 * u * t * (b - a) + 3.0 * t * t * (1.0 - b)
+    };
+
+    // Solve for the curve parameter at which X is `t`.
+    let mut parameter = t;
+    for _ in 0..EASING_ITERATIONS {
+        let slope = derivative(ctrl0.x, ctrl1.x, parameter);
+        if slope.abs() < 1e-6 {
+            break
+        }
+        parameter = (parameter - (bezier(ctrl0.x, ctrl1.x, parameter) - t) / slope).max(0.0)
+                                                                                   .min(1.0);
+    }
+    bezier(ctrl0.y, ctrl1.y, parameter)
+}
+
+fn number(value: &Value) -> Option<f32> {
+    value.as_f64().map(|value| value as f32)
+}
+
+fn scalar(values: &[f32]) -> f32 {
+    values.first().cloned().unwrap_or(0.0)
+}
+
+fn point(values: &[f32]) -> Point2D<f32> {
+    Point2D::new(scalar(values), values.get(1).cloned().unwrap_or(0.0))
+}
+
+// Converts a Lottie color, with components from 0 to 1, to a canvas color.
+fn color_value(values: &[f32]) -> [u8; 4] {
+    let channel = |index: usize, default: f32| {
+        (values.get(index).cloned().unwrap_or(default).max(0.0).min(1.0) * 255.0).round() as u8
+    };
+    [channel(0, 0.0), channel(1, 0.0), channel(2, 0.0), channel(3, 1.0)]
+}
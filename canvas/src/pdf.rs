@@ -0,0 +1,516 @@
+// pathfinder/canvas/src/pdf.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Writes pictures as vector PDF documents, so that what's printed matches what's drawn on
+//! screen.
+//!
+//! A picture is written as a single page, one point per canvas pixel, from the same fills that
+//! the software canvas rasterizes. Paths become PDF paths, solid colors and opacity become fill
+//! colors and graphics states, gradients become shadings, and image patterns become image
+//! XObjects drawn through a clip. Conic gradients, which PDF can't describe, become images.
+//!
+//! Some features don't survive the trip:
+//!
+//! * No fonts are embedded, subset or otherwise. Pictures record text as the glyph outlines that
+//!   `fill_text()` fills, so it's written as paths: it looks identical, but it can't be selected,
+//!   searched, or copied, and it takes more space than an embedded subset would.
+//!
+//! * PDF shadings are opaque and only pad, so the alpha of gradient stops is ignored and
+//!   repeating and reflecting gradients are padded.
+//!
+//! * Of the Porter-Duff operators, only source-over exists in PDF. The others are drawn as
+//!   source-over, except for clears, which are skipped, since a page can't be erased.
+
+use euclid::{Point2D, Rect, Size2D, Transform2D, Vector2D};
+use lyon_path::PathEvent;
+use pathfinder_partitioner::FillRule;
+use pathfinder_path_utils::arcs::ArcToQuadraticTransformer;
+use pathfinder_rasterizer::blend::BlendMode;
+use pathfinder_rasterizer::paint::{Gradient, GradientGeometry, Image, Paint, Pattern};
+use pathfinder_rasterizer::paint::PatternRepeat;
+use pathfinder_rasterizer::raster;
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use picture::{DrawCommand, Picture};
+
+/// The tolerance, in points, that arcs are approximated to.
+const ARC_TOLERANCE: f32 = 0.1;
+
+/// The most copies of an image that a repeating pattern is tiled with in one fill.
+const MAX_PATTERN_TILES: i64 = 4096;
+
+// Objects with fixed numbers. Everything else is numbered after these as it's written.
+const CATALOG_OBJECT: usize = 1;
+const PAGES_OBJECT: usize = 2;
+const PAGE_OBJECT: usize = 3;
+const CONTENTS_OBJECT: usize = 4;
+
+/// Writes `picture` as a one-page PDF document of `page_size`, in canvas pixels.
+///
+/// Text is written as outlines, not as text in an embedded font.
+pub fn write_pdf<W>(picture: &Picture, page_size: &Size2D<f32>, mut writer: W) -> io::Result<()>
+                    where W: Write {
+    let mut document = Document::new();
+
+    // Flip the page so that Y points down, as it does on the canvas.
+    let mut contents = String::new();
+    drop(writeln!(contents, "1 0 0 -1 0 {} cm", page_size.height));
+    for command in &picture.commands {
+        document.write_command(&mut contents, command)
+    }
+    document.set_object(CONTENTS_OBJECT, stream_object("", contents.as_bytes()));
+
+    document.set_object(CATALOG_OBJECT,
+                        format!("<< /Type /Catalog /Pages {} 0 R >>", PAGES_OBJECT).into_bytes());
+    document.set_object(PAGES_OBJECT,
+                        format!("<< /Type /Pages /Kids [{} 0 R] /Count 1 >>",
+                                PAGE_OBJECT).into_bytes());
+    let page = format!("<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] \
+                        /Resources << /ExtGState << {} >> /Shading << {} >> \
+                        /XObject << {} >> >> /Contents {} 0 R >>",
+                       PAGES_OBJECT,
+                       page_size.width,
+                       page_size.height,
+                       resource_dictionary(&document.graphics_states),
+                       resource_dictionary(&document.shadings),
+                       resource_dictionary(&document.images),
+                       CONTENTS_OBJECT);
+    document.set_object(PAGE_OBJECT, page.into_bytes());
+
+    try!(document.write_to(&mut writer));
+    writer.flush()
+}
+
+/// Writes `picture` to a PDF file at `path`.
+pub fn save_pdf<P>(picture: &Picture, page_size: &Size2D<f32>, path: P) -> io::Result<()>
+                   where P: AsRef<Path> {
+    let file = try!(File::create(path));
+    write_pdf(picture, page_size, BufWriter::new(file))
+}
+
+// A resource that the page refers to by name.
+struct Resource<K> {
+    key: K,
+    name: String,
+    object: usize,
+}
+
+struct Document {
+    objects: Vec<Vec<u8>>,
+    graphics_states: Vec<Resource<(u8, &'static str)>>,
+    shadings: Vec<Resource<()>>,
+    // Images are keyed by address, so that an image shared by many patterns is written once.
+    images: Vec<Resource<*const Image>>,
+}
+
+impl Document {
+    fn new() -> Document {
+        Document {
+            objects: vec![vec![]; CONTENTS_OBJECT],
+            graphics_states: vec![],
+            shadings: vec![],
+            images: vec![],
+        }
+    }
+
+    fn add_object(&mut self, object: Vec<u8>) -> usize {
+        self.objects.push(object);
+        self.objects.len()
+    }
+
+    #[inline]
+    fn set_object(&mut self, number: usize, object: Vec<u8>) {
+        self.objects[number - 1] = object
+    }
+
+    fn write_command(&mut self, contents: &mut String, command: &DrawCommand) {
+        let blend_mode = match pdf_blend_mode(command.blend_mode) {
+            None => return,
+            Some(blend_mode) => blend_mode,
+        };
+        let mut alpha = command.alpha;
+        if let Paint::Color(color) = command.paint {
+            alpha *= color[3] as f32 / 255.0
+        }
+        let alpha = (alpha.max(0.0).min(1.0) * 255.0).round() as u8;
+        if alpha == 0 {
+            return
+        }
+
+        let graphics_state = self.graphics_state((alpha, blend_mode));
+        contents.push_str("q\n");
+        drop(writeln!(contents, "/{} gs", graphics_state));
+        if let Paint::Color(color) = command.paint {
+            drop(writeln!(contents,
+                          "{} {} {} rg",
+                          color[0] as f32 / 255.0,
+                          color[1] as f32 / 255.0,
+                          color[2] as f32 / 255.0));
+        }
+        write_path(contents, &command.path);
+
+        let (fill, clip) = match command.fill_rule {
+            FillRule::Winding => ("f", "W n"),
+            FillRule::EvenOdd => ("f*", "W* n"),
+        };
+        match command.paint {
+            Paint::Color(_) => {
+                contents.push_str(fill);
+                contents.push('\n');
+            }
+            Paint::Gradient(ref gradient) => {
+                contents.push_str(clip);
+                contents.push('\n');
+                if let GradientGeometry::Conic { .. } = gradient.geometry {
+                    // PDF has no conic shading, so draw it as an image.
+                    let bounds = path_bounds(&command.path).round_out().to_i32();
+                    let pattern = Pattern {
+                        image: Arc::new(gradient.to_image(&bounds)),
+                        transform: Transform2D::create_translation(bounds.origin.x as f32,
+                                                                   bounds.origin.y as f32),
+                        repeat: PatternRepeat::NoRepeat,
+                    };
+                    self.write_pattern(contents, &pattern, &bounds.to_f32());
+                } else if let Some(shading) = self.shading(gradient) {
+                    drop(writeln!(contents, "/{} sh", shading));
+                }
+            }
+            Paint::Pattern(ref pattern) => {
+                contents.push_str(clip);
+                contents.push('\n');
+                self.write_pattern(contents, pattern, &path_bounds(&command.path));
+            }
+        }
+        contents.push_str("Q\n");
+    }
+
+    fn graphics_state(&mut self, key: (u8, &'static str)) -> String {
+        if let Some(resource) = self.graphics_states.iter().find(|resource| resource.key == key) {
+            return resource.name.clone()
+        }
+        let alpha = key.0 as f32 / 255.0;
+        let object = self.add_object(format!("<< /Type /ExtGState /ca {} /CA {} /BM /{} >>",
+                                             alpha,
+                                             alpha,
+                                             key.1).into_bytes());
+        let name = format!("GS{}", self.graphics_states.len());
+        self.graphics_states.push(Resource {
+            key: key,
+            name: name.clone(),
+            object: object,
+        });
+        name
+    }
+
+    fn shading(&mut self, gradient: &Gradient) -> Option<String> {
+        let function = match stitching_function(gradient) {
+            None => return None,
+            Some(function) => function,
+        };
+        let (shading_type, coords) = match gradient.geometry {
+            GradientGeometry::Linear { from, to } => {
+                (2, format!("{} {} {} {}", from.x, from.y, to.x, to.y))
+            }
+            GradientGeometry::Radial { center, radius } => {
+                (3, format!("{} {} 0 {} {} {}", center.x, center.y, center.x, center.y, radius))
+            }
+            GradientGeometry::Conic { .. } => return None,
+        };
+        let object = self.add_object(format!("<< /ShadingType {} /ColorSpace /DeviceRGB \
+                                              /Coords [{}] /Function {} \
+                                              /Extend [true true] >>",
+                                             shading_type,
+                                             coords,
+                                             function).into_bytes());
+        let name = format!("Sh{}", self.shadings.len());
+        self.shadings.push(Resource {
+            key: (),
+            name: name.clone(),
+            object: object,
+        });
+        Some(name)
+    }
+
+    // Draws the image once per tile that the fill's bounds touch, in the clip already set up.
+    fn write_pattern(&mut self, contents: &mut String, pattern: &Pattern, bounds: &Rect<f32>) {
+        let inverse_transform = match pattern.transform.inverse() {
+            None => return,
+            Some(inverse_transform) => inverse_transform,
+        };
+        let image_size = pattern.image.size();
+        if image_size.width == 0 || image_size.height == 0 {
+            return
+        }
+        let image = self.image(&pattern.image);
+
+        // Find the tiles in image space.
+        let corners = [
+            bounds.origin,
+            bounds.top_right(),
+            bounds.bottom_left(),
+            bounds.bottom_right(),
+        ];
+        let image_corners: Vec<_> = corners.iter().map(|corner| {
+            inverse_transform.transform_point(corner)
+        }).collect();
+        let image_bounds = Rect::from_points(&image_corners);
+        let tile_range = |min: f32, max: f32, length: u32, repeat: bool| {
+            if !repeat {
+                return (0, 1)
+            }
+            ((min / length as f32).floor() as i64, (max / length as f32).ceil() as i64)
+        };
+        let (repeat_x, repeat_y) = match pattern.repeat {
+            PatternRepeat::Repeat => (true, true),
+            PatternRepeat::RepeatX => (true, false),
+            PatternRepeat::RepeatY => (false, true),
+            PatternRepeat::NoRepeat => (false, false),
+        };
+        let tiles_x = tile_range(image_bounds.origin.x,
+                                 image_bounds.max_x(),
+                                 image_size.width,
+                                 repeat_x);
+        let tiles_y = tile_range(image_bounds.origin.y,
+                                 image_bounds.max_y(),
+                                 image_size.height,
+                                 repeat_y);
+        if (tiles_x.1 - tiles_x.0).saturating_mul(tiles_y.1 - tiles_y.0) > MAX_PATTERN_TILES {
+            return
+        }
+
+        let transform = &pattern.transform;
+        drop(writeln!(contents,
+                      "{} {} {} {} {} {} cm",
+                      transform.m11,
+                      transform.m12,
+                      transform.m21,
+                      transform.m22,
+                      transform.m31,
+                      transform.m32));
+        for tile_y in tiles_y.0..tiles_y.1 {
+            for tile_x in tiles_x.0..tiles_x.1 {
+                // Image space has its first row at the top of the unit square.
+                drop(writeln!(contents,
+                              "q {} 0 0 {} {} {} cm /{} Do Q",
+                              image_size.width,
+                              -(image_size.height as f32),
+                              tile_x * image_size.width as i64,
+                              (tile_y + 1) * image_size.height as i64,
+                              image));
+            }
+        }
+    }
+
+    fn image(&mut self, image: &Arc<Image>) -> String {
+        let key = &**image as *const Image;
+        if let Some(resource) = self.images.iter().find(|resource| resource.key == key) {
+            return resource.name.clone()
+        }
+
+        let mut colors = Vec::with_capacity(image.pixels().len() / 4 * 3);
+        let mut alphas = Vec::with_capacity(image.pixels().len() / 4);
+        for pixel in image.pixels().chunks(4) {
+            let color = raster::unpremultiply([pixel[0], pixel[1], pixel[2], pixel[3]]);
+            colors.extend_from_slice(&color[0..3]);
+            alphas.push(color[3]);
+        }
+
+        let size = image.size();
+        let mask_header = format!("/Type /XObject /Subtype /Image /Width {} /Height {} \
+                                   /ColorSpace /DeviceGray /BitsPerComponent 8",
+                                  size.width,
+                                  size.height);
+        let mask = self.add_object(stream_object(&mask_header, &alphas));
+        let header = format!("/Type /XObject /Subtype /Image /Width {} /Height {} \
+                              /ColorSpace /DeviceRGB /BitsPerComponent 8 /SMask {} 0 R",
+                             size.width,
+                             size.height,
+                             mask);
+        let object = self.add_object(stream_object(&header, &colors));
+
+        let name = format!("Im{}", self.images.len());
+        self.images.push(Resource {
+            key: key,
+            name: name.clone(),
+            object: object,
+        });
+        name
+    }
+
+    fn write_to<W>(&self, writer: &mut W) -> io::Result<()> where W: Write {
+        let mut offsets = Vec::with_capacity(self.objects.len());
+        let mut offset = 0;
+        let header = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n";
+        try!(writer.write_all(header));
+        offset += header.len();
+
+        for (index, object) in self.objects.iter().enumerate() {
+            offsets.push(offset);
+            let object_header = format!("{} 0 obj\n", index + 1);
+            try!(writer.write_all(object_header.as_bytes()));
+            try!(writer.write_all(object));
+            try!(writer.write_all(b"\nendobj\n"));
+            offset += object_header.len() + object.len() + b"\nendobj\n".len();
+        }
+
+        try!(write!(writer, "xref\n0 {}\n0000000000 65535 f \n", self.objects.len() + 1));
+        for offset in offsets {
+            try!(write!(writer, "{:010} 00000 n \n", offset));
+        }
+        write!(writer,
+               "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+               self.objects.len() + 1,
+               CATALOG_OBJECT,
+               offset)
+    }
+}
+
+fn pdf_blend_mode(blend_mode: BlendMode) -> Option<&'static str> {
+    match blend_mode {
+        BlendMode::Clear => None,
+        BlendMode::Multiply => Some("Multiply"),
+        BlendMode::Screen => Some("Screen"),
+        BlendMode::Overlay => Some("Overlay"),
+        BlendMode::Darken => Some("Darken"),
+        BlendMode::Lighten => Some("Lighten"),
+        BlendMode::ColorDodge => Some("ColorDodge"),
+        BlendMode::ColorBurn => Some("ColorBurn"),
+        BlendMode::HardLight => Some("HardLight"),
+        BlendMode::SoftLight => Some("SoftLight"),
+        BlendMode::Difference => Some("Difference"),
+        BlendMode::Exclusion => Some("Exclusion"),
+        _ => Some("Normal"),
+    }
+}
+
+// Writes the path construction operators for `path`. Quadratic curves are raised to cubics,
+// since PDF only has the latter.
+fn write_path(contents: &mut String, path: &[PathEvent]) {
+    let events = ArcToQuadraticTransformer::new(path.iter().cloned(), ARC_TOLERANCE);
+    let mut current_point = Point2D::zero();
+    for event in events {
+        match event {
+            PathEvent::MoveTo(to) => {
+                drop(writeln!(contents, "{} {} m", to.x, to.y));
+                current_point = to
+            }
+            PathEvent::LineTo(to) => {
+                drop(writeln!(contents, "{} {} l", to.x, to.y));
+                current_point = to
+            }
+            PathEvent::QuadraticTo(ctrl, to) => {
+                let ctrl0 = current_point.lerp(ctrl, 2.0 / 3.0);
+                let ctrl1 = to.lerp(ctrl, 2.0 / 3.0);
+                drop(writeln!(contents,
+                              "{} {} {} {} {} {} c",
+                              ctrl0.x,
+                              ctrl0.y,
+                              ctrl1.x,
+                              ctrl1.y,
+                              to.x,
+                              to.y));
+                current_point = to
+            }
+            PathEvent::CubicTo(ctrl0, ctrl1, to) => {
+                drop(writeln!(contents,
+                              "{} {} {} {} {} {} c",
+                              ctrl0.x,
+                              ctrl0.y,
+                              ctrl1.x,
+                              ctrl1.y,
+                              to.x,
+                              to.y));
+                current_point = to
+            }
+            PathEvent::Close => contents.push_str("h\n"),
+            PathEvent::Arc(..) => unreachable!("Arcs should have been converted to quadratics!"),
+        }
+    }
+}
+
+// Returns the bounds of all the points of `path`, including control points.
+fn path_bounds(path: &[PathEvent]) -> Rect<f32> {
+    let mut points = vec![];
+    for event in path {
+        match *event {
+            PathEvent::MoveTo(to) | PathEvent::LineTo(to) => points.push(to),
+            PathEvent::QuadraticTo(ctrl, to) => points.extend_from_slice(&[ctrl, to]),
+            PathEvent::CubicTo(ctrl0, ctrl1, to) => points.extend_from_slice(&[ctrl0, ctrl1, to]),
+            PathEvent::Arc(center, radii, _, _) => {
+                let radius = radii.x.abs().max(radii.y.abs());
+                points.push(center - Vector2D::new(radius, radius));
+                points.push(center + Vector2D::new(radius, radius));
+            }
+            PathEvent::Close => {}
+        }
+    }
+    Rect::from_points(&points)
+}
+
+// Builds a function from 0..1 to the gradient's colors, interpolating between its stops.
+fn stitching_function(gradient: &Gradient) -> Option<String> {
+    let mut stops = gradient.stops.clone();
+    match (stops.first().cloned(), stops.last().cloned()) {
+        (Some(mut first), Some(mut last)) => {
+            if first.offset > 0.0 {
+                first.offset = 0.0;
+                stops.insert(0, first);
+            }
+            if last.offset < 1.0 {
+                last.offset = 1.0;
+                stops.push(last);
+            }
+        }
+        _ => return None,
+    }
+
+    let rgb = |color: [u8; 4]| {
+        format!("{} {} {}",
+                color[0] as f32 / 255.0,
+                color[1] as f32 / 255.0,
+                color[2] as f32 / 255.0)
+    };
+    let mut functions = String::new();
+    let mut bounds = String::new();
+    let mut encode = String::new();
+    for (index, pair) in stops.windows(2).enumerate() {
+        drop(write!(functions,
+                    "<< /FunctionType 2 /Domain [0 1] /C0 [{}] /C1 [{}] /N 1 >> ",
+                    rgb(pair[0].color),
+                    rgb(pair[1].color)));
+        if index > 0 {
+            drop(write!(bounds, "{} ", pair[0].offset));
+        }
+        encode.push_str("0 1 ");
+    }
+    Some(format!("<< /FunctionType 3 /Domain [0 1] /Functions [{}] /Bounds [{}] /Encode [{}] >>",
+                 functions.trim_right(),
+                 bounds.trim_right(),
+                 encode.trim_right()))
+}
+
+fn resource_dictionary<K>(resources: &[Resource<K>]) -> String {
+    let mut dictionary = String::new();
+    for resource in resources {
+        drop(write!(dictionary, "/{} {} 0 R ", resource.name, resource.object));
+    }
+    dictionary.trim_right().to_owned()
+}
+
+fn stream_object(header: &str, data: &[u8]) -> Vec<u8> {
+    let mut object = format!("<< {} /Length {} >>\nstream\n", header, data.len()).into_bytes();
+    object.extend_from_slice(data);
+    object.extend_from_slice(b"\nendstream");
+    object
+}
@@ -0,0 +1,83 @@
+// pathfinder/canvas/src/picture.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Recorded drawing commands that can be replayed many times.
+//!
+//! A picture is recorded by drawing into a context between `begin_recording()` and
+//! `end_recording()`, and replayed with `draw_picture()`. Each command is partitioned once, when
+//! it's recorded. Replaying a picture under a transform that only translates it by whole pixels
+//! reuses those meshes, so static content such as toolbars and map tiles costs no more than
+//! rasterization per frame. Under any other transform, the recorded paths are transformed and
+//! partitioned again.
+
+use euclid::{Transform2D, Vector2D};
+use lyon_path::PathEvent;
+use pathfinder_partitioner::FillRule;
+use pathfinder_partitioner::batch;
+use pathfinder_partitioner::mesh::Mesh;
+use pathfinder_partitioner::mesh_pack::MeshPack;
+use pathfinder_rasterizer::blend::BlendMode;
+use pathfinder_rasterizer::paint::Paint;
+
+/// A fill, in the coordinate space of the picture it belongs to.
+#[derive(Clone, Debug)]
+pub(crate) struct DrawCommand {
+    pub(crate) path: Vec<PathEvent>,
+    pub(crate) fill_rule: FillRule,
+    /// The path, partitioned.
+    pub(crate) mesh: Mesh,
+    pub(crate) paint: Paint,
+    pub(crate) blend_mode: BlendMode,
+    pub(crate) alpha: f32,
+}
+
+/// A recorded sequence of drawing commands.
+#[derive(Clone, Debug, Default)]
+pub struct Picture {
+    pub(crate) commands: Vec<DrawCommand>,
+}
+
+impl Picture {
+    /// Returns true if nothing was drawn while the picture was being recorded.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Returns the number of fills in the picture.
+    #[inline]
+    pub fn command_count(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Partitions the path of each fill again with `approx_tolerance`, and collects the meshes
+    /// into a mesh pack in drawing order, for offline baking.
+    ///
+    /// Only geometry is kept: paints, blend modes, and clips aren't part of the mesh pack format.
+    pub fn to_mesh_pack(&self, approx_tolerance: Option<f32>) -> MeshPack {
+        batch::build_mesh_pack(&self.commands, |command| {
+            batch::partition_path(&command.path, command.fill_rule, approx_tolerance)
+        })
+    }
+}
+
+/// Returns the offset that `transform` applies, if all it does is translate by whole pixels.
+pub(crate) fn pixel_translation(transform: &Transform2D<f32>) -> Option<Vector2D<i32>> {
+    if transform.m11 != 1.0 || transform.m12 != 0.0 || transform.m21 != 0.0 ||
+            transform.m22 != 1.0 {
+        return None
+    }
+    let (x, y) = (transform.m31.round(), transform.m32.round());
+    if x == transform.m31 && y == transform.m32 {
+        Some(Vector2D::new(x as i32, y as i32))
+    } else {
+        None
+    }
+}
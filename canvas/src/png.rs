@@ -0,0 +1,90 @@
+// pathfinder/canvas/src/png.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Encodes rendered canvases as PNG images, for tests and command-line tools, and decodes PNG
+//! images, such as bitmap glyphs, for pattern paints.
+
+use euclid::Size2D;
+use image::png::PNGEncoder;
+use image::{self, ColorType, ImageFormat};
+use pathfinder_rasterizer::paint::Image;
+use pathfinder_rasterizer::raster::{self, Canvas};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Encodes the current layer of `canvas` as an 8-bit RGBA PNG.
+///
+/// Layers that are still open aren't composited first; pop them, or use `Canvas::into_pixels()`
+/// and `write_png_pixels()`, to export everything that was drawn.
+#[inline]
+pub fn write_png<W>(canvas: &Canvas, writer: W) -> io::Result<()> where W: Write {
+    write_png_pixels(canvas.pixels(), canvas.size().width, canvas.size().height, writer)
+}
+
+/// Encodes premultiplied RGBA pixels, as a `Canvas` stores them, as an 8-bit RGBA PNG.
+pub fn write_png_pixels<W>(pixels: &[u8], width: u32, height: u32, writer: W) -> io::Result<()>
+                           where W: Write {
+    if pixels.len() != width as usize * height as usize * 4 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "wrong number of pixels"))
+    }
+
+    let mut straight_pixels = Vec::with_capacity(pixels.len());
+    for pixel in pixels.chunks(4) {
+        straight_pixels.extend_from_slice(&raster::unpremultiply([pixel[0],
+                                                                  pixel[1],
+                                                                  pixel[2],
+                                                                  pixel[3]]))
+    }
+
+    PNGEncoder::new(writer).encode(&straight_pixels, width, height, ColorType::RGBA(8))
+}
+
+/// Decodes a PNG image into premultiplied pixels.
+pub fn read_png(data: &[u8]) -> io::Result<Image> {
+    let decoded = match image::load_from_memory_with_format(data, ImageFormat::PNG) {
+        Ok(decoded) => decoded.to_rgba(),
+        Err(_) => return Err(io::Error::from(io::ErrorKind::InvalidData)),
+    };
+
+    let size = Size2D::new(decoded.width(), decoded.height());
+    let mut pixels = decoded.into_raw();
+    for pixel in pixels.chunks_mut(4) {
+        let premultiplied = raster::premultiply([pixel[0], pixel[1], pixel[2], pixel[3]]);
+        pixel.copy_from_slice(&premultiplied);
+    }
+    Image::new(&size, pixels).map_err(|_| io::Error::from(io::ErrorKind::InvalidData))
+}
+
+/// Writes the current layer of `canvas` to a PNG file at `path`.
+pub fn save_png<P>(canvas: &Canvas, path: P) -> io::Result<()> where P: AsRef<Path> {
+    let file = try!(File::create(path));
+    let mut writer = BufWriter::new(file);
+    try!(write_png(canvas, &mut writer));
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::Size2D;
+
+    use super::{read_png, write_png_pixels};
+
+    #[test]
+    fn pixels_survive_a_round_trip() {
+        // Premultiplied opaque red, translucent green, and transparent.
+        let pixels = vec![255, 0, 0, 255, 0, 128, 0, 128, 0, 0, 0, 0];
+        let mut data = vec![];
+        write_png_pixels(&pixels, 3, 1, &mut data).unwrap();
+        let image = read_png(&data).unwrap();
+        assert_eq!(image.size(), Size2D::new(3, 1));
+        assert_eq!(image.pixels(), &pixels[..]);
+    }
+}
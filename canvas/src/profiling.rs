@@ -0,0 +1,100 @@
+// pathfinder/canvas/src/profiling.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Per-stage timings of frames drawn with a context.
+//!
+//! A frame is everything drawn between `begin_frame()` and `end_frame()`, which returns its
+//! profile. Stages are timed with the system's monotonic clock as the work happens, so profiling
+//! costs a couple of clock reads per fill.
+
+use std::time::{Duration, Instant};
+
+/// The stages of a frame that are timed separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfileStage {
+    /// Partitioning paths into meshes.
+    Partition,
+    /// Rasterizing meshes into the canvas or a layer.
+    Fill,
+    /// Compositing layers onto what's underneath them. Global alpha is applied as each fill is
+    /// drawn, without a layer, so nothing the context draws currently records this stage.
+    Composite,
+}
+
+/// How long each stage of a frame took.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameProfile {
+    /// The time from `begin_frame()` to `end_frame()`.
+    pub total: Duration,
+    /// The time not spent in any other stage: building, transforming, and stroking paths,
+    /// outlining glyphs, and whatever the app did between drawing calls.
+    pub scene_build: Duration,
+    pub partition: Duration,
+    pub fill: Duration,
+    pub composite: Duration,
+    /// The number of meshes rasterized.
+    pub fill_count: u32,
+}
+
+impl FrameProfile {
+    /// Returns the time spent in `stage`.
+    #[inline]
+    pub fn stage(&self, stage: ProfileStage) -> Duration {
+        match stage {
+            ProfileStage::Partition => self.partition,
+            ProfileStage::Fill => self.fill,
+            ProfileStage::Composite => self.composite,
+        }
+    }
+}
+
+/// Accumulates the profile of the current frame.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Profiler {
+    frame_start: Instant,
+    profile: FrameProfile,
+}
+
+impl Profiler {
+    #[inline]
+    pub(crate) fn new() -> Profiler {
+        Profiler {
+            frame_start: Instant::now(),
+            profile: FrameProfile::default(),
+        }
+    }
+
+    /// Adds the time since `start` to `stage`.
+    #[inline]
+    pub(crate) fn record(&mut self, stage: ProfileStage, start: Instant) {
+        let elapsed = start.elapsed();
+        match stage {
+            ProfileStage::Partition => self.profile.partition += elapsed,
+            ProfileStage::Fill => {
+                self.profile.fill += elapsed;
+                self.profile.fill_count += 1;
+            }
+            ProfileStage::Composite => self.profile.composite += elapsed,
+        }
+    }
+
+    /// Ends the frame and returns its profile.
+    pub(crate) fn finish(self) -> FrameProfile {
+        let mut profile = self.profile;
+        profile.total = self.frame_start.elapsed();
+        let staged = profile.partition + profile.fill + profile.composite;
+        profile.scene_build = if profile.total > staged {
+            profile.total - staged
+        } else {
+            Duration::new(0, 0)
+        };
+        profile
+    }
+}
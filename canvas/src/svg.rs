@@ -0,0 +1,1393 @@
+// pathfinder/canvas/src/svg.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Loads SVG documents and draws them into a canvas context.
+//!
+//! Besides path geometry and the basic shapes, this handles the features real-world icon sets
+//! lean on: linear and radial gradients, including `href` inheritance and bounding box units;
+//! `clipPath`; `defs` and `use`; group and element opacity; and the root element's `viewBox` and
+//! `preserveAspectRatio`. Gradients become canvas paints, clip paths become entries on the
+//! canvas's clip stack, and opacity becomes a layer. Clips and layers go straight to the canvas,
+//! so pictures recorded from a document lose them; `uses_clip_paths()` and
+//! `uses_group_opacity()` tell whether that matters.
+//!
+//! Styles are read from presentation attributes and `style` attributes. Style sheets, text,
+//! images, patterns, masks, markers, and filters aren't supported. Radial gradients under skewed
+//! or non-uniformly scaled transforms are drawn as circles, since canvas gradients can't be
+//! elliptical.
+
+use euclid::{Angle, Point2D, Rect, Size2D, Transform2D, Vector2D};
+use lyon_path::PathEvent;
+use pathfinder_partitioner::FillRule;
+use pathfinder_path_utils::arcs::{ArcConversion, ArcTo};
+use pathfinder_path_utils::stroke::{LineCap, LineJoin};
+use pathfinder_path_utils::transform::{self, Transform2DPathIter};
+use pathfinder_rasterizer::blend::BlendMode;
+use pathfinder_rasterizer::paint::{Gradient, GradientGeometry, GradientStop, Paint};
+use pathfinder_rasterizer::paint::SpreadMode;
+use pathfinder_rasterizer::raster::Color;
+use std::collections::HashMap;
+use std::f32;
+use xml::reader::{EventReader, XmlEvent};
+
+use {CanvasRenderingContext2D, Path2D};
+
+/// How deeply `use` elements may refer to one another, to guard against cycles.
+const MAX_USE_DEPTH: u32 = 32;
+
+/// How many `use` elements may be instantiated in one drawing of a document, counting those in
+/// clip paths. Depth alone doesn't bound the work: if each of 32 levels uses the next twice,
+/// the last level is drawn 2³² times.
+const MAX_USE_INSTANTIATIONS: u32 = 10_000;
+
+/// The tolerance, in user units, that elliptical arcs in path data are approximated to.
+const ARC_TOLERANCE: f32 = 0.05;
+
+/// The distance from the end of a quarter ellipse to its control points, as a fraction of the
+/// radius.
+const QUARTER_ARC_HANDLE: f32 = 0.552_284_8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SvgError {
+    /// The document isn't well-formed XML.
+    InvalidXml,
+    /// The document's root element isn't `svg`.
+    NotAnSvg,
+}
+
+/// A parsed SVG document.
+#[derive(Clone, Debug)]
+pub struct SvgDocument {
+    root: Element,
+    size: Size2D<f32>,
+    // Elements by ID, as paths of child indices from the root.
+    ids: HashMap<String, Vec<usize>>,
+}
+
+impl SvgDocument {
+    pub fn from_bytes(data: &[u8]) -> Result<SvgDocument, SvgError> {
+        let root = try!(parse_xml(data));
+        if root.name != "svg" {
+            return Err(SvgError::NotAnSvg)
+        }
+
+        let view_box = root.attribute("viewBox").and_then(parse_view_box);
+        let default_size = view_box.map_or(Size2D::new(100.0, 100.0), |view_box| view_box.size);
+        let size = Size2D::new(root.length("width", default_size.width)
+                                   .unwrap_or(default_size.width),
+                               root.length("height", default_size.height)
+                                   .unwrap_or(default_size.height));
+
+        let mut ids = HashMap::new();
+        collect_ids(&root, &mut vec![], &mut ids);
+        Ok(SvgDocument {
+            root: root,
+            size: size,
+            ids: ids,
+        })
+    }
+
+    /// Returns the size the document asks to be drawn at, from the root element's `width` and
+    /// `height`.
+    #[inline]
+    pub fn size(&self) -> Size2D<f32> {
+        self.size
+    }
+
+    /// Draws the document at its own size, with its origin at the context's origin.
+    #[inline]
+    pub fn draw(&self, context: &mut CanvasRenderingContext2D) {
+        self.draw_into(context, &Rect::new(Point2D::zero(), self.size))
+    }
+
+    /// Draws the document scaled into `viewport`, in the context's current user space, as the
+    /// root element's `viewBox` and `preserveAspectRatio` direct.
+    ///
+    /// `use` elements past the `MAX_USE_INSTANTIATIONS`th are skipped, as browsers do, so that
+    /// documents that reference the same content exponentially many times finish quickly.
+    pub fn draw_into(&self, context: &mut CanvasRenderingContext2D, viewport: &Rect<f32>) {
+        context.save();
+        context.transform(&viewport_transform(&self.root, viewport));
+        let style = Style::initial();
+        let mut remaining_uses = MAX_USE_INSTANTIATIONS;
+        for child in &self.root.children {
+            self.draw_element(context, child, &style, viewport.size, 0, &mut remaining_uses)
+        }
+        context.restore();
+    }
+
+    /// Draws only the element with the given `id`, in the document's own user space without
+    /// the root element's `viewBox`, with the styles and transforms of its ancestors. Returns
+    /// false if no element has that `id`.
+    ///
+    /// This is how glyphs from a font's `SVG ` table are drawn, since each one is an element of
+    /// a document that may hold many.
+    pub fn draw_element_with_id(&self, context: &mut CanvasRenderingContext2D, id: &str)
+                                -> bool {
+        let indices = match self.ids.get(id) {
+            None => return false,
+            Some(indices) => indices,
+        };
+
+        context.save();
+        let mut style = Style::initial();
+        let mut element = &self.root;
+        for (depth, &index) in indices.iter().enumerate() {
+            style = style.inherit(element);
+            // As in `draw_into()`, the root element's own transform doesn't apply.
+            if depth > 0 {
+                if let Some(transform) = element.attribute("transform").and_then(parse_transform) {
+                    context.transform(&transform)
+                }
+            }
+            element = &element.children[index];
+        }
+        let mut remaining_uses = MAX_USE_INSTANTIATIONS;
+        self.draw_element(context, element, &style, self.size, 0, &mut remaining_uses);
+        context.restore();
+        true
+    }
+
+    /// Returns true if any element refers to a `clipPath` in its `clip-path` attribute.
+    ///
+    /// Pictures recorded from such a document lose their clips, since recordings keep only the
+    /// paths that are filled.
+    pub fn uses_clip_paths(&self) -> bool {
+        any_element(&self.root, &|element| {
+            element.attribute("clip-path").and_then(url_reference).and_then(|reference| {
+                self.element(reference)
+            }).map_or(false, |clip_path| clip_path.name == "clipPath")
+        })
+    }
+
+    /// Returns true if any element has an `opacity` below 1.
+    ///
+    /// Pictures recorded from such a document draw those elements opaque, since recordings don't
+    /// keep the layers that group opacity becomes.
+    pub fn uses_group_opacity(&self) -> bool {
+        any_element(&self.root, &|element| {
+            element.number("opacity").map_or(false, |opacity| opacity.max(0.0).min(1.0) < 1.0)
+        })
+    }
+
+    fn element(&self, reference: &str) -> Option<&Element> {
+        let id = match local_reference(reference) {
+            None => return None,
+            Some(id) => id,
+        };
+        self.ids.get(id).map(|indices| {
+            indices.iter().fold(&self.root, |element, &index| &element.children[index])
+        })
+    }
+
+    // `viewport_size` is what percentages resolve against. `remaining_uses` is how many more
+    // `use` elements may be instantiated.
+    fn draw_element(&self,
+                    context: &mut CanvasRenderingContext2D,
+                    element: &Element,
+                    parent_style: &Style,
+                    viewport_size: Size2D<f32>,
+                    depth: u32,
+                    remaining_uses: &mut u32) {
+        if element.attribute("display") == Some("none") {
+            return
+        }
+        let style = parent_style.inherit(element);
+
+        context.save();
+        if let Some(transform) = element.attribute("transform").and_then(parse_transform) {
+            context.transform(&transform)
+        }
+
+        let opacity = element.number("opacity").unwrap_or(1.0).max(0.0).min(1.0);
+        if opacity < 1.0 {
+            context.canvas_mut().set_blend_mode(BlendMode::SourceOver);
+            context.canvas_mut().push_layer(opacity);
+        }
+        let clip_pushed = match element.attribute("clip-path") {
+            Some(reference) => {
+                self.push_clip(context, element, reference, viewport_size, remaining_uses)
+            }
+            None => false,
+        };
+
+        match &*element.name {
+            "svg" => {
+                let viewport = Rect::new(Point2D::new(element.length("x", viewport_size.width)
+                                                             .unwrap_or(0.0),
+                                                      element.length("y", viewport_size.height)
+                                                             .unwrap_or(0.0)),
+                                         Size2D::new(element.length("width", viewport_size.width)
+                                                            .unwrap_or(viewport_size.width),
+                                                     element.length("height",
+                                                                    viewport_size.height)
+                                                            .unwrap_or(viewport_size.height)));
+                context.transform(&viewport_transform(element, &viewport));
+                for child in &element.children {
+                    self.draw_element(context,
+                                      child,
+                                      &style,
+                                      viewport.size,
+                                      depth,
+                                      remaining_uses)
+                }
+            }
+            "g" | "a" | "switch" => {
+                for child in &element.children {
+                    self.draw_element(context,
+                                      child,
+                                      &style,
+                                      viewport_size,
+                                      depth,
+                                      remaining_uses)
+                }
+            }
+            "use" => {
+                let referenced = element.attribute("href").and_then(|href| self.element(href));
+                if let Some(referenced) = referenced {
+                    if depth < MAX_USE_DEPTH && *remaining_uses > 0 {
+                        *remaining_uses -= 1;
+                        context.translate(&Vector2D::new(element.length("x", viewport_size.width)
+                                                                .unwrap_or(0.0),
+                                                         element.length("y",
+                                                                        viewport_size.height)
+                                                                .unwrap_or(0.0)));
+                        if referenced.name == "symbol" {
+                            // Symbols are only drawn through `use`, as groups.
+                            let style = style.inherit(referenced);
+                            for child in &referenced.children {
+                                self.draw_element(context,
+                                                  child,
+                                                  &style,
+                                                  viewport_size,
+                                                  depth + 1,
+                                                  remaining_uses)
+                            }
+                        } else {
+                            self.draw_element(context,
+                                              referenced,
+                                              &style,
+                                              viewport_size,
+                                              depth + 1,
+                                              remaining_uses)
+                        }
+                    }
+                }
+            }
+            _ => {
+                if let Some(path) = shape_path(element, viewport_size) {
+                    self.draw_shape(context, &path, &style, viewport_size)
+                }
+            }
+        }
+
+        if clip_pushed {
+            context.canvas_mut().pop_clip();
+        }
+        if opacity < 1.0 {
+            context.canvas_mut().pop_layer();
+        }
+        context.restore();
+    }
+
+    fn draw_shape(&self,
+                  context: &mut CanvasRenderingContext2D,
+                  path: &Path2D,
+                  style: &Style,
+                  viewport_size: Size2D<f32>) {
+        let bounds = path_bounds(path.events());
+
+        if let Some(paint) = self.paint(&style.fill, style.fill_opacity, style, &bounds,
+                                        viewport_size) {
+            context.set_fill_style(paint);
+            context.fill_path(path, style.fill_rule);
+        }
+
+        if style.stroke_width > 0.0 {
+            if let Some(paint) = self.paint(&style.stroke, style.stroke_opacity, style, &bounds,
+                                            viewport_size) {
+                context.set_stroke_style(paint);
+                context.set_line_width(style.stroke_width);
+                context.set_line_cap(style.line_cap);
+                context.set_line_join(style.line_join);
+                context.stroke_path(path);
+            }
+        }
+    }
+
+    fn paint(&self,
+             paint: &SvgPaint,
+             opacity: f32,
+             style: &Style,
+             bounds: &Rect<f32>,
+             viewport_size: Size2D<f32>)
+             -> Option<Paint> {
+        match *paint {
+            SvgPaint::None => None,
+            SvgPaint::Color(color) => Some(Paint::Color(apply_opacity(color, opacity))),
+            SvgPaint::CurrentColor => Some(Paint::Color(apply_opacity(style.color, opacity))),
+            SvgPaint::Reference(ref reference, fallback) => {
+                match self.gradient(reference, opacity, bounds, viewport_size) {
+                    Some(gradient) => Some(Paint::Gradient(gradient)),
+                    None => fallback.map(|color| Paint::Color(apply_opacity(color, opacity))),
+                }
+            }
+        }
+    }
+
+    // Resolves a gradient into user space, following `href` links for attributes and stops
+    // that it doesn't specify itself.
+    fn gradient(&self,
+                reference: &str,
+                opacity: f32,
+                bounds: &Rect<f32>,
+                viewport_size: Size2D<f32>)
+                -> Option<Gradient> {
+        let mut chain = vec![];
+        let mut next = self.element(reference);
+        while let Some(element) = next {
+            if chain.len() as u32 > MAX_USE_DEPTH {
+                break
+            }
+            chain.push(element);
+            next = element.attribute("href").and_then(|href| self.element(href));
+        }
+        let gradient = match chain.first() {
+            Some(element) if element.name.ends_with("Gradient") => *element,
+            _ => return None,
+        };
+
+        let bounding_box_units = chain_attribute(&chain, "gradientUnits") != Some("userSpaceOnUse");
+        let reference_size = if bounding_box_units {
+            Size2D::new(1.0, 1.0)
+        } else {
+            viewport_size
+        };
+        let length = |name: &str, reference: f32, default: f32| {
+            chain_attribute(&chain, name).and_then(|value| parse_length(value, reference))
+                                         .unwrap_or(default)
+        };
+
+        // The transform from gradient space to user space.
+        let mut transform = chain_attribute(&chain, "gradientTransform")
+            .and_then(parse_transform)
+            .unwrap_or(Transform2D::identity());
+        if bounding_box_units {
+            if bounds.size.width <= 0.0 || bounds.size.height <= 0.0 {
+                return None
+            }
+            transform = transform.post_scale(bounds.size.width, bounds.size.height)
+                                 .post_translate(bounds.origin.to_vector())
+        }
+
+        let geometry = if gradient.name == "linearGradient" {
+            let (width, height) = (reference_size.width, reference_size.height);
+            GradientGeometry::Linear {
+                from: transform.transform_point(&Point2D::new(length("x1", width, 0.0),
+                                                              length("y1", height, 0.0))),
+                to: transform.transform_point(&Point2D::new(length("x2", width, width),
+                                                            length("y2", height, 0.0))),
+            }
+        } else {
+            let (width, height) = (reference_size.width, reference_size.height);
+            let diagonal = ((width * width + height * height) * 0.5).sqrt();
+            let center = Point2D::new(length("cx", width, width * 0.5),
+                                      length("cy", height, height * 0.5));
+            GradientGeometry::Radial {
+                center: transform.transform_point(&center),
+                radius: length("r", diagonal, diagonal * 0.5) *
+                    transform::max_scale_factor(&transform),
+            }
+        };
+
+        let stop_element = chain.iter().find(|element| {
+            element.children.iter().any(|child| child.name == "stop")
+        });
+        let mut stops: Vec<GradientStop> = vec![];
+        if let Some(stop_element) = stop_element {
+            for stop in stop_element.children.iter().filter(|child| child.name == "stop") {
+                let stop_opacity = stop.number("stop-opacity").unwrap_or(1.0);
+                let color = stop.attribute("stop-color").and_then(parse_color)
+                                                        .unwrap_or([0, 0, 0, 255]);
+                // Offsets must not decrease.
+                let offset = stop.attribute("offset").and_then(|offset| parse_length(offset, 1.0))
+                                                     .unwrap_or(0.0)
+                                                     .max(0.0)
+                                                     .min(1.0);
+                let offset = stops.last().map_or(offset, |last| offset.max(last.offset));
+                stops.push(GradientStop {
+                    offset: offset,
+                    color: apply_opacity(color, stop_opacity * opacity),
+                })
+            }
+        }
+
+        Some(Gradient {
+            geometry: geometry,
+            stops: stops,
+            spread: match chain_attribute(&chain, "spreadMethod") {
+                Some("repeat") => SpreadMode::Repeat,
+                Some("reflect") => SpreadMode::Reflect,
+                _ => SpreadMode::Pad,
+            },
+        })
+    }
+
+    // Pushes the clip path `reference` onto the canvas's clip stack. Returns false if it
+    // doesn't refer to a clip path.
+    fn push_clip(&self,
+                 context: &mut CanvasRenderingContext2D,
+                 element: &Element,
+                 reference: &str,
+                 viewport_size: Size2D<f32>,
+                 remaining_uses: &mut u32)
+                 -> bool {
+        let reference = match url_reference(reference) {
+            None => return false,
+            Some(reference) => reference,
+        };
+        let clip_path = match self.element(reference) {
+            Some(clip_path) if clip_path.name == "clipPath" => clip_path,
+            _ => return false,
+        };
+
+        // Map the unit square onto the bounds of the clipped element, if it asks for that.
+        let mut transform = context.current_transform();
+        if clip_path.attribute("clipPathUnits") == Some("objectBoundingBox") {
+            let bounds = shape_path(element, viewport_size).map(|path| path_bounds(path.events()))
+                                                           .unwrap_or(Rect::zero());
+            transform = Transform2D::create_scale(bounds.size.width, bounds.size.height)
+                .post_translate(bounds.origin.to_vector())
+                .post_mul(&transform)
+        }
+        if let Some(clip_transform) = clip_path.attribute("transform").and_then(parse_transform) {
+            transform = clip_transform.post_mul(&transform)
+        }
+
+        // The clip region is the union of the children's, each with its own clip rule.
+        let mut paths = vec![];
+        self.clip_path_events(clip_path,
+                              &transform,
+                              clip_rule(clip_path, FillRule::Winding),
+                              viewport_size,
+                              &mut paths,
+                              0,
+                              remaining_uses);
+        let paths: Vec<_> = paths.iter().map(|&(ref events, fill_rule)| {
+            (&events[..], fill_rule)
+        }).collect();
+        context.canvas_mut().push_clip_union(&paths);
+        true
+    }
+
+    // Collects the geometry of each of a clip path's children, in canvas pixels, with its clip
+    // rule. `parent_clip_rule` is the one the children inherit.
+    fn clip_path_events(&self,
+                        element: &Element,
+                        transform: &Transform2D<f32>,
+                        parent_clip_rule: FillRule,
+                        viewport_size: Size2D<f32>,
+                        paths: &mut Vec<(Vec<PathEvent>, FillRule)>,
+                        depth: u32,
+                        remaining_uses: &mut u32) {
+        for child in &element.children {
+            if child.attribute("display") == Some("none") {
+                continue
+            }
+            let mut child_transform = *transform;
+            if let Some(transform) = child.attribute("transform").and_then(parse_transform) {
+                child_transform = transform.post_mul(&child_transform)
+            }
+
+            if child.name == "use" {
+                let referenced = child.attribute("href").and_then(|href| self.element(href));
+                if let Some(referenced) = referenced {
+                    if depth < MAX_USE_DEPTH && *remaining_uses > 0 {
+                        *remaining_uses -= 1;
+                        let offset = Vector2D::new(child.length("x", viewport_size.width)
+                                                        .unwrap_or(0.0),
+                                                   child.length("y", viewport_size.height)
+                                                        .unwrap_or(0.0));
+                        let use_transform = Transform2D::create_translation(offset.x, offset.y)
+                            .post_mul(&child_transform);
+                        let wrapper = Element {
+                            name: "g".to_owned(),
+                            attributes: HashMap::new(),
+                            children: vec![referenced.clone()],
+                        };
+                        self.clip_path_events(&wrapper,
+                                              &use_transform,
+                                              clip_rule(child, parent_clip_rule),
+                                              viewport_size,
+                                              paths,
+                                              depth + 1,
+                                              remaining_uses)
+                    }
+                }
+                continue
+            }
+
+            if let Some(path) = shape_path(child, viewport_size) {
+                let events = Transform2DPathIter::new(path.events().iter().cloned(),
+                                                      &child_transform).collect();
+                paths.push((events, clip_rule(child, parent_clip_rule)))
+            }
+        }
+    }
+}
+
+// The inherited properties that affect painting.
+#[derive(Clone, Debug)]
+struct Style {
+    color: Color,
+    fill: SvgPaint,
+    fill_opacity: f32,
+    fill_rule: FillRule,
+    stroke: SvgPaint,
+    stroke_opacity: f32,
+    stroke_width: f32,
+    line_cap: LineCap,
+    line_join: LineJoin,
+}
+
+impl Style {
+    fn initial() -> Style {
+        Style {
+            color: [0, 0, 0, 255],
+            fill: SvgPaint::Color([0, 0, 0, 255]),
+            fill_opacity: 1.0,
+            fill_rule: FillRule::Winding,
+            stroke: SvgPaint::None,
+            stroke_opacity: 1.0,
+            stroke_width: 1.0,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter(4.0),
+        }
+    }
+
+    fn inherit(&self, element: &Element) -> Style {
+        let mut style = self.clone();
+        if let Some(color) = element.attribute("color").and_then(parse_color) {
+            style.color = color
+        }
+        if let Some(fill) = element.attribute("fill").and_then(parse_paint) {
+            style.fill = fill
+        }
+        if let Some(stroke) = element.attribute("stroke").and_then(parse_paint) {
+            style.stroke = stroke
+        }
+        if let Some(fill_opacity) = element.number("fill-opacity") {
+            style.fill_opacity = fill_opacity.max(0.0).min(1.0)
+        }
+        if let Some(stroke_opacity) = element.number("stroke-opacity") {
+            style.stroke_opacity = stroke_opacity.max(0.0).min(1.0)
+        }
+        match element.attribute("fill-rule") {
+            Some("evenodd") => style.fill_rule = FillRule::EvenOdd,
+            Some("nonzero") => style.fill_rule = FillRule::Winding,
+            _ => {}
+        }
+        if let Some(stroke_width) = element.length("stroke-width", 100.0) {
+            style.stroke_width = stroke_width
+        }
+        match element.attribute("stroke-linecap") {
+            Some("butt") => style.line_cap = LineCap::Butt,
+            Some("round") => style.line_cap = LineCap::Round,
+            Some("square") => style.line_cap = LineCap::Square,
+            _ => {}
+        }
+        let miter_limit = match element.number("stroke-miterlimit") {
+            Some(miter_limit) => miter_limit,
+            None => {
+                match style.line_join {
+                    LineJoin::Miter(miter_limit) => miter_limit,
+                    _ => 4.0,
+                }
+            }
+        };
+        match element.attribute("stroke-linejoin") {
+            Some("miter") => style.line_join = LineJoin::Miter(miter_limit),
+            Some("round") => style.line_join = LineJoin::Round,
+            Some("bevel") => style.line_join = LineJoin::Bevel,
+            _ => {
+                if let LineJoin::Miter(_) = style.line_join {
+                    style.line_join = LineJoin::Miter(miter_limit)
+                }
+            }
+        }
+        style
+    }
+}
+
+#[derive(Clone, Debug)]
+enum SvgPaint {
+    None,
+    Color(Color),
+    CurrentColor,
+    /// A reference to a paint server, with the fallback color to use if it doesn't resolve.
+    Reference(String, Option<Color>),
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    // Presentation attributes and `style` declarations, keyed by local name.
+    attributes: HashMap<String, String>,
+    children: Vec<Element>,
+}
+
+impl Element {
+    #[inline]
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(|value| value.trim())
+    }
+
+    fn number(&self, name: &str) -> Option<f32> {
+        self.attribute(name).and_then(|value| value.parse().ok())
+    }
+
+    // Percentages are of `reference`.
+    fn length(&self, name: &str, reference: f32) -> Option<f32> {
+        self.attribute(name).and_then(|value| parse_length(value, reference))
+    }
+}
+
+fn parse_xml(data: &[u8]) -> Result<Element, SvgError> {
+    let mut stack: Vec<Element> = vec![];
+    for event in EventReader::new(data) {
+        match event {
+            Err(_) => return Err(SvgError::InvalidXml),
+            Ok(XmlEvent::StartElement { name, attributes, .. }) => {
+                let mut element = Element {
+                    name: name.local_name,
+                    attributes: HashMap::new(),
+                    children: vec![],
+                };
+                let mut style = None;
+                for attribute in attributes {
+                    if attribute.name.local_name == "style" {
+                        style = Some(attribute.value)
+                    } else {
+                        element.attributes.insert(attribute.name.local_name, attribute.value);
+                    }
+                }
+                // Declarations in `style` take precedence over presentation attributes.
+                if let Some(style) = style {
+                    for declaration in style.split(';') {
+                        let mut parts = declaration.splitn(2, ':');
+                        if let (Some(property), Some(value)) = (parts.next(), parts.next()) {
+                            element.attributes.insert(property.trim().to_owned(),
+                                                      value.trim().to_owned());
+                        }
+                    }
+                }
+                stack.push(element)
+            }
+            Ok(XmlEvent::EndElement { .. }) => {
+                let element = stack.pop().unwrap();
+                match stack.last_mut() {
+                    None => return Ok(element),
+                    Some(parent) => parent.children.push(element),
+                }
+            }
+            Ok(_) => {}
+        }
+    }
+    Err(SvgError::InvalidXml)
+}
+
+// Returns true if `predicate` holds for `element` or any of its descendants.
+fn any_element<F>(element: &Element, predicate: &F) -> bool where F: Fn(&Element) -> bool {
+    predicate(element) || element.children.iter().any(|child| any_element(child, predicate))
+}
+
+// Returns the clip rule that `element` sets, or `inherited` if it doesn't set one.
+fn clip_rule(element: &Element, inherited: FillRule) -> FillRule {
+    match element.attribute("clip-rule") {
+        Some("evenodd") => FillRule::EvenOdd,
+        Some("nonzero") => FillRule::Winding,
+        _ => inherited,
+    }
+}
+
+// Returns the first value of an attribute along a chain of `href` references.
+fn chain_attribute<'a>(chain: &[&'a Element], name: &str) -> Option<&'a str> {
+    chain.iter().filter_map(|element| element.attribute(name)).next()
+}
+
+fn collect_ids(element: &Element, indices: &mut Vec<usize>, ids: &mut HashMap<String, Vec<usize>>) {
+    if let Some(id) = element.attribute("id") {
+        ids.entry(id.to_owned()).or_insert(indices.clone());
+    }
+    for (index, child) in element.children.iter().enumerate() {
+        indices.push(index);
+        collect_ids(child, indices, ids);
+        indices.pop();
+    }
+}
+
+// Returns the transform that maps an element's `viewBox` into `viewport`, honoring its
+// `preserveAspectRatio`. Without a view box, this just moves the origin.
+fn viewport_transform(element: &Element, viewport: &Rect<f32>) -> Transform2D<f32> {
+    let view_box = match element.attribute("viewBox").and_then(parse_view_box) {
+        Some(ref view_box) if view_box.size.width > 0.0 && view_box.size.height > 0.0 => {
+            *view_box
+        }
+        _ => return Transform2D::create_translation(viewport.origin.x, viewport.origin.y),
+    };
+
+    let mut scale = Vector2D::new(viewport.size.width / view_box.size.width,
+                                  viewport.size.height / view_box.size.height);
+    let mut parts = element.attribute("preserveAspectRatio").unwrap_or("").split_whitespace();
+    let align = parts.next().unwrap_or("xMidYMid");
+    let slice = parts.next() == Some("slice");
+    let mut translation = viewport.origin.to_vector();
+    if align != "none" {
+        let uniform_scale = if slice { scale.x.max(scale.y) } else { scale.x.min(scale.y) };
+        scale = Vector2D::new(uniform_scale, uniform_scale);
+
+        let extra = viewport.size.to_vector() - view_box.size.to_vector() * uniform_scale;
+        let fraction = |name: &str| {
+            if align.contains(&format!("{}Mid", name)) {
+                0.5
+            } else if align.contains(&format!("{}Max", name)) {
+                1.0
+            } else {
+                0.0
+            }
+        };
+        translation = translation + Vector2D::new(extra.x * fraction("x"), extra.y * fraction("Y"));
+    }
+
+    Transform2D::create_translation(-view_box.origin.x, -view_box.origin.y)
+        .post_scale(scale.x, scale.y)
+        .post_translate(translation)
+}
+
+// Returns the geometry of a shape element in its user space, or `None` if it isn't one.
+fn shape_path(element: &Element, viewport_size: Size2D<f32>) -> Option<Path2D> {
+    let (width, height) = (viewport_size.width, viewport_size.height);
+    let diagonal = ((width * width + height * height) * 0.5).sqrt();
+    let length = |name: &str, reference: f32| element.length(name, reference).unwrap_or(0.0);
+
+    let mut path = Path2D::new();
+    match &*element.name {
+        "path" => parse_path_data(element.attribute("d").unwrap_or(""), &mut path),
+        "rect" => {
+            let rect = Rect::new(Point2D::new(length("x", width), length("y", height)),
+                                 Size2D::new(length("width", width), length("height", height)));
+            if rect.size.width <= 0.0 || rect.size.height <= 0.0 {
+                return None
+            }
+            // A missing corner radius defaults to the other one.
+            let (rx, ry) = (element.length("rx", width), element.length("ry", height));
+            let radii = Vector2D::new(rx.or(ry).unwrap_or(0.0).min(rect.size.width * 0.5),
+                                      ry.or(rx).unwrap_or(0.0).min(rect.size.height * 0.5));
+            if radii.x > 0.0 && radii.y > 0.0 {
+                push_rounded_rect(&mut path, &rect, &radii)
+            } else {
+                path.rect(&rect)
+            }
+        }
+        "circle" => {
+            let radius = length("r", diagonal);
+            let center = Point2D::new(length("cx", width), length("cy", height));
+            push_ellipse(&mut path, &center, &Vector2D::new(radius, radius))
+        }
+        "ellipse" => {
+            let radii = Vector2D::new(length("rx", width), length("ry", height));
+            let center = Point2D::new(length("cx", width), length("cy", height));
+            push_ellipse(&mut path, &center, &radii)
+        }
+        "line" => {
+            path.move_to(&Point2D::new(length("x1", width), length("y1", height)));
+            path.line_to(&Point2D::new(length("x2", width), length("y2", height)));
+        }
+        "polyline" | "polygon" => {
+            let numbers = parse_numbers(element.attribute("points").unwrap_or(""));
+            for (index, point) in numbers.chunks(2).filter(|point| point.len() == 2).enumerate() {
+                let point = Point2D::new(point[0], point[1]);
+                if index == 0 { path.move_to(&point) } else { path.line_to(&point) }
+            }
+            if element.name == "polygon" {
+                path.close_path()
+            }
+        }
+        _ => return None,
+    }
+    Some(path)
+}
+
+fn push_ellipse(path: &mut Path2D, center: &Point2D<f32>, radii: &Vector2D<f32>) {
+    if radii.x <= 0.0 || radii.y <= 0.0 {
+        return
+    }
+    let handle = Vector2D::new(radii.x, radii.y) * QUARTER_ARC_HANDLE;
+    let (left, right) = (center.x - radii.x, center.x + radii.x);
+    let (top, bottom) = (center.y - radii.y, center.y + radii.y);
+    path.move_to(&Point2D::new(right, center.y));
+    path.bezier_curve_to(&Point2D::new(right, center.y + handle.y),
+                         &Point2D::new(center.x + handle.x, bottom),
+                         &Point2D::new(center.x, bottom));
+    path.bezier_curve_to(&Point2D::new(center.x - handle.x, bottom),
+                         &Point2D::new(left, center.y + handle.y),
+                         &Point2D::new(left, center.y));
+    path.bezier_curve_to(&Point2D::new(left, center.y - handle.y),
+                         &Point2D::new(center.x - handle.x, top),
+                         &Point2D::new(center.x, top));
+    path.bezier_curve_to(&Point2D::new(center.x + handle.x, top),
+                         &Point2D::new(right, center.y - handle.y),
+                         &Point2D::new(right, center.y));
+    path.close_path();
+}
+
+fn push_rounded_rect(path: &mut Path2D, rect: &Rect<f32>, radii: &Vector2D<f32>) {
+    let handle = *radii * (1.0 - QUARTER_ARC_HANDLE);
+    let (left, top, right, bottom) = (rect.origin.x, rect.origin.y, rect.max_x(), rect.max_y());
+    path.move_to(&Point2D::new(left + radii.x, top));
+    path.line_to(&Point2D::new(right - radii.x, top));
+    path.bezier_curve_to(&Point2D::new(right - handle.x, top),
+                         &Point2D::new(right, top + handle.y),
+                         &Point2D::new(right, top + radii.y));
+    path.line_to(&Point2D::new(right, bottom - radii.y));
+    path.bezier_curve_to(&Point2D::new(right, bottom - handle.y),
+                         &Point2D::new(right - handle.x, bottom),
+                         &Point2D::new(right - radii.x, bottom));
+    path.line_to(&Point2D::new(left + radii.x, bottom));
+    path.bezier_curve_to(&Point2D::new(left + handle.x, bottom),
+                         &Point2D::new(left, bottom - handle.y),
+                         &Point2D::new(left, bottom - radii.y));
+    path.line_to(&Point2D::new(left, top + radii.y));
+    path.bezier_curve_to(&Point2D::new(left, top + handle.y),
+                         &Point2D::new(left + handle.x, top),
+                         &Point2D::new(left + radii.x, top));
+    path.close_path();
+}
+
+// Parses SVG path data into `path`. Parsing stops at the first error, keeping what came before
+// it, as SVG specifies.
+fn parse_path_data(data: &str, path: &mut Path2D) {
+    let mut tokens = PathDataTokenizer::new(data);
+    let mut command = None;
+    let (mut current_point, mut subpath_start) = (Point2D::zero(), Point2D::zero());
+    // The control point that a following smooth curve reflects.
+    let mut last_ctrl: Option<(char, Point2D<f32>)> = None;
+
+    loop {
+        if let Some(letter) = tokens.command() {
+            command = Some(letter)
+        } else if tokens.at_end() {
+            return
+        }
+        let letter = match command {
+            None => return,
+            Some(letter) => letter,
+        };
+        let relative = letter.is_lowercase();
+        let origin = if relative { current_point.to_vector() } else { Vector2D::zero() };
+        let mut ctrl = None;
+
+        match letter.to_ascii_uppercase() {
+            'M' => {
+                let to = match tokens.point() { None => return, Some(to) => to + origin };
+                path.move_to(&to);
+                current_point = to;
+                subpath_start = to;
+                // Further coordinate pairs are implicit line-tos.
+                command = Some(if relative { 'l' } else { 'L' });
+            }
+            'L' => {
+                let to = match tokens.point() { None => return, Some(to) => to + origin };
+                path.line_to(&to);
+                current_point = to;
+            }
+            'H' => {
+                let x = match tokens.number() { None => return, Some(x) => x + origin.x };
+                current_point = Point2D::new(x, current_point.y);
+                path.line_to(&current_point);
+            }
+            'V' => {
+                let y = match tokens.number() { None => return, Some(y) => y + origin.y };
+                current_point = Point2D::new(current_point.x, y);
+                path.line_to(&current_point);
+            }
+            'C' | 'S' => {
+                let ctrl0 = if letter.to_ascii_uppercase() == 'C' {
+                    match tokens.point() { None => return, Some(ctrl0) => ctrl0 + origin }
+                } else {
+                    match last_ctrl {
+                        Some(('C', last)) => current_point + (current_point - last),
+                        _ => current_point,
+                    }
+                };
+                let (ctrl1, to) = match (tokens.point(), tokens.point()) {
+                    (Some(ctrl1), Some(to)) => (ctrl1 + origin, to + origin),
+                    _ => return,
+                };
+                path.bezier_curve_to(&ctrl0, &ctrl1, &to);
+                current_point = to;
+                ctrl = Some(('C', ctrl1));
+            }
+            'Q' | 'T' => {
+                let ctrl0 = if letter.to_ascii_uppercase() == 'Q' {
+                    match tokens.point() { None => return, Some(ctrl0) => ctrl0 + origin }
+                } else {
+                    match last_ctrl {
+                        Some(('Q', last)) => current_point + (current_point - last),
+                        _ => current_point,
+                    }
+                };
+                let to = match tokens.point() { None => return, Some(to) => to + origin };
+                path.quadratic_curve_to(&ctrl0, &to);
+                current_point = to;
+                ctrl = Some(('Q', ctrl0));
+            }
+            'A' => {
+                let (radii, x_rotation) = match (tokens.point(), tokens.number()) {
+                    (Some(radii), Some(x_rotation)) => (radii.to_vector(), x_rotation),
+                    _ => return,
+                };
+                let (large_arc, sweep) = match (tokens.flag(), tokens.flag()) {
+                    (Some(large_arc), Some(sweep)) => (large_arc, sweep),
+                    _ => return,
+                };
+                let to = match tokens.point() { None => return, Some(to) => to + origin };
+                let arc = ArcTo {
+                    radii: radii,
+                    x_rotation: Angle::degrees(x_rotation),
+                    large_arc: large_arc,
+                    sweep: sweep,
+                    to: to,
+                };
+                match arc.to_center_arc(&current_point) {
+                    ArcConversion::Empty => {}
+                    ArcConversion::Line(to) => path.line_to(&to),
+                    ArcConversion::Arc(center_arc) => {
+                        center_arc.for_each_quadratic(ARC_TOLERANCE, |quadratic| {
+                            path.quadratic_curve_to(&quadratic.ctrl, &quadratic.to)
+                        })
+                    }
+                }
+                current_point = to;
+            }
+            'Z' => {
+                path.close_path();
+                current_point = subpath_start;
+                // A command letter must follow.
+                command = None;
+            }
+            _ => return,
+        }
+        last_ctrl = ctrl;
+    }
+}
+
+struct PathDataTokenizer<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PathDataTokenizer<'a> {
+    fn new(data: &'a str) -> PathDataTokenizer<'a> {
+        PathDataTokenizer {
+            data: data.as_bytes(),
+            position: 0,
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while self.position < self.data.len() {
+            match self.data[self.position] {
+                b' ' | b'\t' | b'\n' | b'\r' | b',' => self.position += 1,
+                _ => break,
+            }
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_separators();
+        self.position == self.data.len()
+    }
+
+    fn command(&mut self) -> Option<char> {
+        self.skip_separators();
+        match self.data.get(self.position) {
+            Some(&byte) if (byte as char).is_ascii_alphabetic() && byte != b'e' && byte != b'E' => {
+                self.position += 1;
+                Some(byte as char)
+            }
+            _ => None,
+        }
+    }
+
+    fn number(&mut self) -> Option<f32> {
+        self.skip_separators();
+        let start = self.position;
+        let mut seen_dot = false;
+        let mut seen_digit = false;
+        self.skip_sign();
+        while let Some(&byte) = self.data.get(self.position) {
+            match byte {
+                b'0'...b'9' => seen_digit = true,
+                // A second dot starts the next number, as in "0.5.5".
+                b'.' if !seen_dot => seen_dot = true,
+                _ => break,
+            }
+            self.position += 1
+        }
+        if !seen_digit {
+            self.position = start;
+            return None
+        }
+        let has_exponent = match self.data.get(self.position) {
+            Some(&b'e') | Some(&b'E') => true,
+            _ => false,
+        };
+        if has_exponent {
+            let mantissa_end = self.position;
+            self.position += 1;
+            self.skip_sign();
+            let exponent_start = self.position;
+            while let Some(&b'0'...b'9') = self.data.get(self.position) {
+                self.position += 1
+            }
+            if self.position == exponent_start {
+                self.position = mantissa_end
+            }
+        }
+        let text = String::from_utf8_lossy(&self.data[start..self.position]);
+        text.parse().ok()
+    }
+
+    fn skip_sign(&mut self) {
+        match self.data.get(self.position) {
+            Some(&b'+') | Some(&b'-') => self.position += 1,
+            _ => {}
+        }
+    }
+
+    fn point(&mut self) -> Option<Point2D<f32>> {
+        match (self.number(), self.number()) {
+            (Some(x), Some(y)) => Some(Point2D::new(x, y)),
+            _ => None,
+        }
+    }
+
+    // Arc flags are single digits that needn't be separated from what follows.
+    fn flag(&mut self) -> Option<bool> {
+        self.skip_separators();
+        let flag = match self.data.get(self.position) {
+            Some(&b'0') => false,
+            Some(&b'1') => true,
+            _ => return None,
+        };
+        self.position += 1;
+        Some(flag)
+    }
+}
+
+// Parses a `transform` attribute: a list of `matrix()`, `translate()`, `scale()`, `rotate()`,
+// `skewX()`, and `skewY()`, applied right to left.
+fn parse_transform(value: &str) -> Option<Transform2D<f32>> {
+    let mut transform = Transform2D::identity();
+    let mut rest = value;
+    while let Some(open) = rest.find('(') {
+        let close = match rest.find(')') {
+            Some(close) if close > open => close,
+            _ => return None,
+        };
+        let name = rest[..open].trim_matches(|character: char| {
+            character.is_whitespace() || character == ','
+        });
+        let arguments = parse_numbers(&rest[(open + 1)..close]);
+        let argument = |index: usize, default: f32| {
+            arguments.get(index).cloned().unwrap_or(default)
+        };
+        let function = match (name, arguments.len()) {
+            ("matrix", 6) => {
+                Transform2D::row_major(arguments[0],
+                                       arguments[1],
+                                       arguments[2],
+                                       arguments[3],
+                                       arguments[4],
+                                       arguments[5])
+            }
+            ("translate", 1) | ("translate", 2) => {
+                Transform2D::create_translation(argument(0, 0.0), argument(1, 0.0))
+            }
+            ("scale", 1) | ("scale", 2) => {
+                let x = argument(0, 1.0);
+                Transform2D::create_scale(x, argument(1, x))
+            }
+            ("rotate", 1) | ("rotate", 3) => {
+                let (cx, cy) = (argument(1, 0.0), argument(2, 0.0));
+                Transform2D::create_translation(-cx, -cy)
+                    .post_rotate(&Angle::degrees(argument(0, 0.0)))
+                    .post_translate(Vector2D::new(cx, cy))
+            }
+            ("skewX", 1) => {
+                Transform2D::row_major(1.0, 0.0, argument(0, 0.0).to_radians().tan(), 1.0, 0.0, 0.0)
+            }
+            ("skewY", 1) => {
+                Transform2D::row_major(1.0, argument(0, 0.0).to_radians().tan(), 0.0, 1.0, 0.0, 0.0)
+            }
+            _ => return None,
+        };
+        transform = function.post_mul(&transform);
+        rest = &rest[(close + 1)..];
+    }
+    Some(transform)
+}
+
+fn parse_numbers(value: &str) -> Vec<f32> {
+    let mut tokens = PathDataTokenizer::new(value);
+    let mut numbers = vec![];
+    while let Some(number) = tokens.number() {
+        numbers.push(number)
+    }
+    numbers
+}
+
+fn parse_view_box(value: &str) -> Option<Rect<f32>> {
+    let numbers = parse_numbers(value);
+    if numbers.len() != 4 {
+        return None
+    }
+    Some(Rect::new(Point2D::new(numbers[0], numbers[1]), Size2D::new(numbers[2], numbers[3])))
+}
+
+// Parses a length or percentage. Font-relative units assume 16-pixel text.
+fn parse_length(value: &str, reference: f32) -> Option<f32> {
+    let value = value.trim();
+    let split = value.find(|character: char| {
+        character.is_ascii_alphabetic() && character != 'e' && character != 'E' ||
+            character == '%'
+    }).unwrap_or(value.len());
+    let number: f32 = match value[..split].trim().parse() {
+        Ok(number) => number,
+        Err(_) => return None,
+    };
+    let factor = match &value[split..] {
+        "" | "px" => 1.0,
+        "%" => reference / 100.0,
+        "pt" => 4.0 / 3.0,
+        "pc" => 16.0,
+        "in" => 96.0,
+        "cm" => 96.0 / 2.54,
+        "mm" => 96.0 / 25.4,
+        "em" => 16.0,
+        "ex" => 8.0,
+        _ => return None,
+    };
+    Some(number * factor)
+}
+
+fn parse_paint(value: &str) -> Option<SvgPaint> {
+    let value = value.trim();
+    match value {
+        "none" => return Some(SvgPaint::None),
+        "currentColor" => return Some(SvgPaint::CurrentColor),
+        "inherit" => return None,
+        _ => {}
+    }
+    if value.starts_with("url(") {
+        let close = match value.find(')') {
+            None => return None,
+            Some(close) => close,
+        };
+        let fallback = parse_color(&value[(close + 1)..]);
+        return url_reference(&value[..(close + 1)]).map(|reference| {
+            SvgPaint::Reference(reference.to_owned(), fallback)
+        })
+    }
+    parse_color(value).map(SvgPaint::Color)
+}
+
+// Returns the `#id` inside `url(#id)`.
+fn url_reference(value: &str) -> Option<&str> {
+    let value = value.trim();
+    if value.starts_with("url(") && value.ends_with(')') {
+        Some(value[4..(value.len() - 1)].trim().trim_matches(|character| {
+            character == '\'' || character == '"'
+        }))
+    } else {
+        None
+    }
+}
+
+// Returns the ID in a same-document reference `#id`.
+fn local_reference(reference: &str) -> Option<&str> {
+    let reference = reference.trim();
+    if reference.starts_with('#') { Some(&reference[1..]) } else { None }
+}
+
+fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim();
+    if value.starts_with('#') {
+        let digits: Vec<u8> = value[1..].chars().filter_map(|digit| digit.to_digit(16))
+                                                .map(|digit| digit as u8)
+                                                .collect();
+        if digits.len() != value.len() - 1 {
+            return None
+        }
+        return match digits.len() {
+            3 => Some([digits[0] * 17, digits[1] * 17, digits[2] * 17, 255]),
+            6 => {
+                Some([
+                    digits[0] * 16 + digits[1],
+                    digits[2] * 16 + digits[3],
+                    digits[4] * 16 + digits[5],
+                    255,
+                ])
+            }
+            _ => None,
+        }
+    }
+
+    if value.starts_with("rgb(") && value.ends_with(')') {
+        let channels: Vec<_> = value[4..(value.len() - 1)].split(',').map(|channel| {
+            let channel = channel.trim();
+            parse_length(channel, 255.0).map(|channel| channel.round().max(0.0).min(255.0) as u8)
+        }).collect();
+        return match (channels.get(0), channels.get(1), channels.get(2), channels.len()) {
+            (Some(&Some(r)), Some(&Some(g)), Some(&Some(b)), 3) => Some([r, g, b, 255]),
+            _ => None,
+        }
+    }
+
+    let rgb = match &*value.to_ascii_lowercase() {
+        "black" => [0, 0, 0],
+        "white" => [255, 255, 255],
+        "red" => [255, 0, 0],
+        "lime" => [0, 255, 0],
+        "green" => [0, 128, 0],
+        "blue" => [0, 0, 255],
+        "yellow" => [255, 255, 0],
+        "cyan" | "aqua" => [0, 255, 255],
+        "magenta" | "fuchsia" => [255, 0, 255],
+        "gray" | "grey" => [128, 128, 128],
+        "silver" => [192, 192, 192],
+        "maroon" => [128, 0, 0],
+        "olive" => [128, 128, 0],
+        "navy" => [0, 0, 128],
+        "purple" => [128, 0, 128],
+        "teal" => [0, 128, 128],
+        "orange" => [255, 165, 0],
+        "transparent" => return Some([0; 4]),
+        _ => return None,
+    };
+    Some([rgb[0], rgb[1], rgb[2], 255])
+}
+
+fn apply_opacity(color: Color, opacity: f32) -> Color {
+    [color[0], color[1], color[2], (color[3] as f32 * opacity).round() as u8]
+}
+
+// Returns the bounds of the points of a path, including control points, which contain it.
+fn path_bounds(events: &[PathEvent]) -> Rect<f32> {
+    let mut points = vec![];
+    for event in events {
+        match *event {
+            PathEvent::MoveTo(to) | PathEvent::LineTo(to) => points.push(to),
+            PathEvent::QuadraticTo(ctrl, to) => points.extend_from_slice(&[ctrl, to]),
+            PathEvent::CubicTo(ctrl0, ctrl1, to) => points.extend_from_slice(&[ctrl0, ctrl1, to]),
+            PathEvent::Arc(..) | PathEvent::Close => {}
+        }
+    }
+    Rect::from_points(&points)
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::Size2D;
+
+    use CanvasRenderingContext2D;
+    use super::SvgDocument;
+
+    #[test]
+    fn exponential_use_fan_out_is_bounded() {
+        // Each level uses the next one twice, so without a budget the rectangle at the bottom
+        // would be drawn 2³⁰ times.
+        let mut document = String::from("<svg xmlns='http://www.w3.org/2000/svg' \
+                                         width='4' height='4'><defs>\
+                                         <rect id='l30' width='4' height='4' fill='red'/>");
+        for level in (0..30).rev() {
+            document.push_str(&format!("<g id='l{}'><use href='#l{}'/><use href='#l{}'/></g>",
+                                       level,
+                                       level + 1,
+                                       level + 1));
+        }
+        document.push_str("</defs><use href='#l0'/></svg>");
+
+        let document = SvgDocument::from_bytes(document.as_bytes()).unwrap();
+        let mut context = CanvasRenderingContext2D::new(&Size2D::new(4, 4));
+        document.draw(&mut context);
+        assert_eq!(&context.canvas().pixels()[0..4], &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn elements_drawn_by_id_keep_their_ancestors_styles_and_transforms() {
+        let document = SvgDocument::from_bytes(b"<svg xmlns='http://www.w3.org/2000/svg'>\
+                                                 <g fill='red' transform='translate(2, 0)'>\
+                                                 <rect id='glyph1' width='2' height='2' \
+                                                 fill='blue'/>\
+                                                 <rect id='glyph2' width='2' height='2'/>\
+                                                 </g></svg>").unwrap();
+        let mut context = CanvasRenderingContext2D::new(&Size2D::new(4, 2));
+        assert!(document.draw_element_with_id(&mut context, "glyph2"));
+        assert!(!document.draw_element_with_id(&mut context, "glyph3"));
+        assert_eq!(&context.canvas().pixels()[0..4], &[0, 0, 0, 0]);
+        assert_eq!(&context.canvas().pixels()[8..12], &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn clip_paths_are_the_union_of_their_children() {
+        // As one even-odd path, the two rectangles would leave out the pixel where they overlap.
+        let document = SvgDocument::from_bytes(b"<svg xmlns='http://www.w3.org/2000/svg' \
+                                                 width='4' height='1'>\
+                                                 <clipPath id='clip' clip-rule='evenodd'>\
+                                                 <rect width='2' height='1'/>\
+                                                 <rect x='1' width='2' height='1'/>\
+                                                 </clipPath><rect width='4' height='1' \
+                                                 fill='red' clip-path='url(#clip)'/>\
+                                                 </svg>").unwrap();
+        let mut context = CanvasRenderingContext2D::new(&Size2D::new(4, 1));
+        document.draw(&mut context);
+        assert_eq!(&context.canvas().pixels()[4..8], &[255, 0, 0, 255]);
+        assert_eq!(&context.canvas().pixels()[12..16], &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn only_references_to_clip_paths_count_as_clipping() {
+        let clipped = SvgDocument::from_bytes(b"<svg xmlns='http://www.w3.org/2000/svg'>\
+                                                <clipPath id='clip'><rect width='1' \
+                                                height='1'/></clipPath><g><rect width='2' \
+                                                height='2' clip-path='url(#clip)'/></g>\
+                                                </svg>").unwrap();
+        let unclipped = SvgDocument::from_bytes(b"<svg xmlns='http://www.w3.org/2000/svg'>\
+                                                  <rect id='rect' width='1' height='1'/>\
+                                                  <rect width='2' height='2' \
+                                                  clip-path='url(#rect)'/></svg>").unwrap();
+        assert!(clipped.uses_clip_paths());
+        assert!(!unclipped.uses_clip_paths());
+    }
+
+    #[test]
+    fn translucent_elements_use_group_opacity() {
+        let translucent = SvgDocument::from_bytes(b"<svg xmlns='http://www.w3.org/2000/svg'>\
+                                                    <g><rect width='1' height='1' \
+                                                    opacity='0.5'/></g></svg>").unwrap();
+        let opaque = SvgDocument::from_bytes(b"<svg xmlns='http://www.w3.org/2000/svg'>\
+                                               <rect width='1' height='1' opacity='1' \
+                                               fill-opacity='0.5'/></svg>").unwrap();
+        assert!(translucent.uses_group_opacity());
+        assert!(!opaque.uses_group_opacity());
+    }
+}
@@ -105,7 +105,7 @@ const MESH_PACK_FOURCC: string = 'PFMP';
 
 const MESH_FOURCC: string = 'mesh';
 
-// Must match the FourCCs in `pathfinder_partitioner::mesh_library::MeshLibrary::serialize_into()`.
+// Must match the FourCCs in `pathfinder_partitioner::mesh_pack::MeshPack::write_to()`.
 const BUFFER_TYPE_FOURCCS: BufferTypeFourCCTable = {
     bbox: 'bBoxes',
     bqii: 'bQuadVertexInteriorIndices',
@@ -114,6 +114,10 @@ const BUFFER_TYPE_FOURCCS: BufferTypeFourCCTable = {
     sseg: 'stencilSegments',
 };
 
+// Must match `pathfinder_partitioner::mesh_pack::WriteOptions::quantize_vertex_positions`.
+const QUANTIZED_BOUNDS_FOURCC: string = 'qbnd';
+const QUANTIZED_B_QUAD_VERTEX_POSITIONS_FOURCC: string = 'bqvq';
+
 const RANGE_TO_COUNT_TABLE: RangeToCountTable = {
     bBoxPathRanges: 'bBoxCount',
     bQuadVertexInteriorIndexPathRanges: 'bQuadVertexInteriorIndexCount',
@@ -217,6 +221,9 @@ export class PathfinderMesh implements MeshLike<ArrayBuffer> {
     stencilNormals!: ArrayBuffer;
 
     constructor(data: ArrayBuffer) {
+        let quantizedBounds: Float32Array | null = null;
+        let quantizedPositions: Uint16Array | null = null;
+
         let offset = 0;
         while (offset < data.byteLength) {
             const fourCC = toFourCC(data, offset);
@@ -226,10 +233,21 @@ export class PathfinderMesh implements MeshLike<ArrayBuffer> {
 
             if (BUFFER_TYPE_FOURCCS.hasOwnProperty(fourCC))
                 this[BUFFER_TYPE_FOURCCS[fourCC]] = data.slice(startOffset, endOffset);
+            else if (fourCC === QUANTIZED_BOUNDS_FOURCC)
+                quantizedBounds = new Float32Array(data.slice(startOffset, endOffset));
+            else if (fourCC === QUANTIZED_B_QUAD_VERTEX_POSITIONS_FOURCC)
+                quantizedPositions = new Uint16Array(data.slice(startOffset, endOffset));
 
             offset = endOffset;
         }
 
+        if (quantizedPositions != null && quantizedPositions.length > 0) {
+            if (quantizedBounds == null || quantizedBounds.length < 4)
+                panic("Quantized vertex positions without bounds!");
+            this.bQuadVertexPositions = dequantizePositions(quantizedPositions,
+                                                            unwrapNull(quantizedBounds));
+        }
+
         for (const type of Object.keys(BUFFER_TYPE_FOURCCS) as Array<keyof MeshLike<void>>) {
             if (this[type] == null)
                 this[type] = new ArrayBuffer(0);
@@ -370,6 +388,18 @@ function sizeOfPrimitive(primitiveType: PrimitiveType): number {
     }
 }
 
+// Expands 16-bit coordinates, alternating X and Y, that are normalized to `bounds` (origin X,
+// origin Y, width, height) back to the floating point layout of a `bqvp` chunk. Quantization
+// only shrinks the file; the GPU gets the same floating point positions either way.
+function dequantizePositions(positions: Uint16Array, bounds: Float32Array): ArrayBuffer {
+    const result = new Float32Array(positions.length);
+    for (let index = 0; index < positions.length; index++) {
+        const axis = index % 2;
+        result[index] = bounds[axis] + positions[index] / 0xffff * bounds[axis + 2];
+    }
+    return result.buffer as ArrayBuffer;
+}
+
 function toFourCC(buffer: ArrayBuffer, position: number): string {
     let result = "";
     const bytes = new Uint8Array(buffer, position, 4);
@@ -498,7 +498,9 @@ class ReferenceTestAppController extends DemoAppController<ReferenceTestView> {
     }
 
     private loadTextRendering(): void {
-        this.glyphStore.partition().then(result => {
+        const font = unwrapNull(this.font);
+        const pixelsPerUnit = this.currentFontSize / font.opentypeFont.unitsPerEm;
+        this.glyphStore.partition(pixelsPerUnit).then(result => {
             const textRun = unwrapNull(this.textRun);
 
             this.baseMeshes = result.meshes;
@@ -73,6 +73,10 @@ And the mome raths outgrabe.`;
 
 const INITIAL_FONT_SIZE: number = 72.0;
 
+/// How far the font size may drift from the size the glyphs were partitioned for, as a ratio,
+/// before they're partitioned again so that curves stay smooth.
+const REPARTITION_SCALE_RATIO: number = 2.0;
+
 const DEFAULT_FONT: string = 'open-sans';
 
 const B_POSITION_SIZE: number = 8;
@@ -138,6 +142,8 @@ class TextDemoController extends DemoAppController<TextDemoView> {
 
     private _fontSize!: number;
     private _rotationAngle!: number;
+    private partitionedPixelsPerUnit: number | null = null;
+    private repartitioning: boolean = false;
     private _emboldenAmount!: number;
 
     private text: string;
@@ -265,7 +271,8 @@ class TextDemoController extends DemoAppController<TextDemoView> {
         uniqueGlyphIDs = _.sortedUniq(uniqueGlyphIDs);
 
         const glyphStore = new GlyphStore(font, uniqueGlyphIDs);
-        glyphStore.partition().then(result => {
+        const pixelsPerUnit = this._fontSize / font.opentypeFont.unitsPerEm;
+        glyphStore.partition(pixelsPerUnit).then(result => {
             const meshes = this.expandMeshes(result.meshes, uniqueGlyphIDs.length);
 
             this.view.then(view => {
@@ -273,6 +280,7 @@ class TextDemoController extends DemoAppController<TextDemoView> {
                 this.layout = newLayout;
                 this.glyphStore = glyphStore;
                 this.meshes = meshes;
+                this.partitionedPixelsPerUnit = pixelsPerUnit;
 
                 view.attachText();
                 view.attachMeshes([this.meshes]);
@@ -280,6 +288,34 @@ class TextDemoController extends DemoAppController<TextDemoView> {
         });
     }
 
+    /// Partitions the glyphs again if the font size has moved too far from the size they were
+    /// partitioned for, since the server approximates curves to a tolerance in device pixels.
+    private repartitionIfNeeded(): void {
+        if (this.partitionedPixelsPerUnit == null || this.repartitioning)
+            return;
+        const ratio = this.pixelsPerUnit / this.partitionedPixelsPerUnit;
+        if (ratio < REPARTITION_SCALE_RATIO && ratio > 1.0 / REPARTITION_SCALE_RATIO)
+            return;
+
+        const glyphStore = this.glyphStore;
+        const pixelsPerUnit = this.pixelsPerUnit;
+        this.repartitioning = true;
+        glyphStore.partition(pixelsPerUnit).then(result => {
+            this.repartitioning = false;
+
+            // Drop the result if the text changed in the meantime.
+            if (this.glyphStore !== glyphStore)
+                return;
+
+            this.partitionedPixelsPerUnit = pixelsPerUnit;
+            this.meshes = this.expandMeshes(result.meshes, glyphStore.glyphIDs.length);
+            this.view.then(view => view.attachMeshes([this.meshes]));
+
+            // The user may have kept zooming while the request was in flight.
+            this.repartitionIfNeeded();
+        });
+    }
+
     private expandMeshes(meshes: PathfinderMeshPack, glyphCount: number): PathfinderPackedMeshes {
         const pathIDs = [];
         for (let glyphIndex = 0; glyphIndex < glyphCount; glyphIndex++) {
@@ -302,6 +338,7 @@ class TextDemoController extends DemoAppController<TextDemoView> {
     set fontSize(newFontSize: number) {
         this._fontSize = newFontSize;
         this.view.then(view => view.renderer.relayoutText());
+        this.repartitionIfNeeded();
     }
 
     get rotationAngle(): number {
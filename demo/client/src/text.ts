@@ -271,7 +271,9 @@ export class GlyphStore {
         this.glyphIDs = glyphIDs;
     }
 
-    partition(): Promise<PartitionResult> {
+    /// `pixelsPerUnit`, if given, is the scale the glyphs will be drawn at, which the server
+    /// approximates curves for.
+    partition(pixelsPerUnit?: number | undefined): Promise<PartitionResult> {
         // Build the partitioning request to the server.
         let fontFace;
         if (this.font.builtinFontName != null)
@@ -279,12 +281,14 @@ export class GlyphStore {
         else
             fontFace = { Custom: base64js.fromByteArray(new Uint8Array(this.font.data)) };
 
-        const request = {
+        const request: any = {
             face: fontFace,
             fontIndex: 0,
             glyphs: this.glyphIDs.map(id => ({ id: id, transform: [1, 0, 0, 1, 0, 0] })),
             pointSize: this.font.opentypeFont.unitsPerEm,
         };
+        if (pixelsPerUnit != null)
+            request.deviceTransform = [pixelsPerUnit, 0, 0, pixelsPerUnit, 0, 0];
 
         // Make the request.
         let time = 0;
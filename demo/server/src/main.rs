@@ -21,6 +21,7 @@ extern crate image;
 extern crate lru_cache;
 extern crate lyon_geom;
 extern crate lyon_path;
+extern crate pathfinder_font_utils;
 extern crate pathfinder_partitioner;
 extern crate pathfinder_path_utils;
 extern crate rocket;
@@ -47,12 +48,15 @@ use lru_cache::LruCache;
 use lyon_path::PathEvent;
 use lyon_path::builder::{FlatPathBuilder, PathBuilder};
 use lyon_path::iterator::PathIter;
+use pathfinder_font_utils::hinting::HintingLevel;
+use pathfinder_font_utils::synthesis;
 use pathfinder_partitioner::FillRule;
+use pathfinder_partitioner::batch;
 use pathfinder_partitioner::mesh_pack::MeshPack;
-use pathfinder_partitioner::partitioner::Partitioner;
 use pathfinder_path_utils::cubic_to_quadratic::CubicToQuadraticTransformer;
-use pathfinder_path_utils::stroke::{StrokeStyle, StrokeToFillIter};
-use pathfinder_path_utils::transform::Transform2DPathIter;
+use pathfinder_path_utils::embolden;
+use pathfinder_path_utils::stroke::{DEFAULT_MITER_LIMIT, LineJoin, StrokeStyle, StrokeToFillIter};
+use pathfinder_path_utils::transform::{self, Transform2DPathIter};
 use rocket::http::{ContentType, Header, Status};
 use rocket::request::Request;
 use rocket::response::{NamedFile, Redirect, Responder, Response};
@@ -75,7 +79,12 @@ const SUGGESTED_JSON_SIZE_LIMIT: u64 = 32 * 1024 * 1024;
 
 const MESH_PACK_CACHE_SIZE: usize = 16;
 
-const CUBIC_TO_QUADRATIC_APPROX_TOLERANCE: f32 = 5.0;
+/// The maximum error, in device pixels, allowed when approximating curves.
+const DEVICE_APPROX_TOLERANCE: f32 = 0.25;
+
+/// The size, in device pixels, that an SVG view box is assumed to be drawn at if the client
+/// doesn't send a device transform.
+const DEFAULT_SVG_DEVICE_SIZE: f32 = 1000.0;
 
 lazy_static! {
     static ref MESH_PACK_CACHE: Mutex<LruCache<MeshPackCacheKey, PartitionResponder>> = {
@@ -124,6 +133,14 @@ static BUILTIN_SVGS: [(&'static str, &'static str); 4] = [
 struct MeshPackCacheKey {
     builtin_font_name: String,
     glyph_ids: Vec<u32>,
+    /// The bits of the point size and device transform, since the approximation tolerance
+    /// depends on them.
+    point_size_bits: u64,
+    device_transform_bits: Option<Vec<u32>>,
+    hinting: HintingLevel,
+    embolden_bits: u32,
+    stroke: Option<(u32, PartitionLineJoin)>,
+    oblique_angle_bits: u32,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -134,6 +151,82 @@ struct PartitionFontRequest {
     glyphs: Vec<PartitionGlyph>,
     #[serde(rename = "pointSize")]
     point_size: f64,
+    /// How much to hint the glyphs for `point_size`. Defaults to no hinting.
+    #[serde(default)]
+    hinting: PartitionFontHinting,
+    /// How far to move the glyphs' edges outward to embolden them, in pixels. Defaults to zero.
+    #[serde(default)]
+    embolden: f32,
+    /// If present, the glyph outlines are stroked instead of filled.
+    #[serde(default)]
+    stroke: Option<PartitionFontStroke>,
+    /// The angle to slant the glyphs by, in degrees, for a synthetic oblique. Defaults to zero.
+    #[serde(rename = "obliqueAngle", default)]
+    oblique_angle: f32,
+    /// The transform from the transformed glyphs to device pixels, which sets how finely curves
+    /// are approximated. Defaults to the scale from font units to `point_size`.
+    #[serde(rename = "deviceTransform", default)]
+    device_transform: Option<Transform2D<f32>>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct PartitionFontStroke {
+    /// The stroke width, in pixels.
+    width: f32,
+    #[serde(default)]
+    join: PartitionLineJoin,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+enum PartitionLineJoin {
+    #[serde(rename = "bevel")]
+    Bevel,
+    #[serde(rename = "miter")]
+    Miter,
+    #[serde(rename = "round")]
+    Round,
+}
+
+impl Default for PartitionLineJoin {
+    fn default() -> PartitionLineJoin {
+        PartitionLineJoin::Bevel
+    }
+}
+
+impl PartitionLineJoin {
+    fn to_line_join(self) -> LineJoin {
+        match self {
+            PartitionLineJoin::Bevel => LineJoin::Bevel,
+            PartitionLineJoin::Miter => LineJoin::Miter(DEFAULT_MITER_LIMIT),
+            PartitionLineJoin::Round => LineJoin::Round,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+enum PartitionFontHinting {
+    #[serde(rename = "none")]
+    None,
+    #[serde(rename = "vertical")]
+    Vertical,
+    #[serde(rename = "full")]
+    Full,
+}
+
+impl Default for PartitionFontHinting {
+    fn default() -> PartitionFontHinting {
+        PartitionFontHinting::None
+    }
+}
+
+impl PartitionFontHinting {
+    fn to_hinting_level(self) -> HintingLevel {
+        match self {
+            PartitionFontHinting::None => HintingLevel::None,
+            PartitionFontHinting::Vertical => HintingLevel::Vertical,
+            PartitionFontHinting::Full => HintingLevel::Full,
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -222,6 +315,10 @@ struct PartitionSvgPathsRequest {
     view_box_width: f32,
     #[serde(rename = "viewBoxHeight")]
     view_box_height: f32,
+    /// The transform from the view box to device pixels, which sets how finely curves are
+    /// approximated. Defaults to fitting the view box into `DEFAULT_SVG_DEVICE_SIZE` pixels.
+    #[serde(rename = "deviceTransform", default)]
+    device_transform: Option<Transform2D<f32>>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -255,6 +352,9 @@ impl PartitionSvgFillRule {
 struct PathDescriptor {
     path_index: usize,
     fill_rule: FillRule,
+    /// The tolerance, in path units, for approximating curves, both when partitioning and when
+    /// building stencil segments.
+    approx_tolerance: f32,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -270,36 +370,26 @@ struct PathPartitioningResult {
 }
 
 impl PathPartitioningResult {
-    fn compute(pack: &mut MeshPack,
-               path_descriptors: &[PathDescriptor],
-               paths: &[Vec<PathEvent>],
-               approx_tolerance: Option<f32>)
+    fn compute(pack: &mut MeshPack, path_descriptors: &[PathDescriptor], paths: &[Vec<PathEvent>])
                -> PathPartitioningResult {
         let timestamp_before = Instant::now();
 
-        for (path, path_descriptor) in paths.iter().zip(path_descriptors.iter()) {
-            let mut partitioner = Partitioner::new();
-            if let Some(tolerance) = approx_tolerance {
-                partitioner.builder_mut().set_approx_tolerance(tolerance);
-            }
-
-            path.iter().for_each(|event| partitioner.builder_mut().path_event(*event));
-            partitioner.partition(path_descriptor.fill_rule);
-            partitioner.builder_mut().build_and_reset();
-
-            partitioner.mesh_mut().push_stencil_segments(
-                CubicToQuadraticTransformer::new(path.iter().cloned(),
-                                                 CUBIC_TO_QUADRATIC_APPROX_TOLERANCE));
-            partitioner.mesh_mut().push_stencil_normals(
-                CubicToQuadraticTransformer::new(path.iter().cloned(),
-                                                 CUBIC_TO_QUADRATIC_APPROX_TOLERANCE));
-            pack.push(partitioner.into_mesh());
-        }
+        let jobs: Vec<_> = paths.iter().zip(path_descriptors.iter()).collect();
+        let new_pack = batch::build_mesh_pack(&jobs, |&(path, path_descriptor)| {
+            let tolerance = path_descriptor.approx_tolerance;
+            let mut mesh = batch::partition_path(path, path_descriptor.fill_rule, Some(tolerance));
+            mesh.push_stencil_segments(CubicToQuadraticTransformer::new(path.iter().cloned(),
+                                                                        tolerance));
+            mesh.push_stencil_normals(CubicToQuadraticTransformer::new(path.iter().cloned(),
+                                                                       tolerance));
+            mesh
+        });
+        pack.meshes.extend(new_pack.meshes);
 
         let time_elapsed = timestamp_before.elapsed();
 
         let mut data_buffer = Cursor::new(vec![]);
-        drop(pack.serialize_into(&mut data_buffer));
+        drop(pack.write_to(&mut data_buffer));
 
         PathPartitioningResult {
             encoded_data: Arc::new(data_buffer.into_inner()),
@@ -405,6 +495,14 @@ fn partition_font(request: Json<PartitionFontRequest>) -> Result<PartitionRespon
             Some(MeshPackCacheKey {
                 builtin_font_name: (*builtin_font_name).clone(),
                 glyph_ids: request.glyphs.iter().map(|glyph| glyph.id).collect(),
+                point_size_bits: request.point_size.to_bits(),
+                device_transform_bits: request.device_transform.map(|transform| {
+                    transform.to_row_major_array().iter().map(|value| value.to_bits()).collect()
+                }),
+                hinting: request.hinting.to_hinting_level(),
+                embolden_bits: request.embolden.to_bits(),
+                stroke: request.stroke.map(|stroke| (stroke.width.to_bits(), stroke.join)),
+                oblique_angle_bits: request.oblique_angle.to_bits(),
             })
         }
         _ => None,
@@ -425,6 +523,27 @@ fn partition_font(request: Json<PartitionFontRequest>) -> Result<PartitionRespon
         Err(_) => return Err(FontError::FontLoadingFailed),
     };
 
+    // The glyph outlines are partitioned after their transforms are applied, so the only
+    // transform left between them and device space is the device transform.
+    let units_per_em = font.metrics().units_per_em as f32;
+    let device_transform = request.device_transform.unwrap_or_else(|| {
+        Transform2D::create_scale(request.point_size as f32 / units_per_em,
+                                  request.point_size as f32 / units_per_em)
+    });
+    let approx_tolerance = transform::local_tolerance(DEVICE_APPROX_TOLERANCE, &device_transform);
+    let oblique_transform = synthesis::oblique_transform(request.oblique_angle);
+    let hinting_options = request.hinting
+                                 .to_hinting_level()
+                                 .to_hinting_options(request.point_size as f32);
+
+    // Emboldening, stroking, and slanting happen in font units, before the glyph transforms.
+    let font_units_per_pixel = units_per_em / request.point_size as f32;
+    let stroke_style = request.stroke.map(|stroke| {
+        let mut stroke_style = StrokeStyle::new(stroke.width * font_units_per_pixel);
+        stroke_style.join = stroke.join.to_line_join();
+        stroke_style
+    });
+
     // Read glyph info.
     let mut paths: Vec<Vec<PathEvent>> = vec![];
     let mut path_descriptors = vec![];
@@ -433,10 +552,26 @@ fn partition_font(request: Json<PartitionFontRequest>) -> Result<PartitionRespon
         // This might fail; if so, just leave it blank.
         // FIXME(pcwalton): Should we add first-class support for transforms to `font-kit`?
         let mut path_builder = lyon_path::default::Path::builder();
-        match font.outline(glyph.id, HintingOptions::None, &mut path_builder) {
+        match font.outline(glyph.id, hinting_options, &mut path_builder) {
             Ok(()) => {
-                paths.push(Transform2DPathIter::new(path_builder.build().into_iter(),
-                                                    &glyph.transform).collect())
+                let mut path: Vec<PathEvent> = path_builder.build().into_iter().collect();
+                if request.embolden != 0.0 {
+                    // Emboldening approximates arcs in font units, before every transform.
+                    let transforms = [oblique_transform, glyph.transform, device_transform];
+                    let tolerance = transform::local_tolerance_for_chain(DEVICE_APPROX_TOLERANCE,
+                                                                         &transforms);
+                    path = embolden::embolden_path(path.into_iter(),
+                                                   request.embolden * font_units_per_pixel,
+                                                   tolerance);
+                }
+                if let Some(stroke_style) = stroke_style {
+                    path = StrokeToFillIter::new(PathIter::new(path.into_iter()),
+                                                 stroke_style).collect();
+                }
+                if request.oblique_angle != 0.0 {
+                    path = Transform2DPathIter::new(path.into_iter(), &oblique_transform).collect();
+                }
+                paths.push(Transform2DPathIter::new(path.into_iter(), &glyph.transform).collect())
             }
             Err(_) => paths.push(vec![]),
         };
@@ -444,6 +579,7 @@ fn partition_font(request: Json<PartitionFontRequest>) -> Result<PartitionRespon
         path_descriptors.push(PathDescriptor {
             path_index: glyph_index,
             fill_rule: FillRule::Winding,
+            approx_tolerance: approx_tolerance,
         })
     }
 
@@ -451,8 +587,7 @@ fn partition_font(request: Json<PartitionFontRequest>) -> Result<PartitionRespon
     let mut pack = MeshPack::new();
     let path_partitioning_result = PathPartitioningResult::compute(&mut pack,
                                                                    &path_descriptors,
-                                                                   &paths,
-                                                                   None);
+                                                                   &paths);
 
     // Build the response.
     let elapsed_ms = path_partitioning_result.elapsed_ms();
@@ -482,6 +617,14 @@ fn partition_svg_paths(request: Json<PartitionSvgPathsRequest>)
     let mut pack = MeshPack::new();
     let mut path_index = 0;
 
+    // Compute approximation tolerance.
+    let device_transform = request.device_transform.unwrap_or_else(|| {
+        let view_box_size = f32::max(request.view_box_width, request.view_box_height);
+        Transform2D::create_scale(DEFAULT_SVG_DEVICE_SIZE / view_box_size,
+                                  DEFAULT_SVG_DEVICE_SIZE / view_box_size)
+    });
+    let tolerance = transform::local_tolerance(DEVICE_APPROX_TOLERANCE, &device_transform);
+
     for path in &request.paths {
         let mut stream = vec![];
 
@@ -516,6 +659,7 @@ fn partition_svg_paths(request: Json<PartitionSvgPathsRequest>)
         path_descriptors.push(PathDescriptor {
             path_index: path_index,
             fill_rule: fill_rule,
+            approx_tolerance: tolerance,
         });
 
         match path.kind {
@@ -531,14 +675,10 @@ fn partition_svg_paths(request: Json<PartitionSvgPathsRequest>)
         path_index += 1;
     }
 
-    // Compute approximation tolerance.
-    let tolerance = f32::max(request.view_box_width, request.view_box_height) * 0.001;
-
     // Partition the paths.
     let path_partitioning_result = PathPartitioningResult::compute(&mut pack,
                                                                    &path_descriptors,
-                                                                   &paths,
-                                                                   Some(tolerance));
+                                                                   &paths);
 
     // Return the response.
     let elapsed_ms = path_partitioning_result.elapsed_ms();
@@ -0,0 +1,134 @@
+// pathfinder/font-utils/src/bidi.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Shapes text that mixes left-to-right and right-to-left scripts, using the Unicode
+//! bidirectional algorithm (UAX #9).
+//!
+//! The text is split into runs of one embedding level, and each run is shaped in its own
+//! direction. `layout::layout_bidi_paragraph()` then breaks the runs into lines and reorders each
+//! line for display.
+//!
+//! This module is only available with the `shaping` feature.
+
+use std::ops::Range;
+use unicode_bidi::{BidiInfo, Level};
+
+use shaping::{self, ShapingOptions, TextDirection, TextRun};
+
+/// A run of text at one embedding level, shaped in its direction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BidiRun {
+    /// The byte range of the run in the text.
+    pub text_range: Range<usize>,
+    /// The embedding level. Odd levels are right to left.
+    pub level: u8,
+    /// The shaped glyphs, in visual order. Their clusters are byte offsets into the whole text,
+    /// and their positions are relative to the start of the run.
+    pub run: TextRun,
+}
+
+impl BidiRun {
+    #[inline]
+    pub fn direction(&self) -> TextDirection {
+        if self.level % 2 == 1 {
+            TextDirection::RightToLeft
+        } else {
+            TextDirection::LeftToRight
+        }
+    }
+}
+
+/// Text split into directional runs and shaped.
+pub struct BidiText<'a> {
+    info: BidiInfo<'a>,
+    /// The runs, in logical order.
+    pub runs: Vec<BidiRun>,
+}
+
+impl<'a> BidiText<'a> {
+    #[inline]
+    pub fn text(&self) -> &'a str {
+        self.info.text
+    }
+
+    /// Returns true if the first paragraph of the text runs right to left.
+    #[inline]
+    pub fn is_rtl(&self) -> bool {
+        self.info.paragraphs.first().map_or(false, |paragraph| paragraph.level.is_rtl())
+    }
+
+    /// Returns the byte ranges of the directional runs on a line, in the order they should be
+    /// displayed from left to right. The line must lie within one paragraph.
+    pub fn visual_ranges(&self, line: Range<usize>) -> Vec<Range<usize>> {
+        let paragraph = match self.info.paragraphs.iter().find(|paragraph| {
+            paragraph.range.start <= line.start && line.start < paragraph.range.end
+        }) {
+            None => return vec![line],
+            Some(paragraph) => paragraph,
+        };
+        let line_end = line.end.min(paragraph.range.end);
+        self.info.visual_runs(paragraph, line.start..line_end).1
+    }
+}
+
+/// Splits `text` into directional runs and shapes each one, as `shaping::shape()` does.
+///
+/// The paragraph direction is taken from `base_direction` if it is given, and from the first
+/// strong character of each paragraph otherwise. The `direction` in `options` is ignored.
+pub fn shape_bidi_text<'a>(font_data: &[u8],
+                           font_index: u32,
+                           text: &'a str,
+                           point_size: f32,
+                           options: &ShapingOptions,
+                           base_direction: Option<TextDirection>)
+                           -> BidiText<'a> {
+    let base_level = base_direction.map(|direction| {
+        match direction {
+            TextDirection::RightToLeft | TextDirection::BottomToTop => Level::rtl(),
+            TextDirection::LeftToRight | TextDirection::TopToBottom => Level::ltr(),
+        }
+    });
+    let info = BidiInfo::new(text, base_level);
+
+    let mut runs = vec![];
+    let mut run_start = 0;
+    while run_start < text.len() {
+        let level = info.levels[run_start];
+        let run_end = (run_start..text.len()).find(|&index| info.levels[index] != level)
+                                             .unwrap_or(text.len());
+
+        let mut run_options = options.clone();
+        run_options.direction = Some(if level.is_rtl() {
+            TextDirection::RightToLeft
+        } else {
+            TextDirection::LeftToRight
+        });
+        let mut run = shaping::shape(font_data,
+                                     font_index,
+                                     &text[run_start..run_end],
+                                     point_size,
+                                     &run_options);
+        for glyph in &mut run.glyphs {
+            glyph.cluster += run_start as u32
+        }
+
+        runs.push(BidiRun {
+            text_range: run_start..run_end,
+            level: level.number(),
+            run: run,
+        });
+        run_start = run_end;
+    }
+
+    BidiText {
+        info: info,
+        runs: runs,
+    }
+}
@@ -0,0 +1,364 @@
+// pathfinder/font-utils/src/bitmap.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Embedded PNG bitmap glyphs from the `CBLC`/`CBDT` and `sbix` tables, as used by bitmap emoji
+//! fonts.
+//!
+//! A font stores its bitmaps in *strikes*, each drawn for one pixels-per-em size. To draw a
+//! bitmap glyph, pick the strike nearest the requested size, reserve `size` pixels for it in the
+//! glyph atlas (for example with `pathfinder_gfx_utils::ShelfBinPacker`), decode the PNG into that
+//! space, and draw it scaled by `scale()` at `origin`, just as a vector glyph's atlas entry would
+//! be drawn. `pathfinder_canvas` instead fills the glyph's rectangle with the image as a pattern.
+
+use euclid::{Point2D, Size2D};
+use std::i32;
+use std::io::{self, ErrorKind};
+
+use sfnt::{self, FontData};
+
+const BITMAP_SIZE_RECORD_SIZE: usize = 48;
+const INDEX_SUBTABLE_RECORD_SIZE: usize = 8;
+const SBIX_GLYPH_HEADER_SIZE: usize = 8;
+
+// `CBDT` image formats: PNG data with small metrics, big metrics, or metrics in `CBLC`.
+const IMAGE_FORMAT_PNG_SMALL_METRICS: u16 = 17;
+const IMAGE_FORMAT_PNG_BIG_METRICS: u16 = 18;
+const IMAGE_FORMAT_PNG: u16 = 19;
+
+// `sbix` graphic types.
+const GRAPHIC_TYPE_PNG: u32 = 0x706e6720;
+const GRAPHIC_TYPE_DUPE: u32 = 0x64757065;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// One bitmap glyph image.
+#[derive(Clone, Copy, Debug)]
+pub struct BitmapGlyph<'a> {
+    /// The PNG image data.
+    pub data: &'a [u8],
+    /// The pixels-per-em size of the strike that the image belongs to.
+    pub ppem: u16,
+    /// The position of the upper left corner of the image relative to the glyph origin, in
+    /// strike pixels, with Y pointing up.
+    pub origin: Point2D<i32>,
+    /// The size of the image, in strike pixels.
+    pub size: Size2D<i32>,
+}
+
+impl<'a> BitmapGlyph<'a> {
+    /// Returns the factor to scale the image by to draw it at `ppem` pixels per em.
+    #[inline]
+    pub fn scale(&self, ppem: f32) -> f32 {
+        ppem / self.ppem as f32
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Strike {
+    ppem: u16,
+    // `CBLC`: the offset of the strike's `BitmapSize` record. `sbix`: the offset of the strike.
+    offset: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Source<'a> {
+    Cbdt { cblc: &'a [u8], cbdt: &'a [u8] },
+    Sbix { sbix: &'a [u8], glyph_count: u32 },
+}
+
+/// The bitmap strikes of a font.
+#[derive(Clone, Debug)]
+pub struct BitmapGlyphs<'a> {
+    source: Source<'a>,
+    strikes: Vec<Strike>,
+}
+
+impl<'a> BitmapGlyphs<'a> {
+    /// Reads the `CBLC`/`CBDT` tables, or the `sbix` table if those are absent. Returns
+    /// `Ok(None)` if the font has no bitmap glyphs.
+    pub fn from_font_data(font_data: &FontData<'a>) -> io::Result<Option<BitmapGlyphs<'a>>> {
+        if let (Some(cblc), Some(cbdt)) = (font_data.table(sfnt::CBLC),
+                                           font_data.table(sfnt::CBDT)) {
+            let strike_count = try!(sfnt::read_u32(cblc, 4)) as usize;
+            let mut strikes = Vec::with_capacity(strike_count);
+            for strike_index in 0..strike_count {
+                let offset = 8 + strike_index * BITMAP_SIZE_RECORD_SIZE;
+                let ppem = match cblc.get(offset + 44) {
+                    None => return Err(io::Error::from(ErrorKind::UnexpectedEof)),
+                    Some(&ppem) => ppem as u16,
+                };
+                strikes.push(Strike {
+                    ppem: ppem,
+                    offset: offset,
+                })
+            }
+            return Ok(Some(BitmapGlyphs {
+                source: Source::Cbdt {
+                    cblc: cblc,
+                    cbdt: cbdt,
+                },
+                strikes: strikes,
+            }))
+        }
+
+        if let (Some(sbix), Some(maxp)) = (font_data.table(sfnt::SBIX),
+                                          font_data.table(sfnt::MAXP)) {
+            let glyph_count = try!(sfnt::read_u16(maxp, 4)) as u32;
+            let strike_count = try!(sfnt::read_u32(sbix, 4)) as usize;
+            let mut strikes = Vec::with_capacity(strike_count);
+            for strike_index in 0..strike_count {
+                let offset = try!(sfnt::read_u32(sbix, 8 + strike_index * 4)) as usize;
+                strikes.push(Strike {
+                    ppem: try!(sfnt::read_u16(sbix, offset)),
+                    offset: offset,
+                })
+            }
+            return Ok(Some(BitmapGlyphs {
+                source: Source::Sbix {
+                    sbix: sbix,
+                    glyph_count: glyph_count,
+                },
+                strikes: strikes,
+            }))
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the pixels-per-em sizes of the strikes, in the order the font lists them.
+    pub fn strike_ppems(&self) -> Vec<u16> {
+        self.strikes.iter().map(|strike| strike.ppem).collect()
+    }
+
+    /// Returns the glyph's image from the strike nearest to `ppem`, or `Ok(None)` if that strike
+    /// has no PNG image for it, in which case it should be drawn from its outline.
+    ///
+    /// When two strikes are equally near, the larger one is used, since scaling down looks
+    /// better than scaling up.
+    pub fn glyph(&self, glyph_id: u32, ppem: f32) -> io::Result<Option<BitmapGlyph<'a>>> {
+        let strike = match self.nearest_strike(ppem) {
+            None => return Ok(None),
+            Some(strike) => strike,
+        };
+        match self.source {
+            Source::Cbdt { cblc, cbdt } => cbdt_glyph(cblc, cbdt, &strike, glyph_id),
+            Source::Sbix { sbix, glyph_count } => {
+                sbix_glyph(sbix, glyph_count, &strike, glyph_id, true)
+            }
+        }
+    }
+
+    fn nearest_strike(&self, ppem: f32) -> Option<Strike> {
+        let mut nearest: Option<Strike> = None;
+        for strike in &self.strikes {
+            if let Some(current) = nearest {
+                let distance = (strike.ppem as f32 - ppem).abs();
+                let current_distance = (current.ppem as f32 - ppem).abs();
+                if distance > current_distance ||
+                        (distance == current_distance && strike.ppem < current.ppem) {
+                    continue
+                }
+            }
+            nearest = Some(*strike)
+        }
+        nearest
+    }
+}
+
+fn cbdt_glyph<'a>(cblc: &'a [u8], cbdt: &'a [u8], strike: &Strike, glyph_id: u32)
+                  -> io::Result<Option<BitmapGlyph<'a>>> {
+    let subtables_offset = try!(sfnt::read_u32(cblc, strike.offset)) as usize;
+    let subtable_count = try!(sfnt::read_u32(cblc, strike.offset + 8)) as usize;
+
+    for subtable_index in 0..subtable_count {
+        let record_offset = subtables_offset + subtable_index * INDEX_SUBTABLE_RECORD_SIZE;
+        let first_glyph_id = try!(sfnt::read_u16(cblc, record_offset)) as u32;
+        let last_glyph_id = try!(sfnt::read_u16(cblc, record_offset + 2)) as u32;
+        if glyph_id < first_glyph_id || glyph_id > last_glyph_id {
+            continue
+        }
+
+        let subtable_offset = subtables_offset +
+            try!(sfnt::read_u32(cblc, record_offset + 4)) as usize;
+        let index_format = try!(sfnt::read_u16(cblc, subtable_offset));
+        let image_format = try!(sfnt::read_u16(cblc, subtable_offset + 2));
+        let image_data_offset = try!(sfnt::read_u32(cblc, subtable_offset + 4)) as usize;
+        let body_offset = subtable_offset + 8;
+        let glyph_index = (glyph_id - first_glyph_id) as usize;
+
+        // Find the glyph's image, and its metrics if this subtable holds them.
+        let (image_range, subtable_metrics) = match index_format {
+            1 => {
+                let start = try!(sfnt::read_u32(cblc, body_offset + glyph_index * 4));
+                let end = try!(sfnt::read_u32(cblc, body_offset + glyph_index * 4 + 4));
+                ((start as usize, end as usize), None)
+            }
+            3 => {
+                let start = try!(sfnt::read_u16(cblc, body_offset + glyph_index * 2));
+                let end = try!(sfnt::read_u16(cblc, body_offset + glyph_index * 2 + 2));
+                ((start as usize, end as usize), None)
+            }
+            2 => {
+                let image_size = try!(sfnt::read_u32(cblc, body_offset)) as usize;
+                let metrics = try!(read_big_metrics(cblc, body_offset + 4));
+                ((glyph_index * image_size, (glyph_index + 1) * image_size), Some(metrics))
+            }
+            4 => {
+                let pair_count = try!(sfnt::read_u32(cblc, body_offset)) as usize;
+                let mut image_range = None;
+                for pair_index in 0..pair_count {
+                    let pair_offset = body_offset + 4 + pair_index * 4;
+                    if try!(sfnt::read_u16(cblc, pair_offset)) as u32 == glyph_id {
+                        let start = try!(sfnt::read_u16(cblc, pair_offset + 2)) as usize;
+                        let end = try!(sfnt::read_u16(cblc, pair_offset + 6)) as usize;
+                        image_range = Some((start, end));
+                        break
+                    }
+                }
+                match image_range {
+                    None => return Ok(None),
+                    Some(image_range) => (image_range, None),
+                }
+            }
+            5 => {
+                let image_size = try!(sfnt::read_u32(cblc, body_offset)) as usize;
+                let metrics = try!(read_big_metrics(cblc, body_offset + 4));
+                let glyph_id_count = try!(sfnt::read_u32(cblc, body_offset + 12)) as usize;
+                let mut image_index = None;
+                for glyph_id_index in 0..glyph_id_count {
+                    let offset = body_offset + 16 + glyph_id_index * 2;
+                    if try!(sfnt::read_u16(cblc, offset)) as u32 == glyph_id {
+                        image_index = Some(glyph_id_index);
+                        break
+                    }
+                }
+                match image_index {
+                    None => return Ok(None),
+                    Some(index) => ((index * image_size, (index + 1) * image_size), Some(metrics)),
+                }
+            }
+            _ => return Ok(None),
+        };
+
+        let (start, end) = (image_data_offset + image_range.0, image_data_offset + image_range.1);
+        let image = match cbdt.get(start..end) {
+            None => return Err(io::Error::from(ErrorKind::InvalidData)),
+            Some(image) => image,
+        };
+
+        let (metrics, data_offset) = match image_format {
+            IMAGE_FORMAT_PNG_SMALL_METRICS => (try!(read_small_metrics(image, 0)), 5),
+            IMAGE_FORMAT_PNG_BIG_METRICS => (try!(read_big_metrics(image, 0)), 8),
+            IMAGE_FORMAT_PNG => {
+                match subtable_metrics {
+                    None => return Err(io::Error::from(ErrorKind::InvalidData)),
+                    Some(metrics) => (metrics, 0),
+                }
+            }
+            _ => return Ok(None),
+        };
+
+        let data_length = try!(sfnt::read_u32(image, data_offset)) as usize;
+        let data = match image.get((data_offset + 4)..(data_offset + 4 + data_length)) {
+            None => return Err(io::Error::from(ErrorKind::InvalidData)),
+            Some(data) => data,
+        };
+
+        return Ok(Some(BitmapGlyph {
+            data: data,
+            ppem: strike.ppem,
+            origin: metrics.0,
+            size: metrics.1,
+        }))
+    }
+
+    return Ok(None);
+
+    // Returns the bearing and size from a `SmallGlyphMetrics` record.
+    fn read_small_metrics(data: &[u8], offset: usize)
+                          -> io::Result<(Point2D<i32>, Size2D<i32>)> {
+        match data.get(offset..(offset + 5)) {
+            None => Err(io::Error::from(ErrorKind::UnexpectedEof)),
+            Some(metrics) => {
+                Ok((Point2D::new(metrics[2] as i8 as i32, metrics[3] as i8 as i32),
+                    Size2D::new(metrics[1] as i32, metrics[0] as i32)))
+            }
+        }
+    }
+
+    // Returns the horizontal bearing and size from a `BigGlyphMetrics` record.
+    fn read_big_metrics(data: &[u8], offset: usize) -> io::Result<(Point2D<i32>, Size2D<i32>)> {
+        match data.get(offset..(offset + 8)) {
+            None => Err(io::Error::from(ErrorKind::UnexpectedEof)),
+            Some(metrics) => read_small_metrics(metrics, 0),
+        }
+    }
+}
+
+fn sbix_glyph<'a>(sbix: &'a [u8],
+                  glyph_count: u32,
+                  strike: &Strike,
+                  glyph_id: u32,
+                  follow_dupes: bool)
+                  -> io::Result<Option<BitmapGlyph<'a>>> {
+    if glyph_id >= glyph_count {
+        return Ok(None)
+    }
+
+    let offsets_offset = strike.offset + 4 + glyph_id as usize * 4;
+    let start = strike.offset + try!(sfnt::read_u32(sbix, offsets_offset)) as usize;
+    let end = strike.offset + try!(sfnt::read_u32(sbix, offsets_offset + 4)) as usize;
+    if end <= start + SBIX_GLYPH_HEADER_SIZE {
+        // No image for this glyph in this strike.
+        return Ok(None)
+    }
+
+    let glyph_data = match sbix.get(start..end) {
+        None => return Err(io::Error::from(ErrorKind::InvalidData)),
+        Some(glyph_data) => glyph_data,
+    };
+    let origin_offset_x = try!(sfnt::read_u16(glyph_data, 0)) as i16 as i32;
+    let origin_offset_y = try!(sfnt::read_u16(glyph_data, 2)) as i16 as i32;
+    let graphic_type = try!(sfnt::read_u32(glyph_data, 4));
+    let data = &glyph_data[SBIX_GLYPH_HEADER_SIZE..];
+
+    match graphic_type {
+        GRAPHIC_TYPE_DUPE if follow_dupes => {
+            // The data is the ID of another glyph with the same image. Don't chase chains of
+            // duplicates, so that malformed fonts can't loop forever.
+            let original_glyph_id = try!(sfnt::read_u16(data, 0)) as u32;
+            sbix_glyph(sbix, glyph_count, strike, original_glyph_id, false)
+        }
+        GRAPHIC_TYPE_PNG => {
+            let size = try!(png_size(data));
+
+            // The origin offset is the position of the lower left corner of the image.
+            Ok(Some(BitmapGlyph {
+                data: data,
+                ppem: strike.ppem,
+                origin: Point2D::new(origin_offset_x, origin_offset_y + size.height),
+                size: size,
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
+// Reads the image size from the `IHDR` chunk, which always comes first.
+fn png_size(data: &[u8]) -> io::Result<Size2D<i32>> {
+    if !data.starts_with(&PNG_SIGNATURE) {
+        return Err(io::Error::from(ErrorKind::InvalidData))
+    }
+    let (width, height) = (try!(sfnt::read_u32(data, 16)), try!(sfnt::read_u32(data, 20)));
+    if width > i32::MAX as u32 || height > i32::MAX as u32 {
+        return Err(io::Error::from(ErrorKind::InvalidData))
+    }
+    Ok(Size2D::new(width as i32, height as i32))
+}
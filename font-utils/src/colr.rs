@@ -0,0 +1,232 @@
+// pathfinder/font-utils/src/colr.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Layered color glyphs from the `COLR` and `CPAL` tables, as used by emoji and icon fonts.
+//!
+//! A color glyph is a stack of ordinary glyphs, each filled with one palette color. Each layer
+//! can be partitioned and rendered like any other glyph and then composited bottom to top, which
+//! is how `pathfinder_canvas` draws text in color fonts.
+
+use font_kit::error::GlyphLoadingError;
+use font_kit::font::Font;
+use font_kit::hinting::HintingOptions;
+use lyon_path::PathEvent;
+use lyon_path::builder::FlatPathBuilder;
+use lyon_path::default::Path as LyonPath;
+use std::io::{self, ErrorKind};
+
+use sfnt::{self, FontData};
+
+const BASE_GLYPH_RECORD_SIZE: usize = 6;
+const LAYER_RECORD_SIZE: usize = 4;
+const COLOR_RECORD_SIZE: usize = 4;
+
+/// The palette entry index that means "use the text color".
+const FOREGROUND_PALETTE_ENTRY: u16 = 0xffff;
+
+/// An 8-bit straight-alpha RGBA color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColorU {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+/// The color that one layer of a color glyph is filled with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayerColor {
+    /// A color from the selected palette.
+    Palette(ColorU),
+    /// The color of the surrounding text.
+    Foreground,
+}
+
+/// One layer of a color glyph, as stored in the font.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LayerRecord {
+    /// The glyph that supplies this layer's outline.
+    pub glyph_id: u32,
+    /// The index of this layer's color within a palette.
+    pub palette_entry: u16,
+}
+
+/// One layer of a color glyph, ready to be partitioned.
+#[derive(Clone, Debug)]
+pub struct ColorGlyphLayer {
+    pub outline: Vec<PathEvent>,
+    pub color: LayerColor,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct BaseGlyphRecord {
+    glyph_id: u16,
+    first_layer_index: u16,
+    layer_count: u16,
+}
+
+/// The color glyphs and palettes of a font.
+#[derive(Clone, Debug)]
+pub struct ColorGlyphs {
+    base_glyphs: Vec<BaseGlyphRecord>,
+    layers: Vec<LayerRecord>,
+    palettes: Vec<Vec<ColorU>>,
+}
+
+impl ColorGlyphs {
+    /// Reads the `COLR` and `CPAL` tables. Returns `Ok(None)` if the font has no color glyphs.
+    ///
+    /// A font with a `COLR` table but no `CPAL` table is accepted; every layer then uses the
+    /// foreground color.
+    pub fn from_font_data(font_data: &FontData) -> io::Result<Option<ColorGlyphs>> {
+        let colr = match font_data.table(sfnt::COLR) {
+            None => return Ok(None),
+            Some(colr) => colr,
+        };
+
+        let base_glyph_count = try!(sfnt::read_u16(colr, 2)) as usize;
+        let base_glyphs_offset = try!(sfnt::read_u32(colr, 4)) as usize;
+        let layers_offset = try!(sfnt::read_u32(colr, 8)) as usize;
+        let layer_count = try!(sfnt::read_u16(colr, 12)) as usize;
+
+        let mut base_glyphs = Vec::with_capacity(base_glyph_count);
+        for base_glyph_index in 0..base_glyph_count {
+            let offset = base_glyphs_offset + base_glyph_index * BASE_GLYPH_RECORD_SIZE;
+            let record = BaseGlyphRecord {
+                glyph_id: try!(sfnt::read_u16(colr, offset)),
+                first_layer_index: try!(sfnt::read_u16(colr, offset + 2)),
+                layer_count: try!(sfnt::read_u16(colr, offset + 4)),
+            };
+            if record.first_layer_index as usize + record.layer_count as usize > layer_count {
+                return Err(io::Error::from(ErrorKind::InvalidData))
+            }
+            base_glyphs.push(record);
+        }
+
+        // The records are supposed to be sorted already, but `glyph_layers()` relies on it.
+        base_glyphs.sort_by_key(|record| record.glyph_id);
+
+        let mut layers = Vec::with_capacity(layer_count);
+        for layer_index in 0..layer_count {
+            let offset = layers_offset + layer_index * LAYER_RECORD_SIZE;
+            layers.push(LayerRecord {
+                glyph_id: try!(sfnt::read_u16(colr, offset)) as u32,
+                palette_entry: try!(sfnt::read_u16(colr, offset + 2)),
+            })
+        }
+
+        let palettes = match font_data.table(sfnt::CPAL) {
+            None => vec![],
+            Some(cpal) => try!(read_palettes(cpal)),
+        };
+
+        return Ok(Some(ColorGlyphs {
+            base_glyphs: base_glyphs,
+            layers: layers,
+            palettes: palettes,
+        }));
+
+        fn read_palettes(cpal: &[u8]) -> io::Result<Vec<Vec<ColorU>>> {
+            let entry_count = try!(sfnt::read_u16(cpal, 2)) as usize;
+            let palette_count = try!(sfnt::read_u16(cpal, 4)) as usize;
+            let colors_offset = try!(sfnt::read_u32(cpal, 8)) as usize;
+
+            let mut palettes = Vec::with_capacity(palette_count);
+            for palette_index in 0..palette_count {
+                let first_color_index = try!(sfnt::read_u16(cpal, 12 + palette_index * 2));
+
+                let mut palette = Vec::with_capacity(entry_count);
+                for entry_index in 0..entry_count {
+                    let offset = colors_offset +
+                        (first_color_index as usize + entry_index) * COLOR_RECORD_SIZE;
+
+                    // Color records are stored as BGRA.
+                    match cpal.get(offset..(offset + COLOR_RECORD_SIZE)) {
+                        None => return Err(io::Error::from(ErrorKind::UnexpectedEof)),
+                        Some(bgra) => {
+                            palette.push(ColorU {
+                                r: bgra[2],
+                                g: bgra[1],
+                                b: bgra[0],
+                                a: bgra[3],
+                            })
+                        }
+                    }
+                }
+                palettes.push(palette);
+            }
+            Ok(palettes)
+        }
+    }
+
+    /// Returns the number of palettes. Palette 0 is the default.
+    #[inline]
+    pub fn palette_count(&self) -> usize {
+        self.palettes.len()
+    }
+
+    #[inline]
+    pub fn palette(&self, palette_index: usize) -> Option<&[ColorU]> {
+        self.palettes.get(palette_index).map(|palette| &palette[..])
+    }
+
+    /// Returns the layers of the glyph, bottommost first, or `None` if it isn't a color glyph.
+    pub fn glyph_layers(&self, glyph_id: u32) -> Option<&[LayerRecord]> {
+        if glyph_id > u16::max_value() as u32 {
+            return None
+        }
+        let glyph_id = glyph_id as u16;
+        let record = match self.base_glyphs.binary_search_by_key(&glyph_id, |record| {
+            record.glyph_id
+        }) {
+            Err(_) => return None,
+            Ok(base_glyph_index) => &self.base_glyphs[base_glyph_index],
+        };
+
+        let first_layer_index = record.first_layer_index as usize;
+        Some(&self.layers[first_layer_index..(first_layer_index + record.layer_count as usize)])
+    }
+
+    /// Looks up the color of the layer in the given palette.
+    ///
+    /// Entries that are out of range, like missing palettes, fall back to the foreground color.
+    pub fn layer_color(&self, layer: &LayerRecord, palette_index: usize) -> LayerColor {
+        if layer.palette_entry == FOREGROUND_PALETTE_ENTRY {
+            return LayerColor::Foreground
+        }
+        match self.palette(palette_index).and_then(|palette| {
+            palette.get(layer.palette_entry as usize)
+        }) {
+            None => LayerColor::Foreground,
+            Some(&color) => LayerColor::Palette(color),
+        }
+    }
+
+    /// Loads the outline and color of each layer of the glyph, bottommost first. Returns
+    /// `Ok(None)` if it isn't a color glyph, in which case it should be drawn normally.
+    pub fn layer_outlines(&self, font: &Font, glyph_id: u32, palette_index: usize)
+                          -> Result<Option<Vec<ColorGlyphLayer>>, GlyphLoadingError> {
+        let layers = match self.glyph_layers(glyph_id) {
+            None => return Ok(None),
+            Some(layers) => layers,
+        };
+
+        let mut color_glyph_layers = Vec::with_capacity(layers.len());
+        for layer in layers {
+            let mut path_builder = LyonPath::builder();
+            try!(font.outline(layer.glyph_id, HintingOptions::None, &mut path_builder));
+            color_glyph_layers.push(ColorGlyphLayer {
+                outline: path_builder.build().iter().collect(),
+                color: self.layer_color(layer, palette_index),
+            })
+        }
+        Ok(Some(color_glyph_layers))
+    }
+}
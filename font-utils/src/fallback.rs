@@ -0,0 +1,310 @@
+// pathfinder/font-utils/src/fallback.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Shapes text with an ordered list of fonts, so that characters the first font doesn't cover,
+//! such as other scripts and emoji, come from the next font that does instead of rendering as
+//! `.notdef` boxes.
+//!
+//! The text is shaped with the first face. Each run of clusters that came out with any `.notdef`
+//! glyphs is shaped again on its own with the next face, and so on down the chain. Clusters that
+//! no face covers are left with the last face's glyphs. Shaping doesn't see across the boundary
+//! of a fallback run, so joining and kerning between runs are lost, as they are in browsers.
+//!
+//! This module is only available with the `shaping` feature.
+
+use euclid::{Point2D, Vector2D};
+use font_kit::family_name::FamilyName;
+use font_kit::handle::Handle;
+use font_kit::properties::Properties;
+use font_kit::source::SystemSource;
+use std::fs;
+use std::ops::Range;
+use std::sync::Arc;
+
+use shaping::{self, ShapedGlyph, ShapingOptions, TextDirection};
+
+/// The glyph ID that fonts map characters they don't support to.
+const NOTDEF_GLYPH_ID: u32 = 0;
+
+/// One face in a fallback chain.
+#[derive(Clone, Debug)]
+pub struct FallbackFace {
+    pub data: Arc<Vec<u8>>,
+    /// The face within `data`, if it's a collection.
+    pub font_index: u32,
+}
+
+/// An ordered list of faces to take glyphs from, most preferred first.
+#[derive(Clone, Debug, Default)]
+pub struct FontFallbackChain {
+    pub faces: Vec<FallbackFace>,
+}
+
+impl FontFallbackChain {
+    #[inline]
+    pub fn new() -> FontFallbackChain {
+        FontFallbackChain::default()
+    }
+
+    #[inline]
+    pub fn push(&mut self, data: Arc<Vec<u8>>, font_index: u32) {
+        self.faces.push(FallbackFace {
+            data: data,
+            font_index: font_index,
+        })
+    }
+
+    /// Appends the installed face that best matches the first available family in
+    /// `family_names` with `properties`. Returns false if there's no match or it can't be read.
+    ///
+    /// Listing generic families last, such as `FamilyName::SansSerif`, makes a chain end with
+    /// whatever the system uses for them.
+    pub fn push_system_face(&mut self, family_names: &[FamilyName], properties: &Properties)
+                            -> bool {
+        let handle = match SystemSource::new().select_best_match(family_names, properties) {
+            Ok(handle) => handle,
+            Err(_) => return false,
+        };
+        match handle {
+            Handle::Memory { bytes, font_index } => self.push(bytes, font_index),
+            Handle::Path { path, font_index } => {
+                match fs::read(path) {
+                    Ok(data) => self.push(Arc::new(data), font_index),
+                    Err(_) => return false,
+                }
+            }
+        }
+        true
+    }
+}
+
+/// A run of text that was shaped with one face of the chain.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FallbackSegment {
+    /// The index of the face in the chain.
+    pub face_index: usize,
+    /// The byte range of the text that this segment covers.
+    pub text_range: Range<usize>,
+    /// The glyphs, in visual order within the segment, positioned relative to its start, with
+    /// clusters as byte offsets into the whole text.
+    pub glyphs: Vec<ShapedGlyph>,
+    /// The total advance of the segment, in pixels.
+    pub advance: Vector2D<f32>,
+}
+
+/// One glyph of a `FallbackTextRun`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FallbackGlyph {
+    /// The index of the face in the chain that `glyph.glyph_id` belongs to.
+    pub face_index: usize,
+    /// The glyph, positioned relative to the start of the whole run.
+    pub glyph: ShapedGlyph,
+}
+
+/// Text shaped across a fallback chain.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FallbackTextRun {
+    /// The segments, in the order they appear in the text.
+    pub segments: Vec<FallbackSegment>,
+    /// The glyphs of all segments, in visual order.
+    pub glyphs: Vec<FallbackGlyph>,
+    /// The total advance of the run, in pixels.
+    pub advance: Vector2D<f32>,
+}
+
+/// Shapes `text` at `point_size` pixels per em, taking each cluster from the first face in
+/// `chain` that has glyphs for all of it.
+///
+/// Segments are laid out in text order, or in reverse if `options.direction` is right to left
+/// or bottom to top. Text that mixes directions should be split with the `bidi` module first and
+/// each directional run shaped separately. Returns an empty run if the chain is empty.
+pub fn shape_with_fallback(chain: &FontFallbackChain,
+                           text: &str,
+                           point_size: f32,
+                           options: &ShapingOptions)
+                           -> FallbackTextRun {
+    let mut segments = vec![];
+    if !chain.faces.is_empty() {
+        shape_range(chain, 0, text, 0..text.len(), point_size, options, &mut segments);
+    }
+
+    let reversed = match options.direction {
+        Some(TextDirection::RightToLeft) | Some(TextDirection::BottomToTop) => true,
+        _ => false,
+    };
+    let mut visual_order: Vec<&FallbackSegment> = segments.iter().collect();
+    if reversed {
+        visual_order.reverse()
+    }
+
+    let mut pen = Point2D::zero();
+    let mut glyphs = vec![];
+    for segment in visual_order {
+        glyphs.extend(segment.glyphs.iter().map(|glyph| {
+            FallbackGlyph {
+                face_index: segment.face_index,
+                glyph: ShapedGlyph {
+                    position: glyph.position + pen.to_vector(),
+                    ..*glyph
+                },
+            }
+        }));
+        pen += segment.advance;
+    }
+
+    FallbackTextRun {
+        segments: segments,
+        glyphs: glyphs,
+        advance: pen.to_vector(),
+    }
+}
+
+// Shapes `text[range]` with the face at `face_index` and appends the segments it produces, in
+// text order, shaping the clusters it's missing with the following faces.
+fn shape_range(chain: &FontFallbackChain,
+               face_index: usize,
+               text: &str,
+               range: Range<usize>,
+               point_size: f32,
+               options: &ShapingOptions,
+               segments: &mut Vec<FallbackSegment>) {
+    let face = &chain.faces[face_index];
+    let run = shaping::shape(&face.data,
+                             face.font_index,
+                             &text[range.clone()],
+                             point_size,
+                             options);
+    let glyphs: Vec<_> = run.glyphs.iter().map(|glyph| {
+        ShapedGlyph {
+            cluster: glyph.cluster + range.start as u32,
+            ..*glyph
+        }
+    }).collect();
+
+    // Find each cluster's start and whether any of its glyphs are missing, in text order.
+    let mut clusters: Vec<(u32, bool)> = vec![];
+    for glyph in &glyphs {
+        let missing = glyph.glyph_id == NOTDEF_GLYPH_ID;
+        match clusters.binary_search_by_key(&glyph.cluster, |&(cluster, _)| cluster) {
+            Ok(index) => clusters[index].1 |= missing,
+            Err(index) => clusters.insert(index, (glyph.cluster, missing)),
+        }
+    }
+
+    let is_last_face = face_index + 1 == chain.faces.len();
+    let mut cluster_index = 0;
+    while cluster_index < clusters.len() {
+        // Gather the longest run of clusters that are all present or all missing.
+        let missing = clusters[cluster_index].1 && !is_last_face;
+        let mut end_index = cluster_index + 1;
+        while end_index < clusters.len() && (clusters[end_index].1 && !is_last_face) == missing {
+            end_index += 1
+        }
+
+        let start = clusters[cluster_index].0 as usize;
+        let end = if end_index < clusters.len() {
+            clusters[end_index].0 as usize
+        } else {
+            range.end
+        };
+
+        if missing {
+            shape_range(chain, face_index + 1, text, start..end, point_size, options, segments)
+        } else {
+            segments.push(segment_from_glyphs(face_index, &glyphs, start..end))
+        }
+        cluster_index = end_index;
+    }
+}
+
+// Makes a segment of the glyphs in `glyphs`, a shaped run, whose clusters start in
+// `text_range`.
+fn segment_from_glyphs(face_index: usize, glyphs: &[ShapedGlyph], text_range: Range<usize>)
+                       -> FallbackSegment {
+    let mut segment_glyphs = vec![];
+    let (mut pen, mut segment_start) = (Vector2D::zero(), None);
+    for glyph in glyphs {
+        let cluster = glyph.cluster as usize;
+        if cluster >= text_range.start && cluster < text_range.end {
+            // The pen is at the segment's start when its first glyph is reached.
+            let start = *segment_start.get_or_insert(pen);
+            segment_glyphs.push(ShapedGlyph {
+                position: glyph.position - start,
+                ..*glyph
+            });
+        }
+        pen += glyph.advance;
+    }
+
+    let advance = segment_glyphs.iter().fold(Vector2D::zero(), |advance, glyph| {
+        advance + glyph.advance
+    });
+    FallbackSegment {
+        face_index: face_index,
+        text_range: text_range,
+        glyphs: segment_glyphs,
+        advance: advance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use shaping::ShapingOptions;
+    use super::{FontFallbackChain, shape_with_fallback};
+
+    // Open Sans has no arrows, Nimbus Sans has no geometric shapes, and none of the three has
+    // CJK ideographs.
+    fn chain() -> FontFallbackChain {
+        let mut chain = FontFallbackChain::new();
+        for data in &[&include_bytes!("../../resources/fonts/open-sans/OpenSans-Regular.ttf")[..],
+                      &include_bytes!("../../resources/fonts/nimbus-sans/NimbusSanL-Regu.ttf")[..],
+                      &include_bytes!("../../resources/fonts/inter-ui/Inter-UI-Regular.ttf")[..]] {
+            chain.push(Arc::new(data.to_vec()), 0)
+        }
+        chain
+    }
+
+    #[test]
+    fn each_run_comes_from_the_first_face_that_covers_it() {
+        let text = "a\u{2192}b\u{25cf}\u{4e00}";
+        let run = shape_with_fallback(&chain(), text, 16.0, &ShapingOptions::default());
+        let segments: Vec<_> = run.segments.iter().map(|segment| {
+            (segment.face_index, segment.text_range.clone())
+        }).collect();
+        // The ideograph stays with the last face it was tried in, along with the circle.
+        assert_eq!(segments, vec![(0, 0..1), (1, 1..4), (0, 4..5), (2, 5..11)]);
+
+        let glyphs: Vec<_> = run.glyphs.iter().map(|glyph| {
+            (glyph.face_index, glyph.glyph.cluster, glyph.glyph.glyph_id != 0)
+        }).collect();
+        assert_eq!(glyphs, vec![(0, 0, true), (1, 1, true), (0, 4, true), (2, 5, true),
+                                (2, 8, false)]);
+    }
+
+    #[test]
+    fn fallback_runs_are_laid_out_end_to_end() {
+        let run = shape_with_fallback(&chain(),
+                                      "a\u{2192}b\u{25cf}",
+                                      16.0,
+                                      &ShapingOptions::default());
+        let total_advance = run.segments.iter().fold(0.0, |advance, segment| {
+            advance + segment.advance.x
+        });
+        assert!((run.advance.x - total_advance).abs() < 1e-3);
+
+        let mut pen = 0.0;
+        for glyph in &run.glyphs {
+            assert!((glyph.glyph.position.x - pen).abs() < 1e-3);
+            pen += glyph.glyph.advance.x;
+        }
+    }
+}
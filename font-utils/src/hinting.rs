@@ -0,0 +1,48 @@
+// pathfinder/font-utils/src/hinting.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Chooses how much glyph outlines are hinted before they are partitioned.
+//!
+//! Hinting is done by the `font-kit` loader, which runs the font's TrueType instructions (or the
+//! loader's autohinter) for a particular size. Hinted outlines only look right at that size, so
+//! meshes built from them should be cached per size.
+
+use font_kit::hinting::HintingOptions;
+
+/// How much to hint glyph outlines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HintingLevel {
+    /// Use the outlines as designed. Meshes can be drawn at any size.
+    None,
+    /// Snap only vertical features, such as baselines and x-heights, to the pixel grid.
+    Vertical,
+    /// Snap features to the pixel grid in both directions.
+    Full,
+}
+
+impl Default for HintingLevel {
+    #[inline]
+    fn default() -> HintingLevel {
+        HintingLevel::None
+    }
+}
+
+impl HintingLevel {
+    /// Returns the options to pass to `font-kit` to hint glyphs drawn at `point_size` pixels per
+    /// em.
+    #[inline]
+    pub fn to_hinting_options(self, point_size: f32) -> HintingOptions {
+        match self {
+            HintingLevel::None => HintingOptions::None,
+            HintingLevel::Vertical => HintingOptions::Vertical(point_size),
+            HintingLevel::Full => HintingOptions::Full(point_size),
+        }
+    }
+}
@@ -0,0 +1,384 @@
+// pathfinder/font-utils/src/layout.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Breaks shaped paragraphs into lines and aligns them.
+//!
+//! Line break opportunities come from the Unicode line breaking algorithm (UAX #14). The
+//! paragraph is shaped once, as a whole, and the lines are cut from the result, so shaping
+//! across a break (for example, a ligature or kerning pair spanning it) isn't redone.
+//!
+//! This module is only available with the `shaping` feature.
+
+use euclid::{Point2D, Vector2D};
+use std::cmp::Ordering;
+use std::ops::Range;
+use xi_unicode::LineBreakIterator;
+
+use bidi::BidiText;
+use shaping::{ShapedGlyph, TextRun};
+
+/// How lines are positioned within the paragraph width.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextAlignment {
+    Left,
+    Right,
+    Center,
+    /// Spaces are widened so that lines fill the width. The last line of the paragraph, and lines
+    /// ending in a forced break, are aligned left.
+    Justify,
+}
+
+/// Settings for `layout_paragraph()`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParagraphOptions {
+    /// The width to break lines at, in pixels.
+    pub width: f32,
+    /// The distance between baselines, in pixels.
+    pub line_height: f32,
+    pub alignment: TextAlignment,
+}
+
+/// One line of a laid-out paragraph.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineLayout {
+    /// The byte range of the text on this line, including any trailing whitespace.
+    pub text_range: Range<usize>,
+    /// The range of the paragraph's glyphs that belong to this line.
+    pub glyph_range: Range<usize>,
+    /// The Y position of the baseline. The first line's baseline is at zero, and later lines are
+    /// below it, at negative Y.
+    pub baseline: f32,
+    /// The width of the line's glyphs, not counting trailing whitespace or justification.
+    pub width: f32,
+}
+
+/// A laid-out paragraph.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParagraphLayout {
+    /// The glyphs, with positions relative to the left end of the first line's baseline.
+    pub glyphs: Vec<ShapedGlyph>,
+    pub lines: Vec<LineLayout>,
+}
+
+// A run of text between two line break opportunities.
+struct Segment {
+    text_range: Range<usize>,
+    // In logical order, so that the whitespace at the end of the text is at the end. Right-to-left
+    // runs have their glyphs in visual order, which is the reverse.
+    glyph_indices: Vec<usize>,
+    advance: f32,
+    trailing_whitespace_advance: f32,
+    ends_in_forced_break: bool,
+}
+
+struct Line<'a> {
+    segments: &'a [Segment],
+    is_last: bool,
+    // Whether the paragraph runs right to left.
+    is_rtl: bool,
+}
+
+/// Breaks `run`, which was shaped from `text`, into lines no wider than `options.width`, and
+/// positions the glyphs on them.
+///
+/// The glyphs are laid out in the order they appear in the run, so this is only suitable for
+/// text in one direction. Use `layout_bidi_paragraph()` for text that may mix directions.
+///
+/// Text that can't be broken to fit, such as a single long word, overflows the width.
+pub fn layout_paragraph(text: &str, run: &TextRun, options: &ParagraphOptions)
+                        -> ParagraphLayout {
+    layout_lines(text, run, options, false, |line_range| vec![line_range])
+}
+
+/// Breaks a paragraph shaped with `bidi::shape_bidi_text()` into lines no wider than
+/// `options.width`, reorders each line for display, and positions the glyphs on it.
+///
+/// Lines are broken in logical order and then reordered, as UAX #9 specifies, so a line's
+/// glyphs can come from several runs. Justified text aligns its last line to the start of the
+/// paragraph direction.
+pub fn layout_bidi_paragraph(bidi_text: &BidiText, options: &ParagraphOptions)
+                             -> ParagraphLayout {
+    let mut run = TextRun {
+        glyphs: vec![],
+        advance: Vector2D::zero(),
+    };
+    for bidi_run in &bidi_text.runs {
+        run.glyphs.extend(bidi_run.run.glyphs.iter().map(|glyph| {
+            ShapedGlyph {
+                position: glyph.position + run.advance,
+                ..*glyph
+            }
+        }));
+        run.advance += bidi_run.run.advance;
+    }
+
+    layout_lines(bidi_text.text(),
+                 &run,
+                 options,
+                 bidi_text.is_rtl(),
+                 |line_range| bidi_text.visual_ranges(line_range))
+}
+
+fn layout_lines<F>(text: &str,
+                   run: &TextRun,
+                   options: &ParagraphOptions,
+                   is_rtl: bool,
+                   visual_ranges: F)
+                   -> ParagraphLayout
+                   where F: Fn(Range<usize>) -> Vec<Range<usize>> {
+    let segments = build_segments(text, run);
+    let pens = pen_positions(run);
+
+    let mut layout = ParagraphLayout {
+        glyphs: vec![],
+        lines: vec![],
+    };
+    let mut line_start = 0;
+    while line_start < segments.len() {
+        // Take segments until the next one doesn't fit or a forced break ends the line.
+        let mut line_end = line_start;
+        let mut advance = 0.0;
+        while line_end < segments.len() {
+            let segment = &segments[line_end];
+            let width = advance + segment.advance - segment.trailing_whitespace_advance;
+            if line_end > line_start && width > options.width {
+                break
+            }
+            advance += segment.advance;
+            line_end += 1;
+            if segment.ends_in_forced_break {
+                break
+            }
+        }
+
+        let line = Line {
+            segments: &segments[line_start..line_end],
+            is_last: line_end == segments.len(),
+            is_rtl: is_rtl,
+        };
+        push_line(&mut layout, text, run, &pens, &line, &visual_ranges, options);
+        line_start = line_end;
+    }
+
+    layout
+}
+
+fn build_segments(text: &str, run: &TextRun) -> Vec<Segment> {
+    let mut segments = vec![];
+    let mut segment_start = 0;
+    for (break_offset, is_forced) in LineBreakIterator::new(text) {
+        segments.push(Segment {
+            text_range: segment_start..break_offset,
+            glyph_indices: vec![],
+            advance: 0.0,
+            trailing_whitespace_advance: 0.0,
+            ends_in_forced_break: is_forced && break_offset < text.len(),
+        });
+        segment_start = break_offset;
+    }
+
+    for (glyph_index, glyph) in run.glyphs.iter().enumerate() {
+        let cluster = glyph.cluster as usize;
+        let segment_index = match segments.binary_search_by(|segment| {
+            if segment.text_range.end <= cluster {
+                Ordering::Less
+            } else if segment.text_range.start > cluster {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        }) {
+            Ok(segment_index) => segment_index,
+            Err(_) => continue,
+        };
+        let segment = &mut segments[segment_index];
+        segment.glyph_indices.push(glyph_index);
+        segment.advance += glyph.advance.x;
+    }
+
+    // Measure the whitespace at the end of each segment, which hangs past the end of a line.
+    for segment in &mut segments {
+        segment.glyph_indices.sort_by_key(|&glyph_index| (run.glyphs[glyph_index].cluster,
+                                                          glyph_index));
+        segment.trailing_whitespace_advance = segment.trailing_whitespace_glyphs(text, run)
+                                                     .iter()
+                                                     .map(|&glyph_index| {
+            run.glyphs[glyph_index].advance.x
+        }).sum();
+    }
+
+    segments
+}
+
+impl Segment {
+    // Returns the glyphs of the whitespace at the end of the segment's text.
+    fn trailing_whitespace_glyphs(&self, text: &str, run: &TextRun) -> &[usize] {
+        let trailing_glyph_count = self.glyph_indices.iter().rev().take_while(|&&glyph_index| {
+            is_whitespace(text, &run.glyphs[glyph_index])
+        }).count();
+        &self.glyph_indices[(self.glyph_indices.len() - trailing_glyph_count)..]
+    }
+}
+
+fn push_line<F>(layout: &mut ParagraphLayout,
+                text: &str,
+                run: &TextRun,
+                pens: &[Point2D<f32>],
+                line: &Line,
+                visual_ranges: &F,
+                options: &ParagraphOptions)
+                where F: Fn(Range<usize>) -> Vec<Range<usize>> {
+    let (first_segment, last_segment) = match (line.segments.first(), line.segments.last()) {
+        (Some(first_segment), Some(last_segment)) => (first_segment, last_segment),
+        _ => return,
+    };
+    let text_range = first_segment.text_range.start..last_segment.text_range.end;
+
+    let mut glyph_indices: Vec<usize> = line.segments.iter().flat_map(|segment| {
+        segment.glyph_indices.iter().cloned()
+    }).collect();
+    let advance: f32 = line.segments.iter().map(|segment| segment.advance).sum();
+    let width = advance - last_segment.trailing_whitespace_advance;
+
+    // Work out where the line starts and how much to widen each space by. Trailing whitespace
+    // isn't widened, since it hangs past the end of the line.
+    let trailing_glyph_indices = last_segment.trailing_whitespace_glyphs(text, run);
+    let extra_space = (options.width - width).max(0.0);
+    let (mut x, space_expansion) = match options.alignment {
+        TextAlignment::Left => (0.0, 0.0),
+        TextAlignment::Right => (extra_space, 0.0),
+        TextAlignment::Center => (extra_space * 0.5, 0.0),
+        TextAlignment::Justify if line.is_last || last_segment.ends_in_forced_break => {
+            (if line.is_rtl { extra_space } else { 0.0 }, 0.0)
+        }
+        TextAlignment::Justify => {
+            let space_count = glyph_indices[..(glyph_indices.len() - trailing_glyph_indices.len())]
+                .iter()
+                .filter(|&&glyph_index| is_whitespace(text, &run.glyphs[glyph_index]))
+                .count();
+            if space_count == 0 {
+                (0.0, 0.0)
+            } else {
+                (0.0, extra_space / space_count as f32)
+            }
+        }
+    };
+
+    // Trailing whitespace hangs past the end of the line, which is on the left in right-to-left
+    // paragraphs.
+    if line.is_rtl {
+        x -= last_segment.trailing_whitespace_advance
+    }
+
+    // Put the glyphs in visual order. Within each directional run, they already are.
+    glyph_indices.sort();
+    let mut visual_glyph_indices = Vec::with_capacity(glyph_indices.len());
+    for range in visual_ranges(text_range.clone()) {
+        visual_glyph_indices.extend(glyph_indices.iter().cloned().filter(|&glyph_index| {
+            let cluster = run.glyphs[glyph_index].cluster as usize;
+            cluster >= range.start && cluster < range.end
+        }))
+    }
+
+    // Place the glyphs, keeping each one's offset from its pen position in the run.
+    let baseline = -(layout.lines.len() as f32) * options.line_height;
+    let first_glyph_index = layout.glyphs.len();
+    for &glyph_index in &visual_glyph_indices {
+        let glyph = &run.glyphs[glyph_index];
+        let offset = glyph.position - pens[glyph_index];
+        let mut advance = glyph.advance;
+        if space_expansion > 0.0 && is_whitespace(text, glyph) &&
+                !trailing_glyph_indices.contains(&glyph_index) {
+            advance.x += space_expansion
+        }
+        layout.glyphs.push(ShapedGlyph {
+            glyph_id: glyph.glyph_id,
+            cluster: glyph.cluster,
+            position: Point2D::new(x, baseline) + offset,
+            advance: advance,
+        });
+        x += advance.x;
+    }
+
+    layout.lines.push(LineLayout {
+        text_range: text_range,
+        glyph_range: first_glyph_index..layout.glyphs.len(),
+        baseline: baseline,
+        width: width,
+    })
+}
+
+// Returns the pen position before each glyph of the run.
+fn pen_positions(run: &TextRun) -> Vec<Point2D<f32>> {
+    let mut pen = Point2D::zero();
+    run.glyphs.iter().map(|glyph| {
+        let glyph_pen = pen;
+        pen += glyph.advance;
+        glyph_pen
+    }).collect()
+}
+
+fn is_whitespace(text: &str, glyph: &ShapedGlyph) -> bool {
+    text.get((glyph.cluster as usize)..).and_then(|rest| rest.chars().next())
+                                        .map_or(false, char::is_whitespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::{Point2D, Vector2D};
+
+    use shaping::{ShapedGlyph, TextRun};
+    use super::{ParagraphLayout, ParagraphOptions, TextAlignment, layout_lines};
+
+    // Lays out `text` as a right-to-left paragraph shaped with one 10-pixel glyph per byte.
+    fn layout_rtl(text: &str, width: f32, alignment: TextAlignment) -> ParagraphLayout {
+        let mut pen = 0.0;
+        let glyphs = (0..text.len()).rev().map(|cluster| {
+            let glyph = ShapedGlyph {
+                glyph_id: cluster as u32 + 1,
+                cluster: cluster as u32,
+                position: Point2D::new(pen, 0.0),
+                advance: Vector2D::new(10.0, 0.0),
+            };
+            pen += 10.0;
+            glyph
+        }).collect();
+        let run = TextRun {
+            glyphs: glyphs,
+            advance: Vector2D::new(pen, 0.0),
+        };
+        let options = ParagraphOptions {
+            width: width,
+            line_height: 10.0,
+            alignment: alignment,
+        };
+        layout_lines(text, &run, &options, true, |line_range| vec![line_range])
+    }
+
+    #[test]
+    fn rtl_trailing_whitespace_is_found_in_logical_order() {
+        // The glyph of the space after "ab" comes first in the run, since the run is visual.
+        let layout = layout_rtl("ab cd", 25.0, TextAlignment::Right);
+        assert_eq!(layout.lines.len(), 2);
+        assert_eq!(layout.lines[0].text_range, 0..3);
+        assert_eq!(layout.lines[0].width, 20.0);
+        // The space hangs off the left end.
+        let space = layout.glyphs.iter().find(|glyph| glyph.cluster == 2).unwrap();
+        assert_eq!(space.position.x, -5.0);
+    }
+
+    #[test]
+    fn rtl_justification_does_not_widen_trailing_whitespace() {
+        let layout = layout_rtl("ab cd", 25.0, TextAlignment::Justify);
+        for glyph in &layout.glyphs[layout.lines[0].glyph_range.clone()] {
+            assert_eq!(glyph.advance.x, 10.0);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+// pathfinder/font-utils/src/lib.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Font utilities that build on `font-kit`: reading the OpenType tables it doesn't expose, such
+//! as color glyph layers, SVG glyph documents, and embedded bitmaps; collecting metrics for
+//! layout; decoding WOFF fonts; choosing how to hint outlines; synthesizing oblique and
+//! small-caps styles; and generating signed distance fields.
+//!
+//! The table readers work on the raw font data, so they can be used alongside any `font-kit`
+//! loader.
+//!
+//! With the `shaping` feature, the `shaping` module shapes text with HarfBuzz, the `bidi` module
+//! shapes text that mixes directions, the `fallback` module shapes text across a list of fonts,
+//! and the `layout` module breaks shaped paragraphs into lines.
+
+extern crate byteorder;
+extern crate euclid;
+extern crate flate2;
+extern crate font_kit;
+#[cfg(feature = "shaping")]
+extern crate harfbuzz_rs;
+extern crate lyon_path;
+extern crate pathfinder_path_utils;
+#[cfg(feature = "shaping")]
+extern crate unicode_bidi;
+#[cfg(feature = "shaping")]
+extern crate xi_unicode;
+
+#[cfg(feature = "shaping")]
+pub mod bidi;
+pub mod bitmap;
+pub mod colr;
+#[cfg(feature = "shaping")]
+pub mod fallback;
+pub mod hinting;
+#[cfg(feature = "shaping")]
+pub mod layout;
+pub mod metrics;
+pub mod sdf;
+pub mod sfnt;
+#[cfg(feature = "shaping")]
+pub mod shaping;
+pub mod svg;
+pub mod synthesis;
+pub mod woff;
@@ -0,0 +1,178 @@
+// pathfinder/font-utils/src/metrics.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Font-wide and per-glyph metrics for laying out text, from `font-kit`, so that layout code can
+//! measure glyphs with the same loader it outlines them with.
+//!
+//! All values are in font units, with Y pointing up. Multiply them by `FontMetrics::scale()` to
+//! get pixels.
+
+use euclid::Rect;
+use font_kit::error::GlyphLoadingError;
+use font_kit::font::Font;
+
+use sfnt::{self, FontData};
+
+const OS_2_FS_SELECTION_OFFSET: usize = 62;
+const OS_2_TYPO_ASCENDER_OFFSET: usize = 68;
+const OS_2_TYPO_DESCENDER_OFFSET: usize = 70;
+const OS_2_TYPO_LINE_GAP_OFFSET: usize = 72;
+
+/// The `fsSelection` bit that says the typographic metrics should be used for line spacing.
+const USE_TYPO_METRICS: u16 = 1 << 7;
+
+/// Metrics that apply to the whole font.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FontMetrics {
+    pub units_per_em: u32,
+    /// The distance from the baseline to the top of the line.
+    pub ascent: f32,
+    /// The distance from the baseline to the bottom of the line. This is usually negative.
+    pub descent: f32,
+    /// The extra space the font asks for between lines.
+    pub line_gap: f32,
+    /// The height of flat lowercase letters, if the font says.
+    pub x_height: Option<f32>,
+    /// The height of flat capital letters, if the font says.
+    pub cap_height: Option<f32>,
+}
+
+impl FontMetrics {
+    /// Returns the metrics that `font-kit` reports for the font, which was loaded from face
+    /// `font_index` of its data.
+    ///
+    /// If the face's `OS/2` table asks for its typographic metrics to be used for line spacing,
+    /// they replace the ones `font-kit` reports, which come from `hhea`. `OS/2` tables too short
+    /// to have them, as old ones are, are ignored.
+    pub fn from_font(font: &Font, font_index: u32) -> FontMetrics {
+        let font_kit_metrics = font.metrics();
+        let positive = |value: f32| if value > 0.0 { Some(value) } else { None };
+        let mut metrics = FontMetrics {
+            units_per_em: font_kit_metrics.units_per_em,
+            ascent: font_kit_metrics.ascent,
+            descent: font_kit_metrics.descent,
+            line_gap: font_kit_metrics.line_gap,
+            x_height: positive(font_kit_metrics.x_height),
+            cap_height: positive(font_kit_metrics.cap_height),
+        };
+
+        // `font-kit` doesn't say which face of a collection it loaded, so the caller does.
+        let font_data = font.copy_font_data();
+        let os_2 = font_data.as_ref().and_then(|font_data| {
+            FontData::new(font_data, font_index).ok().and_then(|font_data| {
+                font_data.table(sfnt::OS_2)
+            })
+        });
+        if let Some((ascent, descent, line_gap)) = os_2.and_then(typographic_line_metrics) {
+            metrics.ascent = ascent;
+            metrics.descent = descent;
+            metrics.line_gap = line_gap;
+        }
+
+        metrics
+    }
+
+    /// Returns the distance between the baselines of consecutive lines.
+    #[inline]
+    pub fn line_height(&self) -> f32 {
+        self.ascent - self.descent + self.line_gap
+    }
+
+    /// Returns the factor that converts font units to pixels at `point_size` pixels per em.
+    #[inline]
+    pub fn scale(&self, point_size: f32) -> f32 {
+        point_size / self.units_per_em as f32
+    }
+}
+
+/// Metrics for one glyph.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphMetrics {
+    /// How far the glyph moves the pen horizontally.
+    pub advance: f32,
+    /// The bounds of the glyph's outline relative to its origin, as `font-kit` reports them.
+    pub bounds: Rect<f32>,
+}
+
+impl GlyphMetrics {
+    /// Returns the advance and bounds of the glyph, from `font-kit`.
+    pub fn from_font(font: &Font, glyph_id: u32) -> Result<GlyphMetrics, GlyphLoadingError> {
+        Ok(GlyphMetrics {
+            advance: try!(font.advance(glyph_id)).x,
+            bounds: try!(font.typographic_bounds(glyph_id)),
+        })
+    }
+
+    /// Returns the distance from the origin to the left edge of the outline.
+    #[inline]
+    pub fn left_side_bearing(&self) -> f32 {
+        self.bounds.origin.x
+    }
+
+    /// Returns the distance from the right edge of the outline to the advance.
+    #[inline]
+    pub fn right_side_bearing(&self) -> f32 {
+        self.advance - self.bounds.max_x()
+    }
+}
+
+// Returns the typographic ascender, descender, and line gap from an `OS/2` table, if it has them
+// and asks for them to be used.
+fn typographic_line_metrics(os_2: &[u8]) -> Option<(f32, f32, f32)> {
+    let fs_selection = sfnt::read_u16(os_2, OS_2_FS_SELECTION_OFFSET).unwrap_or(0);
+    if fs_selection & USE_TYPO_METRICS == 0 {
+        return None
+    }
+    match (sfnt::read_i16(os_2, OS_2_TYPO_ASCENDER_OFFSET),
+           sfnt::read_i16(os_2, OS_2_TYPO_DESCENDER_OFFSET),
+           sfnt::read_i16(os_2, OS_2_TYPO_LINE_GAP_OFFSET)) {
+        (Ok(ascent), Ok(descent), Ok(line_gap)) => {
+            Some((ascent as f32, descent as f32, line_gap as f32))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use font_kit::font::Font;
+    use std::sync::Arc;
+
+    use super::{FontMetrics, GlyphMetrics, USE_TYPO_METRICS, typographic_line_metrics};
+
+    #[test]
+    fn short_os_2_tables_are_ignored() {
+        // A version 0 table as Apple first defined it ends before the typographic metrics.
+        let mut os_2 = vec![0; 68];
+        os_2[63] = USE_TYPO_METRICS as u8;
+        assert_eq!(typographic_line_metrics(&os_2), None);
+        assert_eq!(typographic_line_metrics(&os_2[..40]), None);
+
+        os_2.extend_from_slice(&[0x03, 0xe8, 0xff, 0x38, 0x00, 0x64]);
+        assert_eq!(typographic_line_metrics(&os_2), Some((1000.0, -200.0, 100.0)));
+        os_2[63] = 0;
+        assert_eq!(typographic_line_metrics(&os_2), None);
+    }
+
+    #[test]
+    fn metrics_come_from_font_kit() {
+        let data = include_bytes!("../../resources/fonts/open-sans/OpenSans-Regular.ttf");
+        let font = Font::from_bytes(Arc::new(data.to_vec()), 0).unwrap();
+        let metrics = FontMetrics::from_font(&font, 0);
+        assert_eq!(metrics.units_per_em, 2048);
+        assert!(metrics.ascent > 0.0 && metrics.descent < 0.0);
+
+        let glyph_id = font.glyph_for_char('H').unwrap();
+        let glyph_metrics = GlyphMetrics::from_font(&font, glyph_id).unwrap();
+        assert_eq!(glyph_metrics.advance, font.advance(glyph_id).unwrap().x);
+        assert!(glyph_metrics.left_side_bearing() > 0.0);
+        assert!(glyph_metrics.right_side_bearing() > 0.0);
+    }
+}
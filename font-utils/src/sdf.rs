@@ -0,0 +1,113 @@
+// pathfinder/font-utils/src/sdf.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Generates signed distance fields for glyphs, as an alternative to partitioning them into
+//! meshes.
+//!
+//! A distance field generated at one size can be drawn at many others, and outlines and glows
+//! can be drawn from it by thresholding at different distances. See
+//! `pathfinder_path_utils::sdf` for the encoding.
+
+use euclid::{Point2D, Size2D, Transform2D, Vector2D};
+use font_kit::error::GlyphLoadingError;
+use font_kit::font::Font;
+use font_kit::hinting::HintingOptions;
+use lyon_path::PathEvent;
+use lyon_path::builder::FlatPathBuilder;
+use lyon_path::default::Path as LyonPath;
+use pathfinder_path_utils::sdf::{self, DistanceField, DistanceFieldKind};
+use pathfinder_path_utils::transform::Transform2DPathIter;
+use std::f32;
+
+/// The tolerance, in field pixels, to which curves are approximated.
+const DISTANCE_FIELD_APPROX_TOLERANCE: f32 = 0.05;
+
+/// Settings for `glyph_distance_field()`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DistanceFieldOptions {
+    pub kind: DistanceFieldKind,
+    /// The size to generate the field at, in pixels per em.
+    pub point_size: f32,
+    /// The span of distances, in field pixels, that the encoded values cover. Outlines and glows
+    /// can extend up to half of this beyond the glyph.
+    pub range: f32,
+    /// The number of pixels to leave around the glyph's bounds. This should be at least half of
+    /// `range`.
+    pub padding: u32,
+}
+
+impl Default for DistanceFieldOptions {
+    fn default() -> DistanceFieldOptions {
+        DistanceFieldOptions {
+            kind: DistanceFieldKind::MultiChannel,
+            point_size: 32.0,
+            range: 4.0,
+            padding: 2,
+        }
+    }
+}
+
+/// A distance field for one glyph.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GlyphDistanceField {
+    pub field: DistanceField,
+    /// The position of the upper left corner of the field relative to the glyph origin, in field
+    /// pixels, with Y pointing up.
+    pub origin: Point2D<i32>,
+}
+
+/// Generates a distance field for the glyph. Returns `Ok(None)` if the glyph has no outline, such
+/// as for a space.
+pub fn glyph_distance_field(font: &Font, glyph_id: u32, options: &DistanceFieldOptions)
+                            -> Result<Option<GlyphDistanceField>, GlyphLoadingError> {
+    let mut path_builder = LyonPath::builder();
+    try!(font.outline(glyph_id, HintingOptions::None, &mut path_builder));
+    let path = path_builder.build();
+
+    // Find the bounds of the outline's points in field pixels. The control points of a curve
+    // contain it, so this is conservative.
+    let scale = options.point_size / font.metrics().units_per_em as f32;
+    let (mut min, mut max) = (Point2D::new(f32::INFINITY, f32::INFINITY),
+                              Point2D::new(f32::NEG_INFINITY, f32::NEG_INFINITY));
+    for event in path.iter() {
+        let points = match event {
+            PathEvent::MoveTo(to) | PathEvent::LineTo(to) => [to, to, to],
+            PathEvent::QuadraticTo(ctrl, to) => [ctrl, to, to],
+            PathEvent::CubicTo(ctrl1, ctrl2, to) => [ctrl1, ctrl2, to],
+            PathEvent::Arc(..) | PathEvent::Close => continue,
+        };
+        for point in &points {
+            min = min.min(*point * scale);
+            max = max.max(*point * scale);
+        }
+    }
+    if min.x > max.x || min.y > max.y {
+        return Ok(None)
+    }
+
+    let padding = options.padding as i32;
+    let origin = Point2D::new(min.x.floor() as i32 - padding, max.y.ceil() as i32 + padding);
+    let size = Size2D::new((max.x.ceil() as i32 + padding - origin.x) as u32,
+                           (origin.y - (min.y.floor() as i32 - padding)) as u32);
+
+    // Flip Y so that rows run from the top of the glyph down.
+    let transform = Transform2D::create_scale(scale, -scale)
+                                .post_translate(Vector2D::new(-origin.x as f32, origin.y as f32));
+    let field = sdf::generate_distance_field(Transform2DPathIter::new(path.iter(), &transform),
+                                             &size,
+                                             options.range,
+                                             options.kind,
+                                             DISTANCE_FIELD_APPROX_TOLERANCE);
+
+    Ok(Some(GlyphDistanceField {
+        field: field,
+        origin: origin,
+    }))
+}
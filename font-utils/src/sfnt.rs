@@ -0,0 +1,139 @@
+// pathfinder/font-utils/src/sfnt.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Locates tables in OpenType and TrueType font files and collections.
+
+use byteorder::{BigEndian, ByteOrder};
+use std::io::{self, ErrorKind};
+
+/// The tag that starts a TrueType or OpenType collection (`ttcf`).
+pub const TTCF: u32 = 0x74746366;
+
+/// The color bitmap data table (`CBDT`).
+pub const CBDT: u32 = 0x43424454;
+/// The color bitmap location table (`CBLC`).
+pub const CBLC: u32 = 0x43424c43;
+/// The color glyph layer table (`COLR`).
+pub const COLR: u32 = 0x434f4c52;
+/// The color palette table (`CPAL`).
+pub const CPAL: u32 = 0x4350414c;
+/// The glyph substitution table (`GSUB`).
+pub const GSUB: u32 = 0x47535542;
+/// The font header table (`head`).
+pub const HEAD: u32 = 0x68656164;
+/// The horizontal header table (`hhea`).
+pub const HHEA: u32 = 0x68686561;
+/// The horizontal metrics table (`hmtx`).
+pub const HMTX: u32 = 0x686d7478;
+/// The maximum profile table (`maxp`), which holds the glyph count.
+pub const MAXP: u32 = 0x6d617870;
+/// The OS/2 and Windows metrics table (`OS/2`).
+pub const OS_2: u32 = 0x4f532f32;
+/// The standard bitmap graphics table (`sbix`).
+pub const SBIX: u32 = 0x73626978;
+/// The SVG glyph document table (`SVG `).
+pub const SVG: u32 = 0x53564720;
+
+const OFFSET_TABLE_SIZE: usize = 12;
+const TABLE_RECORD_SIZE: usize = 16;
+const TTC_HEADER_SIZE: usize = 12;
+
+/// One font in a font file, with its table directory parsed.
+#[derive(Clone, Debug)]
+pub struct FontData<'a> {
+    data: &'a [u8],
+    tables: Vec<TableRecord>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct TableRecord {
+    tag: u32,
+    offset: u32,
+    length: u32,
+}
+
+impl<'a> FontData<'a> {
+    /// Parses the table directory of the font at `font_index` in `data`.
+    ///
+    /// `font_index` must be 0 unless `data` is a collection.
+    pub fn new(data: &'a [u8], font_index: u32) -> io::Result<FontData<'a>> {
+        let offset_table_offset = if try!(read_u32(data, 0)) == TTCF {
+            if font_index >= try!(font_count(data)) {
+                return Err(io::Error::from(ErrorKind::NotFound))
+            }
+            try!(read_u32(data, TTC_HEADER_SIZE + font_index as usize * 4)) as usize
+        } else if font_index == 0 {
+            0
+        } else {
+            return Err(io::Error::from(ErrorKind::NotFound))
+        };
+
+        let table_count = try!(read_u16(data, offset_table_offset + 4)) as usize;
+        let mut tables = Vec::with_capacity(table_count);
+        for table_index in 0..table_count {
+            let record_offset = offset_table_offset + OFFSET_TABLE_SIZE +
+                table_index * TABLE_RECORD_SIZE;
+            let record = TableRecord {
+                tag: try!(read_u32(data, record_offset)),
+                offset: try!(read_u32(data, record_offset + 8)),
+                length: try!(read_u32(data, record_offset + 12)),
+            };
+            if (record.offset as usize).saturating_add(record.length as usize) > data.len() {
+                return Err(io::Error::from(ErrorKind::InvalidData))
+            }
+            tables.push(record);
+        }
+
+        Ok(FontData {
+            data: data,
+            tables: tables,
+        })
+    }
+
+    /// Returns the contents of the table with the given tag, if the font has one.
+    pub fn table(&self, tag: u32) -> Option<&'a [u8]> {
+        self.tables.iter().find(|record| record.tag == tag).map(|record| {
+            let start = record.offset as usize;
+            &self.data[start..(start + record.length as usize)]
+        })
+    }
+}
+
+/// Returns the number of fonts in `data`: the number of fonts in the collection if it is one, or
+/// 1 otherwise.
+pub fn font_count(data: &[u8]) -> io::Result<u32> {
+    if try!(read_u32(data, 0)) == TTCF {
+        read_u32(data, 8)
+    } else {
+        Ok(1)
+    }
+}
+
+/// Reads a big-endian `u16` at `offset`, failing if it is out of bounds.
+pub fn read_u16(data: &[u8], offset: usize) -> io::Result<u16> {
+    match data.get(offset..(offset + 2)) {
+        Some(bytes) => Ok(BigEndian::read_u16(bytes)),
+        None => Err(io::Error::from(ErrorKind::UnexpectedEof)),
+    }
+}
+
+/// Reads a big-endian `i16` at `offset`, failing if it is out of bounds.
+#[inline]
+pub fn read_i16(data: &[u8], offset: usize) -> io::Result<i16> {
+    read_u16(data, offset).map(|value| value as i16)
+}
+
+/// Reads a big-endian `u32` at `offset`, failing if it is out of bounds.
+pub fn read_u32(data: &[u8], offset: usize) -> io::Result<u32> {
+    match data.get(offset..(offset + 4)) {
+        Some(bytes) => Ok(BigEndian::read_u32(bytes)),
+        None => Err(io::Error::from(ErrorKind::UnexpectedEof)),
+    }
+}
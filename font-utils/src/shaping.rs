@@ -0,0 +1,115 @@
+// pathfinder/font-utils/src/shaping.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Shapes text with HarfBuzz, producing positioned glyph IDs that can be looked up in a mesh pack
+//! built for the same font.
+//!
+//! This module is only available with the `shaping` feature.
+
+use euclid::{Point2D, Vector2D};
+use harfbuzz_rs::{self, Direction, Face, Font, Language, Tag, UnicodeBuffer};
+
+/// The direction text is laid out in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextDirection {
+    LeftToRight,
+    RightToLeft,
+    TopToBottom,
+    BottomToTop,
+}
+
+/// Settings for `shape()`. Anything left as `None` is guessed from the text.
+#[derive(Clone, Debug, Default)]
+pub struct ShapingOptions {
+    /// The ISO 15924 script tag, such as `*b"Arab"`.
+    pub script: Option<[u8; 4]>,
+    /// The BCP 47 language tag, such as `"ar"`.
+    pub language: Option<String>,
+    pub direction: Option<TextDirection>,
+}
+
+/// One glyph of a shaped run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapedGlyph {
+    pub glyph_id: u32,
+    /// The byte offset in the text of the first character that this glyph comes from. Glyphs
+    /// from the same cluster (for example, a base letter and its marks) share a value.
+    pub cluster: u32,
+    /// Where to draw the glyph's origin, in pixels relative to the start of the run, with Y
+    /// pointing up.
+    pub position: Point2D<f32>,
+    /// How far this glyph moves the pen, in pixels.
+    pub advance: Vector2D<f32>,
+}
+
+/// A shaped run of text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextRun {
+    /// The glyphs, in visual order.
+    pub glyphs: Vec<ShapedGlyph>,
+    /// The total advance of the run, in pixels.
+    pub advance: Vector2D<f32>,
+}
+
+/// Shapes `text` with the font at `font_index` in `font_data`, at `point_size` pixels per em.
+///
+/// If `font_data` is a collection, `font_index` selects the face, as with `font-kit`.
+pub fn shape(font_data: &[u8],
+             font_index: u32,
+             text: &str,
+             point_size: f32,
+             options: &ShapingOptions)
+             -> TextRun {
+    let face = Face::from_bytes(font_data, font_index);
+    let scale = point_size / face.upem() as f32;
+    let font = Font::new(face);
+
+    let mut buffer = UnicodeBuffer::new().add_str(text);
+    if let Some(script) = options.script {
+        buffer = buffer.set_script(Tag::new(script[0] as char,
+                                            script[1] as char,
+                                            script[2] as char,
+                                            script[3] as char));
+    }
+    if let Some(language) = options.language.as_ref().and_then(|language| {
+        language.parse::<Language>().ok()
+    }) {
+        buffer = buffer.set_language(language);
+    }
+    if let Some(direction) = options.direction {
+        buffer = buffer.set_direction(match direction {
+            TextDirection::LeftToRight => Direction::Ltr,
+            TextDirection::RightToLeft => Direction::Rtl,
+            TextDirection::TopToBottom => Direction::Ttb,
+            TextDirection::BottomToTop => Direction::Btt,
+        });
+    }
+
+    let output = harfbuzz_rs::shape(&font, buffer, &[]);
+
+    let mut pen = Point2D::zero();
+    let mut glyphs = Vec::with_capacity(output.len());
+    for (info, position) in output.get_glyph_infos().iter().zip(output.get_glyph_positions()) {
+        let offset = Vector2D::new(position.x_offset as f32, position.y_offset as f32) * scale;
+        let advance = Vector2D::new(position.x_advance as f32, position.y_advance as f32) * scale;
+        glyphs.push(ShapedGlyph {
+            glyph_id: info.codepoint,
+            cluster: info.cluster,
+            position: pen + offset,
+            advance: advance,
+        });
+        pen += advance;
+    }
+
+    TextRun {
+        glyphs: glyphs,
+        advance: pen.to_vector(),
+    }
+}
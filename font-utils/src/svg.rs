@@ -0,0 +1,156 @@
+// pathfinder/font-utils/src/svg.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! SVG glyph documents from the `SVG ` table.
+//!
+//! Each document is a complete SVG file that draws one or more glyphs. `pathfinder_canvas` draws
+//! the element for each glyph from the decompressed document, partitioning its paths like any
+//! other SVG. Glyphs without a document should be drawn from their outlines.
+
+use flate2::read::GzDecoder;
+use std::io::{self, ErrorKind, Read};
+
+use sfnt::{self, FontData};
+
+const DOCUMENT_RECORD_SIZE: usize = 12;
+
+/// The first two bytes of a gzip stream. Documents may be stored compressed.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+#[derive(Clone, Copy, Debug)]
+struct DocumentRecord {
+    start_glyph_id: u16,
+    end_glyph_id: u16,
+    offset: u32,
+    length: u32,
+}
+
+/// An SVG document from the font, along with the range of glyphs it draws.
+#[derive(Clone, Debug)]
+pub struct SvgGlyphDocument {
+    /// The first glyph that this document draws.
+    pub start_glyph_id: u32,
+    /// The last glyph that this document draws, inclusive.
+    pub end_glyph_id: u32,
+    /// The uncompressed SVG source.
+    pub data: Vec<u8>,
+}
+
+impl SvgGlyphDocument {
+    /// Returns the `id` of the element within this document that draws the glyph.
+    #[inline]
+    pub fn element_id(glyph_id: u32) -> String {
+        format!("glyph{}", glyph_id)
+    }
+}
+
+/// The SVG glyph documents of a font.
+#[derive(Clone, Debug)]
+pub struct SvgGlyphs<'a> {
+    document_list: &'a [u8],
+    records: Vec<DocumentRecord>,
+}
+
+impl<'a> SvgGlyphs<'a> {
+    /// Reads the `SVG ` table. Returns `Ok(None)` if the font has no SVG glyphs.
+    pub fn from_font_data(font_data: &FontData<'a>) -> io::Result<Option<SvgGlyphs<'a>>> {
+        let table = match font_data.table(sfnt::SVG) {
+            None => return Ok(None),
+            Some(table) => table,
+        };
+
+        let document_list_offset = try!(sfnt::read_u32(table, 2)) as usize;
+        let document_list = match table.get(document_list_offset..) {
+            None => return Err(io::Error::from(ErrorKind::InvalidData)),
+            Some(document_list) => document_list,
+        };
+
+        let record_count = try!(sfnt::read_u16(document_list, 0)) as usize;
+        let mut records = Vec::with_capacity(record_count);
+        for record_index in 0..record_count {
+            let offset = 2 + record_index * DOCUMENT_RECORD_SIZE;
+            let record = DocumentRecord {
+                start_glyph_id: try!(sfnt::read_u16(document_list, offset)),
+                end_glyph_id: try!(sfnt::read_u16(document_list, offset + 2)),
+                offset: try!(sfnt::read_u32(document_list, offset + 4)),
+                length: try!(sfnt::read_u32(document_list, offset + 8)),
+            };
+            if record.end_glyph_id < record.start_glyph_id ||
+                    (record.offset as usize).saturating_add(record.length as usize) >
+                    document_list.len() {
+                return Err(io::Error::from(ErrorKind::InvalidData))
+            }
+            records.push(record);
+        }
+
+        // `document()` binary searches the records.
+        records.sort_by_key(|record| record.start_glyph_id);
+
+        Ok(Some(SvgGlyphs {
+            document_list: document_list,
+            records: records,
+        }))
+    }
+
+    /// Returns true if the glyph has an SVG document.
+    #[inline]
+    pub fn has_document(&self, glyph_id: u32) -> bool {
+        self.find_record(glyph_id).is_some()
+    }
+
+    /// Returns the document that draws the glyph, decompressing it if necessary, or `Ok(None)`
+    /// if the glyph has none.
+    pub fn document(&self, glyph_id: u32) -> io::Result<Option<SvgGlyphDocument>> {
+        let record = match self.find_record(glyph_id) {
+            None => return Ok(None),
+            Some(record) => record,
+        };
+
+        let start = record.offset as usize;
+        let raw_data = &self.document_list[start..(start + record.length as usize)];
+
+        let data = if raw_data.starts_with(&GZIP_MAGIC) {
+            let mut data = vec![];
+            try!(GzDecoder::new(raw_data).read_to_end(&mut data));
+            data
+        } else {
+            raw_data.to_vec()
+        };
+
+        Ok(Some(SvgGlyphDocument {
+            start_glyph_id: record.start_glyph_id as u32,
+            end_glyph_id: record.end_glyph_id as u32,
+            data: data,
+        }))
+    }
+
+    fn find_record(&self, glyph_id: u32) -> Option<&DocumentRecord> {
+        if glyph_id > u16::max_value() as u32 {
+            return None
+        }
+        let glyph_id = glyph_id as u16;
+
+        // Find the last record starting at or before the glyph.
+        let record_index = match self.records.binary_search_by_key(&glyph_id, |record| {
+            record.start_glyph_id
+        }) {
+            Ok(record_index) => record_index,
+            Err(0) => return None,
+            Err(record_index) => record_index - 1,
+        };
+
+        let record = &self.records[record_index];
+        if glyph_id <= record.end_glyph_id {
+            Some(record)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,145 @@
+// pathfinder/font-utils/src/synthesis.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Synthesizes oblique and small-caps styles for faces that lack them, by transforming the
+//! outlines of the regular glyphs.
+//!
+//! The transforms here apply to glyph outlines in font units, with Y pointing up, before any
+//! transform that positions the glyph. Compose them with the glyph's own transform, such as the
+//! `transform` of each glyph in a partitioning request.
+
+use euclid::Transform2D;
+use font_kit::font::Font;
+use font_kit::properties::Style;
+use std::io;
+
+use sfnt::{self, FontData};
+
+/// The slant used for synthetic obliques, in degrees. This is the CSS default for
+/// `font-style: oblique`.
+pub const DEFAULT_OBLIQUE_ANGLE: f32 = 14.0;
+
+/// The size of synthetic small capitals relative to full capitals, used when the font doesn't
+/// say what its x-height is.
+pub const DEFAULT_SMALL_CAPS_SCALE: f32 = 0.7;
+
+/// The OpenType feature that substitutes real small capitals (`smcp`).
+const SMCP: u32 = 0x736d6370;
+
+const OS_2_X_HEIGHT_OFFSET: usize = 86;
+const OS_2_CAP_HEIGHT_OFFSET: usize = 88;
+const GSUB_FEATURE_LIST_OFFSET: usize = 6;
+const FEATURE_RECORD_SIZE: usize = 6;
+
+/// How to synthesize missing styles.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SynthesisOptions {
+    /// The angle to slant glyphs by, in degrees, or zero for none. Positive angles lean the tops
+    /// of the glyphs to the right.
+    pub oblique_angle: f32,
+    /// If present, lowercase letters are drawn as capitals scaled by this factor.
+    pub small_caps_scale: Option<f32>,
+}
+
+impl Default for SynthesisOptions {
+    #[inline]
+    fn default() -> SynthesisOptions {
+        SynthesisOptions {
+            oblique_angle: 0.0,
+            small_caps_scale: None,
+        }
+    }
+}
+
+impl SynthesisOptions {
+    /// Chooses the synthesis needed to draw `font` italic and/or in small capitals, leaving out
+    /// whatever the face already provides: an italic or oblique face isn't slanted again, and a
+    /// face with an `smcp` feature is expected to be shaped with it instead.
+    pub fn for_font(font: &Font, font_data: &FontData, italic: bool, small_caps: bool)
+                    -> SynthesisOptions {
+        let oblique_angle = if italic && font.properties().style == Style::Normal {
+            DEFAULT_OBLIQUE_ANGLE
+        } else {
+            0.0
+        };
+        let has_real_small_caps = has_small_caps_feature(font_data).unwrap_or(false);
+        let small_caps_scale = if small_caps && !has_real_small_caps {
+            Some(small_caps_scale(font_data))
+        } else {
+            None
+        };
+        SynthesisOptions {
+            oblique_angle: oblique_angle,
+            small_caps_scale: small_caps_scale,
+        }
+    }
+
+    /// Returns the glyph to draw for `character` and the transform to apply to its outline. The
+    /// glyph's advance should be scaled by the transform's horizontal scale as well.
+    ///
+    /// Returns `None` if the font has no glyph for the character.
+    pub fn glyph_for_char(&self, font: &Font, character: char) -> Option<(u32, Transform2D<f32>)> {
+        let mut transform = oblique_transform(self.oblique_angle);
+
+        if let Some(scale) = self.small_caps_scale {
+            let mut uppercase = character.to_uppercase();
+            if let (true, Some(capital), None) = (character.is_lowercase(),
+                                                  uppercase.next(),
+                                                  uppercase.next()) {
+                if let Some(glyph_id) = font.glyph_for_char(capital) {
+                    transform = transform.pre_mul(&Transform2D::create_scale(scale, scale));
+                    return Some((glyph_id, transform))
+                }
+            }
+        }
+
+        font.glyph_for_char(character).map(|glyph_id| (glyph_id, transform))
+    }
+}
+
+/// Returns the transform that slants an outline by `angle` degrees, pivoting around the
+/// baseline.
+pub fn oblique_transform(angle: f32) -> Transform2D<f32> {
+    Transform2D::row_major(1.0, 0.0, angle.to_radians().tan(), 1.0, 0.0, 0.0)
+}
+
+/// Returns the factor to scale capitals by to draw small capitals: the ratio of the x-height to
+/// the cap height if the `OS/2` table has them, or `DEFAULT_SMALL_CAPS_SCALE` otherwise.
+pub fn small_caps_scale(font_data: &FontData) -> f32 {
+    let os_2 = match font_data.table(sfnt::OS_2) {
+        None => return DEFAULT_SMALL_CAPS_SCALE,
+        Some(os_2) => os_2,
+    };
+    match (sfnt::read_u16(os_2, OS_2_X_HEIGHT_OFFSET),
+           sfnt::read_u16(os_2, OS_2_CAP_HEIGHT_OFFSET)) {
+        (Ok(x_height), Ok(cap_height)) if x_height > 0 && x_height < cap_height => {
+            x_height as f32 / cap_height as f32
+        }
+        _ => DEFAULT_SMALL_CAPS_SCALE,
+    }
+}
+
+/// Returns true if the font's `GSUB` table has an `smcp` feature, which provides real small
+/// capitals.
+pub fn has_small_caps_feature(font_data: &FontData) -> io::Result<bool> {
+    let gsub = match font_data.table(sfnt::GSUB) {
+        None => return Ok(false),
+        Some(gsub) => gsub,
+    };
+    let feature_list_offset = try!(sfnt::read_u16(gsub, GSUB_FEATURE_LIST_OFFSET)) as usize;
+    let feature_count = try!(sfnt::read_u16(gsub, feature_list_offset));
+    for feature_index in 0..(feature_count as usize) {
+        let record_offset = feature_list_offset + 2 + feature_index * FEATURE_RECORD_SIZE;
+        if try!(sfnt::read_u32(gsub, record_offset)) == SMCP {
+            return Ok(true)
+        }
+    }
+    Ok(false)
+}
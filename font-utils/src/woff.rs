@@ -0,0 +1,130 @@
+// pathfinder/font-utils/src/woff.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Decodes WOFF 1.0 web fonts into ordinary OpenType data that `font-kit` can load.
+//!
+//! WOFF2 is not handled here, because reconstructing its transformed `glyf` and `loca` tables
+//! amounts to a second font compiler. The demo server accepts WOFF2 anyway, since its font
+//! sanitizer decodes it.
+
+use byteorder::{BigEndian, WriteBytesExt};
+use flate2::read::ZlibDecoder;
+use std::io::{self, ErrorKind, Read};
+
+use sfnt;
+
+/// The signature that starts a WOFF 1.0 file (`wOFF`).
+pub const WOFF_SIGNATURE: u32 = 0x774f4646;
+
+const WOFF_HEADER_SIZE: usize = 44;
+const WOFF_TABLE_RECORD_SIZE: usize = 20;
+const SFNT_OFFSET_TABLE_SIZE: usize = 12;
+const SFNT_TABLE_RECORD_SIZE: usize = 16;
+
+/// Returns true if `data` looks like a WOFF 1.0 file.
+#[inline]
+pub fn is_woff(data: &[u8]) -> bool {
+    sfnt::read_u32(data, 0).ok() == Some(WOFF_SIGNATURE)
+}
+
+/// Decodes a WOFF 1.0 file, decompressing its tables and rebuilding the OpenType table
+/// directory.
+///
+/// Extended metadata and private data are dropped, since they aren't part of the font.
+pub fn decode(data: &[u8]) -> io::Result<Vec<u8>> {
+    if !is_woff(data) {
+        return Err(io::Error::from(ErrorKind::InvalidData))
+    }
+
+    let flavor = try!(sfnt::read_u32(data, 4));
+    let table_count = try!(sfnt::read_u16(data, 12));
+    if table_count == 0 {
+        return Err(io::Error::from(ErrorKind::InvalidData))
+    }
+
+    struct Table<'a> {
+        tag: u32,
+        checksum: u32,
+        compressed_data: &'a [u8],
+        original_length: usize,
+    }
+
+    let mut tables = Vec::with_capacity(table_count as usize);
+    for table_index in 0..(table_count as usize) {
+        let record_offset = WOFF_HEADER_SIZE + table_index * WOFF_TABLE_RECORD_SIZE;
+        let offset = try!(sfnt::read_u32(data, record_offset + 4)) as usize;
+        let compressed_length = try!(sfnt::read_u32(data, record_offset + 8)) as usize;
+        let original_length = try!(sfnt::read_u32(data, record_offset + 12)) as usize;
+        let compressed_data = match data.get(offset..offset.saturating_add(compressed_length)) {
+            None => return Err(io::Error::from(ErrorKind::InvalidData)),
+            Some(compressed_data) => compressed_data,
+        };
+        if compressed_length > original_length {
+            return Err(io::Error::from(ErrorKind::InvalidData))
+        }
+        tables.push(Table {
+            tag: try!(sfnt::read_u32(data, record_offset)),
+            checksum: try!(sfnt::read_u32(data, record_offset + 16)),
+            compressed_data: compressed_data,
+            original_length: original_length,
+        })
+    }
+
+    // The table directory must be sorted by tag.
+    tables.sort_by_key(|table| table.tag);
+
+    // Write the offset table.
+    let (mut search_range_tables, mut entry_selector) = (1, 0);
+    while search_range_tables * 2 <= tables.len() {
+        search_range_tables *= 2;
+        entry_selector += 1;
+    }
+    let search_range = search_range_tables * SFNT_TABLE_RECORD_SIZE;
+    let range_shift = tables.len() * SFNT_TABLE_RECORD_SIZE - search_range;
+    if range_shift > u16::max_value() as usize {
+        return Err(io::Error::from(ErrorKind::InvalidData))
+    }
+
+    let mut output = vec![];
+    try!(output.write_u32::<BigEndian>(flavor));
+    try!(output.write_u16::<BigEndian>(table_count));
+    try!(output.write_u16::<BigEndian>(search_range as u16));
+    try!(output.write_u16::<BigEndian>(entry_selector));
+    try!(output.write_u16::<BigEndian>(range_shift as u16));
+
+    // Write the table directory, laying the tables out in order with 4-byte alignment.
+    let mut table_offset = SFNT_OFFSET_TABLE_SIZE + tables.len() * SFNT_TABLE_RECORD_SIZE;
+    for table in &tables {
+        try!(output.write_u32::<BigEndian>(table.tag));
+        try!(output.write_u32::<BigEndian>(table.checksum));
+        try!(output.write_u32::<BigEndian>(table_offset as u32));
+        try!(output.write_u32::<BigEndian>(table.original_length as u32));
+        table_offset += (table.original_length + 3) & !3;
+    }
+
+    // Write the table data. Tables are stored uncompressed if compression wouldn't help.
+    for table in &tables {
+        let start = output.len();
+        if table.compressed_data.len() == table.original_length {
+            output.extend_from_slice(table.compressed_data);
+        } else {
+            try!(ZlibDecoder::new(table.compressed_data).take(table.original_length as u64)
+                                                         .read_to_end(&mut output));
+        }
+        if output.len() - start != table.original_length {
+            return Err(io::Error::from(ErrorKind::InvalidData))
+        }
+        while output.len() % 4 != 0 {
+            output.push(0)
+        }
+    }
+
+    Ok(output)
+}
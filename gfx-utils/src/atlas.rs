@@ -0,0 +1,311 @@
+// pathfinder/gfx-utils/src/atlas.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A glyph atlas that spans multiple textures and evicts least-recently-used entries to stay
+//! within a memory budget.
+//!
+//! Unlike `ShelfBinPacker`, space is reclaimed when entries are removed, so long-running
+//! applications don't run out of atlas space. Each page is one texture, packed with shelves.
+//! Call `begin_frame()` once per frame; entries that have been looked up or allocated during the
+//! current frame are never evicted.
+
+use euclid::{Point2D, Rect, Size2D};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// Shelves are reused only for entries at least this fraction of their height, to limit waste.
+const MIN_SHELF_FILL: f32 = 0.7;
+
+/// Where an entry lives in the atlas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AtlasAllocation {
+    /// The texture page.
+    pub page: u32,
+    /// The area of the page that the entry may draw into.
+    pub rect: Rect<i32>,
+}
+
+/// A multi-page atlas keyed by `K`, such as a glyph ID and subpixel offset.
+pub struct Atlas<K> where K: Clone + Eq + Hash {
+    page_size: Size2D<i32>,
+    padding: i32,
+    max_pages: u32,
+    pages: Vec<Page>,
+    entries: HashMap<K, Entry>,
+    // Indexed by the time each entry was last used, so that the first entry is the least recently
+    // used one.
+    lru: BTreeMap<(u64, u64), K>,
+    current_frame: u64,
+    next_serial: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    allocation: AtlasAllocation,
+    shelf_index: usize,
+    lru_key: (u64, u64),
+}
+
+struct Page {
+    shelves: Vec<Shelf>,
+    next_shelf_y: i32,
+}
+
+struct Shelf {
+    y: i32,
+    height: i32,
+    // Free horizontal ranges as (x, width), sorted by X and never adjacent.
+    free_ranges: Vec<(i32, i32)>,
+    entry_count: u32,
+}
+
+impl<K> Atlas<K> where K: Clone + Eq + Hash {
+    /// Creates an empty atlas of `page_size` pages, each `bytes_per_pixel` bytes per pixel.
+    ///
+    /// New pages are added as needed until they would take more than `memory_budget` bytes; after
+    /// that, entries are evicted instead. There is always room for at least one page. `padding`
+    /// pixels are left between entries and around the edges of each page.
+    pub fn new(page_size: &Size2D<i32>, padding: i32, bytes_per_pixel: usize, memory_budget: usize)
+               -> Atlas<K> {
+        let page_bytes = page_size.width as usize * page_size.height as usize * bytes_per_pixel;
+        let max_pages = if page_bytes == 0 {
+            1
+        } else {
+            (memory_budget / page_bytes).max(1) as u32
+        };
+
+        Atlas {
+            page_size: *page_size,
+            padding: padding,
+            max_pages: max_pages,
+            pages: vec![],
+            entries: HashMap::new(),
+            lru: BTreeMap::new(),
+            current_frame: 0,
+            next_serial: 0,
+        }
+    }
+
+    /// Starts a new frame. Entries used only in earlier frames become eligible for eviction.
+    #[inline]
+    pub fn begin_frame(&mut self) {
+        self.current_frame += 1
+    }
+
+    /// Returns the number of pages, which is the number of textures the renderer needs.
+    #[inline]
+    pub fn page_count(&self) -> u32 {
+        self.pages.len() as u32
+    }
+
+    #[inline]
+    pub fn page_size(&self) -> Size2D<i32> {
+        self.page_size
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Looks up an entry and marks it as used in this frame.
+    pub fn get(&mut self, key: &K) -> Option<AtlasAllocation> {
+        let (current_frame, serial) = (self.current_frame, self.next_serial);
+        let entry = match self.entries.get_mut(key) {
+            None => return None,
+            Some(entry) => entry,
+        };
+        if entry.lru_key.0 != current_frame {
+            let key = self.lru.remove(&entry.lru_key).unwrap();
+            entry.lru_key = (current_frame, serial);
+            self.lru.insert(entry.lru_key, key);
+            self.next_serial += 1;
+        }
+        Some(entry.allocation)
+    }
+
+    /// Reserves `size` pixels for `key`, evicting least-recently-used entries from earlier frames
+    /// if there is no room. Any existing entry for `key` is replaced.
+    ///
+    /// Fails if the entry doesn't fit on a page, or if everything that could make room for it was
+    /// used in this frame.
+    pub fn allocate(&mut self, key: K, size: &Size2D<i32>) -> Result<AtlasAllocation, ()> {
+        self.remove(&key);
+
+        let slot_size = Size2D::new(size.width + self.padding, size.height + self.padding);
+        if size.width < 0 || size.height < 0 ||
+                slot_size.width > self.page_size.width - self.padding ||
+                slot_size.height > self.page_size.height - self.padding {
+            return Err(())
+        }
+
+        loop {
+            if let Some((allocation, shelf_index)) = self.allocate_slot(&slot_size) {
+                let lru_key = (self.current_frame, self.next_serial);
+                self.next_serial += 1;
+                let allocation = AtlasAllocation {
+                    page: allocation.page,
+                    rect: Rect::new(allocation.rect.origin, *size),
+                };
+                self.entries.insert(key.clone(), Entry {
+                    allocation: allocation,
+                    shelf_index: shelf_index,
+                    lru_key: lru_key,
+                });
+                self.lru.insert(lru_key, key);
+                return Ok(allocation)
+            }
+
+            if (self.pages.len() as u32) < self.max_pages {
+                self.pages.push(Page::new(self.padding));
+                continue
+            }
+
+            // Evict the least recently used entry, unless it is in use.
+            let victim = match self.lru.iter().next() {
+                Some((&(frame, _), victim)) if frame < self.current_frame => victim.clone(),
+                _ => return Err(()),
+            };
+            self.remove(&victim);
+        }
+    }
+
+    /// Removes an entry, freeing its space. Returns true if there was one.
+    pub fn remove(&mut self, key: &K) -> bool {
+        let entry = match self.entries.remove(key) {
+            None => return false,
+            Some(entry) => entry,
+        };
+        self.lru.remove(&entry.lru_key);
+
+        let slot_width = entry.allocation.rect.size.width + self.padding;
+        let page = &mut self.pages[entry.allocation.page as usize];
+        page.shelves[entry.shelf_index].free(entry.allocation.rect.origin.x, slot_width);
+
+        // Give empty shelves at the bottom of the page back to the page, so that they can be
+        // reused at a different height.
+        while page.shelves.last().map_or(false, |shelf| shelf.is_empty()) {
+            let shelf = page.shelves.pop().unwrap();
+            page.next_shelf_y = shelf.y;
+        }
+        true
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        self.pages.clear();
+        self.entries.clear();
+        self.lru.clear();
+    }
+
+    // Finds room for a slot in an existing shelf, or in a new shelf on an existing page.
+    fn allocate_slot(&mut self, slot_size: &Size2D<i32>) -> Option<(AtlasAllocation, usize)> {
+        let (padding, page_size) = (self.padding, self.page_size);
+
+        for (page_index, page) in self.pages.iter_mut().enumerate() {
+            // First, try the shelf that wastes the least height.
+            let mut best_shelf: Option<(usize, i32)> = None;
+            for (shelf_index, shelf) in page.shelves.iter().enumerate() {
+                let fits = shelf.height >= slot_size.height &&
+                    (slot_size.height as f32 >= shelf.height as f32 * MIN_SHELF_FILL ||
+                     shelf.is_empty()) &&
+                    shelf.free_ranges.iter().any(|&(_, width)| width >= slot_size.width);
+                let waste = shelf.height - slot_size.height;
+                if fits && best_shelf.map_or(true, |(_, best_waste)| waste < best_waste) {
+                    best_shelf = Some((shelf_index, waste))
+                }
+            }
+
+            // Otherwise, open a new shelf.
+            let shelf_index = match best_shelf {
+                Some((shelf_index, _)) => shelf_index,
+                None if page.next_shelf_y + slot_size.height <= page_size.height => {
+                    page.shelves.push(Shelf {
+                        y: page.next_shelf_y,
+                        height: slot_size.height,
+                        free_ranges: vec![(padding, page_size.width - padding)],
+                        entry_count: 0,
+                    });
+                    page.next_shelf_y += slot_size.height;
+                    page.shelves.len() - 1
+                }
+                None => continue,
+            };
+
+            let shelf = &mut page.shelves[shelf_index];
+            let x = shelf.allocate(slot_size.width).unwrap();
+            return Some((AtlasAllocation {
+                page: page_index as u32,
+                rect: Rect::new(Point2D::new(x, shelf.y), *slot_size),
+            }, shelf_index))
+        }
+
+        None
+    }
+}
+
+impl Page {
+    fn new(padding: i32) -> Page {
+        Page {
+            shelves: vec![],
+            next_shelf_y: padding,
+        }
+    }
+}
+
+impl Shelf {
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.entry_count == 0
+    }
+
+    // Takes the first free range that is wide enough.
+    fn allocate(&mut self, width: i32) -> Option<i32> {
+        let range_index = match self.free_ranges.iter().position(|&(_, free)| free >= width) {
+            None => return None,
+            Some(range_index) => range_index,
+        };
+        let (x, free_width) = self.free_ranges[range_index];
+        if free_width == width {
+            self.free_ranges.remove(range_index);
+        } else {
+            self.free_ranges[range_index] = (x + width, free_width - width);
+        }
+        self.entry_count += 1;
+        Some(x)
+    }
+
+    // Returns a range to the free list, merging it with its neighbors.
+    fn free(&mut self, x: i32, width: i32) {
+        let index = self.free_ranges.iter().position(|&(free_x, _)| free_x > x)
+                                    .unwrap_or(self.free_ranges.len());
+        self.free_ranges.insert(index, (x, width));
+
+        if index + 1 < self.free_ranges.len() &&
+                self.free_ranges[index].0 + self.free_ranges[index].1 ==
+                self.free_ranges[index + 1].0 {
+            self.free_ranges[index].1 += self.free_ranges[index + 1].1;
+            self.free_ranges.remove(index + 1);
+        }
+        if index > 0 &&
+                self.free_ranges[index - 1].0 + self.free_ranges[index - 1].1 ==
+                self.free_ranges[index].0 {
+            self.free_ranges[index - 1].1 += self.free_ranges[index].1;
+            self.free_ranges.remove(index);
+        }
+
+        self.entry_count -= 1;
+    }
+}
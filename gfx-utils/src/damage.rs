@@ -0,0 +1,124 @@
+// pathfinder/gfx-utils/src/damage.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Tracks what changed between frames, so that only the damaged parts need to be redrawn.
+//!
+//! Each frame, report every item drawn along with its bounds and a *version* that changes
+//! whenever its appearance does (for example, a hash of its glyph ID and color). The tracker
+//! compares this against the previous frame and returns the rectangles to redraw, which can then
+//! be used as scissor rectangles.
+
+use euclid::Rect;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::mem;
+
+/// Once a frame has more damaged rectangles than this, they're merged into one.
+const MAX_DAMAGE_RECTS: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Item {
+    bounds: Rect<i32>,
+    version: u64,
+}
+
+pub struct DamageTracker<K> where K: Clone + Eq + Hash {
+    previous_items: HashMap<K, Item>,
+    current_items: HashMap<K, Item>,
+    // Set when the whole frame must be redrawn, such as on the first frame.
+    full_damage: bool,
+}
+
+impl<K> DamageTracker<K> where K: Clone + Eq + Hash {
+    /// Creates a tracker. The first frame is considered fully damaged.
+    pub fn new() -> DamageTracker<K> {
+        DamageTracker {
+            previous_items: HashMap::new(),
+            current_items: HashMap::new(),
+            full_damage: true,
+        }
+    }
+
+    /// Records that the item `key` is drawn this frame within `bounds`.
+    pub fn add(&mut self, key: K, bounds: &Rect<i32>, version: u64) {
+        self.current_items.insert(key, Item {
+            bounds: *bounds,
+            version: version,
+        });
+    }
+
+    /// Marks the whole frame as damaged, as when the viewport is resized.
+    #[inline]
+    pub fn invalidate(&mut self) {
+        self.full_damage = true
+    }
+
+    /// Finishes the frame and returns the areas that need to be redrawn, or `None` if everything
+    /// does.
+    ///
+    /// An item that appeared, disappeared, moved, or changed version damages both its old and its
+    /// new bounds. Overlapping rectangles are merged.
+    pub fn end_frame(&mut self) -> Option<Vec<Rect<i32>>> {
+        let previous_items = mem::replace(&mut self.previous_items, HashMap::new());
+        let current_items = mem::replace(&mut self.current_items, HashMap::new());
+
+        let mut damage = vec![];
+        for (key, item) in &current_items {
+            match previous_items.get(key) {
+                Some(previous_item) if previous_item == item => {}
+                Some(previous_item) => {
+                    damage.push(previous_item.bounds);
+                    damage.push(item.bounds);
+                }
+                None => damage.push(item.bounds),
+            }
+        }
+        for (key, previous_item) in &previous_items {
+            if !current_items.contains_key(key) {
+                damage.push(previous_item.bounds)
+            }
+        }
+
+        self.previous_items = current_items;
+        if self.full_damage {
+            self.full_damage = false;
+            return None
+        }
+
+        Some(merge_rects(damage))
+    }
+}
+
+// Merges overlapping rectangles until none overlap, and collapses them into their union if
+// there are too many.
+fn merge_rects(mut rects: Vec<Rect<i32>>) -> Vec<Rect<i32>> {
+    rects.retain(|rect| rect.size.width > 0 && rect.size.height > 0);
+
+    let mut merged = true;
+    while merged {
+        merged = false;
+        'outer: for index in 0..rects.len() {
+            for other_index in (index + 1)..rects.len() {
+                if rects[index].intersects(&rects[other_index]) {
+                    let other = rects.swap_remove(other_index);
+                    rects[index] = rects[index].union(&other);
+                    merged = true;
+                    break 'outer
+                }
+            }
+        }
+    }
+
+    if rects.len() > MAX_DAMAGE_RECTS {
+        let union = rects.iter().skip(1).fold(rects[0], |union, rect| union.union(rect));
+        return vec![union]
+    }
+    rects
+}
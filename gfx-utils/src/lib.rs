@@ -13,6 +13,9 @@ extern crate euclid;
 use euclid::{Point2D, Size2D, Vector2D};
 use std::cmp;
 
+pub mod atlas;
+pub mod damage;
+
 pub struct ShelfBinPacker {
     next: Point2D<i32>,
     max_size: Size2D<i32>,
@@ -0,0 +1,106 @@
+// pathfinder/partitioner/fuzz/fuzz_targets/partition_degenerate.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Partitions paths made from the fuzzer's input and checks that no B-quad comes out with a
+//! non-finite vertex or with its right side left of its left side.
+//!
+//! Coordinates are taken from a small grid, with optional nudges of a few ulps, so that the
+//! coincident, collinear, and nearly collinear inputs that trip up the sweep are common.
+//!
+//! Run with `cargo fuzz run partition_degenerate` from the `partitioner` directory.
+
+#![no_main]
+
+extern crate euclid;
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate lyon_path;
+extern crate pathfinder_partitioner;
+
+use euclid::Point2D;
+use lyon_path::PathEvent;
+use pathfinder_partitioner::FillRule;
+use pathfinder_partitioner::batch;
+use pathfinder_partitioner::mesh::Mesh;
+
+fuzz_target!(|data: &[u8]| {
+    let path = path_from_bytes(data);
+    for &fill_rule in &[FillRule::Winding, FillRule::EvenOdd] {
+        check_mesh(&batch::partition_path(&path, fill_rule, None));
+        check_mesh(&batch::partition_path_strict(&path, fill_rule, None));
+    }
+});
+
+// Each command is an opcode byte followed by the bytes of its points, two per point.
+fn path_from_bytes(data: &[u8]) -> Vec<PathEvent> {
+    let mut events = vec![];
+    let mut bytes = data.iter().cloned();
+    while let Some(opcode) = bytes.next() {
+        let mut point = || {
+            match (bytes.next(), bytes.next()) {
+                (Some(x), Some(y)) => Some(Point2D::new(coordinate(x), coordinate(y))),
+                _ => None,
+            }
+        };
+        let event = match opcode % 4 {
+            0 => point().map(PathEvent::MoveTo),
+            1 => point().map(PathEvent::LineTo),
+            2 => {
+                match (point(), point()) {
+                    (Some(ctrl), Some(to)) => Some(PathEvent::QuadraticTo(ctrl, to)),
+                    _ => None,
+                }
+            }
+            _ => Some(PathEvent::Close),
+        };
+        match event {
+            Some(event) => events.push(event),
+            None => break,
+        }
+    }
+
+    // The builder ignores everything before the first move.
+    if events.is_empty() {
+        return events
+    }
+    if let PathEvent::MoveTo(_) = events[0] {} else {
+        events.insert(0, PathEvent::MoveTo(Point2D::zero()))
+    }
+    events
+}
+
+// The low five bits pick a grid line, and the top three nudge it by up to three ulps either way.
+// The grid starts away from zero, where a nudge would flip the sign bit.
+fn coordinate(byte: u8) -> f32 {
+    let value = ((byte & 0x1f) + 1) as f32 * 4.0;
+    let nudge = (byte >> 5) as i32 - 3;
+    f32::from_bits((value.to_bits() as i32 + nudge) as u32)
+}
+
+fn check_mesh(mesh: &Mesh) {
+    for positions in &mesh.b_quad_vertex_positions {
+        for point in &[positions.upper_left_vertex_position,
+                       positions.upper_control_point_position,
+                       positions.upper_right_vertex_position,
+                       positions.lower_right_vertex_position,
+                       positions.lower_control_point_position,
+                       positions.lower_left_vertex_position] {
+            assert!(point.x.is_finite() && point.y.is_finite(),
+                    "non-finite B-quad {:?}",
+                    positions);
+        }
+        assert!(positions.upper_left_vertex_position.x <= positions.upper_right_vertex_position.x,
+                "inverted upper edge {:?}",
+                positions);
+        assert!(positions.lower_left_vertex_position.x <= positions.lower_right_vertex_position.x,
+                "inverted lower edge {:?}",
+                positions);
+    }
+}
@@ -0,0 +1,84 @@
+// pathfinder/partitioner/src/batch.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Partitions many independent paths (for example, all the glyphs in a font) at once.
+//!
+//! If the `rayon` feature is enabled, paths are partitioned in parallel. Either way, the meshes
+//! in the resulting mesh pack are in the same order as the input paths, so the output is
+//! identical regardless of thread count or scheduling.
+
+use lyon_path::PathEvent;
+use lyon_path::builder::{FlatPathBuilder, PathBuilder};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use mesh::Mesh;
+use mesh_pack::MeshPack;
+use partitioner::Partitioner;
+use FillRule;
+
+/// Partitions a single path into a mesh.
+///
+/// If `approx_tolerance` is `None`, the builder's default tolerance is used.
+pub fn partition_path(path: &[PathEvent], fill_rule: FillRule, approx_tolerance: Option<f32>)
+                      -> Mesh {
+    partition_path_with_strictness(path, fill_rule, approx_tolerance, false)
+}
+
+/// Partitions a single path into a mesh in strict mode. See `Partitioner::set_strict()`.
+pub fn partition_path_strict(path: &[PathEvent],
+                             fill_rule: FillRule,
+                             approx_tolerance: Option<f32>)
+                             -> Mesh {
+    partition_path_with_strictness(path, fill_rule, approx_tolerance, true)
+}
+
+fn partition_path_with_strictness(path: &[PathEvent],
+                                  fill_rule: FillRule,
+                                  approx_tolerance: Option<f32>,
+                                  strict: bool)
+                                  -> Mesh {
+    let mut partitioner = Partitioner::new();
+    partitioner.set_strict(strict);
+    if let Some(tolerance) = approx_tolerance {
+        partitioner.builder_mut().set_approx_tolerance(tolerance);
+    }
+
+    path.iter().for_each(|event| partitioner.builder_mut().path_event(*event));
+    partitioner.partition(fill_rule);
+    partitioner.builder_mut().build_and_reset();
+    partitioner.into_mesh()
+}
+
+/// Partitions each path with its fill rule and collects the meshes into a mesh pack, in order.
+pub fn partition_paths(paths: &[(Vec<PathEvent>, FillRule)], approx_tolerance: Option<f32>)
+                       -> MeshPack {
+    build_mesh_pack(paths, |&(ref path, fill_rule)| {
+        partition_path(path, fill_rule, approx_tolerance)
+    })
+}
+
+/// Calls `build` on each item to produce a mesh and collects the meshes into a mesh pack, in
+/// order.
+///
+/// This is the building block for callers that need to do more than partition each path, such as
+/// also pushing stencil segments to each mesh.
+pub fn build_mesh_pack<T, F>(items: &[T], build: F) -> MeshPack
+                             where T: Sync, F: Fn(&T) -> Mesh + Sync + Send {
+    #[cfg(feature = "rayon")]
+    let meshes = items.par_iter().map(build).collect();
+    #[cfg(not(feature = "rayon"))]
+    let meshes = items.iter().map(build).collect();
+
+    MeshPack {
+        meshes: meshes,
+    }
+}
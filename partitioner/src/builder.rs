@@ -12,7 +12,9 @@ use arrayvec::ArrayVec;
 use euclid::{Angle, Point2D, Vector2D};
 use lyon_geom::{CubicBezierSegment, QuadraticBezierSegment};
 use lyon_path::builder::{FlatPathBuilder, PathBuilder};
+use pathfinder_path_utils::arcs::{ArcConversion, ArcTo, CenterArc};
 use pathfinder_path_utils::cubic_to_quadratic::CubicToQuadraticSegmentIter;
+use pathfinder_path_utils::curve::Curve;
 use std::ops::Range;
 
 const TANGENT_PARAMETER_TOLERANCE: f32 = 0.001;
@@ -72,6 +74,35 @@ impl Builder {
         }
     }
 
+    /// Adds an elliptical arc from the current position, specified the way SVG's `A` path
+    /// command does.
+    pub fn arc_to(&mut self, arc: &ArcTo) {
+        let from = self.current_position();
+        match arc.to_center_arc(&from) {
+            ArcConversion::Empty => {}
+            ArcConversion::Line(to) => self.line_to(to),
+            ArcConversion::Arc(ref arc) => self.center_arc(arc),
+        }
+    }
+
+    /// Adds a custom curve, approximated with quadratic curves to within the approximation
+    /// tolerance. The curve should start at the current position.
+    pub fn curve_to<C>(&mut self, curve: &C) where C: Curve {
+        let mut quadratics = vec![];
+        curve.for_each_quadratic(self.approx_tolerance, |quadratic| quadratics.push(*quadratic));
+        for quadratic in quadratics {
+            self.quadratic_bezier_to(quadratic.ctrl, quadratic.to)
+        }
+    }
+
+    fn center_arc(&mut self, arc: &CenterArc) {
+        let mut quadratics = vec![];
+        arc.for_each_quadratic(self.approx_tolerance, |quadratic| quadratics.push(*quadratic));
+        for quadratic in quadratics {
+            self.quadratic_bezier_to(quadratic.ctrl, quadratic.to)
+        }
+    }
+
     #[inline]
     fn first_position_of_subpath(&self) -> Option<Point2D<f32>> {
         self.subpath_ranges
@@ -177,12 +208,19 @@ impl PathBuilder for Builder {
         }
     }
 
+    /// Adds an arc around `center` that starts at the current position and sweeps through
+    /// `angle`.
     fn arc(&mut self,
-           _center: Point2D<f32>,
-           _radii: Vector2D<f32>,
-           _angle: Angle<f32>,
-           _x_rotation: Angle<f32>) {
-        panic!("TODO: Support arcs in the Pathfinder builder!")
+           center: Point2D<f32>,
+           radii: Vector2D<f32>,
+           angle: Angle<f32>,
+           x_rotation: Angle<f32>) {
+        let arc = CenterArc::from_path_event(&self.current_position(),
+                                             &center,
+                                             &radii,
+                                             angle,
+                                             x_rotation);
+        self.center_arc(&arc)
     }
 }
 
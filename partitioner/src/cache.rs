@@ -0,0 +1,158 @@
+// pathfinder/partitioner/src/cache.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A least-recently-used cache of partitioned glyph meshes, so that identical glyphs don't have
+//! to be repartitioned every time a scene is rebuilt.
+
+use lru_cache::LruCache;
+use std::hash::Hash;
+
+use mesh::Mesh;
+use mesh_pack::MeshPack;
+
+#[cfg(feature = "serde")]
+use std::io::{self, Read};
+
+/// Identifies one partitioned glyph.
+///
+/// `F` identifies the font; it can be anything hashable, such as a font name, a path, or a hash
+/// of the font data.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GlyphMeshKey<F> where F: Clone + Eq + Hash {
+    pub font: F,
+    pub glyph_id: u32,
+    /// The bits of the approximation tolerance the glyph was partitioned with, since meshes
+    /// built with different tolerances aren't interchangeable.
+    pub tolerance_bits: u32,
+}
+
+impl<F> GlyphMeshKey<F> where F: Clone + Eq + Hash {
+    #[inline]
+    pub fn new(font: F, glyph_id: u32, tolerance: f32) -> GlyphMeshKey<F> {
+        GlyphMeshKey {
+            font: font,
+            glyph_id: glyph_id,
+            tolerance_bits: tolerance.to_bits(),
+        }
+    }
+}
+
+/// A cache of glyph meshes that holds at most a fixed number of meshes, evicting the least
+/// recently used ones first.
+pub struct GlyphMeshCache<F> where F: Clone + Eq + Hash {
+    meshes: LruCache<GlyphMeshKey<F>, Mesh>,
+}
+
+impl<F> GlyphMeshCache<F> where F: Clone + Eq + Hash {
+    /// Creates a cache that holds up to `capacity` meshes.
+    ///
+    /// Panics if `capacity` is zero, since such a cache couldn't return the meshes that
+    /// `get_or_insert_with()` builds.
+    #[inline]
+    pub fn new(capacity: usize) -> GlyphMeshCache<F> {
+        assert!(capacity > 0, "A glyph mesh cache must be able to hold at least one mesh!");
+        GlyphMeshCache {
+            meshes: LruCache::new(capacity),
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.meshes.len()
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.meshes.capacity()
+    }
+
+    /// Changes the maximum number of meshes, evicting meshes if necessary.
+    ///
+    /// Panics if `capacity` is zero.
+    #[inline]
+    pub fn set_capacity(&mut self, capacity: usize) {
+        assert!(capacity > 0, "A glyph mesh cache must be able to hold at least one mesh!");
+        self.meshes.set_capacity(capacity)
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.meshes.clear()
+    }
+
+    /// Returns the cached mesh for the key, if there is one, and marks it as recently used.
+    #[inline]
+    pub fn get(&mut self, key: &GlyphMeshKey<F>) -> Option<&Mesh> {
+        self.meshes.get_mut(key).map(|mesh| &*mesh)
+    }
+
+    /// Caches a mesh, returning the mesh previously cached under the same key, if any.
+    #[inline]
+    pub fn insert(&mut self, key: GlyphMeshKey<F>, mesh: Mesh) -> Option<Mesh> {
+        self.meshes.insert(key, mesh)
+    }
+
+    /// Returns the cached mesh for the key, calling `partition` to build and cache it first if it
+    /// isn't already cached.
+    pub fn get_or_insert_with<P>(&mut self, key: GlyphMeshKey<F>, partition: P) -> &Mesh
+                                 where P: FnOnce() -> Mesh {
+        if !self.meshes.contains_key(&key) {
+            let mesh = partition();
+            self.meshes.insert(key.clone(), mesh);
+        }
+        self.meshes.get_mut(&key).unwrap()
+    }
+
+    /// Fills the cache from a baked mesh pack in which the mesh at index *n* is that of glyph
+    /// *n*, as the `pathfinder` command-line tool produces.
+    ///
+    /// All the glyphs are assumed to have been partitioned with `tolerance`. If the mesh pack
+    /// holds more meshes than the cache has room for, the last ones win.
+    pub fn prewarm(&mut self, font: &F, tolerance: f32, mesh_pack: MeshPack) {
+        for (glyph_id, mesh) in mesh_pack.meshes.into_iter().enumerate() {
+            self.meshes.insert(GlyphMeshKey::new(font.clone(), glyph_id as u32, tolerance), mesh);
+        }
+    }
+
+    /// Like `prewarm()`, but reads the mesh pack from a file produced by
+    /// `MeshPack::write_to()`.
+    #[cfg(feature = "serde")]
+    pub fn prewarm_from_reader<R>(&mut self, font: &F, tolerance: f32, reader: &mut R)
+                                  -> io::Result<()>
+                                  where R: Read {
+        let mesh_pack = try!(MeshPack::read_from(reader));
+        self.prewarm(font, tolerance, mesh_pack);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mesh::Mesh;
+
+    use super::{GlyphMeshCache, GlyphMeshKey};
+
+    #[test]
+    fn get_or_insert_with_evicts_at_capacity() {
+        let mut cache = GlyphMeshCache::new(1);
+        for glyph_id in 0..3 {
+            cache.get_or_insert_with(GlyphMeshKey::new("font", glyph_id, 0.1), Mesh::new);
+            assert_eq!(cache.len(), 1);
+        }
+        assert!(cache.get(&GlyphMeshKey::new("font", 0, 0.1)).is_none());
+        assert!(cache.get(&GlyphMeshKey::new("font", 2, 0.1)).is_some());
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_capacity_is_rejected() {
+        GlyphMeshCache::<&'static str>::new(0);
+    }
+}
@@ -0,0 +1,168 @@
+// pathfinder/partitioner/src/debug.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Dumps meshes to formats that other tools can display, for tracking down partitioner bugs
+//! and attaching geometry to bug reports. `pathfinder_rasterizer::overlay` draws the same
+//! geometry over a software canvas.
+
+use euclid::Point2D;
+use serde_json;
+use std::f32;
+use std::io::{self, ErrorKind, Write};
+
+use mesh::Mesh;
+
+/// The format that `debug_export()` writes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DebugExportFormat {
+    /// Wavefront OBJ, viewable in Blender and most other 3D tools.
+    ///
+    /// The B-quad vertex positions become vertices in the Z = 0 plane, in the same order as in
+    /// the mesh. The interior triangles become faces in the group `interior`, and the upper and
+    /// lower curves of each B-quad become polylines through their control points in the group
+    /// `edges`.
+    Obj,
+    /// JSON containing every buffer of the mesh, as produced by its `Serialize` implementation.
+    Json,
+    /// An SVG document, viewable in any browser.
+    ///
+    /// Each B-quad is filled with its own color and labeled with its index; hovering over it
+    /// shows its vertex indices. Control triangles of curved edges are outlined with dashes, red
+    /// where the curve is convex (bulging out of the B-quad) and blue where it is concave. Mesh
+    /// coordinates are written unchanged, so paths with Y pointing up appear upside down.
+    Svg,
+}
+
+/// Writes the B-quads and triangles of the mesh to `writer` in the given format.
+pub fn debug_export<W>(mesh: &Mesh, format: DebugExportFormat, writer: &mut W) -> io::Result<()>
+                       where W: Write {
+    match format {
+        DebugExportFormat::Obj => export_obj(mesh, writer),
+        DebugExportFormat::Json => {
+            serde_json::to_writer_pretty(writer, mesh).map_err(|_| {
+                io::Error::from(ErrorKind::Other)
+            })
+        }
+        DebugExportFormat::Svg => export_svg(mesh, writer),
+    }
+}
+
+fn export_obj<W>(mesh: &Mesh, writer: &mut W) -> io::Result<()> where W: Write {
+    try!(writeln!(writer, "# Pathfinder mesh: {} B-quads", mesh.b_quad_vertex_positions.len()));
+    try!(writeln!(writer, "o mesh"));
+
+    for positions in &mesh.b_quad_vertex_positions {
+        // This is the order that `b_quad_vertex_interior_indices` refers to.
+        for point in &[
+            positions.upper_left_vertex_position,
+            positions.upper_control_point_position,
+            positions.upper_right_vertex_position,
+            positions.lower_right_vertex_position,
+            positions.lower_control_point_position,
+            positions.lower_left_vertex_position,
+        ] {
+            try!(writeln!(writer, "v {} {} 0", point.x, point.y));
+        }
+    }
+
+    // OBJ indices are 1-based.
+    try!(writeln!(writer, "g interior"));
+    for triangle in mesh.b_quad_vertex_interior_indices.chunks(3) {
+        if triangle.len() == 3 {
+            try!(writeln!(writer, "f {} {} {}", triangle[0] + 1, triangle[1] + 1, triangle[2] + 1));
+        }
+    }
+
+    try!(writeln!(writer, "g edges"));
+    for b_quad_index in 0..(mesh.b_quad_vertex_positions.len() as u32) {
+        let first_index = b_quad_index * 6 + 1;
+        try!(writeln!(writer, "l {} {} {}", first_index, first_index + 1, first_index + 2));
+        try!(writeln!(writer, "l {} {} {}", first_index + 3, first_index + 4, first_index + 5));
+    }
+
+    Ok(())
+}
+
+fn export_svg<W>(mesh: &Mesh, writer: &mut W) -> io::Result<()> where W: Write {
+    let bounds = mesh.b_quad_bounds();
+    let extent = f32::max(bounds.size.width, bounds.size.height).max(1.0);
+    let (stroke_width, padding) = (extent / 500.0, extent / 20.0);
+
+    try!(writeln!(writer,
+                  "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">",
+                  bounds.origin.x - padding,
+                  bounds.origin.y - padding,
+                  bounds.size.width + padding * 2.0,
+                  bounds.size.height + padding * 2.0));
+    try!(writeln!(writer,
+                  "<g stroke=\"black\" stroke-width=\"{}\" font-size=\"{}\">",
+                  stroke_width,
+                  extent / 50.0));
+
+    for (b_quad_index, positions) in mesh.b_quad_vertex_positions.iter().enumerate() {
+        let (ul, uc, ur) = (positions.upper_left_vertex_position,
+                            positions.upper_control_point_position,
+                            positions.upper_right_vertex_position);
+        let (lr, lc, ll) = (positions.lower_right_vertex_position,
+                            positions.lower_control_point_position,
+                            positions.lower_left_vertex_position);
+
+        // Spread the hues out so that neighboring B-quads are easy to tell apart.
+        let hue = (b_quad_index * 137) % 360;
+        try!(write!(writer, "<g><title>B-quad {}", b_quad_index));
+        if let Some(b_quad) = mesh.b_quads.get(b_quad_index) {
+            try!(write!(writer,
+                        ": upper {} {} {}, lower {} {} {}",
+                        b_quad.upper_left_vertex_index,
+                        b_quad.upper_control_point_vertex_index,
+                        b_quad.upper_right_vertex_index,
+                        b_quad.lower_left_vertex_index,
+                        b_quad.lower_control_point_vertex_index,
+                        b_quad.lower_right_vertex_index));
+        }
+        try!(writeln!(writer, "</title>"));
+        try!(writeln!(writer,
+                      "<path fill=\"hsl({}, 70%, 70%)\" fill-opacity=\"0.5\" \
+                       d=\"M {} {} Q {} {} {} {} L {} {} Q {} {} {} {} Z\"/>",
+                      hue,
+                      ul.x, ul.y, uc.x, uc.y, ur.x, ur.y,
+                      lr.x, lr.y, lc.x, lc.y, ll.x, ll.y));
+
+        // The same convexity test that the mesh uses to triangulate the interior.
+        let upper_determinant = (ur - ul).cross(uc - ul);
+        let lower_determinant = (ll - lr).cross(lc - lr);
+        for &(from, ctrl, to, determinant) in &[(ul, uc, ur, upper_determinant),
+                                                (lr, lc, ll, lower_determinant)] {
+            if determinant.abs() <= f32::EPSILON * extent * extent {
+                continue
+            }
+            let color = if determinant > 0.0 { "blue" } else { "red" };
+            try!(writeln!(writer,
+                          "<polygon fill=\"none\" stroke=\"{}\" stroke-dasharray=\"{} {}\" \
+                           points=\"{},{} {},{} {},{}\"/>",
+                          color,
+                          stroke_width * 4.0,
+                          stroke_width * 2.0,
+                          from.x, from.y, ctrl.x, ctrl.y, to.x, to.y));
+        }
+
+        let center = Point2D::new((ul.x + ur.x + lr.x + ll.x) * 0.25,
+                                  (ul.y + ur.y + lr.y + ll.y) * 0.25);
+        try!(writeln!(writer,
+                      "<text x=\"{}\" y=\"{}\" stroke=\"none\" text-anchor=\"middle\">{}</text>",
+                      center.x,
+                      center.y,
+                      b_quad_index));
+        try!(writeln!(writer, "</g>"));
+    }
+
+    try!(writeln!(writer, "</g>"));
+    writeln!(writer, "</svg>")
+}
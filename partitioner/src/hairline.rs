@@ -0,0 +1,120 @@
+// pathfinder/partitioner/src/hairline.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Draws strokes thinner than a pixel as antialiased lines of constant width, without converting
+//! them to fills.
+//!
+//! A stroke a fraction of a pixel wide, converted to a fill, covers a different set of sample
+//! points depending on exactly where it falls, so it flickers or vanishes as it moves. A hairline
+//! instead always touches the one or two pixels nearest each point of the path, with coverage
+//! proportional to its width, so it stays visible and steady at any position. Hairlines have no
+//! caps or joins.
+
+use euclid::Point2D;
+use lyon_geom::QuadraticBezierSegment;
+use lyon_path::PathEvent;
+use pathfinder_path_utils::arcs::ArcToQuadraticTransformer;
+use pathfinder_path_utils::cubic_to_quadratic::CubicToQuadraticTransformer;
+use pathfinder_path_utils::flatten::FlattenedQuadratic;
+use std::collections::BTreeMap;
+use std::f32;
+
+use spans::{self, Span};
+
+/// Strokes wider than this many pixels look the same converted to fills, which are more exact.
+pub const MAX_HAIRLINE_WIDTH: f32 = 1.0;
+
+/// Computes the coverage spans of the path stroked as a hairline `width` pixels wide, in pixel
+/// coordinates.
+///
+/// `width` is clamped to `MAX_HAIRLINE_WIDTH`. Curves are flattened to within `tolerance`
+/// pixels. Spans are sorted by scanline and then by column, as `mesh_spans()` returns them.
+pub fn hairline_spans<I>(events: I, width: f32, tolerance: f32) -> Vec<Span>
+                         where I: Iterator<Item = PathEvent> {
+    let width = width.max(0.0).min(MAX_HAIRLINE_WIDTH);
+    let events = CubicToQuadraticTransformer::new(ArcToQuadraticTransformer::new(events,
+                                                                                 tolerance),
+                                                  tolerance);
+
+    let mut coverage = BTreeMap::new();
+    let (mut first_point, mut last_point) = (Point2D::zero(), Point2D::zero());
+    for event in events {
+        match event {
+            PathEvent::MoveTo(to) => {
+                first_point = to;
+                last_point = to;
+            }
+            PathEvent::LineTo(to) => {
+                add_line(&mut coverage, &last_point, &to, width);
+                last_point = to;
+            }
+            PathEvent::QuadraticTo(ctrl, to) => {
+                let segment = QuadraticBezierSegment { from: last_point, ctrl: ctrl, to: to };
+                for point in FlattenedQuadratic::new(&segment, tolerance) {
+                    add_line(&mut coverage, &last_point, &point, width);
+                    last_point = point;
+                }
+            }
+            PathEvent::Close => {
+                add_line(&mut coverage, &last_point, &first_point, width);
+                last_point = first_point;
+            }
+            PathEvent::CubicTo(..) | PathEvent::Arc(..) => {
+                unreachable!("Cubics and arcs should have been converted to quadratics!")
+            }
+        }
+    }
+
+    spans::coverage_spans(&coverage)
+}
+
+// Adds the coverage of one line segment, in the manner of Xiaolin Wu's algorithm: at each pixel
+// step along the major axis, the two pixels straddling the line along the minor axis share its
+// coverage.
+fn add_line(coverage: &mut BTreeMap<(i32, i32), f32>,
+            from: &Point2D<f32>,
+            to: &Point2D<f32>,
+            width: f32) {
+    let vector = *to - *from;
+    if !(vector.square_length() > 0.0) || !vector.square_length().is_finite() {
+        return
+    }
+
+    // Work in a space where X is the major axis, swapping back when storing coverage.
+    let steep = vector.y.abs() > vector.x.abs();
+    let (from, to) = if steep {
+        (Point2D::new(from.y, from.x), Point2D::new(to.y, to.x))
+    } else {
+        (*from, *to)
+    };
+    let (from, to) = if from.x <= to.x { (from, to) } else { (to, from) };
+    let slope = (to.y - from.y) / (to.x - from.x);
+
+    // A line crosses each column for `sqrt(1 + slope²)` of its length, so diagonal lines would
+    // look thinner than horizontal ones without this.
+    let intensity = width * (1.0 + slope * slope).sqrt();
+
+    for column in (from.x.floor() as i32)..(to.x.ceil() as i32) {
+        let (left, right) = (f32::max(from.x, column as f32), f32::min(to.x, (column + 1) as f32));
+        let span = right - left;
+        if !(span > 0.0) {
+            continue
+        }
+
+        // Split between the pixels whose centers are just above and below the line.
+        let y = from.y + ((left + right) * 0.5 - from.x) * slope - 0.5;
+        let row = y.floor();
+        let fraction = y - row;
+        for &(row, weight) in &[(row as i32, 1.0 - fraction), (row as i32 + 1, fraction)] {
+            let key = if steep { (column, row) } else { (row, column) };
+            *coverage.entry(key).or_insert(0.0) += weight * span * intensity;
+        }
+    }
+}
@@ -20,42 +20,80 @@
 //! partitioner as a preprocessing step and store the resulting mesh library on disk. To aid this
 //! use case, mesh libraries can be serialized into a simple binary format. Of course, meshes can
 //! also be generated dynamically and rendered on the fly.
+//! 
+//! The `serde` feature enables that format, along with the `remote` and `streaming` modules,
+//! which encode meshes the same way. It also makes meshes, mesh packs, B-quads, and vertex data
+//! implement Serde's `Serialize` and `Deserialize`, so they can be stored with any other
+//! Serde-compatible encoder, and turns on the `serialization` feature of
+//! `pathfinder_path_utils` to do the same for its path types.
+//!
+//! The `debug-export` feature enables the `debug` module, which writes meshes as OBJ, JSON, or
+//! SVG files for inspecting them in other tools. It implies `serde`.
 
 extern crate arrayvec;
+#[cfg(feature = "serde")]
 extern crate bincode;
 extern crate bit_vec;
 extern crate byteorder;
 extern crate env_logger;
 extern crate euclid;
+extern crate lru_cache;
 extern crate lyon_path;
 extern crate pathfinder_path_utils;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "serde")]
 extern crate serde;
+#[cfg(feature = "debug-export")]
+extern crate serde_json;
 
 use lyon_path::geom as lyon_geom;
 
 #[macro_use]
 extern crate log;
+#[cfg(feature = "serde")]
 #[macro_use]
 extern crate serde_derive;
 
-use euclid::Point2D;
-use std::u32;
+use euclid::{Point2D, Rect};
+use std::{u16, u32};
 
+pub mod batch;
 pub mod builder;
+pub mod cache;
+#[cfg(feature = "debug-export")]
+pub mod debug;
+pub mod hairline;
+pub mod lod;
 pub mod mesh;
 pub mod mesh_pack;
+pub mod occlusion;
 pub mod partitioner;
+pub mod predicates;
+#[cfg(feature = "serde")]
+pub mod remote;
+pub mod scene;
+pub mod spans;
+#[cfg(feature = "serde")]
+pub mod streaming;
+pub mod tiling;
 
-/// The fill rule.
+/// The fill rule, which determines which regions of a path are considered to be inside it.
+///
+/// These correspond to the SVG `fill-rule` values `evenodd` and `nonzero` respectively.
 #[repr(C)]
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FillRule {
+    /// A point is inside the path if a ray from it crosses an odd number of edges.
     EvenOdd = 0,
+    /// A point is inside the path if the winding number of the path around it is nonzero.
     Winding = 1,
 }
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BQuad {
     pub upper_left_vertex_index: u32,
     pub upper_right_vertex_index: u32,
@@ -103,7 +141,8 @@ impl BQuad {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BQuadVertexPositions {
     pub upper_left_vertex_position: Point2D<f32>,
     pub upper_control_point_position: Point2D<f32>,
@@ -113,6 +152,77 @@ pub struct BQuadVertexPositions {
     pub lower_left_vertex_position: Point2D<f32>,
 }
 
+impl BQuadVertexPositions {
+    /// Quantizes these positions to 16-bit unsigned normalized coordinates within `bounds`, which
+    /// must contain them.
+    pub fn quantize(&self, bounds: &Rect<f32>) -> QuantizedBQuadVertexPositions {
+        QuantizedBQuadVertexPositions {
+            upper_left_vertex_position: quantize_point(&self.upper_left_vertex_position, bounds),
+            upper_control_point_position: quantize_point(&self.upper_control_point_position,
+                                                         bounds),
+            upper_right_vertex_position: quantize_point(&self.upper_right_vertex_position, bounds),
+            lower_right_vertex_position: quantize_point(&self.lower_right_vertex_position, bounds),
+            lower_control_point_position: quantize_point(&self.lower_control_point_position,
+                                                         bounds),
+            lower_left_vertex_position: quantize_point(&self.lower_left_vertex_position, bounds),
+        }
+    }
+}
+
+/// B-quad vertex positions stored as 16-bit unsigned normalized coordinates relative to a
+/// bounding rectangle, typically that of the glyph.
+///
+/// Each coordinate maps linearly from 0 at the minimum edge of the bounds to 65535 at the maximum
+/// edge, so the maximum error is half of the bounds' extent divided by 65535.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct QuantizedBQuadVertexPositions {
+    pub upper_left_vertex_position: [u16; 2],
+    pub upper_control_point_position: [u16; 2],
+    pub upper_right_vertex_position: [u16; 2],
+    pub lower_right_vertex_position: [u16; 2],
+    pub lower_control_point_position: [u16; 2],
+    pub lower_left_vertex_position: [u16; 2],
+}
+
+impl QuantizedBQuadVertexPositions {
+    /// Recovers approximate positions, given the bounds that were used to quantize them.
+    pub fn dequantize(&self, bounds: &Rect<f32>) -> BQuadVertexPositions {
+        BQuadVertexPositions {
+            upper_left_vertex_position: dequantize_point(&self.upper_left_vertex_position, bounds),
+            upper_control_point_position: dequantize_point(&self.upper_control_point_position,
+                                                           bounds),
+            upper_right_vertex_position: dequantize_point(&self.upper_right_vertex_position,
+                                                          bounds),
+            lower_right_vertex_position: dequantize_point(&self.lower_right_vertex_position,
+                                                          bounds),
+            lower_control_point_position: dequantize_point(&self.lower_control_point_position,
+                                                           bounds),
+            lower_left_vertex_position: dequantize_point(&self.lower_left_vertex_position, bounds),
+        }
+    }
+}
+
+fn quantize_point(point: &Point2D<f32>, bounds: &Rect<f32>) -> [u16; 2] {
+    return [
+        quantize(point.x, bounds.origin.x, bounds.size.width),
+        quantize(point.y, bounds.origin.y, bounds.size.height),
+    ];
+
+    fn quantize(value: f32, origin: f32, extent: f32) -> u16 {
+        if extent <= 0.0 {
+            return 0
+        }
+        let normalized = ((value - origin) / extent).max(0.0).min(1.0);
+        (normalized * u16::MAX as f32).round() as u16
+    }
+}
+
+fn dequantize_point(point: &[u16; 2], bounds: &Rect<f32>) -> Point2D<f32> {
+    Point2D::new(bounds.origin.x + point[0] as f32 / u16::MAX as f32 * bounds.size.width,
+                 bounds.origin.y + point[1] as f32 / u16::MAX as f32 * bounds.size.height)
+}
+
 #[derive(Clone, Copy, PartialEq, Debug)]
 #[repr(u8)]
 pub(crate) enum BVertexKind {
@@ -122,7 +232,8 @@ pub(crate) enum BVertexKind {
     ConcaveControlPoint,
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[repr(C)]
 pub struct BVertexLoopBlinnData {
     pub tex_coord: [u8; 2],
@@ -0,0 +1,132 @@
+// pathfinder/partitioner/src/lod.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Level-of-detail meshes: the same path partitioned at several approximation tolerances, so
+//! that small on-screen paths can be drawn with coarser, cheaper meshes.
+
+use lyon_path::PathEvent;
+use std::cmp::Ordering;
+use std::f32;
+
+use batch;
+use mesh::Mesh;
+use FillRule;
+
+/// One level of detail.
+#[derive(Debug, Clone)]
+pub struct LodLevel {
+    /// The approximation tolerance, in path units, that this mesh was partitioned with.
+    pub tolerance: f32,
+    pub mesh: Mesh,
+}
+
+/// A path partitioned at several levels of detail.
+#[derive(Debug, Clone)]
+pub struct LodMeshes {
+    /// The levels, from finest (smallest tolerance) to coarsest.
+    pub levels: Vec<LodLevel>,
+    /// The larger of the width and height of the path's bounding box, in path units.
+    pub extent: f32,
+}
+
+impl LodMeshes {
+    /// Partitions the path once per tolerance. If the `rayon` feature is enabled, the levels are
+    /// partitioned in parallel. Tolerances that are NaN or infinite are ignored.
+    pub fn new(path: &[PathEvent], fill_rule: FillRule, tolerances: &[f32]) -> LodMeshes {
+        let mut tolerances: Vec<f32> =
+            tolerances.iter().cloned().filter(|tolerance| tolerance.is_finite()).collect();
+        tolerances.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        tolerances.dedup();
+
+        let mesh_pack = batch::build_mesh_pack(&tolerances, |&tolerance| {
+            batch::partition_path(path, fill_rule, Some(tolerance))
+        });
+
+        let mut levels = Vec::with_capacity(tolerances.len());
+        for (tolerance, mesh) in tolerances.into_iter().zip(mesh_pack.meshes.into_iter()) {
+            levels.push(LodLevel {
+                tolerance: tolerance,
+                mesh: mesh,
+            })
+        }
+
+        let extent = match levels.first() {
+            None => 0.0,
+            Some(level) => {
+                let bounds = level.mesh.b_quad_bounds();
+                f32::max(bounds.size.width, bounds.size.height)
+            }
+        };
+
+        LodMeshes {
+            levels: levels,
+            extent: extent,
+        }
+    }
+
+    /// Returns `count` tolerances, starting at `finest` and each `ratio` times the previous one.
+    pub fn geometric_tolerances(finest: f32, ratio: f32, count: u32) -> Vec<f32> {
+        (0..count).map(|level| finest * ratio.powi(level as i32)).collect()
+    }
+
+    /// Returns the coarsest level that is accurate to `device_tolerance` pixels when the path is
+    /// drawn at `scale` device pixels per path unit, or the finest level if none is accurate
+    /// enough.
+    pub fn select_for_scale(&self, scale: f32, device_tolerance: f32) -> Option<&LodLevel> {
+        let local_tolerance = if scale > 0.0 {
+            device_tolerance / scale
+        } else {
+            f32::INFINITY
+        };
+        self.levels
+            .iter()
+            .rev()
+            .find(|level| level.tolerance <= local_tolerance)
+            .or(self.levels.first())
+    }
+
+    /// Like `select_for_scale()`, but takes the projected size of the path on screen: the larger
+    /// of its width and height, in device pixels.
+    pub fn select_for_screen_size(&self, projected_size: f32, device_tolerance: f32)
+                                  -> Option<&LodLevel> {
+        let scale = if self.extent > 0.0 {
+            projected_size / self.extent
+        } else {
+            0.0
+        };
+        self.select_for_scale(scale, device_tolerance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::Point2D;
+    use lyon_path::PathEvent;
+    use std::f32;
+
+    use FillRule;
+    use super::LodMeshes;
+
+    #[test]
+    fn non_finite_tolerances_are_ignored() {
+        let path = [
+            PathEvent::MoveTo(Point2D::new(0.0, 0.0)),
+            PathEvent::QuadraticTo(Point2D::new(5.0, -5.0), Point2D::new(10.0, 0.0)),
+            PathEvent::LineTo(Point2D::new(10.0, 10.0)),
+            PathEvent::LineTo(Point2D::new(0.0, 10.0)),
+            PathEvent::Close,
+        ];
+        let tolerances = [1.0, f32::NAN, 0.25, f32::INFINITY, 1.0];
+        let lod_meshes = LodMeshes::new(&path, FillRule::Winding, &tolerances);
+        let level_tolerances: Vec<f32> =
+            lod_meshes.levels.iter().map(|level| level.tolerance).collect();
+        assert_eq!(level_tolerances, vec![0.25, 1.0]);
+    }
+}
@@ -18,7 +18,8 @@ use std::u32;
 
 use {BQuad, BQuadVertexPositions, BVertexLoopBlinnData};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Mesh {
     pub b_quads: Vec<BQuad>,
     // FIXME(pcwalton): Merge with `b_vertex_positions` below.
@@ -234,6 +235,24 @@ impl Mesh {
         }
     }
 
+    /// Returns the bounding rectangle of all B-quad vertex positions, including control points.
+    ///
+    /// This is the rectangle that B-quad vertex positions are quantized relative to.
+    pub fn b_quad_bounds(&self) -> Rect<f32> {
+        let mut points = Vec::with_capacity(self.b_quad_vertex_positions.len() * 6);
+        for positions in &self.b_quad_vertex_positions {
+            points.extend_from_slice(&[
+                positions.upper_left_vertex_position,
+                positions.upper_control_point_position,
+                positions.upper_right_vertex_position,
+                positions.lower_right_vertex_position,
+                positions.lower_control_point_position,
+                positions.lower_left_vertex_position,
+            ]);
+        }
+        Rect::from_points(points.iter())
+    }
+
     /// Computes vertex normals necessary for emboldening and/or stem darkening. This is intended
     /// for stencil-and-cover.
     pub fn push_stencil_normals<I>(&mut self, stream: I) where I: Iterator<Item = PathEvent> {
@@ -249,7 +268,8 @@ impl Mesh {
     }
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BBox {
     pub upper_left_position: Point2D<f32>,
     pub lower_right_position: Point2D<f32>,
@@ -265,14 +285,16 @@ pub struct BBox {
     pub lower_mode: f32,
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct StencilSegment {
     pub from: Point2D<f32>,
     pub ctrl: Point2D<f32>,
     pub to: Point2D<f32>,
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct StencilNormals {
     pub from: Vector2D<f32>,
     pub ctrl: Vector2D<f32>,
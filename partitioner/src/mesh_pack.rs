@@ -8,14 +8,47 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use bincode;
-use byteorder::{LittleEndian, WriteBytesExt};
 use mesh::Mesh;
+
+#[cfg(feature = "serde")]
+use bincode;
+#[cfg(feature = "serde")]
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+#[cfg(feature = "serde")]
+use euclid::Rect;
+#[cfg(feature = "serde")]
 use serde::Serialize;
-use std::io::{self, ErrorKind, Seek, SeekFrom, Write};
-use std::u32;
+#[cfg(feature = "serde")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "serde")]
+use std::io::{self, Cursor, ErrorKind, Read, Seek, SeekFrom, Write};
+#[cfg(feature = "serde")]
+use QuantizedBQuadVertexPositions;
+
+/// The version of the mesh pack format that `write_to()` produces.
+///
+/// Bump this whenever the layout of an existing chunk changes. Adding new chunks doesn't require
+/// a version bump, since readers skip chunks they don't recognize. Files without a `vers` chunk
+/// predate versioning and are treated as version 0, which has the same layout as version 1.
+pub const MESH_PACK_VERSION: u32 = 1;
+
+/// Options that control how `MeshPack::write_to_with_options()` encodes meshes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WriteOptions {
+    /// If true, B-quad vertex positions are quantized to 16-bit normalized coordinates relative
+    /// to the bounds of each mesh and written to `qbnd` and `bqvq` chunks in place of `bqvp`.
+    ///
+    /// This only affects storage: it halves the size of the vertex positions in the file, but
+    /// readers expand them back to floating point on load. `read_from()` does, and so does the
+    /// demo client, so GPU uploads and the shaders' vertex layout are the same either way.
+    /// Dequantizing on the GPU would need each mesh's bounds in the vertex shader, and the
+    /// renderers draw the B-quads of many meshes at once. Readers that predate these chunks skip
+    /// them and load meshes with no B-quad vertex positions.
+    pub quantize_vertex_positions: bool,
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MeshPack {
     pub meshes: Vec<Mesh>,
 }
@@ -32,15 +65,41 @@ impl MeshPack {
     pub fn push(&mut self, mesh: Mesh) {
         self.meshes.push(mesh)
     }
+}
 
+// Reading and writing mesh packs needs the `serde` feature.
+#[cfg(feature = "serde")]
+impl MeshPack {
     /// Writes this mesh pack to a RIFF file.
     /// 
     /// RIFF is a dead-simple extensible binary format documented here:
     /// https://msdn.microsoft.com/en-us/library/windows/desktop/ee415713(v=vs.85).aspx
+    ///
+    /// The file consists of a `vers` chunk containing `MESH_PACK_VERSION`, followed by one `mesh`
+    /// chunk per mesh. Each `mesh` chunk contains one subchunk per mesh buffer, each of which is
+    /// a tightly packed little-endian array.
+    pub fn write_to<W>(&self, writer: &mut W) -> io::Result<()> where W: Write + Seek {
+        self.write_to_with_options(writer, &WriteOptions::default())
+    }
+
+    /// The old name of `write_to()`.
+    #[deprecated(note = "use `write_to()` instead")]
+    #[inline]
     pub fn serialize_into<W>(&self, writer: &mut W) -> io::Result<()> where W: Write + Seek {
+        self.write_to(writer)
+    }
+
+    /// Writes this mesh pack to a RIFF file, as `write_to()` does, with the given options.
+    pub fn write_to_with_options<W>(&self, writer: &mut W, options: &WriteOptions)
+                                    -> io::Result<()>
+                                    where W: Write + Seek {
         // `PFMP` for "Pathfinder Mesh Pack".
         try!(writer.write_all(b"RIFF\0\0\0\0PFMP"));
 
+        try!(write_chunk(writer, b"vers", |writer| {
+            writer.write_u32::<LittleEndian>(MESH_PACK_VERSION)
+        }));
+
         // NB: The RIFF spec requires that all chunks be padded to an even byte offset. However,
         // for us, this is guaranteed by construction because each instance of all of the data that
         // we're writing has a byte size that is a multiple of 4. So we don't bother with doing it
@@ -48,8 +107,21 @@ impl MeshPack {
         for mesh in &self.meshes {
             try!(write_chunk(writer, b"mesh", |writer| {
                 try!(write_simple_chunk(writer, b"bqua", &mesh.b_quads));
-                try!(write_simple_chunk(writer, b"bqvp", &mesh.b_quad_vertex_positions));
+                if options.quantize_vertex_positions {
+                    let bounds = mesh.b_quad_bounds();
+                    let quantized_positions: Vec<_> =
+                        mesh.b_quad_vertex_positions
+                            .iter()
+                            .map(|positions| positions.quantize(&bounds))
+                            .collect();
+                    try!(write_simple_chunk(writer, b"qbnd", &[bounds]));
+                    try!(write_simple_chunk(writer, b"bqvq", &quantized_positions));
+                } else {
+                    try!(write_simple_chunk(writer, b"bqvp", &mesh.b_quad_vertex_positions));
+                }
                 try!(write_simple_chunk(writer, b"bqii", &mesh.b_quad_vertex_interior_indices));
+                try!(write_simple_chunk(writer, b"bvpo", &mesh.b_vertex_positions));
+                try!(write_simple_chunk(writer, b"bvlb", &mesh.b_vertex_loop_blinn_data));
                 try!(write_simple_chunk(writer, b"bbox", &mesh.b_boxes));
                 try!(write_simple_chunk(writer, b"sseg", &mesh.stencil_segments));
                 try!(write_simple_chunk(writer, b"snor", &mesh.stencil_normals));
@@ -89,4 +161,181 @@ impl MeshPack {
             })
         }
     }
+
+    /// Reads a mesh pack from a RIFF file produced by `write_to()`.
+    ///
+    /// Returns an error of kind `InvalidData` if the data is malformed or was written by a newer
+    /// version of the format than this one.
+    pub fn read_from<R>(reader: &mut R) -> io::Result<MeshPack> where R: Read {
+        let mut header = [0; 12];
+        try!(reader.read_exact(&mut header));
+        if &header[0..4] != b"RIFF" || &header[8..12] != b"PFMP" {
+            return Err(io::Error::new(ErrorKind::InvalidData, "not a Pathfinder mesh pack"))
+        }
+
+        let riff_length = (&header[4..8]).read_u32::<LittleEndian>().unwrap() as usize;
+        if riff_length < 4 {
+            return Err(io::Error::from(ErrorKind::InvalidData))
+        }
+        // Read incrementally rather than allocating the claimed length up front, so that a
+        // malformed header can't make us allocate memory for data that isn't there.
+        let mut data = vec![];
+        try!(reader.by_ref().take(riff_length as u64 - 4).read_to_end(&mut data));
+        if data.len() != riff_length - 4 {
+            return Err(io::Error::from(ErrorKind::UnexpectedEof))
+        }
+
+        let mut mesh_pack = MeshPack::new();
+        try!(for_each_chunk(&data, |tag, chunk| {
+            match tag {
+                b"vers" => {
+                    let version = try!((&chunk[..]).read_u32::<LittleEndian>());
+                    if version > MESH_PACK_VERSION {
+                        return Err(io::Error::new(ErrorKind::InvalidData,
+                                                  "unsupported mesh pack version"))
+                    }
+                }
+                b"mesh" => mesh_pack.push(try!(read_mesh(chunk))),
+                _ => {}
+            }
+            Ok(())
+        }));
+        return Ok(mesh_pack);
+
+        fn read_mesh(data: &[u8]) -> io::Result<Mesh> {
+            let mut mesh = Mesh::new();
+            let mut quantized_bounds: Vec<Rect<f32>> = vec![];
+            let mut quantized_positions: Vec<QuantizedBQuadVertexPositions> = vec![];
+            try!(for_each_chunk(data, |tag, chunk| {
+                match tag {
+                    b"qbnd" => quantized_bounds = try!(read_simple_chunk(chunk)),
+                    b"bqvq" => quantized_positions = try!(read_simple_chunk(chunk)),
+                    b"bqua" => mesh.b_quads = try!(read_simple_chunk(chunk)),
+                    b"bqvp" => mesh.b_quad_vertex_positions = try!(read_simple_chunk(chunk)),
+                    b"bqii" => {
+                        mesh.b_quad_vertex_interior_indices = try!(read_simple_chunk(chunk))
+                    }
+                    b"bvpo" => mesh.b_vertex_positions = try!(read_simple_chunk(chunk)),
+                    b"bvlb" => mesh.b_vertex_loop_blinn_data = try!(read_simple_chunk(chunk)),
+                    b"bbox" => mesh.b_boxes = try!(read_simple_chunk(chunk)),
+                    b"sseg" => mesh.stencil_segments = try!(read_simple_chunk(chunk)),
+                    b"snor" => mesh.stencil_normals = try!(read_simple_chunk(chunk)),
+                    _ => {}
+                }
+                Ok(())
+            }));
+
+            if !quantized_positions.is_empty() {
+                let bounds = match quantized_bounds.first() {
+                    Some(bounds) => *bounds,
+                    None => return Err(io::Error::from(ErrorKind::InvalidData)),
+                };
+                mesh.b_quad_vertex_positions =
+                    quantized_positions.iter()
+                                       .map(|positions| positions.dequantize(&bounds))
+                                       .collect();
+            }
+
+            Ok(mesh)
+        }
+
+        // Calls `closure` with the tag and contents of each chunk, skipping padding bytes.
+        fn for_each_chunk<F>(data: &[u8], mut closure: F) -> io::Result<()>
+                             where F: FnMut(&[u8; 4], &[u8]) -> io::Result<()> {
+            let mut offset = 0;
+            while offset < data.len() {
+                if offset + 8 > data.len() {
+                    return Err(io::Error::from(ErrorKind::InvalidData))
+                }
+                let mut tag = [0; 4];
+                tag.copy_from_slice(&data[offset..(offset + 4)]);
+                let length = try!((&data[(offset + 4)..(offset + 8)])
+                                  .read_u32::<LittleEndian>()) as usize;
+                let start = offset + 8;
+                if length > data.len() - start {
+                    return Err(io::Error::from(ErrorKind::InvalidData))
+                }
+                try!(closure(&tag, &data[start..(start + length)]));
+                offset = start + length + (length & 1);
+            }
+            Ok(())
+        }
+
+        fn read_simple_chunk<T>(data: &[u8]) -> io::Result<Vec<T>> where T: DeserializeOwned {
+            let mut cursor = Cursor::new(data);
+            let mut result = vec![];
+            while (cursor.position() as usize) < data.len() {
+                result.push(try!(bincode::deserialize_from(&mut cursor).map_err(|_| {
+                    io::Error::from(ErrorKind::InvalidData)
+                })));
+            }
+            Ok(result)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use euclid::Point2D;
+    use std::io::{Cursor, ErrorKind};
+
+    use BQuadVertexPositions;
+    use mesh::Mesh;
+    use super::{MeshPack, WriteOptions};
+
+    #[test]
+    fn quantized_vertex_positions_round_trip() {
+        let point = |x, y| Point2D::new(x, y);
+        let positions = BQuadVertexPositions {
+            upper_left_vertex_position: point(-10.0, 2.5),
+            upper_control_point_position: point(0.0, -4.0),
+            upper_right_vertex_position: point(10.0, 2.5),
+            lower_right_vertex_position: point(10.0, 20.0),
+            lower_control_point_position: point(3.25, 24.0),
+            lower_left_vertex_position: point(-10.0, 20.0),
+        };
+        let mut mesh = Mesh::new();
+        mesh.b_quad_vertex_positions.push(positions);
+        let mut mesh_pack = MeshPack::new();
+        mesh_pack.push(mesh);
+
+        let mut data = Cursor::new(vec![]);
+        let options = WriteOptions { quantize_vertex_positions: true };
+        mesh_pack.write_to_with_options(&mut data, &options).unwrap();
+        data.set_position(0);
+        let read_mesh_pack = MeshPack::read_from(&mut data).unwrap();
+
+        let read_positions = &read_mesh_pack.meshes[0].b_quad_vertex_positions;
+        assert_eq!(read_positions.len(), 1);
+        let expected = [
+            positions.upper_left_vertex_position,
+            positions.upper_control_point_position,
+            positions.upper_right_vertex_position,
+            positions.lower_right_vertex_position,
+            positions.lower_control_point_position,
+            positions.lower_left_vertex_position,
+        ];
+        let actual = [
+            read_positions[0].upper_left_vertex_position,
+            read_positions[0].upper_control_point_position,
+            read_positions[0].upper_right_vertex_position,
+            read_positions[0].lower_right_vertex_position,
+            read_positions[0].lower_control_point_position,
+            read_positions[0].lower_left_vertex_position,
+        ];
+        // The bounds are 20 by 28 units, and quantizing rounds to the nearest 1/65535 of that, so
+        // a full step leaves room for floating point error.
+        for (expected, actual) in expected.iter().zip(actual.iter()) {
+            assert!((expected.x - actual.x).abs() <= 20.0 / 65535.0);
+            assert!((expected.y - actual.y).abs() <= 28.0 / 65535.0);
+        }
+    }
+
+    #[test]
+    fn truncated_mesh_packs_are_rejected() {
+        // The header claims 4 GiB of chunks, but none follow.
+        let data = b"RIFF\xff\xff\xff\xffPFMP";
+        let error = MeshPack::read_from(&mut Cursor::new(&data[..])).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
 }
@@ -0,0 +1,109 @@
+// pathfinder/partitioner/src/occlusion.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Finds meshes that are completely hidden behind opaque meshes drawn after them, so that they
+//! can be skipped.
+//!
+//! The canvas is divided into square tiles. Each opaque mesh marks the tiles that it covers
+//! completely, and a mesh is hidden if every tile its bounding box touches was marked by a mesh
+//! drawn later. This is conservative: a tile that two opaque meshes cover only together doesn't
+//! count, so some hidden meshes are still drawn, but no visible mesh is ever culled.
+
+use euclid::{Point2D, Vector2D};
+use std::collections::{HashMap, HashSet};
+
+use mesh::Mesh;
+use spans;
+
+/// The width and height of an occlusion tile, in pixels.
+pub const OCCLUSION_TILE_SIZE: i32 = 16;
+
+/// A mesh to be drawn, in drawing order.
+#[derive(Clone, Copy, Debug)]
+pub struct OcclusionItem<'a> {
+    pub mesh: &'a Mesh,
+    /// Where the mesh's origin is drawn, in pixels. One mesh unit is one pixel.
+    pub offset: Vector2D<i32>,
+    /// True if the mesh is filled with a fully opaque paint and composited with source-over, so
+    /// that it hides what's underneath it.
+    pub opaque: bool,
+}
+
+/// Returns, for each item, whether it might be visible. Items that can be proven to be hidden,
+/// including empty meshes, are false.
+pub fn visible_items(items: &[OcclusionItem]) -> Vec<bool> {
+    let mut occluded_tiles: HashSet<(i32, i32)> = HashSet::new();
+    let mut visible = vec![false; items.len()];
+
+    // Walk from front to back, so that each item is tested against the ones drawn after it.
+    for (item_index, item) in items.iter().enumerate().rev() {
+        if item.mesh.b_quad_vertex_positions.is_empty() {
+            continue
+        }
+
+        let bounds = item.mesh.b_quad_bounds().translate(&item.offset.to_f32());
+        let min_tile = tile_containing(&bounds.origin);
+        let max_tile = tile_containing(&Point2D::new(bounds.max_x().ceil() - 1.0,
+                                                     bounds.max_y().ceil() - 1.0));
+        let hidden = (min_tile.1..(max_tile.1 + 1)).all(|tile_y| {
+            (min_tile.0..(max_tile.0 + 1)).all(|tile_x| {
+                occluded_tiles.contains(&(tile_x, tile_y))
+            })
+        });
+        if hidden {
+            continue
+        }
+        visible[item_index] = true;
+
+        if item.opaque {
+            occluded_tiles.extend(fully_covered_tiles(item));
+        }
+    }
+
+    visible
+}
+
+fn fully_covered_tiles(item: &OcclusionItem) -> Vec<(i32, i32)> {
+    let mut pixel_counts: HashMap<(i32, i32), i32> = HashMap::new();
+    for span in spans::mesh_spans(item.mesh) {
+        if span.coverage < 255 {
+            continue
+        }
+        let y = span.y + item.offset.y;
+        let (left, right) = (span.x + item.offset.x, span.x + item.offset.x + span.len as i32);
+
+        // Split the span at tile boundaries.
+        let mut x = left;
+        while x < right {
+            let tile = (div_floor(x, OCCLUSION_TILE_SIZE), div_floor(y, OCCLUSION_TILE_SIZE));
+            let tile_right = (tile.0 + 1) * OCCLUSION_TILE_SIZE;
+            let end = if tile_right < right { tile_right } else { right };
+            *pixel_counts.entry(tile).or_insert(0) += end - x;
+            x = end;
+        }
+    }
+
+    let tile_area = OCCLUSION_TILE_SIZE * OCCLUSION_TILE_SIZE;
+    pixel_counts.into_iter()
+                .filter(|&(_, count)| count == tile_area)
+                .map(|(tile, _)| tile)
+                .collect()
+}
+
+fn tile_containing(point: &Point2D<f32>) -> (i32, i32) {
+    (div_floor(point.x.floor() as i32, OCCLUSION_TILE_SIZE),
+     div_floor(point.y.floor() as i32, OCCLUSION_TILE_SIZE))
+}
+
+#[inline]
+fn div_floor(a: i32, b: i32) -> i32 {
+    let quotient = a / b;
+    if (a % b != 0) && ((a < 0) != (b < 0)) { quotient - 1 } else { quotient }
+}
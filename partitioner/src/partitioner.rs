@@ -16,21 +16,27 @@ use lyon_geom::{LineSegment, QuadraticBezierSegment};
 use std::collections::BinaryHeap;
 use std::cmp::Ordering;
 use std::f32;
+use std::f64;
 use std::iter;
 use std::ops::{Add, AddAssign};
 use std::u32;
 
 use builder::Builder;
 use mesh::Mesh;
+use predicates;
 use {BQuad, BVertexLoopBlinnData, BVertexKind, FillRule};
 
 const MAX_B_QUAD_SUBDIVISIONS: u8 = 8;
 
 const INTERSECTION_TOLERANCE: f32 = 0.001;
 
+// The most halvings that strict mode uses to find where two edges cross.
+const MAX_STRICT_INTERSECTION_ITERATIONS: u32 = 32;
+
 pub struct Partitioner {
     path: Builder,
     fill_rule: FillRule,
+    strict: bool,
 
     mesh: Mesh,
 
@@ -46,6 +52,7 @@ impl Partitioner {
         Partitioner {
             path: Builder::new(),
             fill_rule: FillRule::Winding,
+            strict: false,
 
             mesh: Mesh::new(),
 
@@ -81,6 +88,35 @@ impl Partitioner {
         &mut self.path
     }
 
+    /// Returns true if strict mode is on. See `set_strict()`.
+    #[inline]
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Turns strict mode on or off. It's off by default.
+    ///
+    /// In strict mode, the points where edges cross are found by bisection with the
+    /// partitioner's own arithmetic, which uses only addition, subtraction, multiplication,
+    /// division, and square roots, instead of with the iterative curve intersection routines of
+    /// `lyon_geom`. Crossings are placed to within the same tolerance either way, so the result
+    /// looks the same, but it's slower for paths with many self-intersections.
+    ///
+    /// This removes the least portable arithmetic from the sweep, but it doesn't by itself make
+    /// meshes identical across platforms, and that isn't tested. The sweep still splits curves
+    /// with `lyon_geom`, and the builder converts arcs with trigonometric functions and cubic
+    /// curves with `lyon_geom`, neither of which is guaranteed to round the same way
+    /// everywhere.
+    #[inline]
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict
+    }
+
+    /// Partitions the path that has been built so far into B-quads, appending them to the mesh.
+    ///
+    /// The `fill_rule` determines which regions between edges are considered to be inside the
+    /// path. Paths using either rule can be passed in directly; there is no need to convert
+    /// even-odd paths to nonzero form first.
     pub fn partition(&mut self, fill_rule: FillRule) {
         self.path.end_subpath();
 
@@ -323,11 +359,11 @@ impl Partitioner {
         let prev_endpoint = &self.path.endpoints[prev_endpoint_index as usize];
         let next_endpoint = &self.path.endpoints[next_endpoint_index as usize];
 
-        let prev_vector = prev_endpoint.to - endpoint.to;
-        let next_vector = next_endpoint.to - endpoint.to;
-
+        // The orientation is computed exactly, so nearly collinear neighbors can't come out in
+        // the wrong order and cross each other.
         let (upper_control_point, lower_control_point);
-        if prev_vector.cross(next_vector) >= 0.0 {
+        if predicates::orientation(&endpoint.to, &prev_endpoint.to, &next_endpoint.to) !=
+                Ordering::Less {
             new_active_edges[0].right_endpoint_index = prev_endpoint_index;
             new_active_edges[1].right_endpoint_index = next_endpoint_index;
             new_active_edges[0].left_to_right = false;
@@ -399,6 +435,21 @@ impl Partitioner {
             return true
         }
 
+        // If both edges are lines and one lies entirely on one side of the other, which is the
+        // usual case, they can be ordered exactly. Otherwise, they cross, and we need to know
+        // where they are at `x`.
+        if let (Some((prev_left, prev_right)), Some((next_left, next_right))) =
+                (self.line_for_active_edge(prev_active_edge),
+                 self.line_for_active_edge(next_active_edge)) {
+            let left_side = predicates::orientation(&prev_left, &prev_right, &next_left);
+            let right_side = predicates::orientation(&prev_left, &prev_right, &next_right);
+            match (left_side, right_side) {
+                (Ordering::Less, Ordering::Less) => return false,
+                (Ordering::Less, _) | (_, Ordering::Less) => {}
+                (_, _) => return true,
+            }
+        }
+
         // TODO(pcwalton): See if we can speed this up. It's trickier than it seems, due to path
         // self intersection!
         let prev_active_edge_t = self.solve_active_edge_t_for_x(x, prev_active_edge);
@@ -721,35 +772,46 @@ impl Partitioner {
     fn find_point_between_active_edges(&self, endpoint_index: u32) -> u32 {
         let endpoint = &self.path.endpoints[endpoint_index as usize];
         match self.active_edges.iter().position(|active_edge| {
-            self.solve_active_edge_y_for_x(endpoint.to.x, active_edge) > endpoint.to.y
+            match self.line_for_active_edge(active_edge) {
+                Some((left, right)) => {
+                    predicates::orientation(&left, &right, &endpoint.to) == Ordering::Less
+                }
+                None => self.solve_active_edge_y_for_x(endpoint.to.x, active_edge) > endpoint.to.y,
+            }
         }) {
             Some(active_edge_index) => active_edge_index as u32,
             None => self.active_edges.len() as u32,
         }
     }
 
+    // Always returns a parameter from 0 to 1, even for vertical edges and for `x` outside the
+    // edge, so that subdividing an edge never produces vertices off its ends.
     fn solve_active_edge_t_for_x(&self, x: f32, active_edge: &ActiveEdge) -> f32 {
         let left_vertex_position =
             &self.mesh.b_vertex_positions[active_edge.left_vertex_index as usize];
         let right_endpoint_position =
             &self.path.endpoints[active_edge.right_endpoint_index as usize].to;
-        match active_edge.control_point_vertex_index {
-            u32::MAX => {
-                LineSegment {
-                    from: *left_vertex_position,
-                    to: *right_endpoint_position,
-                }.solve_t_for_x(x)
-            }
+        let (from_x, to_x) = (left_vertex_position.x as f64, right_endpoint_position.x as f64);
+        let x = x as f64;
+        if !(x > from_x.min(to_x)) {
+            return if from_x <= to_x { 0.0 } else { 1.0 }
+        }
+        if !(x < from_x.max(to_x)) {
+            return if from_x <= to_x { 1.0 } else { 0.0 }
+        }
+
+        let t = match active_edge.control_point_vertex_index {
+            u32::MAX => (x - from_x) / (to_x - from_x),
             control_point_vertex_index => {
-                let control_point = &self.mesh
-                                         .b_vertex_positions[control_point_vertex_index as usize];
-                let segment = QuadraticBezierSegment {
-                    from: *left_vertex_position,
-                    ctrl: *control_point,
-                    to: *right_endpoint_position,
-                };
-                segment.assume_monotonic().solve_t_for_x(x)
+                let ctrl_x =
+                    self.mesh.b_vertex_positions[control_point_vertex_index as usize].x as f64;
+                solve_monotonic_quadratic_t_for_x(from_x, ctrl_x, to_x, x)
             }
+        };
+        if t.is_finite() {
+            t.max(0.0).min(1.0) as f32
+        } else {
+            0.0
         }
     }
 
@@ -780,6 +842,26 @@ impl Partitioner {
         }
     }
 
+    // Returns the endpoints of the active edge if it's a line that isn't vertical.
+    fn line_for_active_edge(&self, active_edge: &ActiveEdge)
+                            -> Option<(Point2D<f32>, Point2D<f32>)> {
+        if active_edge.control_point_vertex_index != u32::MAX {
+            return None
+        }
+        let left_vertex_position =
+            self.mesh.b_vertex_positions[active_edge.left_vertex_index as usize];
+        let right_endpoint_position =
+            self.path.endpoints[active_edge.right_endpoint_index as usize].to;
+        if left_vertex_position.x < right_endpoint_position.x {
+            Some((left_vertex_position, right_endpoint_position))
+        } else {
+            None
+        }
+    }
+
+    // Finds where the edge crosses the one below it, left of `max_x`. The point is always
+    // within the part of the edges that hasn't been emitted yet, so the B-quads around it can't
+    // come out inverted.
     fn crossing_point_for_active_edge(&self, upper_active_edge_index: u32, max_x: f32)
                                       -> Option<Point2D<f32>> {
         let lower_active_edge_index = upper_active_edge_index + 1;
@@ -791,6 +873,60 @@ impl Partitioner {
             return None
         }
 
+        let min_x = f32::max(
+            self.mesh.b_vertex_positions[upper_active_edge.left_vertex_index as usize].x,
+            self.mesh.b_vertex_positions[lower_active_edge.left_vertex_index as usize].x);
+        let crossing_point = if self.strict {
+            self.bisect_crossing_point(upper_active_edge, lower_active_edge, min_x, max_x)
+        } else {
+            self.intersect_active_edges(upper_active_edge, lower_active_edge, max_x)
+        };
+        match crossing_point {
+            Some(point) if point.x.is_finite() && point.y.is_finite() && min_x <= max_x => {
+                Some(Point2D::new(point.x.max(min_x).min(max_x), point.y))
+            }
+            _ => None,
+        }
+    }
+
+    // Finds where two edges cross by halving the interval over which they swap order, using
+    // only `solve_active_edge_t_for_x()` and `sample_active_edge()`.
+    fn bisect_crossing_point(&self,
+                             upper_active_edge: &ActiveEdge,
+                             lower_active_edge: &ActiveEdge,
+                             min_x: f32,
+                             max_x: f32)
+                             -> Option<Point2D<f32>> {
+        let is_ordered = |x: f32| {
+            self.solve_active_edge_y_for_x(x, upper_active_edge) <=
+                self.solve_active_edge_y_for_x(x, lower_active_edge)
+        };
+        if !(min_x <= max_x) || !is_ordered(min_x) || is_ordered(max_x) {
+            return None
+        }
+
+        let (mut ordered_x, mut swapped_x) = (min_x, max_x);
+        for _ in 0..MAX_STRICT_INTERSECTION_ITERATIONS {
+            if swapped_x - ordered_x <= INTERSECTION_TOLERANCE {
+                break
+            }
+            let mid_x = ordered_x + (swapped_x - ordered_x) * 0.5;
+            if is_ordered(mid_x) {
+                ordered_x = mid_x
+            } else {
+                swapped_x = mid_x
+            }
+        }
+
+        let t = self.solve_active_edge_t_for_x(ordered_x, upper_active_edge);
+        Some(Point2D::new(ordered_x, self.sample_active_edge(t, upper_active_edge).y))
+    }
+
+    fn intersect_active_edges(&self,
+                              upper_active_edge: &ActiveEdge,
+                              lower_active_edge: &ActiveEdge,
+                              max_x: f32)
+                              -> Option<Point2D<f32>> {
         let upper_left_vertex_position =
             &self.mesh.b_vertex_positions[upper_active_edge.left_vertex_index as usize];
         let upper_right_endpoint_position =
@@ -1034,6 +1170,24 @@ impl Partitioner {
     }
 }
 
+// Solves `x(t) = x` for a quadratic Bézier curve that's monotonic in X, given the X
+// coordinates of its points, with the quadratic formula in the form that doesn't cancel.
+fn solve_monotonic_quadratic_t_for_x(from_x: f64, ctrl_x: f64, to_x: f64, x: f64) -> f64 {
+    let (a, b, c) = (from_x - 2.0 * ctrl_x + to_x, 2.0 * (ctrl_x - from_x), from_x - x);
+    if a.abs() <= (b.abs() + c.abs()) * f64::EPSILON {
+        return -c / b
+    }
+
+    let root = (b * b - 4.0 * a * c).max(0.0).sqrt();
+    let q = -0.5 * (if b >= 0.0 { b + root } else { b - root });
+    let (t0, t1) = (q / a, c / q);
+    if t0 >= 0.0 && t0 <= 1.0 {
+        t0
+    } else {
+        t1
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 struct Point {
     position: Point2D<f32>,
@@ -1238,3 +1392,96 @@ impl AddAssign<VertexNormal> for VertexNormal {
         *self = *self + rhs
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use euclid::Point2D;
+    use lyon_path::PathEvent;
+
+    use batch;
+    use mesh::Mesh;
+    use FillRule;
+
+    // A xorshift generator, so that failures reproduce.
+    struct Random(u32);
+
+    impl Random {
+        fn next(&mut self) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0
+        }
+
+        // Returns a point on a coarse grid, so that coincident points, collinear edges, and
+        // shared edges between subpaths are common, sometimes nudged by an ulp or two to make
+        // them nearly but not exactly so. The grid starts away from zero, where a nudge would
+        // flip the sign bit.
+        fn point(&mut self) -> Point2D<f32> {
+            let mut coordinates = [(self.next() % 5 + 1) as f32 * 8.0,
+                                   (self.next() % 5 + 1) as f32 * 8.0];
+            for coordinate in &mut coordinates {
+                if self.next() % 4 == 0 {
+                    let nudge = (self.next() % 5) as i32 - 2;
+                    *coordinate = f32::from_bits((coordinate.to_bits() as i32 + nudge) as u32)
+                }
+            }
+            Point2D::new(coordinates[0], coordinates[1])
+        }
+
+        fn degenerate_path(&mut self) -> Vec<PathEvent> {
+            let mut events = vec![];
+            for _ in 0..(1 + self.next() % 3) {
+                events.push(PathEvent::MoveTo(self.point()));
+                for _ in 0..(2 + self.next() % 6) {
+                    if self.next() % 4 == 0 {
+                        events.push(PathEvent::QuadraticTo(self.point(), self.point()))
+                    } else {
+                        events.push(PathEvent::LineTo(self.point()))
+                    }
+                }
+                events.push(PathEvent::Close);
+            }
+            events
+        }
+    }
+
+    fn assert_no_inverted_b_quads(mesh: &Mesh, path: &[PathEvent]) {
+        for positions in &mesh.b_quad_vertex_positions {
+            for point in &[positions.upper_left_vertex_position,
+                           positions.upper_control_point_position,
+                           positions.upper_right_vertex_position,
+                           positions.lower_right_vertex_position,
+                           positions.lower_control_point_position,
+                           positions.lower_left_vertex_position] {
+                assert!(point.x.is_finite() && point.y.is_finite(),
+                        "non-finite B-quad vertex {:?} for {:?}",
+                        positions,
+                        path);
+            }
+            assert!(positions.upper_left_vertex_position.x <=
+                        positions.upper_right_vertex_position.x,
+                    "inverted upper edge {:?} for {:?}",
+                    positions,
+                    path);
+            assert!(positions.lower_left_vertex_position.x <=
+                        positions.lower_right_vertex_position.x,
+                    "inverted lower edge {:?} for {:?}",
+                    positions,
+                    path);
+        }
+    }
+
+    #[test]
+    fn degenerate_paths_partition_without_inverted_b_quads() {
+        let mut random = Random(0x9e3779b9);
+        for _ in 0..500 {
+            let path = random.degenerate_path();
+            for &fill_rule in &[FillRule::Winding, FillRule::EvenOdd] {
+                assert_no_inverted_b_quads(&batch::partition_path(&path, fill_rule, None), &path);
+                assert_no_inverted_b_quads(&batch::partition_path_strict(&path, fill_rule, None),
+                                           &path);
+            }
+        }
+    }
+}
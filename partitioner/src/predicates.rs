@@ -0,0 +1,177 @@
+// pathfinder/partitioner/src/predicates.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Exact geometric predicates on `f32` points.
+//!
+//! The partitioner asks these instead of comparing floating point cross products directly, which
+//! can come out with the wrong sign for nearly collinear points and sort edges inconsistently.
+//! Each predicate first tries a fast `f64` evaluation with an error bound, after Shewchuk's
+//! "Adaptive Precision Floating-Point Arithmetic and Fast Robust Geometric Predicates", and
+//! falls back to exact arithmetic only when the bound can't settle the sign.
+
+use arrayvec::ArrayVec;
+use euclid::Point2D;
+use std::cmp::Ordering;
+
+// Half of the `f64` machine epsilon: the largest relative error of one rounded operation.
+const EPSILON: f64 = 1.0 / 9007199254740992.0;
+
+// The bound on the relative error of the fast orientation determinant, `(3 + 16ε)ε`.
+const ORIENTATION_ERROR_BOUND: f64 = (3.0 + 16.0 * EPSILON) * EPSILON;
+
+/// Returns the sign of the cross product `(b - a) × (c - a)`, computed exactly.
+///
+/// In a Y-down coordinate system, `Greater` means that `a`, `b`, `c` turn clockwise, `Less`
+/// means counterclockwise, and `Equal` means that the points are exactly collinear. Points with
+/// non-finite coordinates are reported as collinear.
+pub fn orientation(a: &Point2D<f32>, b: &Point2D<f32>, c: &Point2D<f32>) -> Ordering {
+    let (ax, ay, bx, by, cx, cy) = (a.x as f64, a.y as f64,
+                                    b.x as f64, b.y as f64,
+                                    c.x as f64, c.y as f64);
+    let det_left = (bx - ax) * (cy - ay);
+    let det_right = (by - ay) * (cx - ax);
+    let det = det_left - det_right;
+    if !det.is_finite() {
+        return Ordering::Equal
+    }
+
+    let error_bound = ORIENTATION_ERROR_BOUND * (det_left.abs() + det_right.abs());
+    if det > error_bound {
+        return Ordering::Greater
+    }
+    if -det > error_bound {
+        return Ordering::Less
+    }
+
+    // Expand the determinant into products of coordinates. The product of two `f32` values fits
+    // exactly in an `f64`, so only the sum needs extra precision.
+    let mut sum: ArrayVec<[f64; 6]> = ArrayVec::new();
+    for &term in &[bx * cy, -(bx * ay), -(ax * cy), -(by * cx), by * ax, ay * cx] {
+        grow_expansion(&mut sum, term)
+    }
+
+    // The components are in increasing order of magnitude and don't overlap, so the last one
+    // has the sign of the whole sum.
+    match sum.last() {
+        Some(&component) if component > 0.0 => Ordering::Greater,
+        Some(&component) if component < 0.0 => Ordering::Less,
+        _ => Ordering::Equal,
+    }
+}
+
+// Adds `value` to `expansion`, a sum of nonoverlapping components in increasing order of
+// magnitude, keeping it in that form and dropping zero components.
+fn grow_expansion(expansion: &mut ArrayVec<[f64; 6]>, value: f64) {
+    let mut result = ArrayVec::new();
+    let mut carry = value;
+    for &component in expansion.iter() {
+        let (sum, error) = two_sum(carry, component);
+        if error != 0.0 {
+            result.push(error)
+        }
+        carry = sum;
+    }
+    if carry != 0.0 {
+        result.push(carry)
+    }
+    *expansion = result
+}
+
+// Returns `a + b` rounded, and the exact rounding error.
+#[inline]
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    let b_virtual = sum - a;
+    let a_virtual = sum - b_virtual;
+    (sum, (a - a_virtual) + (b - b_virtual))
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::Point2D;
+    use std::cmp::Ordering;
+
+    use super::orientation;
+
+    #[test]
+    fn near_collinear_points_that_f32_gets_wrong() {
+        // The rounded `f32` cross product of these points is positive, but the exact one is
+        // about -0.35.
+        let a = Point2D::new(0.06985542178153992, 0.0907130166888237);
+        let b = Point2D::new(4302.81005859375, 3012.00927734375);
+        let c = Point2D::new(8605.548828125, 6023.9267578125);
+        assert!((b - a).cross(c - a) > 0.0);
+        assert_eq!(orientation(&a, &b, &c), Ordering::Less);
+
+        let a = Point2D::new(0.45816099643707275, 0.5833487510681152);
+        let b = Point2D::new(9052.99609375, 6337.35986328125);
+        let c = Point2D::new(18105.53515625, 12674.13671875);
+        assert!((b - a).cross(c - a) > 0.0);
+        assert_eq!(orientation(&a, &b, &c), Ordering::Less);
+    }
+
+    #[test]
+    fn near_collinear_points_that_f32_calls_collinear() {
+        // The rounded `f32` cross product of these points is exactly zero.
+        let a = Point2D::new(0.5000015497207642, 0.5000004768371582);
+        let b = Point2D::new(12.0, 12.0);
+        let c = Point2D::new(24.0, 24.0);
+        assert_eq!((b - a).cross(c - a), 0.0);
+        assert_eq!(orientation(&a, &b, &c), Ordering::Less);
+
+        let a = Point2D::new(0.5000065565109253, 0.5000098943710327);
+        assert_eq!((b - a).cross(c - a), 0.0);
+        assert_eq!(orientation(&a, &b, &c), Ordering::Greater);
+    }
+
+    #[test]
+    fn exactly_collinear_and_coincident_points() {
+        let a = Point2D::new(0.1, 0.1);
+        let b = Point2D::new(0.2, 0.2);
+        let c = Point2D::new(1.0e7, 1.0e7);
+        assert_eq!(orientation(&a, &a, &a), Ordering::Equal);
+        assert_eq!(orientation(&a, &b, &b), Ordering::Equal);
+        assert_eq!(orientation(&a, &c, &Point2D::new(3.0e7, 3.0e7)), Ordering::Equal);
+
+        let nan = Point2D::new(::std::f32::NAN, 0.0);
+        let infinity = Point2D::new(::std::f32::INFINITY, 1.0);
+        assert_eq!(orientation(&a, &b, &nan), Ordering::Equal);
+        assert_eq!(orientation(&a, &infinity, &b), Ordering::Equal);
+    }
+
+    #[test]
+    fn orientation_is_consistent_under_permutation() {
+        // Points along a line through the origin, nudged by a few ulps, so that most triples
+        // need the exact path.
+        let mut state = 0x2545f491u32;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        };
+        for _ in 0..10000 {
+            let mut points = [Point2D::zero(); 3];
+            for point in &mut points {
+                let t = (next() % 100000) as f32 * 0.37;
+                let x = t * 0.6;
+                let y = t * 0.8;
+                let nudge = (next() % 5) as i32 - 2;
+                *point = Point2D::new(x, f32::from_bits((y.to_bits() as i32 + nudge) as u32));
+            }
+            let (a, b, c) = (&points[0], &points[1], &points[2]);
+            let orientation_abc = orientation(a, b, c);
+            assert_eq!(orientation(b, c, a), orientation_abc);
+            assert_eq!(orientation(c, a, b), orientation_abc);
+            assert_eq!(orientation(a, c, b), orientation_abc.reverse());
+            assert_eq!(orientation(b, a, c), orientation_abc.reverse());
+        }
+    }
+}
@@ -0,0 +1,196 @@
+// pathfinder/partitioner/src/remote.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Partitions paths in another process, so that untrusted vector content can be handled in a
+//! sandbox.
+//!
+//! The protocol is a sequence of messages, each a little-endian `u32` byte length followed by a
+//! `bincode`-encoded `Request` or `Response`. The client sends one request at a time and reads
+//! one response for each. The server side is `serve()`; the `pathfinder-partition-server` tool
+//! runs it over standard input and output, and `RemotePartitioner` spawns and drives such a
+//! process. Applying the operating system sandbox to the child is up to the embedder.
+//!
+//! Messages from the other side are treated as untrusted: oversized messages are rejected, and a
+//! server that crashes on malformed input just causes an error on the client.
+
+use bincode;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use lyon_path::PathEvent;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use batch;
+use mesh_pack::MeshPack;
+use FillRule;
+
+/// The largest message either side will accept, in bytes.
+pub const MAX_MESSAGE_LENGTH: u32 = 64 * 1024 * 1024;
+
+/// One path to partition.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RemotePath {
+    pub events: Vec<PathEvent>,
+    pub fill_rule: FillRule,
+    /// The approximation tolerance. If `None`, the builder's default is used.
+    pub approx_tolerance: Option<f32>,
+}
+
+/// A message from the client to the server.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Request {
+    /// Partitions the paths. The server replies with `Response::MeshPack`, with one mesh per
+    /// path, in order.
+    Partition(Vec<RemotePath>),
+    /// Asks the server to exit. There is no reply.
+    Shutdown,
+}
+
+/// A message from the server to the client.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Response {
+    MeshPack(MeshPack),
+}
+
+/// Writes one message.
+pub fn write_message<W, T>(writer: &mut W, message: &T) -> io::Result<()>
+                           where W: Write, T: Serialize {
+    let data = try!(bincode::serialize(message).map_err(|_| io::Error::from(ErrorKind::Other)));
+    if data.len() > MAX_MESSAGE_LENGTH as usize {
+        return Err(io::Error::from(ErrorKind::InvalidInput))
+    }
+    try!(writer.write_u32::<LittleEndian>(data.len() as u32));
+    try!(writer.write_all(&data));
+    writer.flush()
+}
+
+/// Reads one message. Returns `Ok(None)` if the stream ended cleanly before it.
+pub fn read_message<R, T>(reader: &mut R) -> io::Result<Option<T>>
+                          where R: Read, T: DeserializeOwned {
+    let length = match reader.read_u32::<LittleEndian>() {
+        Ok(length) => length,
+        Err(ref error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    if length > MAX_MESSAGE_LENGTH {
+        return Err(io::Error::from(ErrorKind::InvalidData))
+    }
+
+    // Read incrementally rather than allocating the claimed length up front, so that a peer
+    // can't make us allocate memory it never sends.
+    let mut data = vec![];
+    try!(reader.by_ref().take(length as u64).read_to_end(&mut data));
+    if data.len() != length as usize {
+        return Err(io::Error::from(ErrorKind::UnexpectedEof))
+    }
+    bincode::deserialize(&data).map(Some).map_err(|_| io::Error::from(ErrorKind::InvalidData))
+}
+
+/// Answers requests read from `reader` until it ends or a `Request::Shutdown` arrives.
+pub fn serve<R, W>(reader: &mut R, writer: &mut W) -> io::Result<()> where R: Read, W: Write {
+    loop {
+        match try!(read_message(reader)) {
+            None | Some(Request::Shutdown) => return Ok(()),
+            Some(Request::Partition(paths)) => {
+                let mesh_pack = batch::build_mesh_pack(&paths, |path| {
+                    batch::partition_path(&path.events, path.fill_rule, path.approx_tolerance)
+                });
+                try!(write_message(writer, &Response::MeshPack(mesh_pack)));
+            }
+        }
+    }
+}
+
+/// A child process that partitions paths.
+pub struct RemotePartitioner {
+    child: Child,
+    stdin: BufWriter<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl RemotePartitioner {
+    /// Spawns `command`, which must run `serve()` over its standard input and output, such as
+    /// the `pathfinder-partition-server` tool. Its standard input and output are replaced with
+    /// pipes.
+    pub fn spawn(command: &mut Command) -> io::Result<RemotePartitioner> {
+        let mut child = try!(command.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn());
+        let (stdin, stdout) = match (child.stdin.take(), child.stdout.take()) {
+            (Some(stdin), Some(stdout)) => (stdin, stdout),
+            _ => return Err(io::Error::from(ErrorKind::BrokenPipe)),
+        };
+        Ok(RemotePartitioner {
+            child: child,
+            stdin: BufWriter::new(stdin),
+            stdout: BufReader::new(stdout),
+        })
+    }
+
+    /// Partitions the paths in the child process and returns one mesh per path, in order.
+    ///
+    /// If the child exits or sends a malformed reply, this returns an error, and the partitioner
+    /// should not be used again.
+    pub fn partition(&mut self, paths: &[RemotePath]) -> io::Result<MeshPack> {
+        try!(write_message(&mut self.stdin, &Request::Partition(paths.to_vec())));
+        match try!(read_message(&mut self.stdout)) {
+            None => Err(io::Error::from(ErrorKind::UnexpectedEof)),
+            Some(Response::MeshPack(mesh_pack)) => {
+                if mesh_pack.meshes.len() != paths.len() {
+                    return Err(io::Error::from(ErrorKind::InvalidData))
+                }
+                Ok(mesh_pack)
+            }
+        }
+    }
+}
+
+impl Drop for RemotePartitioner {
+    fn drop(&mut self) {
+        // Tell the server to exit, in case the child is a sandbox launcher that killing it
+        // wouldn't reach, but don't trust the server to listen.
+        drop(write_message(&mut self.stdin, &Request::Shutdown));
+        drop(self.child.kill());
+        drop(self.child.wait());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{LittleEndian, WriteBytesExt};
+    use std::io::{Cursor, ErrorKind};
+
+    use super::{MAX_MESSAGE_LENGTH, Request, read_message, write_message};
+
+    #[test]
+    fn messages_round_trip() {
+        let mut data = vec![];
+        write_message(&mut data, &Request::Shutdown).unwrap();
+        match read_message(&mut Cursor::new(data)).unwrap() {
+            Some(Request::Shutdown) => {}
+            message => panic!("unexpected message {:?}", message),
+        }
+        assert!(read_message::<_, Request>(&mut Cursor::new(vec![])).unwrap().is_none());
+    }
+
+    #[test]
+    fn oversized_and_truncated_messages_are_rejected() {
+        let mut data = vec![];
+        data.write_u32::<LittleEndian>(MAX_MESSAGE_LENGTH + 1).unwrap();
+        let error = read_message::<_, Request>(&mut Cursor::new(data)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        // A length just under the limit with nothing after it must fail without allocating it.
+        let mut data = vec![];
+        data.write_u32::<LittleEndian>(MAX_MESSAGE_LENGTH).unwrap();
+        data.extend_from_slice(&[0; 16]);
+        let error = read_message::<_, Request>(&mut Cursor::new(data)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,123 @@
+// pathfinder/partitioner/src/scene.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Partitions scenes on a background thread, so that the thread that renders them doesn't stall
+//! when the content changes.
+//!
+//! The render thread hands paths to a `SceneProxy` and keeps drawing the last mesh pack it
+//! received until a new one is ready. If scenes are submitted faster than they can be built, the
+//! stale ones are skipped and only the newest is built. With the `rayon` feature, each build is
+//! also spread across the Rayon thread pool, as `batch::partition_paths()` does.
+
+use lyon_path::PathEvent;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use batch;
+use mesh_pack::MeshPack;
+use FillRule;
+
+/// A mesh pack finished by the scene thread.
+#[derive(Debug)]
+pub struct BuiltScene {
+    /// The epoch returned by the `SceneProxy::build()` call that submitted the scene.
+    pub epoch: u64,
+    /// One mesh per submitted path, in order.
+    pub mesh_pack: MeshPack,
+}
+
+struct BuildRequest {
+    epoch: u64,
+    paths: Vec<(Vec<PathEvent>, FillRule)>,
+    approx_tolerance: Option<f32>,
+}
+
+/// A handle to a background thread that partitions scenes.
+pub struct SceneProxy {
+    request_sender: Option<Sender<BuildRequest>>,
+    scene_receiver: Receiver<BuiltScene>,
+    thread: Option<JoinHandle<()>>,
+    next_epoch: u64,
+}
+
+impl SceneProxy {
+    /// Starts the scene thread.
+    pub fn new() -> SceneProxy {
+        let (request_sender, request_receiver) = mpsc::channel();
+        let (scene_sender, scene_receiver) = mpsc::channel();
+        let thread = thread::spawn(move || build_scenes(request_receiver, scene_sender));
+        SceneProxy {
+            request_sender: Some(request_sender),
+            scene_receiver: scene_receiver,
+            thread: Some(thread),
+            next_epoch: 0,
+        }
+    }
+
+    /// Submits a scene to be partitioned and returns its epoch, which increases with each call.
+    ///
+    /// If `approx_tolerance` is `None`, the builder's default tolerance is used.
+    pub fn build(&mut self, paths: Vec<(Vec<PathEvent>, FillRule)>, approx_tolerance: Option<f32>)
+                 -> u64 {
+        let epoch = self.next_epoch;
+        self.next_epoch += 1;
+        if let Some(ref request_sender) = self.request_sender {
+            drop(request_sender.send(BuildRequest {
+                epoch: epoch,
+                paths: paths,
+                approx_tolerance: approx_tolerance,
+            }))
+        }
+        epoch
+    }
+
+    /// Returns the newest scene that has finished building since the last call, without
+    /// blocking, or `None` if there isn't one.
+    pub fn try_recv(&self) -> Option<BuiltScene> {
+        let mut newest_scene = None;
+        while let Ok(scene) = self.scene_receiver.try_recv() {
+            newest_scene = Some(scene)
+        }
+        newest_scene
+    }
+
+    /// Waits for the next scene to finish building. Returns `None` if the scene thread has
+    /// died.
+    pub fn recv(&self) -> Option<BuiltScene> {
+        self.scene_receiver.recv().ok()
+    }
+}
+
+impl Drop for SceneProxy {
+    fn drop(&mut self) {
+        // Closing the channel tells the thread to exit.
+        self.request_sender = None;
+        if let Some(thread) = self.thread.take() {
+            drop(thread.join())
+        }
+    }
+}
+
+fn build_scenes(request_receiver: Receiver<BuildRequest>, scene_sender: Sender<BuiltScene>) {
+    while let Ok(mut request) = request_receiver.recv() {
+        // Skip to the newest request.
+        while let Ok(newer_request) = request_receiver.try_recv() {
+            request = newer_request
+        }
+
+        let mesh_pack = batch::partition_paths(&request.paths, request.approx_tolerance);
+        if scene_sender.send(BuiltScene {
+            epoch: request.epoch,
+            mesh_pack: mesh_pack,
+        }).is_err() {
+            return
+        }
+    }
+}
@@ -0,0 +1,140 @@
+// pathfinder/partitioner/src/spans.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Converts partitioned meshes to per-scanline coverage spans, in the style of FreeType's span
+//! callbacks, for CPU compositors.
+
+use euclid::Point2D;
+use std::collections::BTreeMap;
+use std::f32;
+
+use mesh::Mesh;
+
+/// The number of columns sampled within each pixel by default. Coverage is exact vertically and
+/// sampled horizontally.
+pub const DEFAULT_HORIZONTAL_SAMPLES: u32 = 8;
+
+/// A horizontal run of pixels in one scanline that all have the same coverage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    /// The leftmost pixel column of the run.
+    pub x: i32,
+    /// The scanline. Scanline *y* covers the band from *y* to *y* + 1 in mesh coordinates.
+    pub y: i32,
+    /// The number of pixels in the run.
+    pub len: u32,
+    /// The fraction of each pixel that the path covers, from 0 to 255.
+    pub coverage: u8,
+}
+
+/// Computes the coverage spans of the mesh, treating one mesh unit as one pixel.
+///
+/// Transform the path into pixel space before partitioning it to get spans at a particular size.
+/// Spans are returned sorted by scanline and then by column. Pixels with zero coverage are
+/// omitted.
+#[inline]
+pub fn mesh_spans(mesh: &Mesh) -> Vec<Span> {
+    mesh_spans_with_samples(mesh, DEFAULT_HORIZONTAL_SAMPLES)
+}
+
+/// Like `mesh_spans()`, but sampling `horizontal_samples` columns within each pixel. More
+/// samples make near-vertical edges smoother at the cost of time.
+pub fn mesh_spans_with_samples(mesh: &Mesh, horizontal_samples: u32) -> Vec<Span> {
+    let horizontal_samples = horizontal_samples.max(1);
+
+    // Accumulate coverage per pixel. B-quads don't overlap, so their coverage simply adds up.
+    let mut coverage: BTreeMap<(i32, i32), f32> = BTreeMap::new();
+    let sample_weight = 1.0 / horizontal_samples as f32;
+
+    for positions in &mesh.b_quad_vertex_positions {
+        let upper = [
+            positions.upper_left_vertex_position,
+            positions.upper_control_point_position,
+            positions.upper_right_vertex_position,
+        ];
+        let lower = [
+            positions.lower_left_vertex_position,
+            positions.lower_control_point_position,
+            positions.lower_right_vertex_position,
+        ];
+
+        let left = f32::max(upper[0].x, lower[0].x);
+        let right = f32::min(upper[2].x, lower[2].x);
+        if !(left < right) {
+            continue
+        }
+
+        let (first_column, last_column) = (left.floor() as i32, right.ceil() as i32);
+        for column in first_column..last_column {
+            for sample in 0..horizontal_samples {
+                let x = column as f32 + (sample as f32 + 0.5) * sample_weight;
+                if x < left || x >= right {
+                    continue
+                }
+
+                let (y0, y1) = (solve_y_for_x(&upper, x), solve_y_for_x(&lower, x));
+                let (top, bottom) = (f32::min(y0, y1), f32::max(y0, y1));
+                for row in (top.floor() as i32)..(bottom.ceil() as i32) {
+                    let overlap = f32::min(bottom, (row + 1) as f32) - f32::max(top, row as f32);
+                    if overlap > 0.0 {
+                        *coverage.entry((row, column)).or_insert(0.0) += overlap * sample_weight;
+                    }
+                }
+            }
+        }
+    }
+
+    coverage_spans(&coverage)
+}
+
+/// Run-length encodes coverage from 0 to 1, keyed by scanline and then column, into spans.
+pub(crate) fn coverage_spans(coverage: &BTreeMap<(i32, i32), f32>) -> Vec<Span> {
+    let mut spans: Vec<Span> = vec![];
+    for (&(y, x), &pixel_coverage) in coverage {
+        let pixel_coverage = (pixel_coverage.max(0.0).min(1.0) * 255.0).round() as u8;
+        if pixel_coverage == 0 {
+            continue
+        }
+
+        if let Some(last_span) = spans.last_mut() {
+            if last_span.y == y && last_span.x + last_span.len as i32 == x &&
+                    last_span.coverage == pixel_coverage {
+                last_span.len += 1;
+                continue
+            }
+        }
+
+        spans.push(Span {
+            x: x,
+            y: y,
+            len: 1,
+            coverage: pixel_coverage,
+        })
+    }
+    spans
+}
+
+// Evaluates the Y coordinate of the x-monotonic quadratic curve at `x`.
+fn solve_y_for_x(curve: &[Point2D<f32>; 3], x: f32) -> f32 {
+    let (p0, p1, p2) = (curve[0], curve[1], curve[2]);
+    let (a, b, c) = (p0.x - 2.0 * p1.x + p2.x, 2.0 * (p1.x - p0.x), p0.x - x);
+
+    let t = if a.abs() < 1e-6 {
+        if b.abs() < 1e-6 { 0.0 } else { -c / b }
+    } else {
+        let discriminant = (b * b - 4.0 * a * c).max(0.0).sqrt();
+        let (t0, t1) = ((-b - discriminant) / (2.0 * a), (-b + discriminant) / (2.0 * a));
+        if t0 >= -1e-4 && t0 <= 1.0 + 1e-4 { t0 } else { t1 }
+    };
+
+    let t = t.max(0.0).min(1.0);
+    let one_minus_t = 1.0 - t;
+    one_minus_t * one_minus_t * p0.y + 2.0 * t * one_minus_t * p1.y + t * t * p2.y
+}
@@ -0,0 +1,363 @@
+// pathfinder/partitioner/src/streaming.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Hands the buffers of a mesh pack to a renderer a little at a time, so that a huge document
+//! can be uploaded to the GPU over many frames instead of stalling one.
+//!
+//! Each frame, `MeshUploadStream::upload_frame()` encodes up to a byte budget of mesh data and
+//! passes it to the caller in chunks, each of which is a byte range of one buffer of one mesh,
+//! ready for a call like `glBufferSubData()`. The bytes are exactly what a `write_to()` mesh
+//! chunk contains, so renderers that already read mesh packs can use the same vertex layouts.
+
+use bincode;
+use serde::Serialize;
+
+use mesh::Mesh;
+use mesh_pack::MeshPack;
+
+/// One buffer of a mesh.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MeshBuffer {
+    BQuads,
+    BQuadVertexPositions,
+    BQuadVertexInteriorIndices,
+    BVertexPositions,
+    BVertexLoopBlinnData,
+    BBoxes,
+    StencilSegments,
+    StencilNormals,
+}
+
+/// Every buffer, in the order they're streamed.
+pub const MESH_BUFFERS: [MeshBuffer; 8] = [
+    MeshBuffer::BQuads,
+    MeshBuffer::BQuadVertexPositions,
+    MeshBuffer::BQuadVertexInteriorIndices,
+    MeshBuffer::BVertexPositions,
+    MeshBuffer::BVertexLoopBlinnData,
+    MeshBuffer::BBoxes,
+    MeshBuffer::StencilSegments,
+    MeshBuffer::StencilNormals,
+];
+
+impl MeshBuffer {
+    /// Returns the tag of the chunk that `MeshPack::write_to()` stores this buffer in.
+    pub fn tag(self) -> &'static [u8; 4] {
+        match self {
+            MeshBuffer::BQuads => b"bqua",
+            MeshBuffer::BQuadVertexPositions => b"bqvp",
+            MeshBuffer::BQuadVertexInteriorIndices => b"bqii",
+            MeshBuffer::BVertexPositions => b"bvpo",
+            MeshBuffer::BVertexLoopBlinnData => b"bvlb",
+            MeshBuffer::BBoxes => b"bbox",
+            MeshBuffer::StencilSegments => b"sseg",
+            MeshBuffer::StencilNormals => b"snor",
+        }
+    }
+
+    /// Returns the number of elements in this buffer of `mesh`.
+    pub fn len(self, mesh: &Mesh) -> usize {
+        match self {
+            MeshBuffer::BQuads => mesh.b_quads.len(),
+            MeshBuffer::BQuadVertexPositions => mesh.b_quad_vertex_positions.len(),
+            MeshBuffer::BQuadVertexInteriorIndices => mesh.b_quad_vertex_interior_indices.len(),
+            MeshBuffer::BVertexPositions => mesh.b_vertex_positions.len(),
+            MeshBuffer::BVertexLoopBlinnData => mesh.b_vertex_loop_blinn_data.len(),
+            MeshBuffer::BBoxes => mesh.b_boxes.len(),
+            MeshBuffer::StencilSegments => mesh.stencil_segments.len(),
+            MeshBuffer::StencilNormals => mesh.stencil_normals.len(),
+        }
+    }
+
+    // Appends the encoding of elements `start..end` of this buffer of `mesh` to `data`.
+    fn encode(self, mesh: &Mesh, start: usize, end: usize, data: &mut Vec<u8>) {
+        match self {
+            MeshBuffer::BQuads => encode_elements(&mesh.b_quads[start..end], data),
+            MeshBuffer::BQuadVertexPositions => {
+                encode_elements(&mesh.b_quad_vertex_positions[start..end], data)
+            }
+            MeshBuffer::BQuadVertexInteriorIndices => {
+                encode_elements(&mesh.b_quad_vertex_interior_indices[start..end], data)
+            }
+            MeshBuffer::BVertexPositions => {
+                encode_elements(&mesh.b_vertex_positions[start..end], data)
+            }
+            MeshBuffer::BVertexLoopBlinnData => {
+                encode_elements(&mesh.b_vertex_loop_blinn_data[start..end], data)
+            }
+            MeshBuffer::BBoxes => encode_elements(&mesh.b_boxes[start..end], data),
+            MeshBuffer::StencilSegments => {
+                encode_elements(&mesh.stencil_segments[start..end], data)
+            }
+            MeshBuffer::StencilNormals => encode_elements(&mesh.stencil_normals[start..end], data),
+        }
+    }
+}
+
+/// A byte range of one buffer of one mesh.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UploadChunk {
+    /// The index of the mesh in the mesh pack.
+    pub mesh_index: usize,
+    pub buffer: MeshBuffer,
+    /// Where `data` starts in the buffer, in bytes. The first chunk of each buffer starts at 0.
+    pub offset: usize,
+    /// The size of the whole buffer in bytes, so that it can be allocated when its first chunk
+    /// arrives.
+    pub buffer_size: usize,
+    pub data: Vec<u8>,
+}
+
+/// How far a stream has gotten.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UploadProgress {
+    pub bytes_uploaded: usize,
+    pub total_bytes: usize,
+    /// The number of meshes whose buffers have all been uploaded. Meshes finish in order, so
+    /// meshes before this index can be drawn.
+    pub meshes_completed: usize,
+    pub mesh_count: usize,
+}
+
+impl UploadProgress {
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.meshes_completed == self.mesh_count
+    }
+
+    /// Returns the fraction of the data uploaded, from 0 to 1.
+    #[inline]
+    pub fn fraction(&self) -> f32 {
+        if self.total_bytes == 0 {
+            1.0
+        } else {
+            self.bytes_uploaded as f32 / self.total_bytes as f32
+        }
+    }
+}
+
+/// Streams the meshes of a mesh pack in order, one budget's worth per frame.
+pub struct MeshUploadStream<'a> {
+    mesh_pack: &'a MeshPack,
+    // The size of one element of each buffer, in `MESH_BUFFERS` order.
+    element_sizes: [usize; 8],
+    // The next element to upload.
+    mesh_index: usize,
+    buffer_index: usize,
+    element_index: usize,
+    progress: UploadProgress,
+    progress_callback: Option<Box<FnMut(&UploadProgress) + 'a>>,
+}
+
+impl<'a> MeshUploadStream<'a> {
+    pub fn new(mesh_pack: &'a MeshPack) -> MeshUploadStream<'a> {
+        // Every element of a buffer encodes to the same size, so measure one of each.
+        let mut element_sizes = [0; 8];
+        for (buffer_index, &buffer) in MESH_BUFFERS.iter().enumerate() {
+            if let Some(mesh) = mesh_pack.meshes.iter().find(|mesh| buffer.len(mesh) > 0) {
+                let mut data = vec![];
+                buffer.encode(mesh, 0, 1, &mut data);
+                element_sizes[buffer_index] = data.len();
+            }
+        }
+
+        let total_bytes = mesh_pack.meshes.iter().map(|mesh| {
+            MESH_BUFFERS.iter().zip(element_sizes.iter()).map(|(&buffer, &element_size)| {
+                buffer.len(mesh) * element_size
+            }).sum::<usize>()
+        }).sum();
+
+        let mut stream = MeshUploadStream {
+            mesh_pack: mesh_pack,
+            element_sizes: element_sizes,
+            mesh_index: 0,
+            buffer_index: 0,
+            element_index: 0,
+            progress: UploadProgress {
+                bytes_uploaded: 0,
+                total_bytes: total_bytes,
+                meshes_completed: 0,
+                mesh_count: mesh_pack.meshes.len(),
+            },
+            progress_callback: None,
+        };
+        stream.skip_empty_buffers();
+        stream
+    }
+
+    /// Sets a function to call with the progress at the end of every `upload_frame()`.
+    #[inline]
+    pub fn set_progress_callback(&mut self, callback: Box<FnMut(&UploadProgress) + 'a>) {
+        self.progress_callback = Some(callback)
+    }
+
+    #[inline]
+    pub fn progress(&self) -> UploadProgress {
+        self.progress
+    }
+
+    /// Encodes up to `byte_budget` bytes of mesh data and passes it to `upload` in chunks, in
+    /// order. Returns the progress afterward.
+    ///
+    /// Chunks end on element boundaries. At least one element is uploaded per call, even if it's
+    /// larger than the budget, so the stream always finishes.
+    pub fn upload_frame<F>(&mut self, byte_budget: usize, mut upload: F) -> UploadProgress
+                           where F: FnMut(UploadChunk) {
+        let mut bytes_remaining = byte_budget;
+        let mut uploaded_any = false;
+        while !self.progress.is_complete() {
+            let mesh = &self.mesh_pack.meshes[self.mesh_index];
+            let buffer = MESH_BUFFERS[self.buffer_index];
+            let element_size = self.element_sizes[self.buffer_index];
+            let element_count = buffer.len(mesh);
+
+            let mut affordable_elements = bytes_remaining / element_size;
+            if affordable_elements == 0 {
+                if uploaded_any {
+                    break
+                }
+                affordable_elements = 1
+            }
+            let end = (self.element_index + affordable_elements).min(element_count);
+
+            let mut data = Vec::with_capacity((end - self.element_index) * element_size);
+            buffer.encode(mesh, self.element_index, end, &mut data);
+            bytes_remaining = bytes_remaining.saturating_sub(data.len());
+            self.progress.bytes_uploaded += data.len();
+            upload(UploadChunk {
+                mesh_index: self.mesh_index,
+                buffer: buffer,
+                offset: self.element_index * element_size,
+                buffer_size: element_count * element_size,
+                data: data,
+            });
+            uploaded_any = true;
+
+            self.element_index = end;
+            self.skip_empty_buffers();
+        }
+
+        if let Some(ref mut callback) = self.progress_callback {
+            callback(&self.progress)
+        }
+        self.progress
+    }
+
+    // Moves past finished buffers and meshes to the next element there is to upload.
+    fn skip_empty_buffers(&mut self) {
+        while self.mesh_index < self.mesh_pack.meshes.len() {
+            let mesh = &self.mesh_pack.meshes[self.mesh_index];
+            while self.buffer_index < MESH_BUFFERS.len() &&
+                    self.element_index >= MESH_BUFFERS[self.buffer_index].len(mesh) {
+                self.buffer_index += 1;
+                self.element_index = 0;
+            }
+            if self.buffer_index < MESH_BUFFERS.len() {
+                return
+            }
+            self.mesh_index += 1;
+            self.buffer_index = 0;
+            self.progress.meshes_completed = self.mesh_index;
+        }
+    }
+}
+
+fn encode_elements<T>(elements: &[T], data: &mut Vec<u8>) where T: Serialize {
+    for element in elements {
+        // Writing into a vector can't fail.
+        drop(bincode::serialize_into(&mut *data, element))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{ByteOrder, LittleEndian};
+    use euclid::Point2D;
+    use lyon_path::PathEvent;
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    use FillRule;
+    use batch;
+    use mesh::Mesh;
+    use mesh_pack::MeshPack;
+    use super::{MESH_BUFFERS, MeshUploadStream};
+
+    fn mesh_pack() -> MeshPack {
+        let point = |x, y| Point2D::new(x, y);
+        let path = [
+            PathEvent::MoveTo(point(0.0, 0.0)),
+            PathEvent::QuadraticTo(point(5.0, -5.0), point(10.0, 0.0)),
+            PathEvent::LineTo(point(10.0, 10.0)),
+            PathEvent::CubicTo(point(7.0, 14.0), point(3.0, 6.0), point(0.0, 10.0)),
+            PathEvent::Close,
+        ];
+        let mut mesh = batch::partition_path(&path, FillRule::Winding, None);
+        mesh.push_stencil_segments(path.iter().cloned());
+
+        let mut mesh_pack = MeshPack::new();
+        mesh_pack.push(mesh.clone());
+        mesh_pack.push(Mesh::new());
+        mesh_pack.push(mesh);
+        mesh_pack
+    }
+
+    // Splits RIFF chunks into their tags and contents.
+    fn chunks(mut data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+        let mut chunks = vec![];
+        while !data.is_empty() {
+            let mut tag = [0; 4];
+            tag.copy_from_slice(&data[0..4]);
+            let length = LittleEndian::read_u32(&data[4..8]) as usize;
+            chunks.push((tag, &data[8..(8 + length)]));
+            data = &data[(8 + length)..];
+        }
+        chunks
+    }
+
+    #[test]
+    fn uploaded_chunks_reassemble_to_the_written_mesh_chunks() {
+        let mesh_pack = mesh_pack();
+        let mut written = Cursor::new(vec![]);
+        mesh_pack.write_to(&mut written).unwrap();
+        let written = written.into_inner();
+        let top_level_chunks = chunks(&written[12..]);
+        let written_meshes: Vec<HashMap<_, _>> = top_level_chunks.into_iter().filter(|&(tag, _)| {
+            &tag == b"mesh"
+        }).map(|(_, mesh)| chunks(mesh).into_iter().collect()).collect();
+        assert_eq!(written_meshes.len(), 3);
+
+        // An odd budget splits elements across frames and buffers across chunks.
+        let mut uploaded: Vec<HashMap<[u8; 4], Vec<u8>>> = vec![HashMap::new(); 3];
+        let mut stream = MeshUploadStream::new(&mesh_pack);
+        let mut frame_count = 0;
+        while !stream.progress().is_complete() {
+            stream.upload_frame(37, |chunk| {
+                let data = uploaded[chunk.mesh_index].entry(*chunk.buffer.tag())
+                                                     .or_insert(vec![]);
+                assert_eq!(chunk.offset, data.len());
+                data.extend_from_slice(&chunk.data);
+                assert!(data.len() <= chunk.buffer_size);
+            });
+            frame_count += 1;
+        }
+        assert!(frame_count > 1);
+        assert_eq!(stream.progress().bytes_uploaded, stream.progress().total_bytes);
+
+        for (written_mesh, uploaded_mesh) in written_meshes.iter().zip(uploaded.iter()) {
+            for buffer in &MESH_BUFFERS {
+                let uploaded_data = uploaded_mesh.get(buffer.tag()).map_or(&[][..], |data| {
+                    &data[..]
+                });
+                assert_eq!(uploaded_data, written_mesh[buffer.tag()], "{:?}", buffer);
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+// pathfinder/partitioner/src/tiling.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Partitions very large paths by clipping them into a grid of tiles and partitioning each tile
+//! independently.
+//!
+//! This keeps the number of active edges the partitioner has to track small, and it produces one
+//! mesh per tile, so that tiles can be streamed in and culled individually.
+
+use euclid::{Point2D, Rect, Size2D, Vector2D};
+use lyon_path::PathEvent;
+use pathfinder_path_utils::clip;
+
+use batch;
+use mesh::Mesh;
+use FillRule;
+
+/// One tile of a tiled path.
+#[derive(Debug, Clone)]
+pub struct Tile {
+    /// The column and row of this tile in the grid. Tile (0, 0) is at the origin of the path's
+    /// bounding box.
+    pub position: Point2D<u32>,
+    /// The area that this tile covers.
+    pub rect: Rect<f32>,
+    pub mesh: Mesh,
+}
+
+/// Clips the path into tiles of size `tile_size` and partitions each one.
+///
+/// The grid starts at the minimum corner of the path's bounding box. Tiles that the path doesn't
+/// cover at all are omitted. `approx_tolerance` is used both to convert curves to quadratics for
+/// clipping and for partitioning. If the `rayon` feature is enabled, tiles are partitioned in
+/// parallel.
+pub fn partition_tiled(path: &[PathEvent],
+                       fill_rule: FillRule,
+                       tile_size: &Size2D<f32>,
+                       approx_tolerance: f32)
+                       -> Vec<Tile> {
+    let bounds = match path_bounds(path) {
+        None => return vec![],
+        Some(bounds) => bounds,
+    };
+    if tile_size.width <= 0.0 || tile_size.height <= 0.0 {
+        return vec![]
+    }
+
+    let columns = ((bounds.size.width / tile_size.width).ceil() as u32).max(1);
+    let rows = ((bounds.size.height / tile_size.height).ceil() as u32).max(1);
+
+    let mut tiles = Vec::with_capacity((columns * rows) as usize);
+    for row in 0..rows {
+        for column in 0..columns {
+            let origin = bounds.origin + Vector2D::new(column as f32 * tile_size.width,
+                                                       row as f32 * tile_size.height);
+            tiles.push((Point2D::new(column, row), Rect::new(origin, *tile_size)));
+        }
+    }
+
+    let mesh_pack = batch::build_mesh_pack(&tiles, |&(_, ref rect)| {
+        let clipped_path = clip::clip_path_to_rect(path.iter().cloned(), rect, approx_tolerance);
+        batch::partition_path(&clipped_path, fill_rule, Some(approx_tolerance))
+    });
+
+    tiles.into_iter().zip(mesh_pack.meshes.into_iter()).filter_map(|((position, rect), mesh)| {
+        if mesh.b_quads.is_empty() {
+            return None
+        }
+        Some(Tile {
+            position: position,
+            rect: rect,
+            mesh: mesh,
+        })
+    }).collect()
+}
+
+// Returns the bounding box of all the points in the path, including control points.
+fn path_bounds(path: &[PathEvent]) -> Option<Rect<f32>> {
+    let mut points = vec![];
+    for event in path {
+        match *event {
+            PathEvent::MoveTo(to) | PathEvent::LineTo(to) => points.push(to),
+            PathEvent::QuadraticTo(ctrl, to) => points.extend_from_slice(&[ctrl, to]),
+            PathEvent::CubicTo(ctrl1, ctrl2, to) => points.extend_from_slice(&[ctrl1, ctrl2, to]),
+            PathEvent::Arc(center, radii, _, _) => {
+                // Conservatively include the whole ellipse, ignoring rotation.
+                let radius = f32::max(radii.x.abs(), radii.y.abs());
+                points.push(Point2D::new(center.x - radius, center.y - radius));
+                points.push(Point2D::new(center.x + radius, center.y + radius));
+            }
+            PathEvent::Close => {}
+        }
+    }
+    if points.is_empty() {
+        None
+    } else {
+        Some(Rect::from_points(points.iter()))
+    }
+}
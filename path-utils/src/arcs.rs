@@ -0,0 +1,438 @@
+// pathfinder/path-utils/src/arcs.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Elliptical arcs, in both the SVG endpoint parameterization and the center parameterization
+//! that Lyon's `PathEvent::Arc` uses, and their conversion to quadratic Bézier curves.
+//!
+//! See the SVG 1.1 specification, appendix F.6, "Elliptical arc implementation notes".
+
+use euclid::{Angle, Point2D, Vector2D};
+use lyon_geom::QuadraticBezierSegment;
+use lyon_path::PathEvent;
+use std::f32::consts::PI;
+
+/// The smallest radius that is not considered degenerate.
+const RADIUS_EPSILON: f32 = 1e-6;
+
+/// The largest number of quadratic curves that a single arc will be split into.
+const MAX_QUADRATIC_SEGMENTS: u32 = 1024;
+
+/// An elliptical arc from the current point to `to`, specified the way SVG's `A` path command
+/// does.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct ArcTo {
+    /// The radii of the ellipse, prior to rotation.
+    pub radii: Vector2D<f32>,
+    /// The angle from the X axis to the X axis of the ellipse.
+    pub x_rotation: Angle<f32>,
+    /// If true, the arc sweeps through more than 180°.
+    pub large_arc: bool,
+    /// If true, the arc is drawn in the direction of increasing angle.
+    pub sweep: bool,
+    /// The endpoint of the arc.
+    pub to: Point2D<f32>,
+}
+
+/// The result of converting an `ArcTo` to center parameterization.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum ArcConversion {
+    /// The endpoints coincide, so the arc is omitted entirely, per SVG.
+    Empty,
+    /// One of the radii is zero, so the arc is a straight line to its endpoint, per SVG.
+    Line(Point2D<f32>),
+    /// A proper elliptical arc.
+    Arc(CenterArc),
+}
+
+/// An elliptical arc specified by its center, the angle it starts at, and the angle it sweeps
+/// through.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct CenterArc {
+    pub center: Point2D<f32>,
+    pub radii: Vector2D<f32>,
+    /// The start angle, measured on the unrotated unit circle before scaling by the radii.
+    pub start_angle: Angle<f32>,
+    /// The signed angle the arc sweeps through. Positive values sweep toward increasing angle.
+    ///
+    /// Sweeps beyond a full turn are drawn as a full turn, since they'd retrace the ellipse, and
+    /// non-finite sweeps as nothing.
+    pub sweep_angle: Angle<f32>,
+    pub x_rotation: Angle<f32>,
+}
+
+impl ArcTo {
+    /// Converts this arc, which starts at `from`, to center parameterization.
+    ///
+    /// Radii that are too small to span the endpoints are scaled up, and the signs of the radii
+    /// are ignored, both as SVG specifies.
+    pub fn to_center_arc(&self, from: &Point2D<f32>) -> ArcConversion {
+        if *from == self.to {
+            return ArcConversion::Empty
+        }
+
+        let mut radii = Vector2D::new(self.radii.x.abs(), self.radii.y.abs());
+        if radii.x < RADIUS_EPSILON || radii.y < RADIUS_EPSILON {
+            return ArcConversion::Line(self.to)
+        }
+
+        // Step 1: Compute the midpoint of the chord in the ellipse's rotated frame.
+        let half_chord = rotate(&((*from - self.to) * 0.5), -self.x_rotation.radians);
+
+        // Step 2 (F.6.6): Scale up the radii if necessary.
+        let lambda = (half_chord.x * half_chord.x) / (radii.x * radii.x) +
+            (half_chord.y * half_chord.y) / (radii.y * radii.y);
+        if lambda > 1.0 {
+            radii = radii * lambda.sqrt()
+        }
+
+        // Step 3: Compute the center in the rotated frame.
+        let (rx2, ry2) = (radii.x * radii.x, radii.y * radii.y);
+        let (x2, y2) = (half_chord.x * half_chord.x, half_chord.y * half_chord.y);
+        let denominator = rx2 * y2 + ry2 * x2;
+        let mut coefficient = if denominator == 0.0 {
+            0.0
+        } else {
+            ((rx2 * ry2 - rx2 * y2 - ry2 * x2) / denominator).max(0.0).sqrt()
+        };
+        if self.large_arc == self.sweep {
+            coefficient = -coefficient
+        }
+        let center = Vector2D::new(coefficient * radii.x * half_chord.y / radii.y,
+                                   -coefficient * radii.y * half_chord.x / radii.x);
+
+        // Step 4: Compute the angles.
+        let start = Vector2D::new((half_chord.x - center.x) / radii.x,
+                                  (half_chord.y - center.y) / radii.y);
+        let end = Vector2D::new((-half_chord.x - center.x) / radii.x,
+                                (-half_chord.y - center.y) / radii.y);
+        let start_angle = start.y.atan2(start.x);
+        let mut sweep_angle = end.y.atan2(end.x) - start_angle;
+        if self.sweep && sweep_angle < 0.0 {
+            sweep_angle += 2.0 * PI
+        } else if !self.sweep && sweep_angle > 0.0 {
+            sweep_angle -= 2.0 * PI
+        }
+
+        let midpoint = from.lerp(self.to, 0.5);
+        ArcConversion::Arc(CenterArc {
+            center: midpoint + rotate(&center, self.x_rotation.radians),
+            radii: radii,
+            start_angle: Angle::radians(start_angle),
+            sweep_angle: Angle::radians(sweep_angle),
+            x_rotation: self.x_rotation,
+        })
+    }
+}
+
+impl CenterArc {
+    /// Interprets the arguments of a `PathEvent::Arc` that starts at the point `from`.
+    ///
+    /// Lyon's arc event doesn't carry a start angle, so it's recovered from `from` by undoing the
+    /// rotation and the scaling by the radii before measuring the angle. This yields the correct
+    /// angle for any ellipse, not just circles, even if `from` isn't exactly on the ellipse.
+    pub fn from_path_event(from: &Point2D<f32>,
+                           center: &Point2D<f32>,
+                           radii: &Vector2D<f32>,
+                           sweep_angle: Angle<f32>,
+                           x_rotation: Angle<f32>)
+                           -> CenterArc {
+        let radii = Vector2D::new(radii.x.abs().max(RADIUS_EPSILON),
+                                  radii.y.abs().max(RADIUS_EPSILON));
+        let vector = rotate(&(*from - *center), -x_rotation.radians);
+        let start_angle = (vector.y / radii.y).atan2(vector.x / radii.x);
+        CenterArc {
+            center: *center,
+            radii: radii,
+            start_angle: Angle::radians(start_angle),
+            sweep_angle: sweep_angle,
+            x_rotation: x_rotation,
+        }
+    }
+
+    /// Returns the point on the ellipse at the given angle.
+    pub fn sample(&self, angle: f32) -> Point2D<f32> {
+        self.map_unit_point(&Vector2D::new(angle.cos(), angle.sin()))
+    }
+
+    #[inline]
+    pub fn from(&self) -> Point2D<f32> {
+        self.sample(self.start_angle.radians)
+    }
+
+    #[inline]
+    pub fn to(&self) -> Point2D<f32> {
+        self.sample(self.start_angle.radians + self.clamped_sweep_angle())
+    }
+
+    /// Approximates this arc with quadratic Bézier curves, each of which deviates from the true
+    /// arc by no more than `tolerance`.
+    pub fn for_each_quadratic<F>(&self, tolerance: f32, mut callback: F)
+                                 where F: FnMut(&QuadraticBezierSegment<f32>) {
+        let sweep_angle = self.clamped_sweep_angle();
+        if sweep_angle == 0.0 {
+            return
+        }
+
+        // Each curve spans at most a quarter turn. For a circular arc of radius r split into
+        // pieces of half-angle h, the error of each piece is r (1 - cos h)² / (2 cos h).
+        let max_radius = f32::max(self.radii.x, self.radii.y);
+        let mut segment_count = (sweep_angle.abs() / (PI * 0.5)).ceil().max(1.0) as u32;
+        while segment_count < MAX_QUADRATIC_SEGMENTS {
+            let cos_half_angle = (sweep_angle / segment_count as f32 * 0.5).cos();
+            let one_minus_cos = 1.0 - cos_half_angle;
+            if max_radius * one_minus_cos * one_minus_cos / (2.0 * cos_half_angle) <= tolerance {
+                break
+            }
+            segment_count *= 2;
+        }
+        // Counts that don't start at a power of two, like the 3 segments of three quarter turns,
+        // double past the limit.
+        let segment_count = segment_count.min(MAX_QUADRATIC_SEGMENTS);
+
+        let step = sweep_angle / segment_count as f32;
+        let ctrl_distance = 1.0 / (step * 0.5).cos();
+        let mut from = self.from();
+        for segment_index in 0..segment_count {
+            let angle = self.start_angle.radians + step * segment_index as f32;
+            let ctrl_angle = angle + step * 0.5;
+            let ctrl = self.map_unit_point(&(Vector2D::new(ctrl_angle.cos(), ctrl_angle.sin()) *
+                                             ctrl_distance));
+            let to = if segment_index + 1 == segment_count {
+                self.to()
+            } else {
+                self.sample(angle + step)
+            };
+            callback(&QuadraticBezierSegment {
+                from: from,
+                ctrl: ctrl,
+                to: to,
+            });
+            from = to;
+        }
+    }
+
+    // Returns the sweep angle in radians, limited to a full turn either way, or zero if it isn't
+    // finite.
+    pub(crate) fn clamped_sweep_angle(&self) -> f32 {
+        let sweep_angle = self.sweep_angle.radians;
+        if !sweep_angle.is_finite() {
+            return 0.0
+        }
+        sweep_angle.max(-2.0 * PI).min(2.0 * PI)
+    }
+
+    // Maps a point relative to the unit circle to the ellipse. Since this is an affine transform,
+    // it can be applied to Bézier control points too.
+    fn map_unit_point(&self, point: &Vector2D<f32>) -> Point2D<f32> {
+        let scaled = Vector2D::new(point.x * self.radii.x, point.y * self.radii.y);
+        self.center + rotate(&scaled, self.x_rotation.radians)
+    }
+}
+
+/// Converts `PathEvent::Arc` events to quadratic Bézier curves, passing all other events through.
+pub struct ArcToQuadraticTransformer<I> where I: Iterator<Item = PathEvent> {
+    inner: I,
+    quadratics: Vec<QuadraticBezierSegment<f32>>,
+    first_point: Point2D<f32>,
+    last_point: Point2D<f32>,
+    tolerance: f32,
+}
+
+impl<I> ArcToQuadraticTransformer<I> where I: Iterator<Item = PathEvent> {
+    #[inline]
+    pub fn new(inner: I, tolerance: f32) -> ArcToQuadraticTransformer<I> {
+        ArcToQuadraticTransformer {
+            inner: inner,
+            quadratics: vec![],
+            first_point: Point2D::zero(),
+            last_point: Point2D::zero(),
+            tolerance: tolerance,
+        }
+    }
+}
+
+impl<I> Iterator for ArcToQuadraticTransformer<I> where I: Iterator<Item = PathEvent> {
+    type Item = PathEvent;
+
+    fn next(&mut self) -> Option<PathEvent> {
+        if let Some(quadratic) = self.quadratics.pop() {
+            return Some(PathEvent::QuadraticTo(quadratic.ctrl, quadratic.to))
+        }
+
+        match self.inner.next() {
+            None => None,
+            Some(PathEvent::Arc(center, radii, sweep_angle, x_rotation)) => {
+                let arc = CenterArc::from_path_event(&self.last_point,
+                                                     &center,
+                                                     &radii,
+                                                     sweep_angle,
+                                                     x_rotation);
+                let quadratics = &mut self.quadratics;
+                arc.for_each_quadratic(self.tolerance, |quadratic| quadratics.push(*quadratic));
+                quadratics.reverse();
+                self.last_point = arc.to();
+                self.next()
+            }
+            Some(PathEvent::MoveTo(to)) => {
+                self.first_point = to;
+                self.last_point = to;
+                Some(PathEvent::MoveTo(to))
+            }
+            Some(PathEvent::LineTo(to)) => {
+                self.last_point = to;
+                Some(PathEvent::LineTo(to))
+            }
+            Some(PathEvent::QuadraticTo(ctrl, to)) => {
+                self.last_point = to;
+                Some(PathEvent::QuadraticTo(ctrl, to))
+            }
+            Some(PathEvent::CubicTo(ctrl1, ctrl2, to)) => {
+                self.last_point = to;
+                Some(PathEvent::CubicTo(ctrl1, ctrl2, to))
+            }
+            Some(PathEvent::Close) => {
+                self.last_point = self.first_point;
+                Some(PathEvent::Close)
+            }
+        }
+    }
+}
+
+fn rotate(vector: &Vector2D<f32>, angle: f32) -> Vector2D<f32> {
+    let (sin, cos) = angle.sin_cos();
+    Vector2D::new(vector.x * cos - vector.y * sin, vector.x * sin + vector.y * cos)
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::{Angle, Point2D, Vector2D};
+    use lyon_path::PathEvent;
+    use std::f32::consts::PI;
+
+    use super::{ArcConversion, ArcTo, ArcToQuadraticTransformer, CenterArc};
+
+    fn assert_close(actual: Point2D<f32>, expected: Point2D<f32>) {
+        assert!((actual - expected).length() < 1e-3, "{:?} != {:?}", actual, expected)
+    }
+
+    fn center_arc(from: &Point2D<f32>, arc: &ArcTo) -> CenterArc {
+        match arc.to_center_arc(from) {
+            ArcConversion::Arc(arc) => arc,
+            conversion => panic!("expected an arc, got {:?}", conversion),
+        }
+    }
+
+    #[test]
+    fn converted_arcs_pass_through_both_endpoints() {
+        let from = Point2D::new(1.0, 2.0);
+        for &(large_arc, sweep) in &[(false, false), (false, true), (true, false), (true, true)] {
+            // The last two pairs of radii are too small to span the endpoints, so they're scaled
+            // up, and the arc becomes half of an ellipse either way.
+            for &(radii, x_rotation, scaled) in &[(Vector2D::new(10.0, 5.0), PI / 6.0, false),
+                                                 (Vector2D::new(-4.0, 3.0), 0.0, true),
+                                                 (Vector2D::new(1.0, 2.0), PI / 3.0, true)] {
+                let arc_to = ArcTo {
+                    radii: radii,
+                    x_rotation: Angle::radians(x_rotation),
+                    large_arc: large_arc,
+                    sweep: sweep,
+                    to: Point2D::new(8.0, 9.0),
+                };
+                let arc = center_arc(&from, &arc_to);
+                assert_close(arc.from(), from);
+                assert_close(arc.to(), arc_to.to);
+                assert_eq!(arc.sweep_angle.radians > 0.0, sweep);
+                if !scaled {
+                    assert_eq!(arc.sweep_angle.radians.abs() > PI, large_arc);
+                }
+
+                let mut quadratics = vec![];
+                arc.for_each_quadratic(0.01, |quadratic| quadratics.push(*quadratic));
+                assert_close(quadratics[0].from, from);
+                assert_eq!(quadratics.last().unwrap().to, arc.to());
+                for pair in quadratics.windows(2) {
+                    assert_eq!(pair[0].to, pair[1].from);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn degenerate_arcs_follow_svg() {
+        let from = Point2D::new(1.0, 2.0);
+        let mut arc_to = ArcTo {
+            radii: Vector2D::new(0.0, 5.0),
+            x_rotation: Angle::radians(0.0),
+            large_arc: false,
+            sweep: true,
+            to: Point2D::new(8.0, 9.0),
+        };
+        assert_eq!(arc_to.to_center_arc(&from), ArcConversion::Line(arc_to.to));
+        arc_to.radii = Vector2D::new(5.0, 5.0);
+        arc_to.to = from;
+        assert_eq!(arc_to.to_center_arc(&from), ArcConversion::Empty);
+    }
+
+    #[test]
+    fn transformed_arc_events_end_at_the_arc_endpoint() {
+        let center = Point2D::new(5.0, 0.0);
+        let radii = Vector2D::new(5.0, 2.0);
+        let x_rotation = Angle::radians(PI / 4.0);
+        let from = center + Vector2D::new(5.0, 5.0) * (0.5f32).sqrt();
+        let events = vec![
+            PathEvent::MoveTo(from),
+            PathEvent::Arc(center, radii, Angle::radians(PI * 1.5), x_rotation),
+        ];
+        let arc = CenterArc::from_path_event(&from,
+                                             &center,
+                                             &radii,
+                                             Angle::radians(PI * 1.5),
+                                             x_rotation);
+        assert_close(arc.from(), from);
+
+        let events: Vec<_> = ArcToQuadraticTransformer::new(events.into_iter(), 0.01).collect();
+        assert_eq!(events[0], PathEvent::MoveTo(from));
+        match *events.last().unwrap() {
+            PathEvent::QuadraticTo(_, to) => assert_eq!(to, arc.to()),
+            ref event => panic!("expected a quadratic curve, got {:?}", event),
+        }
+    }
+
+    #[test]
+    fn oversized_sweeps_are_bounded() {
+        let mut arc = CenterArc {
+            center: Point2D::new(0.0, 0.0),
+            radii: Vector2D::new(1.0e6, 1.0),
+            start_angle: Angle::radians(0.0),
+            sweep_angle: Angle::radians(1.0e30),
+            x_rotation: Angle::radians(0.0),
+        };
+        // A full turn ends where it starts.
+        assert_close(arc.to(), arc.from());
+        let mut quadratic_count = 0;
+        arc.for_each_quadratic(1.0e-6, |_| quadratic_count += 1);
+        assert!(quadratic_count <= 1024, "{} quadratics", quadratic_count);
+
+        // Three quarter turns start at 3 segments, which doubles past the limit.
+        arc.sweep_angle = Angle::radians(PI * 1.5);
+        quadratic_count = 0;
+        arc.for_each_quadratic(1.0e-6, |_| quadratic_count += 1);
+        assert_eq!(quadratic_count, 1024);
+
+        for &sweep_angle in &[::std::f32::INFINITY, ::std::f32::NAN] {
+            arc.sweep_angle = Angle::radians(sweep_angle);
+            assert_eq!(arc.to(), arc.from());
+            arc.for_each_quadratic(0.01, |quadratic| panic!("unexpected {:?}", quadratic));
+        }
+    }
+}
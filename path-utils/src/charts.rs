@@ -0,0 +1,237 @@
+// pathfinder/path-utils/src/charts.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Builds the paths that charts are made of: axes, tick marks, data series, and area fills.
+//!
+//! Axes and ticks are produced as filled rectangles in device pixels, with their edges snapped
+//! to pixel boundaries so that thin rules stay crisp. Series paths are open and can be stroked
+//! with `stroke::StrokeToFillIter`; area fills are closed and can be partitioned directly.
+
+use euclid::Point2D;
+use lyon_path::PathEvent;
+
+/// The direction an axis runs in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AxisOrientation {
+    Horizontal,
+    Vertical,
+}
+
+/// How a data series connects its points.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SeriesInterpolation {
+    /// Straight lines between points.
+    Linear,
+    /// Each value holds until the next point, then jumps vertically.
+    StepAfter,
+    /// Each value jumps vertically at the previous point and then holds.
+    StepBefore,
+    /// Monotone cubic curves, which pass through every point without overshooting in Y.
+    Smooth,
+}
+
+/// Moves a line's center so that, if it is `line_width` device pixels wide, its edges land on
+/// pixel boundaries.
+#[inline]
+pub fn snap_line_center(center: f32, line_width: f32) -> f32 {
+    let half_width = snapped_width(line_width) * 0.5;
+    (center - half_width).round() + half_width
+}
+
+/// Returns a filled, pixel-snapped rectangle for an axis line.
+///
+/// `position` is the cross-axis coordinate of the line: the Y coordinate of a horizontal axis
+/// or the X coordinate of a vertical one. The line runs from `start` to `end` along the axis.
+pub fn axis_line(orientation: AxisOrientation,
+                 position: f32,
+                 start: f32,
+                 end: f32,
+                 line_width: f32)
+                 -> Vec<PathEvent> {
+    let mut events = vec![];
+    push_rule(&mut events, orientation, position, start.round(), end.round(), line_width);
+    events
+}
+
+/// Returns filled, pixel-snapped rectangles for tick marks crossing an axis.
+///
+/// `axis_position` is as in `axis_line()`. Each tick is centered at one of `tick_positions`
+/// along the axis and extends `length` pixels across it, toward increasing coordinates if
+/// `length` is positive.
+pub fn tick_marks(orientation: AxisOrientation,
+                  axis_position: f32,
+                  tick_positions: &[f32],
+                  length: f32,
+                  line_width: f32)
+                  -> Vec<PathEvent> {
+    let tick_orientation = match orientation {
+        AxisOrientation::Horizontal => AxisOrientation::Vertical,
+        AxisOrientation::Vertical => AxisOrientation::Horizontal,
+    };
+    let (start, end) = (axis_position.round(), (axis_position + length).round());
+
+    let mut events = vec![];
+    for &tick_position in tick_positions {
+        push_rule(&mut events, tick_orientation, tick_position, start, end, line_width);
+    }
+    events
+}
+
+/// Returns an open path through the points of a data series.
+///
+/// The points should be sorted by X. Returns no events if there are no points.
+pub fn series_path(points: &[Point2D<f32>], interpolation: SeriesInterpolation)
+                   -> Vec<PathEvent> {
+    let mut events = vec![];
+    if let Some(&first_point) = points.first() {
+        events.push(PathEvent::MoveTo(first_point));
+        push_series(&mut events, points, interpolation);
+    }
+    events
+}
+
+/// Returns a closed path that fills the area between a data series and the horizontal line
+/// `y = baseline`.
+///
+/// The points should be sorted by X. Returns no events if there are fewer than two points.
+pub fn area_fill(points: &[Point2D<f32>], interpolation: SeriesInterpolation, baseline: f32)
+                 -> Vec<PathEvent> {
+    if points.len() < 2 {
+        return vec![]
+    }
+
+    let (first_point, last_point) = (points[0], points[points.len() - 1]);
+    let mut events = vec![PathEvent::MoveTo(Point2D::new(first_point.x, baseline)),
+                          PathEvent::LineTo(first_point)];
+    push_series(&mut events, points, interpolation);
+    events.push(PathEvent::LineTo(Point2D::new(last_point.x, baseline)));
+    events.push(PathEvent::Close);
+    events
+}
+
+// Appends the segments of the series after its first point.
+fn push_series(events: &mut Vec<PathEvent>,
+               points: &[Point2D<f32>],
+               interpolation: SeriesInterpolation) {
+    match interpolation {
+        SeriesInterpolation::Linear => {
+            events.extend(points[1..].iter().map(|&point| PathEvent::LineTo(point)))
+        }
+        SeriesInterpolation::StepAfter => {
+            for window in points.windows(2) {
+                events.push(PathEvent::LineTo(Point2D::new(window[1].x, window[0].y)));
+                events.push(PathEvent::LineTo(window[1]));
+            }
+        }
+        SeriesInterpolation::StepBefore => {
+            for window in points.windows(2) {
+                events.push(PathEvent::LineTo(Point2D::new(window[0].x, window[1].y)));
+                events.push(PathEvent::LineTo(window[1]));
+            }
+        }
+        SeriesInterpolation::Smooth => {
+            let tangents = monotone_tangents(points);
+            for (index, window) in points.windows(2).enumerate() {
+                let (from, to) = (window[0], window[1]);
+                let third = (to.x - from.x) / 3.0;
+                if third <= 0.0 {
+                    events.push(PathEvent::LineTo(to));
+                    continue
+                }
+                let ctrl1 = Point2D::new(from.x + third, from.y + tangents[index] * third);
+                let ctrl2 = Point2D::new(to.x - third, to.y - tangents[index + 1] * third);
+                events.push(PathEvent::CubicTo(ctrl1, ctrl2, to));
+            }
+        }
+    }
+}
+
+// Computes the slope at each point for Fritsch–Carlson monotone cubic interpolation.
+fn monotone_tangents(points: &[Point2D<f32>]) -> Vec<f32> {
+    let secants: Vec<f32> = points.windows(2).map(|window| {
+        let dx = window[1].x - window[0].x;
+        if dx > 0.0 {
+            (window[1].y - window[0].y) / dx
+        } else {
+            0.0
+        }
+    }).collect();
+
+    let mut tangents = vec![0.0; points.len()];
+    if secants.is_empty() {
+        return tangents
+    }
+
+    tangents[0] = secants[0];
+    tangents[points.len() - 1] = secants[secants.len() - 1];
+    for index in 1..(points.len() - 1) {
+        let (before, after) = (secants[index - 1], secants[index]);
+        if before * after > 0.0 {
+            tangents[index] = (before + after) * 0.5
+        }
+    }
+
+    // Limit the tangents so that no segment overshoots its endpoints.
+    for (index, &secant) in secants.iter().enumerate() {
+        if secant == 0.0 {
+            tangents[index] = 0.0;
+            tangents[index + 1] = 0.0;
+            continue
+        }
+        let (alpha, beta) = (tangents[index] / secant, tangents[index + 1] / secant);
+        let magnitude = alpha * alpha + beta * beta;
+        if magnitude > 9.0 {
+            let scale = 3.0 / magnitude.sqrt();
+            tangents[index] = scale * alpha * secant;
+            tangents[index + 1] = scale * beta * secant;
+        }
+    }
+
+    tangents
+}
+
+// Appends a snapped rectangle `line_width` wide, centered on `position` across the rule and
+// running from `start` to `end` along it.
+fn push_rule(events: &mut Vec<PathEvent>,
+             orientation: AxisOrientation,
+             position: f32,
+             start: f32,
+             end: f32,
+             line_width: f32) {
+    let width = snapped_width(line_width);
+    let near_edge = snap_line_center(position, width) - width * 0.5;
+    let (start, end) = (f32::min(start, end), f32::max(start, end));
+
+    let corners = match orientation {
+        AxisOrientation::Horizontal => {
+            [Point2D::new(start, near_edge),
+             Point2D::new(end, near_edge),
+             Point2D::new(end, near_edge + width),
+             Point2D::new(start, near_edge + width)]
+        }
+        AxisOrientation::Vertical => {
+            [Point2D::new(near_edge, start),
+             Point2D::new(near_edge + width, start),
+             Point2D::new(near_edge + width, end),
+             Point2D::new(near_edge, end)]
+        }
+    };
+
+    // Both orientations wind the same way, so overlapping rules don't cancel out.
+    events.push(PathEvent::MoveTo(corners[0]));
+    events.extend(corners[1..].iter().map(|&corner| PathEvent::LineTo(corner)));
+    events.push(PathEvent::Close);
+}
+
+// Rounds the width to whole pixels, keeping hairlines at least one pixel wide.
+#[inline]
+fn snapped_width(line_width: f32) -> f32 {
+    line_width.round().max(1.0)
+}
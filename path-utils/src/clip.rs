@@ -0,0 +1,296 @@
+// pathfinder/path-utils/src/clip.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Clips filled paths to rectangles.
+//!
+//! This is the Sutherland-Hodgman algorithm, generalized to quadratic Bézier curves: each subpath
+//! is clipped against each edge of the rectangle in turn, and the parts that leave the rectangle
+//! are replaced with straight lines along its edge. Clipping each subpath separately preserves
+//! winding numbers inside the rectangle, so the result fills the same area under either fill
+//! rule. Open subpaths are treated as closed, as they are when filling.
+
+use euclid::{Point2D, Rect};
+use lyon_geom::QuadraticBezierSegment;
+use lyon_path::PathEvent;
+use std::cmp::Ordering;
+
+use arcs::ArcToQuadraticTransformer;
+use cubic_to_quadratic::CubicToQuadraticTransformer;
+
+/// Clips the filled path described by `events` to `rect`.
+///
+/// Cubic curves and arcs are first approximated with quadratic curves to within `tolerance`.
+/// Subpaths that lie entirely outside the rectangle are dropped.
+pub fn clip_path_to_rect<I>(events: I, rect: &Rect<f32>, tolerance: f32) -> Vec<PathEvent>
+                            where I: Iterator<Item = PathEvent> {
+    let events = CubicToQuadraticTransformer::new(ArcToQuadraticTransformer::new(events,
+                                                                                 tolerance),
+                                                  tolerance);
+
+    let edges = [
+        ClipEdge { axis: Axis::X, value: rect.min_x(), keep_greater: true },
+        ClipEdge { axis: Axis::X, value: rect.max_x(), keep_greater: false },
+        ClipEdge { axis: Axis::Y, value: rect.min_y(), keep_greater: true },
+        ClipEdge { axis: Axis::Y, value: rect.max_y(), keep_greater: false },
+    ];
+
+    let mut output = vec![];
+    for mut subpath in collect_subpaths(events) {
+        for edge in &edges {
+            subpath = edge.clip(&subpath);
+        }
+        push_subpath(&mut output, &subpath);
+    }
+    output
+}
+
+#[derive(Clone, Copy, Debug)]
+enum ClipSegment {
+    Line(Point2D<f32>, Point2D<f32>),
+    Quadratic(QuadraticBezierSegment<f32>),
+}
+
+impl ClipSegment {
+    fn from(&self) -> Point2D<f32> {
+        match *self {
+            ClipSegment::Line(from, _) => from,
+            ClipSegment::Quadratic(ref quadratic) => quadratic.from,
+        }
+    }
+
+    fn to(&self) -> Point2D<f32> {
+        match *self {
+            ClipSegment::Line(_, to) => to,
+            ClipSegment::Quadratic(ref quadratic) => quadratic.to,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Axis {
+    X,
+    Y,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ClipEdge {
+    axis: Axis,
+    value: f32,
+    keep_greater: bool,
+}
+
+impl ClipEdge {
+    // Returns a value that is nonnegative if the point is inside.
+    fn signed_distance(&self, point: &Point2D<f32>) -> f32 {
+        let coordinate = match self.axis {
+            Axis::X => point.x,
+            Axis::Y => point.y,
+        };
+        if self.keep_greater {
+            coordinate - self.value
+        } else {
+            self.value - coordinate
+        }
+    }
+
+    // Moves a point that was computed to lie on the edge exactly onto it, so that floating point
+    // error doesn't produce slivers.
+    fn snap(&self, point: &Point2D<f32>) -> Point2D<f32> {
+        match self.axis {
+            Axis::X => Point2D::new(self.value, point.y),
+            Axis::Y => Point2D::new(point.x, self.value),
+        }
+    }
+
+    fn clip(&self, subpath: &[ClipSegment]) -> Vec<ClipSegment> {
+        let mut pieces = vec![];
+        for segment in subpath {
+            self.split(segment, &mut pieces)
+        }
+
+        let mut output: Vec<ClipSegment> = vec![];
+        for piece in pieces {
+            if !self.piece_is_inside(&piece) {
+                continue
+            }
+
+            // If we skipped over an outside part, walk along the edge to get back here.
+            if let Some(last_point) = output.last().map(|segment| segment.to()) {
+                if last_point != piece.from() {
+                    output.push(ClipSegment::Line(last_point, piece.from()))
+                }
+            }
+            output.push(piece)
+        }
+
+        let endpoints = (output.first().map(|segment| segment.from()),
+                         output.last().map(|segment| segment.to()));
+        if let (Some(first_point), Some(last_point)) = endpoints {
+            if first_point != last_point {
+                output.push(ClipSegment::Line(last_point, first_point))
+            }
+        }
+        output
+    }
+
+    fn piece_is_inside(&self, piece: &ClipSegment) -> bool {
+        let midpoint = match *piece {
+            ClipSegment::Line(from, to) => from.lerp(to, 0.5),
+            ClipSegment::Quadratic(ref quadratic) => quadratic.sample(0.5),
+        };
+        self.signed_distance(&midpoint) >= 0.0
+    }
+
+    // Splits the segment wherever it crosses the edge.
+    fn split(&self, segment: &ClipSegment, pieces: &mut Vec<ClipSegment>) {
+        match *segment {
+            ClipSegment::Line(from, to) => {
+                let (d0, d1) = (self.signed_distance(&from), self.signed_distance(&to));
+                if (d0 < 0.0 && d1 > 0.0) || (d0 > 0.0 && d1 < 0.0) {
+                    let crossing = self.snap(&from.lerp(to, d0 / (d0 - d1)));
+                    pieces.push(ClipSegment::Line(from, crossing));
+                    pieces.push(ClipSegment::Line(crossing, to));
+                } else {
+                    pieces.push(*segment)
+                }
+            }
+            ClipSegment::Quadratic(ref quadratic) => {
+                // The signed distance along the curve is itself a quadratic in Bernstein form.
+                let d0 = self.signed_distance(&quadratic.from);
+                let dc = self.signed_distance(&quadratic.ctrl);
+                let d1 = self.signed_distance(&quadratic.to);
+                let (a, b, c) = (d0 - 2.0 * dc + d1, 2.0 * (dc - d0), d0);
+
+                let mut roots = [0.0; 2];
+                let mut root_count = 0;
+                if a.abs() < 1e-6 {
+                    if b != 0.0 {
+                        roots[0] = -c / b;
+                        root_count = 1;
+                    }
+                } else {
+                    let discriminant = b * b - 4.0 * a * c;
+                    if discriminant >= 0.0 {
+                        let sqrt_discriminant = discriminant.sqrt();
+                        roots[0] = (-b - sqrt_discriminant) / (2.0 * a);
+                        roots[1] = (-b + sqrt_discriminant) / (2.0 * a);
+                        root_count = 2;
+                    }
+                }
+
+                let mut split_points: Vec<f32> =
+                    roots[0..root_count].iter().cloned().filter(|&t| t > 0.0 && t < 1.0).collect();
+                split_points.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+                split_points.dedup();
+
+                let (mut rest, mut consumed_t) = (*quadratic, 0.0);
+                for t in split_points {
+                    let (mut head, mut tail) = rest.split((t - consumed_t) / (1.0 - consumed_t));
+                    head.to = self.snap(&head.to);
+                    tail.from = head.to;
+                    pieces.push(ClipSegment::Quadratic(head));
+                    rest = tail;
+                    consumed_t = t;
+                }
+                pieces.push(ClipSegment::Quadratic(rest));
+            }
+        }
+    }
+}
+
+// Gathers the path into closed subpaths of lines and quadratic curves.
+fn collect_subpaths<I>(events: I) -> Vec<Vec<ClipSegment>> where I: Iterator<Item = PathEvent> {
+    let mut subpaths = vec![];
+    let mut subpath = vec![];
+    let (mut first_point, mut last_point) = (Point2D::zero(), Point2D::zero());
+    for event in events {
+        match event {
+            PathEvent::MoveTo(to) => {
+                finish_subpath(&mut subpaths, &mut subpath, &last_point, &first_point);
+                first_point = to;
+                last_point = to;
+            }
+            PathEvent::LineTo(to) => {
+                subpath.push(ClipSegment::Line(last_point, to));
+                last_point = to;
+            }
+            PathEvent::QuadraticTo(ctrl, to) => {
+                subpath.push(ClipSegment::Quadratic(QuadraticBezierSegment {
+                    from: last_point,
+                    ctrl: ctrl,
+                    to: to,
+                }));
+                last_point = to;
+            }
+            PathEvent::Close => {
+                finish_subpath(&mut subpaths, &mut subpath, &last_point, &first_point);
+                last_point = first_point;
+            }
+            PathEvent::CubicTo(..) | PathEvent::Arc(..) => {
+                unreachable!("Cubics and arcs should have been converted to quadratics!")
+            }
+        }
+    }
+    finish_subpath(&mut subpaths, &mut subpath, &last_point, &first_point);
+    return subpaths;
+
+    fn finish_subpath(subpaths: &mut Vec<Vec<ClipSegment>>,
+                      subpath: &mut Vec<ClipSegment>,
+                      last_point: &Point2D<f32>,
+                      first_point: &Point2D<f32>) {
+        if subpath.is_empty() {
+            return
+        }
+        if last_point != first_point {
+            subpath.push(ClipSegment::Line(*last_point, *first_point))
+        }
+        subpaths.push(subpath.drain(..).collect())
+    }
+}
+
+fn push_subpath(output: &mut Vec<PathEvent>, subpath: &[ClipSegment]) {
+    let first_point = match subpath.first() {
+        None => return,
+        Some(segment) => segment.from(),
+    };
+    output.push(PathEvent::MoveTo(first_point));
+    for segment in subpath {
+        match *segment {
+            ClipSegment::Line(_, to) => output.push(PathEvent::LineTo(to)),
+            ClipSegment::Quadratic(ref quadratic) => {
+                output.push(PathEvent::QuadraticTo(quadratic.ctrl, quadratic.to))
+            }
+        }
+    }
+    output.push(PathEvent::Close)
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::{Point2D, Rect, Size2D};
+    use lyon_path::PathEvent;
+    use std::f32;
+
+    use super::clip_path_to_rect;
+
+    #[test]
+    fn non_finite_coordinates_do_not_panic() {
+        let rect = Rect::new(Point2D::new(0.0, 0.0), Size2D::new(10.0, 10.0));
+        for &bad in &[f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            let events = vec![
+                PathEvent::MoveTo(Point2D::new(-5.0, 5.0)),
+                PathEvent::QuadraticTo(Point2D::new(bad, 20.0), Point2D::new(15.0, 5.0)),
+                PathEvent::QuadraticTo(Point2D::new(5.0, bad), Point2D::new(-5.0, 5.0)),
+                PathEvent::Close,
+            ];
+            drop(clip_path_to_rect(events.into_iter(), &rect, 0.1));
+        }
+    }
+}
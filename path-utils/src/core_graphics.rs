@@ -0,0 +1,115 @@
+// pathfinder/path-utils/src/core_graphics.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Conversion between path events and the elements of a Core Graphics `CGPath`.
+//!
+//! Elements mirror the `CGPathElement`s that `CGPathApply()` reports, with their points copied
+//! out, so this module doesn't depend on the Core Graphics bindings. Core Graphics uses `f64`
+//! coordinates and has no elliptical arc element, so arcs are exported as quadratic curves.
+
+use euclid::Point2D;
+use lyon_path::PathEvent;
+
+use arcs::ArcToQuadraticTransformer;
+
+/// The type of a path element, with the same values as `CGPathElementType`.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum CGPathElementType {
+    MoveToPoint = 0,
+    AddLineToPoint = 1,
+    AddQuadCurveToPoint = 2,
+    AddCurveToPoint = 3,
+    CloseSubpath = 4,
+}
+
+/// One element of a `CGPath`.
+///
+/// The points are in the order Core Graphics stores them: control points first, endpoint last.
+/// Entries past the number the element type uses are zero.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct CGPathElement {
+    pub element_type: CGPathElementType,
+    pub points: [Point2D<f64>; 3],
+}
+
+impl CGPathElement {
+    #[inline]
+    pub fn new(element_type: CGPathElementType, points: &[Point2D<f64>]) -> CGPathElement {
+        let mut element = CGPathElement {
+            element_type: element_type,
+            points: [Point2D::zero(); 3],
+        };
+        element.points[0..points.len()].copy_from_slice(points);
+        element
+    }
+
+    /// The number of points this element uses.
+    #[inline]
+    pub fn point_count(&self) -> usize {
+        match self.element_type {
+            CGPathElementType::MoveToPoint | CGPathElementType::AddLineToPoint => 1,
+            CGPathElementType::AddQuadCurveToPoint => 2,
+            CGPathElementType::AddCurveToPoint => 3,
+            CGPathElementType::CloseSubpath => 0,
+        }
+    }
+
+    /// Converts this element to a path event. This is exact, apart from rounding to `f32`.
+    pub fn to_path_event(&self) -> PathEvent {
+        let point = |index: usize| {
+            Point2D::new(self.points[index].x as f32, self.points[index].y as f32)
+        };
+        match self.element_type {
+            CGPathElementType::MoveToPoint => PathEvent::MoveTo(point(0)),
+            CGPathElementType::AddLineToPoint => PathEvent::LineTo(point(0)),
+            CGPathElementType::AddQuadCurveToPoint => PathEvent::QuadraticTo(point(0), point(1)),
+            CGPathElementType::AddCurveToPoint => {
+                PathEvent::CubicTo(point(0), point(1), point(2))
+            }
+            CGPathElementType::CloseSubpath => PathEvent::Close,
+        }
+    }
+}
+
+/// Converts path events to Core Graphics path elements, approximating arcs with quadratic curves
+/// to within `tolerance`.
+pub fn path_events_to_elements<I>(events: I, tolerance: f32) -> Vec<CGPathElement>
+                                  where I: Iterator<Item = PathEvent> {
+    let point = |point: &Point2D<f32>| Point2D::new(point.x as f64, point.y as f64);
+    ArcToQuadraticTransformer::new(events, tolerance).map(|event| {
+        match event {
+            PathEvent::MoveTo(to) => {
+                CGPathElement::new(CGPathElementType::MoveToPoint, &[point(&to)])
+            }
+            PathEvent::LineTo(to) => {
+                CGPathElement::new(CGPathElementType::AddLineToPoint, &[point(&to)])
+            }
+            PathEvent::QuadraticTo(ctrl, to) => {
+                CGPathElement::new(CGPathElementType::AddQuadCurveToPoint,
+                                   &[point(&ctrl), point(&to)])
+            }
+            PathEvent::CubicTo(ctrl0, ctrl1, to) => {
+                CGPathElement::new(CGPathElementType::AddCurveToPoint,
+                                   &[point(&ctrl0), point(&ctrl1), point(&to)])
+            }
+            PathEvent::Close => CGPathElement::new(CGPathElementType::CloseSubpath, &[]),
+            PathEvent::Arc(..) => unreachable!("Arcs should have been converted to quadratics!"),
+        }
+    }).collect()
+}
+
+/// Converts Core Graphics path elements to path events.
+#[inline]
+pub fn elements_to_path_events(elements: &[CGPathElement]) -> Vec<PathEvent> {
+    elements.iter().map(CGPathElement::to_path_event).collect()
+}
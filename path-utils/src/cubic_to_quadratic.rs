@@ -14,6 +14,8 @@ use euclid::Point2D;
 use lyon_geom::{CubicBezierSegment, QuadraticBezierSegment};
 use lyon_path::PathEvent;
 
+use arcs::CenterArc;
+
 const MAX_APPROXIMATION_ITERATIONS: u8 = 32;
 
 /// Approximates a single cubic Bézier curve with a series of quadratic Bézier curves.
@@ -128,9 +130,14 @@ impl<I> Iterator for CubicToQuadraticTransformer<I> where I: Iterator<Item = Pat
                 Some(PathEvent::QuadraticTo(ctrl, to))
             }
             Some(PathEvent::Close) => Some(PathEvent::Close),
-            Some(PathEvent::Arc(to, vector, angle_from, angle_to)) => {
-                self.last_point = to;
-                Some(PathEvent::Arc(to, vector, angle_from, angle_to))
+            Some(PathEvent::Arc(center, radii, sweep_angle, x_rotation)) => {
+                // The arc ends on the ellipse, not at its center.
+                self.last_point = CenterArc::from_path_event(&self.last_point,
+                                                             &center,
+                                                             &radii,
+                                                             sweep_angle,
+                                                             x_rotation).to();
+                Some(PathEvent::Arc(center, radii, sweep_angle, x_rotation))
             }
         }
     }
@@ -0,0 +1,158 @@
+// pathfinder/path-utils/src/curve.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A trait for arbitrary parametric curves, so that custom segment types (clothoids, spirals,
+//! and so on) can be converted to quadratic Bézier curves and partitioned.
+
+use euclid::{Point2D, Rect};
+use lyon_geom::{CubicBezierSegment, LineSegment, QuadraticBezierSegment};
+use lyon_path::PathEvent;
+
+/// The deepest that a curve will be recursively split when approximating it.
+const MAX_SUBDIVISION_DEPTH: u8 = 16;
+
+/// The parameter values at which `Curve::error()` compares curves by default.
+const ERROR_SAMPLE_POINTS: [f32; 5] = [0.125, 0.25, 0.5, 0.75, 0.875];
+
+/// A parametric curve over the parameter range [0, 1].
+pub trait Curve: Sized {
+    /// Returns the point on the curve at parameter `t`.
+    fn sample(&self, t: f32) -> Point2D<f32>;
+
+    /// Splits the curve into the parts before and after parameter `t`.
+    fn split(&self, t: f32) -> (Self, Self);
+
+    /// Returns a rectangle that contains the curve. It need not be tight.
+    fn bounds(&self) -> Rect<f32>;
+
+    /// Estimates the largest distance between this curve and `approximation`, which has the same
+    /// endpoints.
+    ///
+    /// The default implementation compares the two at a few parameter values. Override it if the
+    /// curve has a closed-form bound or is parameterized very unevenly.
+    fn error(&self, approximation: &QuadraticBezierSegment<f32>) -> f32 {
+        ERROR_SAMPLE_POINTS.iter().map(|&t| {
+            (self.sample(t) - approximation.sample(t)).length()
+        }).fold(0.0, f32::max)
+    }
+
+    #[inline]
+    fn from(&self) -> Point2D<f32> {
+        self.sample(0.0)
+    }
+
+    #[inline]
+    fn to(&self) -> Point2D<f32> {
+        self.sample(1.0)
+    }
+
+    /// Approximates this curve with quadratic Bézier curves, each of which deviates from it by no
+    /// more than `tolerance` according to `error()`.
+    ///
+    /// Each quadratic passes through the endpoints and the midpoint of the part of the curve it
+    /// replaces, and the curve is split in half until that is close enough.
+    fn for_each_quadratic<F>(&self, tolerance: f32, mut callback: F)
+                             where F: FnMut(&QuadraticBezierSegment<f32>) {
+        return approximate(self, tolerance, 0, &mut callback);
+
+        fn approximate<C, F>(curve: &C, tolerance: f32, depth: u8, callback: &mut F)
+                             where C: Curve, F: FnMut(&QuadraticBezierSegment<f32>) {
+            let (from, to) = (curve.from(), curve.to());
+            let midpoint = curve.sample(0.5);
+            let quadratic = QuadraticBezierSegment {
+                from: from,
+                ctrl: (midpoint.to_vector() * 2.0 - (from.to_vector() + to.to_vector()) * 0.5)
+                    .to_point(),
+                to: to,
+            };
+
+            if depth >= MAX_SUBDIVISION_DEPTH || curve.error(&quadratic) <= tolerance {
+                return callback(&quadratic)
+            }
+
+            let (first, second) = curve.split(0.5);
+            approximate(&first, tolerance, depth + 1, callback);
+            approximate(&second, tolerance, depth + 1, callback);
+        }
+    }
+
+    /// Approximates this curve with quadratic curves, returned as path events. No `MoveTo` is
+    /// emitted, so the events continue a subpath that is already at `self.from()`.
+    fn to_path_events(&self, tolerance: f32) -> Vec<PathEvent> {
+        let mut events = vec![];
+        self.for_each_quadratic(tolerance, |quadratic| {
+            events.push(PathEvent::QuadraticTo(quadratic.ctrl, quadratic.to))
+        });
+        events
+    }
+}
+
+impl Curve for LineSegment<f32> {
+    #[inline]
+    fn sample(&self, t: f32) -> Point2D<f32> {
+        LineSegment::sample(self, t)
+    }
+
+    #[inline]
+    fn split(&self, t: f32) -> (LineSegment<f32>, LineSegment<f32>) {
+        LineSegment::split(self, t)
+    }
+
+    #[inline]
+    fn bounds(&self) -> Rect<f32> {
+        self.bounding_rect()
+    }
+
+    #[inline]
+    fn error(&self, _: &QuadraticBezierSegment<f32>) -> f32 {
+        // A quadratic through the endpoints and midpoint of a line is that line.
+        0.0
+    }
+}
+
+impl Curve for QuadraticBezierSegment<f32> {
+    #[inline]
+    fn sample(&self, t: f32) -> Point2D<f32> {
+        QuadraticBezierSegment::sample(self, t)
+    }
+
+    #[inline]
+    fn split(&self, t: f32) -> (QuadraticBezierSegment<f32>, QuadraticBezierSegment<f32>) {
+        QuadraticBezierSegment::split(self, t)
+    }
+
+    #[inline]
+    fn bounds(&self) -> Rect<f32> {
+        self.bounding_rect()
+    }
+
+    #[inline]
+    fn error(&self, _: &QuadraticBezierSegment<f32>) -> f32 {
+        // The approximation reproduces the curve exactly.
+        0.0
+    }
+}
+
+impl Curve for CubicBezierSegment<f32> {
+    #[inline]
+    fn sample(&self, t: f32) -> Point2D<f32> {
+        CubicBezierSegment::sample(self, t)
+    }
+
+    #[inline]
+    fn split(&self, t: f32) -> (CubicBezierSegment<f32>, CubicBezierSegment<f32>) {
+        CubicBezierSegment::split(self, t)
+    }
+
+    #[inline]
+    fn bounds(&self) -> Rect<f32> {
+        self.bounding_rect()
+    }
+}
@@ -0,0 +1,232 @@
+// pathfinder/path-utils/src/embolden.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Makes filled paths bolder by moving their edges outward, for synthesizing bold fonts.
+//!
+//! This follows FreeType's `FT_Outline_EmboldenXY`: every point, including control points, moves
+//! along the bisector of the two edges of the control polygon that meet at it, far enough that
+//! both edges move outward by the requested distance. Unlike stroking the outline, this keeps the
+//! number of segments the same, so the result partitions as cheaply as the original.
+//!
+//! Which way is outward is determined from the orientation of the whole path, so its subpaths
+//! must be wound consistently, as they are in fonts.
+
+use euclid::approxeq::ApproxEq;
+use euclid::{Point2D, Vector2D};
+use lyon_path::PathEvent;
+
+use arcs::ArcToQuadraticTransformer;
+use orientation::Orientation;
+
+/// Points whose edges double back on each other more sharply than this cosine aren't moved,
+/// since the bisector is nearly parallel to the edges there.
+const MIN_COSINE: f32 = -0.9375;
+
+/// Returns the path described by `events` with its edges moved outward by `distance`. Negative
+/// distances make the path thinner.
+///
+/// Arcs are first approximated with quadratic curves to within `tolerance`. Open subpaths are
+/// treated as closed, as they are when filling.
+pub fn embolden_path<I>(events: I, distance: f32, tolerance: f32) -> Vec<PathEvent>
+                        where I: Iterator<Item = PathEvent> {
+    let events: Vec<_> = ArcToQuadraticTransformer::new(events, tolerance).collect();
+    let orientation = Orientation::from_path(events.iter().cloned());
+
+    let mut output = vec![];
+    let mut contour = Contour::new();
+    for event in events {
+        match event {
+            PathEvent::MoveTo(to) => {
+                contour.push_emboldened(&mut output, orientation, distance);
+                contour.points.push(to);
+            }
+            PathEvent::LineTo(to) => contour.push_segment(&[to]),
+            PathEvent::QuadraticTo(ctrl, to) => contour.push_segment(&[ctrl, to]),
+            PathEvent::CubicTo(ctrl1, ctrl2, to) => contour.push_segment(&[ctrl1, ctrl2, to]),
+            PathEvent::Close => contour.push_emboldened(&mut output, orientation, distance),
+            PathEvent::Arc(..) => {
+                unreachable!("Arcs should have been converted to quadratics!")
+            }
+        }
+    }
+    contour.push_emboldened(&mut output, orientation, distance);
+    output
+}
+
+// A closed contour, as the cyclic sequence of points of its control polygon.
+struct Contour {
+    points: Vec<Point2D<f32>>,
+    // The number of points that each segment after the first point consumes.
+    segment_point_counts: Vec<usize>,
+}
+
+impl Contour {
+    fn new() -> Contour {
+        Contour {
+            points: vec![],
+            segment_point_counts: vec![],
+        }
+    }
+
+    fn push_segment(&mut self, points: &[Point2D<f32>]) {
+        if self.points.is_empty() {
+            self.points.push(Point2D::zero())
+        }
+        self.points.extend_from_slice(points);
+        self.segment_point_counts.push(points.len());
+    }
+
+    // Emboldens this contour, appends it to `output`, and clears it.
+    fn push_emboldened(&mut self,
+                       output: &mut Vec<PathEvent>,
+                       orientation: Orientation,
+                       distance: f32) {
+        if self.segment_point_counts.is_empty() {
+            self.points.clear();
+            return
+        }
+
+        // If the contour returns to its start, the last point is the first one.
+        if self.points.len() > 1 && self.points.last() == self.points.first() {
+            self.points.pop();
+        }
+
+        let point_count = self.points.len();
+        let moved_points: Vec<_> = (0..point_count).map(|point_index| {
+            let point = self.points[point_index];
+            let prev = (1..point_count).map(|offset| {
+                self.points[(point_index + point_count - offset) % point_count]
+            }).find(|prev| !points_overlap(prev, &point));
+            let next = (1..point_count).map(|offset| {
+                self.points[(point_index + offset) % point_count]
+            }).find(|next| !points_overlap(next, &point));
+            match (prev, next) {
+                (Some(prev), Some(next)) => {
+                    point + shift(&(point - prev), &(next - point), orientation, distance)
+                }
+                _ => point,
+            }
+        }).collect();
+
+        output.push(PathEvent::MoveTo(moved_points[0]));
+        let mut point_index = 1;
+        for &segment_point_count in &self.segment_point_counts {
+            let segment_start = point_index;
+            let point = |offset: usize| moved_points[(segment_start + offset) % point_count];
+            output.push(match segment_point_count {
+                1 => PathEvent::LineTo(point(0)),
+                2 => PathEvent::QuadraticTo(point(0), point(1)),
+                _ => PathEvent::CubicTo(point(0), point(1), point(2)),
+            });
+            point_index += segment_point_count;
+        }
+        output.push(PathEvent::Close);
+
+        self.points.clear();
+        self.segment_point_counts.clear();
+    }
+}
+
+// Returns how far to move a point whose incoming and outgoing edges are `vector_in` and
+// `vector_out`.
+fn shift(vector_in: &Vector2D<f32>,
+         vector_out: &Vector2D<f32>,
+         orientation: Orientation,
+         distance: f32)
+         -> Vector2D<f32> {
+    let (tangent_in, tangent_out) = (vector_in.normalize(), vector_out.normalize());
+    let cosine = tangent_in.dot(tangent_out);
+    if cosine <= MIN_COSINE {
+        return Vector2D::zero()
+    }
+
+    // Turn each tangent a quarter turn away from the inside of the path.
+    let outward_normal = |tangent: Vector2D<f32>| {
+        match orientation {
+            Orientation::Cw => Vector2D::new(tangent.y, -tangent.x),
+            Orientation::Ccw => Vector2D::new(-tangent.y, tangent.x),
+        }
+    };
+    let bisector = outward_normal(tangent_in) + outward_normal(tangent_out);
+
+    // Moving along the bisector by `distance / d` moves both edges by `distance`. On short edges
+    // at sharp corners, that would move the point past its neighbors, so limit the shift to the
+    // length of the shorter edge instead.
+    let d = 1.0 + cosine;
+    let sine = tangent_in.cross(tangent_out).abs();
+    let shortest_length = vector_in.length().min(vector_out.length());
+    if distance.abs() * sine <= shortest_length * d {
+        bisector * (distance / d)
+    } else {
+        bisector * (shortest_length / sine * distance.signum())
+    }
+}
+
+fn points_overlap(a: &Point2D<f32>, b: &Point2D<f32>) -> bool {
+    a.x.approx_eq(&b.x) && a.y.approx_eq(&b.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::Point2D;
+    use lyon_path::PathEvent;
+
+    use super::embolden_path;
+
+    fn square(points: &[(f32, f32)]) -> Vec<PathEvent> {
+        let mut events = vec![PathEvent::MoveTo(Point2D::new(points[0].0, points[0].1))];
+        events.extend(points[1..].iter().map(|&(x, y)| PathEvent::LineTo(Point2D::new(x, y))));
+        events.push(PathEvent::Close);
+        events
+    }
+
+    fn assert_events_close(actual: &[PathEvent], expected: &[PathEvent]) {
+        assert_eq!(actual.len(), expected.len(), "{:?} != {:?}", actual, expected);
+        for (actual_event, expected_event) in actual.iter().zip(expected) {
+            let close = match (*actual_event, *expected_event) {
+                (PathEvent::MoveTo(a), PathEvent::MoveTo(b)) |
+                (PathEvent::LineTo(a), PathEvent::LineTo(b)) => (a - b).length() < 1e-4,
+                (PathEvent::Close, PathEvent::Close) => true,
+                _ => false,
+            };
+            assert!(close, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn squares_grow_outward_whichever_way_they_wind() {
+        let corners = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+        let bold_corners = [(-1.0, -1.0), (11.0, -1.0), (11.0, 11.0), (-1.0, 11.0)];
+        assert_events_close(&embolden_path(square(&corners).into_iter(), 1.0, 0.1),
+                            &square(&bold_corners));
+
+        let reversed: Vec<_> = corners.iter().rev().cloned().collect();
+        let bold_reversed: Vec<_> = bold_corners.iter().rev().cloned().collect();
+        assert_events_close(&embolden_path(square(&reversed).into_iter(), 1.0, 0.1),
+                            &square(&bold_reversed));
+    }
+
+    #[test]
+    fn negative_distances_thin_the_path() {
+        let corners = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+        let thin_corners = [(1.0, 1.0), (9.0, 1.0), (9.0, 9.0), (1.0, 9.0)];
+        assert_events_close(&embolden_path(square(&corners).into_iter(), -1.0, 0.1),
+                            &square(&thin_corners));
+    }
+
+    #[test]
+    fn open_subpaths_are_emboldened_as_if_closed() {
+        let corners = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+        let mut open = square(&corners);
+        open.pop();
+        let closed = embolden_path(square(&corners).into_iter(), 1.0, 0.1);
+        assert_events_close(&embolden_path(open.into_iter(), 1.0, 0.1), &closed);
+    }
+}
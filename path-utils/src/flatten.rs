@@ -0,0 +1,155 @@
+// pathfinder/path-utils/src/flatten.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Approximates quadratic Bézier curves with lines to within a tolerance.
+//!
+//! The curve is split at evenly spaced parameter values. The second derivative of a quadratic is
+//! the constant `2 * (from - 2 * ctrl + to)`, so a line spanning a parameter interval of `h`
+//! strays at most `h² * |from - 2 * ctrl + to| / 4` from the curve, and `n` lines with
+//! `n = ceil(sqrt(|from - 2 * ctrl + to| / (4 * tolerance)))` are enough.
+
+use euclid::Point2D;
+use lyon_geom::QuadraticBezierSegment;
+
+/// The most lines that one curve is flattened into, so that a tiny, zero, or NaN tolerance can't
+/// ask for an unbounded number.
+pub const MAX_LINES_PER_CURVE: usize = 1024;
+
+/// Returns the number of lines, evenly spaced in parameter, that approximate `segment` to within
+/// `tolerance`. The result is at least 1 and at most `MAX_LINES_PER_CURVE`.
+pub fn quadratic_line_count(segment: &QuadraticBezierSegment<f32>, tolerance: f32) -> usize {
+    let dd = segment.from.to_vector() - segment.ctrl.to_vector() * 2.0 + segment.to.to_vector();
+    let line_count = (dd.length() / (4.0 * tolerance)).sqrt().ceil();
+    if line_count >= 1.0 {
+        (line_count.min(MAX_LINES_PER_CURVE as f32)) as usize
+    } else if line_count.is_nan() && dd.length() > 0.0 {
+        MAX_LINES_PER_CURVE
+    } else {
+        1
+    }
+}
+
+/// Returns the endpoint of each line in the approximation of a quadratic curve, in order. The
+/// start of the curve is omitted, and the last point is exactly its end.
+pub struct FlattenedQuadratic {
+    segment: QuadraticBezierSegment<f32>,
+    line_count: usize,
+    line_index: usize,
+}
+
+impl FlattenedQuadratic {
+    #[inline]
+    pub fn new(segment: &QuadraticBezierSegment<f32>, tolerance: f32) -> FlattenedQuadratic {
+        FlattenedQuadratic {
+            segment: *segment,
+            line_count: quadratic_line_count(segment, tolerance),
+            line_index: 0,
+        }
+    }
+}
+
+impl Iterator for FlattenedQuadratic {
+    type Item = Point2D<f32>;
+
+    fn next(&mut self) -> Option<Point2D<f32>> {
+        if self.line_index == self.line_count {
+            return None
+        }
+        self.line_index += 1;
+        if self.line_index == self.line_count {
+            return Some(self.segment.to)
+        }
+        Some(self.segment.sample(self.line_index as f32 / self.line_count as f32))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.line_count - self.line_index;
+        (remaining, Some(remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::Point2D;
+    use lyon_geom::QuadraticBezierSegment;
+    use std::f32;
+
+    use super::{FlattenedQuadratic, MAX_LINES_PER_CURVE, quadratic_line_count};
+
+    fn distance_to_line(point: &Point2D<f32>, from: &Point2D<f32>, to: &Point2D<f32>) -> f32 {
+        let line = *to - *from;
+        let length_squared = line.square_length();
+        if length_squared == 0.0 {
+            return (*point - *from).length()
+        }
+        let t = ((*point - *from).dot(line) / length_squared).max(0.0).min(1.0);
+        (*point - (*from + line * t)).length()
+    }
+
+    #[test]
+    fn flattened_curves_are_within_tolerance() {
+        let segments = [
+            QuadraticBezierSegment {
+                from: Point2D::new(0.0, 0.0),
+                ctrl: Point2D::new(50.0, 100.0),
+                to: Point2D::new(100.0, 0.0),
+            },
+            QuadraticBezierSegment {
+                from: Point2D::new(10.0, 10.0),
+                ctrl: Point2D::new(200.0, 10.0),
+                to: Point2D::new(10.0, 11.0),
+            },
+            QuadraticBezierSegment {
+                from: Point2D::new(-3.0, 7.0),
+                ctrl: Point2D::new(-2.5, 7.25),
+                to: Point2D::new(-2.0, 7.0),
+            },
+        ];
+        for segment in &segments {
+            for &tolerance in &[0.01, 0.1, 0.25, 1.0] {
+                let line_count = quadratic_line_count(segment, tolerance);
+                let mut from = segment.from;
+                for (line_index, to) in FlattenedQuadratic::new(segment, tolerance).enumerate() {
+                    // A quadratic is farthest from a chord halfway along it in parameter.
+                    let t = (line_index as f32 + 0.5) / line_count as f32;
+                    let midpoint = segment.sample(t);
+                    assert!(distance_to_line(&midpoint, &from, &to) <= tolerance * 1.001);
+                    from = to;
+                }
+                assert_eq!(from, segment.to);
+            }
+        }
+    }
+
+    #[test]
+    fn line_counts_are_bounded() {
+        let segment = QuadraticBezierSegment {
+            from: Point2D::new(0.0, 0.0),
+            ctrl: Point2D::new(50.0, 100.0),
+            to: Point2D::new(100.0, 0.0),
+        };
+        // The second difference is 200 long, so 15 lines stray 200 / (4 * 15²) ≈ 0.22, and 14
+        // would stray more than 0.25.
+        assert_eq!(quadratic_line_count(&segment, 0.25), 15);
+        assert_eq!(quadratic_line_count(&segment, 0.0), MAX_LINES_PER_CURVE);
+        assert_eq!(quadratic_line_count(&segment, f32::NAN), MAX_LINES_PER_CURVE);
+        assert_eq!(quadratic_line_count(&segment, f32::INFINITY), 1);
+
+        let straight = QuadraticBezierSegment {
+            from: Point2D::new(0.0, 0.0),
+            ctrl: Point2D::new(1.0, 1.0),
+            to: Point2D::new(2.0, 2.0),
+        };
+        assert_eq!(quadratic_line_count(&straight, 0.0), 1);
+        assert_eq!(FlattenedQuadratic::new(&straight, 0.1).collect::<Vec<_>>(),
+                   vec![Point2D::new(2.0, 2.0)]);
+    }
+}
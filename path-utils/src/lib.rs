@@ -11,16 +11,40 @@
 //! Various utilities for manipulating Bézier curves.
 //! 
 //! Most of these should go upstream to Lyon at some point.
+//!
+//! If the `serialization` feature is enabled, the plain data types in this crate implement
+//! Serde's `Serialize` and `Deserialize`.
 
 extern crate arrayvec;
 extern crate lyon_path;
 
+#[cfg(feature = "serialization")]
+extern crate serde;
+#[cfg(feature = "serialization")]
+#[macro_use]
+extern crate serde_derive;
+
 use lyon_path::geom as lyon_geom;
 use lyon_path::geom::euclid;
 
+pub mod arcs;
+pub mod charts;
+pub mod clip;
+#[cfg(feature = "core-graphics")]
+pub mod core_graphics;
 pub mod cubic_to_quadratic;
+pub mod curve;
+pub mod embolden;
+pub mod flatten;
 pub mod normals;
 pub mod orientation;
+pub mod perspective;
+pub mod projection;
+pub mod sdf;
 pub mod segments;
+#[cfg(feature = "skia")]
+pub mod skia;
 pub mod stroke;
+pub mod text_on_path;
 pub mod transform;
+pub mod units;
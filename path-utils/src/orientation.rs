@@ -12,6 +12,7 @@ use euclid::Point2D;
 use lyon_path::PathEvent;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub enum Orientation {
     Ccw = -1,
     Cw = 1,
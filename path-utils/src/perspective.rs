@@ -0,0 +1,127 @@
+// pathfinder/path-utils/src/perspective.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Projects filled paths through 3D transforms, for content placed on planes in space, such as
+//! VR panels or map labels tilted toward the horizon.
+//!
+//! Perspective doesn't map Bézier curves to Bézier curves, so paths are flattened into polygons
+//! first and their vertices are projected individually. The result can be partitioned and
+//! antialiased like any other path, since that happens after projection, in screen space.
+//!
+//! Each polygon is clipped against the plane just in front of the eye before the perspective
+//! divide, so content that passes behind the viewer doesn't wrap around. Open subpaths are
+//! treated as closed, as they are when filling.
+
+use euclid::{Point2D, Transform3D};
+use lyon_geom::QuadraticBezierSegment;
+use lyon_path::PathEvent;
+
+use arcs::ArcToQuadraticTransformer;
+use cubic_to_quadratic::CubicToQuadraticTransformer;
+use flatten::FlattenedQuadratic;
+
+/// Points with a homogeneous W coordinate less than this are considered behind the eye.
+const NEAR_W: f32 = 1e-5;
+
+/// Transforms the filled path described by `events` by `transform`, a 4x4 matrix applied to
+/// points on the Z = 0 plane, and projects it onto the screen.
+///
+/// Curves are flattened to within `tolerance` before projection, in the units of the original
+/// path, so areas that the transform magnifies need a correspondingly smaller tolerance.
+pub fn project_path<I>(events: I, transform: &Transform3D<f32>, tolerance: f32)
+                       -> Vec<PathEvent>
+                       where I: Iterator<Item = PathEvent> {
+    let events = CubicToQuadraticTransformer::new(ArcToQuadraticTransformer::new(events,
+                                                                                 tolerance),
+                                                  tolerance);
+
+    let mut output = vec![];
+    let mut polygon: Vec<Point2D<f32>> = vec![];
+    for event in events {
+        match event {
+            PathEvent::MoveTo(to) => {
+                project_polygon(&mut output, &polygon, transform);
+                polygon.clear();
+                polygon.push(to)
+            }
+            PathEvent::LineTo(to) => polygon.push(to),
+            PathEvent::QuadraticTo(ctrl, to) => {
+                let from = polygon.last().cloned().unwrap_or(Point2D::zero());
+                let segment = QuadraticBezierSegment { from: from, ctrl: ctrl, to: to };
+                polygon.extend(FlattenedQuadratic::new(&segment, tolerance))
+            }
+            PathEvent::Close => {
+                project_polygon(&mut output, &polygon, transform);
+                polygon.clear()
+            }
+            PathEvent::CubicTo(..) | PathEvent::Arc(..) => {
+                unreachable!("Cubics and arcs should have been converted to quadratics!")
+            }
+        }
+    }
+    project_polygon(&mut output, &polygon, transform);
+    output
+}
+
+// A point in homogeneous coordinates, before the perspective divide.
+#[derive(Clone, Copy, Debug)]
+struct HomogeneousPoint {
+    x: f32,
+    y: f32,
+    w: f32,
+}
+
+impl HomogeneousPoint {
+    fn lerp(&self, other: &HomogeneousPoint, t: f32) -> HomogeneousPoint {
+        HomogeneousPoint {
+            x: self.x + (other.x - self.x) * t,
+            y: self.y + (other.y - self.y) * t,
+            w: self.w + (other.w - self.w) * t,
+        }
+    }
+}
+
+fn project_polygon(output: &mut Vec<PathEvent>,
+                   polygon: &[Point2D<f32>],
+                   transform: &Transform3D<f32>) {
+    if polygon.len() < 3 {
+        return
+    }
+
+    let points: Vec<HomogeneousPoint> = polygon.iter().map(|point| {
+        HomogeneousPoint {
+            x: point.x * transform.m11 + point.y * transform.m21 + transform.m41,
+            y: point.x * transform.m12 + point.y * transform.m22 + transform.m42,
+            w: point.x * transform.m14 + point.y * transform.m24 + transform.m44,
+        }
+    }).collect();
+
+    // Clip against the near plane, Sutherland-Hodgman style.
+    let mut clipped = Vec::with_capacity(points.len() + 1);
+    for (index, point) in points.iter().enumerate() {
+        let next = &points[(index + 1) % points.len()];
+        let (inside, next_inside) = (point.w >= NEAR_W, next.w >= NEAR_W);
+        if inside {
+            clipped.push(*point)
+        }
+        if inside != next_inside {
+            clipped.push(point.lerp(next, (NEAR_W - point.w) / (next.w - point.w)))
+        }
+    }
+    if clipped.len() < 3 {
+        return
+    }
+
+    for (index, point) in clipped.iter().enumerate() {
+        let point = Point2D::new(point.x / point.w, point.y / point.w);
+        output.push(if index == 0 { PathEvent::MoveTo(point) } else { PathEvent::LineTo(point) })
+    }
+    output.push(PathEvent::Close)
+}
@@ -0,0 +1,266 @@
+// pathfinder/path-utils/src/projection.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Finds the point on a path nearest to a given point.
+
+use euclid::{Point2D, Vector2D};
+use lyon_path::PathEvent;
+use std::f32;
+
+use segments::{Segment, SegmentIter};
+
+/// The number of uniform samples taken along each curve to find a bracket for Newton iteration.
+const SAMPLE_COUNT: u32 = 16;
+
+const MAX_NEWTON_ITERATIONS: u32 = 8;
+
+const T_EPSILON: f32 = 1e-6;
+
+/// Projects `point` onto the path described by `events`.
+///
+/// Returns `(t, segment_index, distance)`, where `segment_index` is the index of the nearest
+/// segment as produced by `SegmentIter` (not counting subpath ends), `t` is the parameter along
+/// that segment, and `distance` is the Euclidean distance from `point` to the path. Returns
+/// `None` if the path has no segments.
+///
+/// Each curve is sampled uniformly, and each sample nearer than its neighbors brackets a candidate
+/// that is refined with Newton's method, falling back to bisection whenever a Newton step would
+/// leave the bracket. The nearest candidate wins.
+pub fn nearest_point_on_path<I>(events: I, point: &Point2D<f32>) -> Option<(f32, usize, f32)>
+                                where I: Iterator<Item = PathEvent> {
+    let mut nearest: Option<(f32, usize, f32)> = None;
+    let mut segment_index = 0;
+    for segment in SegmentIter::new(events) {
+        let (t, square_distance) = match segment {
+            Segment::EndSubpath(_) => continue,
+            Segment::Line(ref line) => {
+                let vector = line.to - line.from;
+                let square_length = vector.square_length();
+                let t = if square_length == 0.0 {
+                    0.0
+                } else {
+                    clamp_t((*point - line.from).dot(vector) / square_length)
+                };
+                (t, (line.from + vector * t - *point).square_length())
+            }
+            Segment::Quadratic(ref quadratic) => {
+                let curve = Polynomial::from_control_points(&[quadratic.from,
+                                                              quadratic.ctrl,
+                                                              quadratic.to]);
+                curve.project(point)
+            }
+            Segment::Cubic(ref cubic) => {
+                let curve = Polynomial::from_control_points(&[cubic.from,
+                                                              cubic.ctrl1,
+                                                              cubic.ctrl2,
+                                                              cubic.to]);
+                curve.project(point)
+            }
+        };
+
+        let is_nearer = match nearest {
+            None => true,
+            Some((_, _, nearest_square_distance)) => square_distance < nearest_square_distance,
+        };
+        if is_nearer {
+            nearest = Some((t, segment_index, square_distance))
+        }
+
+        segment_index += 1;
+    }
+
+    nearest.map(|(t, segment_index, square_distance)| {
+        (t, segment_index, square_distance.sqrt())
+    })
+}
+
+// A Bézier curve in power basis: `c[0] + c[1] t + c[2] t² + c[3] t³`.
+struct Polynomial {
+    coefficients: [Vector2D<f32>; 4],
+}
+
+impl Polynomial {
+    fn from_control_points(points: &[Point2D<f32>]) -> Polynomial {
+        let p0 = points[0].to_vector();
+        let coefficients = match points.len() {
+            3 => {
+                let (p1, p2) = (points[1].to_vector(), points[2].to_vector());
+                [p0, (p1 - p0) * 2.0, p0 - p1 * 2.0 + p2, Vector2D::zero()]
+            }
+            _ => {
+                let (p1, p2, p3) = (points[1].to_vector(),
+                                    points[2].to_vector(),
+                                    points[3].to_vector());
+                [
+                    p0,
+                    (p1 - p0) * 3.0,
+                    (p0 - p1 * 2.0 + p2) * 3.0,
+                    p3 - p0 + (p1 - p2) * 3.0,
+                ]
+            }
+        };
+        Polynomial {
+            coefficients: coefficients,
+        }
+    }
+
+    fn sample(&self, t: f32) -> Vector2D<f32> {
+        let c = &self.coefficients;
+        c[0] + (c[1] + (c[2] + c[3] * t) * t) * t
+    }
+
+    fn derivative(&self, t: f32) -> Vector2D<f32> {
+        let c = &self.coefficients;
+        c[1] + (c[2] * 2.0 + c[3] * (3.0 * t)) * t
+    }
+
+    fn second_derivative(&self, t: f32) -> Vector2D<f32> {
+        let c = &self.coefficients;
+        c[2] * 2.0 + c[3] * (6.0 * t)
+    }
+
+    // Returns the parameter of the nearest point and the squared distance to it.
+    fn project(&self, point: &Point2D<f32>) -> (f32, f32) {
+        let point = point.to_vector();
+        let mut square_distances = [0.0; SAMPLE_COUNT as usize + 1];
+        for (sample_index, square_distance) in square_distances.iter_mut().enumerate() {
+            let t = sample_index as f32 / SAMPLE_COUNT as f32;
+            *square_distance = (self.sample(t) - point).square_length()
+        }
+
+        // The nearest sample can lie in a different valley of the distance from the nearest
+        // point, so refine around every sample that's no farther than its neighbors.
+        let mut nearest = (0.0, f32::INFINITY);
+        for sample_index in 0..square_distances.len() {
+            let square_distance = square_distances[sample_index];
+            let is_local_minimum =
+                (sample_index == 0 || square_distance <= square_distances[sample_index - 1]) &&
+                square_distances.get(sample_index + 1).map_or(true, |&next| {
+                    square_distance <= next
+                });
+            if !is_local_minimum {
+                continue
+            }
+            let candidate = self.refine(&point, sample_index as u32, square_distance);
+            if candidate.1 < nearest.1 {
+                nearest = candidate
+            }
+        }
+        nearest
+    }
+
+    // Refines the nearest point within one sample interval on either side of the sample at
+    // `sample_index`, which is `sample_square_distance` away. Returns the parameter and the
+    // squared distance, which are the sample's if refinement doesn't get any nearer.
+    fn refine(&self, point: &Vector2D<f32>, sample_index: u32, sample_square_distance: f32)
+              -> (f32, f32) {
+        let sample_t = sample_index as f32 / SAMPLE_COUNT as f32;
+        let mut lower_t = sample_index.saturating_sub(1) as f32 / SAMPLE_COUNT as f32;
+        let mut upper_t = (sample_index + 1).min(SAMPLE_COUNT) as f32 / SAMPLE_COUNT as f32;
+
+        // Find a root of `(B(t) - P) · B'(t)`.
+        let mut t = sample_t;
+        for _ in 0..MAX_NEWTON_ITERATIONS {
+            let offset = self.sample(t) - *point;
+            let derivative = self.derivative(t);
+            let value = offset.dot(derivative);
+            let slope = derivative.dot(derivative) + offset.dot(self.second_derivative(t));
+
+            // Shrink the bracket. A negative value means the distance is still decreasing.
+            if value < 0.0 {
+                lower_t = t
+            } else {
+                upper_t = t
+            }
+
+            let mut next_t = if slope > 0.0 { t - value / slope } else { f32::NAN };
+            if !(next_t > lower_t && next_t < upper_t) {
+                next_t = (lower_t + upper_t) * 0.5
+            }
+
+            let done = (next_t - t).abs() < T_EPSILON;
+            t = next_t;
+            if done {
+                break
+            }
+        }
+
+        let square_distance = (self.sample(t) - *point).square_length();
+        if square_distance < sample_square_distance {
+            (t, square_distance)
+        } else {
+            (sample_t, sample_square_distance)
+        }
+    }
+}
+
+fn clamp_t(t: f32) -> f32 {
+    t.max(0.0).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::Point2D;
+    use lyon_path::PathEvent;
+
+    use super::nearest_point_on_path;
+
+    fn assert_nearest(events: &[PathEvent],
+                      point: Point2D<f32>,
+                      expected: (f32, usize, f32),
+                      t_tolerance: f32) {
+        let (t, segment_index, distance) =
+            nearest_point_on_path(events.iter().cloned(), &point).unwrap();
+        assert_eq!(segment_index, expected.1);
+        assert!((t - expected.0).abs() <= t_tolerance, "t = {}, not {}", t, expected.0);
+        assert!((distance - expected.2).abs() <= 1e-4,
+                "distance = {}, not {}",
+                distance,
+                expected.2);
+    }
+
+    fn horizontal_line() -> Vec<PathEvent> {
+        vec![PathEvent::MoveTo(Point2D::new(0.0, 0.0)), PathEvent::LineTo(Point2D::new(10.0, 0.0))]
+    }
+
+    #[test]
+    fn points_project_onto_line_interiors() {
+        assert_nearest(&horizontal_line(), Point2D::new(4.0, 3.0), (0.4, 0, 3.0), 1e-6);
+    }
+
+    #[test]
+    fn projections_are_clamped_to_endpoints() {
+        assert_nearest(&horizontal_line(), Point2D::new(-3.0, 4.0), (0.0, 0, 5.0), 0.0);
+        assert_nearest(&horizontal_line(), Point2D::new(14.0, 3.0), (1.0, 0, 5.0), 0.0);
+        assert!(nearest_point_on_path(vec![PathEvent::MoveTo(Point2D::new(1.0, 1.0))].into_iter(),
+                                      &Point2D::new(0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn quadratics_are_refined_away_from_the_nearest_sample() {
+        // Both endpoints are √2 away, so the nearest sample is the start, but the nearest point
+        // is near the end.
+        let events = [
+            PathEvent::MoveTo(Point2D::new(0.0, 0.0)),
+            PathEvent::QuadraticTo(Point2D::new(6.0, 6.0), Point2D::new(2.0, -2.0)),
+        ];
+        assert_nearest(&events, Point2D::new(1.0, -1.0), (0.973723, 0, 1.339834), 1e-3);
+    }
+
+    #[test]
+    fn ties_go_to_the_first_segment() {
+        let events = [
+            PathEvent::MoveTo(Point2D::new(0.0, 0.0)),
+            PathEvent::LineTo(Point2D::new(10.0, 0.0)),
+            PathEvent::LineTo(Point2D::new(10.0, 10.0)),
+        ];
+        assert_nearest(&events, Point2D::new(5.0, 5.0), (0.5, 0, 5.0), 1e-6);
+    }
+}
@@ -0,0 +1,377 @@
+// pathfinder/path-utils/src/sdf.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Generates signed distance fields from filled paths, for renderers that want to scale text
+//! cheaply or draw outlines and glows with a single texture lookup.
+//!
+//! Single-channel fields store the distance to the nearest edge. Multi-channel fields follow
+//! Chlumský's MSDF technique: the edges of each subpath are split at corners and colored so that
+//! the two edges meeting at a corner never share all of their channels, and each channel stores
+//! the distance to the nearest edge of its color. Taking the median of the three channels in the
+//! shader reconstructs sharp corners that a single channel would round off.
+//!
+//! Every sample is encoded as `0.5 + distance / range`, clamped to [0, 1] and scaled to a byte,
+//! with distances positive inside the path. The edge of the path is therefore at 127.5.
+//!
+//! Paths are filled with the nonzero rule. Multi-channel fields additionally assume that the
+//! subpaths are wound consistently, as they are in fonts: samples where the channels disagree
+//! with the fill are replaced with the single-channel distance, which avoids artifacts but
+//! rounds nearby corners.
+
+use euclid::{Point2D, Size2D, Vector2D};
+use lyon_geom::QuadraticBezierSegment;
+use lyon_path::PathEvent;
+use std::f32;
+
+use arcs::ArcToQuadraticTransformer;
+use cubic_to_quadratic::CubicToQuadraticTransformer;
+use flatten;
+
+/// Two consecutive segments meet at a corner if the angle between them is larger than this.
+const CORNER_ANGLE: f32 = 0.14;
+/// Edges whose distances differ by less than this are considered equally near.
+const DISTANCE_EPSILON: f32 = 1e-4;
+
+const RED: u8 = 1;
+const GREEN: u8 = 2;
+const BLUE: u8 = 4;
+const WHITE: u8 = RED | GREEN | BLUE;
+const EDGE_COLORS: [u8; 3] = [GREEN | BLUE, RED | BLUE, RED | GREEN];
+
+/// The kind of distance field to generate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum DistanceFieldKind {
+    /// One channel per pixel.
+    SingleChannel,
+    /// Three channels per pixel, interleaved as RGB.
+    MultiChannel,
+}
+
+impl DistanceFieldKind {
+    /// Returns the number of bytes per pixel.
+    #[inline]
+    pub fn channel_count(self) -> usize {
+        match self {
+            DistanceFieldKind::SingleChannel => 1,
+            DistanceFieldKind::MultiChannel => 3,
+        }
+    }
+}
+
+/// A generated distance field.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DistanceField {
+    pub kind: DistanceFieldKind,
+    pub size: Size2D<u32>,
+    /// The samples, in rows from top to bottom, with `kind.channel_count()` bytes per pixel.
+    pub data: Vec<u8>,
+}
+
+/// Generates a distance field of `size` pixels for the path described by `events`.
+///
+/// The path must already be in the field's pixel coordinates: the center of pixel (x, y) is at
+/// (x + 0.5, y + 0.5), and the first row is at the smallest Y. `range` is the span of distances,
+/// in pixels, that the encoded values cover; half of it lies on each side of the edge. Curves are
+/// approximated with lines to within `tolerance`.
+///
+/// Every pixel is compared against every edge, so this is meant for glyph-sized fields.
+pub fn generate_distance_field<I>(events: I,
+                                  size: &Size2D<u32>,
+                                  range: f32,
+                                  kind: DistanceFieldKind,
+                                  tolerance: f32)
+                                  -> DistanceField
+                                  where I: Iterator<Item = PathEvent> {
+    let edges = build_edges(events, tolerance);
+
+    // Work out which side of the edges is inside from the overall winding direction.
+    let signed_area: f32 = edges.iter().map(|edge| edge.from.to_vector().cross(edge.to.to_vector()))
+                                       .sum();
+    let inside_sign = if signed_area >= 0.0 { 1.0 } else { -1.0 };
+
+    let channel_count = kind.channel_count();
+    let mut data = Vec::with_capacity(size.width as usize * size.height as usize * channel_count);
+    for y in 0..size.height {
+        for x in 0..size.width {
+            let point = Point2D::new(x as f32 + 0.5, y as f32 + 0.5);
+            let inside = winding_number(&edges, &point) != 0;
+
+            let mut distance = f32::INFINITY;
+            for edge in &edges {
+                distance = distance.min(edge.distance_to(&point).distance)
+            }
+            let true_distance = if inside { distance } else { -distance };
+
+            match kind {
+                DistanceFieldKind::SingleChannel => data.push(encode(true_distance, range)),
+                DistanceFieldKind::MultiChannel => {
+                    let mut channels = [true_distance; 3];
+                    for (channel_index, channel) in channels.iter_mut().enumerate() {
+                        let mut nearest: Option<EdgeDistance> = None;
+                        for edge in edges.iter().filter(|edge| {
+                            edge.color & (1 << channel_index) != 0
+                        }) {
+                            let edge_distance = edge.distance_to(&point);
+                            if nearest.map_or(true, |nearest| edge_distance.is_nearer(&nearest)) {
+                                nearest = Some(edge_distance)
+                            }
+                        }
+                        if let Some(nearest) = nearest {
+                            *channel = nearest.pseudo_distance * inside_sign
+                        }
+                    }
+
+                    // If the median disagrees with the fill, fall back to the true distance.
+                    if (median(channels[0], channels[1], channels[2]) > 0.0) != inside {
+                        channels = [true_distance; 3]
+                    }
+                    for &channel in &channels {
+                        data.push(encode(channel, range))
+                    }
+                }
+            }
+        }
+    }
+
+    DistanceField {
+        kind: kind,
+        size: *size,
+        data: data,
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Edge {
+    from: Point2D<f32>,
+    to: Point2D<f32>,
+    color: u8,
+    // Whether the distance may be measured to the line through the edge beyond each endpoint.
+    // This is true at the ends of the original segments, but not between the lines that
+    // approximate a curve.
+    extends_backward: bool,
+    extends_forward: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct EdgeDistance {
+    distance: f32,
+    // How close to perpendicular the direction to the nearest point is, used to break ties
+    // between edges that share an endpoint.
+    orthogonality: f32,
+    // The distance to the edge, or to its extension at a segment end, signed so that it is
+    // positive to the left of the edge.
+    pseudo_distance: f32,
+}
+
+impl Edge {
+    fn distance_to(&self, point: &Point2D<f32>) -> EdgeDistance {
+        let vector = self.to - self.from;
+        let length = vector.length();
+        let offset = *point - self.from;
+        if length == 0.0 {
+            let distance = offset.length();
+            return EdgeDistance {
+                distance: distance,
+                orthogonality: 0.0,
+                pseudo_distance: distance,
+            }
+        }
+
+        let t = offset.dot(vector) / (length * length);
+        let distance = (offset - vector * t.max(0.0).min(1.0)).length();
+        let perpendicular_distance = vector.cross(offset) / length;
+
+        let pseudo_distance = if (t < 0.0 && self.extends_backward) ||
+                (t > 1.0 && self.extends_forward) {
+            perpendicular_distance
+        } else if perpendicular_distance >= 0.0 {
+            distance
+        } else {
+            -distance
+        };
+
+        let orthogonality = if distance > 0.0 {
+            perpendicular_distance.abs() / distance
+        } else {
+            1.0
+        };
+        EdgeDistance {
+            distance: distance,
+            orthogonality: orthogonality,
+            pseudo_distance: pseudo_distance,
+        }
+    }
+}
+
+impl EdgeDistance {
+    fn is_nearer(&self, other: &EdgeDistance) -> bool {
+        if (self.distance - other.distance).abs() < DISTANCE_EPSILON {
+            self.orthogonality > other.orthogonality
+        } else {
+            self.distance < other.distance
+        }
+    }
+}
+
+// Flattens the path into colored edges, closing any open subpaths.
+fn build_edges<I>(events: I, tolerance: f32) -> Vec<Edge> where I: Iterator<Item = PathEvent> {
+    let events = CubicToQuadraticTransformer::new(ArcToQuadraticTransformer::new(events,
+                                                                                 tolerance),
+                                                  tolerance);
+
+    let mut edges = vec![];
+    let mut subpath = vec![];
+    let (mut first_point, mut current_point) = (Point2D::zero(), Point2D::zero());
+    for event in events {
+        match event {
+            PathEvent::MoveTo(to) => {
+                push_subpath(&mut edges, &mut subpath, current_point, first_point, tolerance);
+                first_point = to;
+                current_point = to;
+            }
+            PathEvent::LineTo(to) => {
+                subpath.push(QuadraticBezierSegment {
+                    from: current_point,
+                    ctrl: current_point.lerp(to, 0.5),
+                    to: to,
+                });
+                current_point = to;
+            }
+            PathEvent::QuadraticTo(ctrl, to) => {
+                subpath.push(QuadraticBezierSegment {
+                    from: current_point,
+                    ctrl: ctrl,
+                    to: to,
+                });
+                current_point = to;
+            }
+            PathEvent::Close => {
+                push_subpath(&mut edges, &mut subpath, current_point, first_point, tolerance);
+                current_point = first_point;
+            }
+            PathEvent::CubicTo(..) | PathEvent::Arc(..) => {
+                unreachable!("Cubics and arcs should have been converted to quadratics!")
+            }
+        }
+    }
+    push_subpath(&mut edges, &mut subpath, current_point, first_point, tolerance);
+    return edges;
+
+    fn push_subpath(edges: &mut Vec<Edge>,
+                    subpath: &mut Vec<QuadraticBezierSegment<f32>>,
+                    current_point: Point2D<f32>,
+                    first_point: Point2D<f32>,
+                    tolerance: f32) {
+        if current_point != first_point {
+            subpath.push(QuadraticBezierSegment {
+                from: current_point,
+                ctrl: current_point.lerp(first_point, 0.5),
+                to: first_point,
+            })
+        }
+        subpath.retain(|segment| segment.from != segment.to || segment.ctrl != segment.to);
+        if subpath.is_empty() {
+            return
+        }
+
+        // Find the segments that start at corners.
+        let corners: Vec<usize> = (0..subpath.len()).filter(|&segment_index| {
+            let prev_segment = &subpath[(segment_index + subpath.len() - 1) % subpath.len()];
+            let (prev_tangent, next_tangent) = (end_tangent(prev_segment),
+                                                start_tangent(&subpath[segment_index]));
+            prev_tangent.dot(next_tangent) <= 0.0 ||
+                prev_tangent.cross(next_tangent).abs() > CORNER_ANGLE.sin()
+        }).collect();
+
+        // Color the runs of segments between corners, making sure that neighboring runs differ.
+        // A smooth subpath, or one with a single corner, is left white.
+        let mut colors = vec![WHITE; subpath.len()];
+        if corners.len() > 1 {
+            for (run_index, &start) in corners.iter().enumerate() {
+                let mut color = EDGE_COLORS[run_index % 3];
+                if run_index == corners.len() - 1 && run_index % 3 == 0 {
+                    color = EDGE_COLORS[1]
+                }
+                let end = corners[(run_index + 1) % corners.len()];
+                let mut segment_index = start;
+                loop {
+                    colors[segment_index] = color;
+                    segment_index = (segment_index + 1) % subpath.len();
+                    if segment_index == end {
+                        break
+                    }
+                }
+            }
+        }
+
+        for (segment, &color) in subpath.iter().zip(colors.iter()) {
+            let line_count = flatten::quadratic_line_count(segment, tolerance);
+            let mut from = segment.from;
+            for line_index in 0..line_count {
+                let to = if line_index + 1 == line_count {
+                    segment.to
+                } else {
+                    segment.sample((line_index + 1) as f32 / line_count as f32)
+                };
+                edges.push(Edge {
+                    from: from,
+                    to: to,
+                    color: color,
+                    extends_backward: line_index == 0,
+                    extends_forward: line_index + 1 == line_count,
+                });
+                from = to;
+            }
+        }
+
+        subpath.clear()
+    }
+
+    fn start_tangent(segment: &QuadraticBezierSegment<f32>) -> Vector2D<f32> {
+        let tangent = if segment.ctrl != segment.from {
+            segment.ctrl - segment.from
+        } else {
+            segment.to - segment.from
+        };
+        tangent.normalize()
+    }
+
+    fn end_tangent(segment: &QuadraticBezierSegment<f32>) -> Vector2D<f32> {
+        let tangent = if segment.ctrl != segment.to {
+            segment.to - segment.ctrl
+        } else {
+            segment.to - segment.from
+        };
+        tangent.normalize()
+    }
+}
+
+// Computes the nonzero winding number of the edges around the point.
+fn winding_number(edges: &[Edge], point: &Point2D<f32>) -> i32 {
+    let mut winding = 0;
+    for edge in edges {
+        let side = (edge.to - edge.from).cross(*point - edge.from);
+        if edge.from.y <= point.y && point.y < edge.to.y && side > 0.0 {
+            winding += 1
+        } else if edge.to.y <= point.y && point.y < edge.from.y && side < 0.0 {
+            winding -= 1
+        }
+    }
+    winding
+}
+
+fn median(a: f32, b: f32, c: f32) -> f32 {
+    a.min(b).max(a.max(b).min(c))
+}
+
+fn encode(distance: f32, range: f32) -> u8 {
+    ((0.5 + distance / range).max(0.0).min(1.0) * 255.0).round() as u8
+}
@@ -90,6 +90,7 @@ impl<I> Iterator for SegmentIter<I> where I: Iterator<Item = PathEvent> {
 }
 
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub enum Segment {
     Line(LineSegment<f32>),
     Quadratic(QuadraticBezierSegment<f32>),
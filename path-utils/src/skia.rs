@@ -0,0 +1,222 @@
+// pathfinder/path-utils/src/skia.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Conversion between path events and Skia's `SkPath` representation: an array of verbs, the
+//! points they consume, and a weight for each conic.
+//!
+//! This works on the arrays that `SkPath::getVerbs()`, `getPoints()`, and the conic weights
+//! expose, so it doesn't depend on any particular Skia binding. Arcs are exported exactly, as
+//! conics. Conics that aren't parabolas have no exact equivalent among path events, so on import
+//! they're approximated with quadratic curves.
+
+use euclid::Point2D;
+use lyon_path::PathEvent;
+use std::f32;
+
+use arcs::CenterArc;
+
+/// The most times a conic will be halved when it's approximated with quadratic curves.
+const MAX_CONIC_SUBDIVISIONS: u32 = 10;
+
+/// A path verb, with the same values as Skia's `SkPath::Verb`.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum SkVerb {
+    /// Consumes one point.
+    Move = 0,
+    /// Consumes one point.
+    Line = 1,
+    /// Consumes two points: the control point and the endpoint.
+    Quad = 2,
+    /// Consumes two points and one weight.
+    Conic = 3,
+    /// Consumes three points.
+    Cubic = 4,
+    Close = 5,
+    /// Marks the end of the path, in the iterator interface. It isn't stored in paths.
+    Done = 6,
+}
+
+/// A path in Skia's representation.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct SkPathData {
+    pub verbs: Vec<SkVerb>,
+    pub points: Vec<Point2D<f32>>,
+    /// One weight per conic verb, in order.
+    pub conic_weights: Vec<f32>,
+}
+
+impl SkPathData {
+    #[inline]
+    pub fn new() -> SkPathData {
+        SkPathData::default()
+    }
+
+    /// Converts path events to Skia's representation. No information is lost.
+    pub fn from_path_events<I>(events: I) -> SkPathData where I: Iterator<Item = PathEvent> {
+        let mut path = SkPathData::new();
+        let (mut first_point, mut last_point) = (Point2D::zero(), Point2D::zero());
+        for event in events {
+            match event {
+                PathEvent::MoveTo(to) => {
+                    path.verbs.push(SkVerb::Move);
+                    path.points.push(to);
+                    first_point = to;
+                    last_point = to;
+                }
+                PathEvent::LineTo(to) => {
+                    path.verbs.push(SkVerb::Line);
+                    path.points.push(to);
+                    last_point = to;
+                }
+                PathEvent::QuadraticTo(ctrl, to) => {
+                    path.verbs.push(SkVerb::Quad);
+                    path.points.extend_from_slice(&[ctrl, to]);
+                    last_point = to;
+                }
+                PathEvent::CubicTo(ctrl0, ctrl1, to) => {
+                    path.verbs.push(SkVerb::Cubic);
+                    path.points.extend_from_slice(&[ctrl0, ctrl1, to]);
+                    last_point = to;
+                }
+                PathEvent::Arc(center, radii, sweep_angle, x_rotation) => {
+                    let arc = CenterArc::from_path_event(&last_point,
+                                                         &center,
+                                                         &radii,
+                                                         sweep_angle,
+                                                         x_rotation);
+                    path.push_arc(&arc);
+                    last_point = arc.to();
+                }
+                PathEvent::Close => {
+                    path.verbs.push(SkVerb::Close);
+                    last_point = first_point;
+                }
+            }
+        }
+        path
+    }
+
+    /// Converts the path to path events, approximating conics with quadratic curves to within
+    /// `tolerance`. Returns `None` if the verbs need more points or weights than there are.
+    pub fn to_path_events(&self, tolerance: f32) -> Option<Vec<PathEvent>> {
+        let (mut point_count, mut weight_count) = (0, 0);
+        for verb in &self.verbs {
+            match *verb {
+                SkVerb::Move | SkVerb::Line => point_count += 1,
+                SkVerb::Quad => point_count += 2,
+                SkVerb::Conic => {
+                    point_count += 2;
+                    weight_count += 1;
+                }
+                SkVerb::Cubic => point_count += 3,
+                SkVerb::Close => {}
+                SkVerb::Done => break,
+            }
+        }
+        if self.points.len() < point_count || self.conic_weights.len() < weight_count {
+            return None
+        }
+
+        let mut events = vec![];
+        let (mut points, mut weights) = (self.points.iter(), self.conic_weights.iter());
+        let mut last_point = Point2D::zero();
+        for verb in &self.verbs {
+            match *verb {
+                SkVerb::Move => {
+                    last_point = *points.next().unwrap();
+                    events.push(PathEvent::MoveTo(last_point))
+                }
+                SkVerb::Line => {
+                    last_point = *points.next().unwrap();
+                    events.push(PathEvent::LineTo(last_point))
+                }
+                SkVerb::Quad => {
+                    let ctrl = *points.next().unwrap();
+                    last_point = *points.next().unwrap();
+                    events.push(PathEvent::QuadraticTo(ctrl, last_point))
+                }
+                SkVerb::Conic => {
+                    let ctrl = *points.next().unwrap();
+                    let to = *points.next().unwrap();
+                    let weight = *weights.next().unwrap();
+                    push_conic(&mut events, &last_point, &ctrl, &to, weight, tolerance, 0);
+                    last_point = to;
+                }
+                SkVerb::Cubic => {
+                    let ctrl0 = *points.next().unwrap();
+                    let ctrl1 = *points.next().unwrap();
+                    last_point = *points.next().unwrap();
+                    events.push(PathEvent::CubicTo(ctrl0, ctrl1, last_point))
+                }
+                SkVerb::Close => events.push(PathEvent::Close),
+                SkVerb::Done => break,
+            }
+        }
+        Some(events)
+    }
+
+    // Appends an elliptical arc as conics of at most a quarter turn each, which represent it
+    // exactly.
+    fn push_arc(&mut self, arc: &CenterArc) {
+        let sweep_angle = arc.clamped_sweep_angle();
+        if sweep_angle == 0.0 {
+            return
+        }
+        let segment_count = (sweep_angle.abs() / (f32::consts::PI * 0.5)).ceil().max(1.0) as u32;
+        let step = sweep_angle / segment_count as f32;
+        let weight = (step * 0.5).cos();
+        for segment_index in 0..segment_count {
+            let angle = arc.start_angle.radians + step * segment_index as f32;
+            // The control point is where the tangents at the ends meet. Since the ellipse is an
+            // affine image of the unit circle, it's the midpoint pushed out by 1 / cos(step / 2).
+            let mid = arc.sample(angle + step * 0.5);
+            let ctrl = arc.center + (mid - arc.center) / weight;
+            let to = if segment_index + 1 == segment_count {
+                arc.to()
+            } else {
+                arc.sample(angle + step)
+            };
+            self.verbs.push(SkVerb::Conic);
+            self.points.extend_from_slice(&[ctrl, to]);
+            self.conic_weights.push(weight);
+        }
+    }
+}
+
+// Approximates a conic with quadratics by halving it until each half is close enough to the
+// parabola through the same points.
+fn push_conic(events: &mut Vec<PathEvent>,
+              from: &Point2D<f32>,
+              ctrl: &Point2D<f32>,
+              to: &Point2D<f32>,
+              weight: f32,
+              tolerance: f32,
+              depth: u32) {
+    // This is Skia's bound on the distance between a conic and its quadratic.
+    let a = weight - 1.0;
+    let k = a / (4.0 * (2.0 + a));
+    let error = (from.to_vector() - ctrl.to_vector() * 2.0 + to.to_vector()).length() * k.abs();
+    if error <= tolerance || depth >= MAX_CONIC_SUBDIVISIONS || !weight.is_finite() {
+        events.push(PathEvent::QuadraticTo(*ctrl, *to));
+        return
+    }
+
+    let mid = (from.to_vector() + ctrl.to_vector() * (2.0 * weight) + to.to_vector()) /
+        (2.0 + 2.0 * weight);
+    let mid = mid.to_point();
+    let half_weight = ((1.0 + weight) * 0.5).sqrt();
+    let ctrl0 = ((from.to_vector() + ctrl.to_vector() * weight) / (1.0 + weight)).to_point();
+    let ctrl1 = ((ctrl.to_vector() * weight + to.to_vector()) / (1.0 + weight)).to_point();
+    push_conic(events, from, &ctrl0, &mid, half_weight, tolerance, depth + 1);
+    push_conic(events, &mid, &ctrl1, to, half_weight, tolerance, depth + 1);
+}
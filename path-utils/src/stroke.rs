@@ -10,14 +10,29 @@
 
 //! Utilities for converting path strokes to fills.
 
+use euclid::approxeq::ApproxEq;
+use euclid::{Angle, Point2D, Vector2D};
 use lyon_path::PathEvent;
 use lyon_path::iterator::PathIterator;
+use std::f32::consts::PI;
 
+use arcs::CenterArc;
 use segments::{Segment, SegmentIter};
 
+/// The tolerance used to approximate round caps and joins, as a fraction of the stroke width.
+const ROUND_APPROX_TOLERANCE: f32 = 0.01;
+
+/// The miter limit that SVG uses by default.
+pub const DEFAULT_MITER_LIMIT: f32 = 4.0;
+
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct StrokeStyle {
     pub width: f32,
+    /// The cap drawn at the ends of open subpaths. Closed subpaths never get caps.
+    pub cap: LineCap,
+    /// The shape drawn on the outside of corners.
+    pub join: LineJoin,
 }
 
 impl StrokeStyle {
@@ -25,10 +40,38 @@ impl StrokeStyle {
     pub fn new(width: f32) -> StrokeStyle {
         StrokeStyle {
             width: width,
+            cap: LineCap::Butt,
+            join: LineJoin::Bevel,
         }
     }
 }
 
+/// The shape drawn at the endpoints of open subpaths, as in SVG's `stroke-linecap`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum LineCap {
+    /// The stroke ends flush with the endpoint.
+    Butt,
+    /// The stroke extends past the endpoint by half the stroke width.
+    Square,
+    /// The stroke ends in a semicircle centered on the endpoint.
+    Round,
+}
+
+/// The shape drawn on the outside of the corners where segments meet, as in SVG's
+/// `stroke-linejoin`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum LineJoin {
+    /// The corner is cut off with a straight line.
+    Bevel,
+    /// The outer edges are extended until they meet, unless the ratio of the length of the
+    /// miter to the stroke width would exceed the limit, in which case the join is beveled.
+    Miter(f32),
+    /// The corner is rounded off with a circular arc centered on it.
+    Round,
+}
+
 pub struct StrokeToFillIter<I> where I: PathIterator {
     inner: SegmentIter<I>,
     subpath: Vec<Segment>,
@@ -36,6 +79,13 @@ pub struct StrokeToFillIter<I> where I: PathIterator {
     state: StrokeToFillState,
     style: StrokeStyle,
     first_point_in_subpath: bool,
+    subpath_caps: Vec<LineCap>,
+    subpath_index: usize,
+    subpath_closed: bool,
+    subpath_start: Option<(Point2D<f32>, Vector2D<f32>)>,
+    subpath_end: Option<(Point2D<f32>, Vector2D<f32>)>,
+    previous_end: Option<(Point2D<f32>, Vector2D<f32>)>,
+    just_capped: bool,
 }
 
 impl<I> StrokeToFillIter<I> where I: PathIterator {
@@ -48,15 +98,126 @@ impl<I> StrokeToFillIter<I> where I: PathIterator {
             state: StrokeToFillState::Forward,
             style: style,
             first_point_in_subpath: true,
+            subpath_caps: vec![],
+            subpath_index: 0,
+            subpath_closed: false,
+            subpath_start: None,
+            subpath_end: None,
+            previous_end: None,
+            just_capped: false,
+        }
+    }
+
+    /// Overrides the cap for individual subpaths: the *n*th subpath, if open, is capped with
+    /// `caps[n]`. Subpaths beyond the end of `caps` use the cap from the stroke style.
+    #[inline]
+    pub fn set_subpath_caps(&mut self, caps: Vec<LineCap>) {
+        self.subpath_caps = caps
+    }
+
+    fn current_cap(&self) -> LineCap {
+        match self.subpath_caps.get(self.subpath_index) {
+            Some(&cap) => cap,
+            None => self.style.cap,
+        }
+    }
+
+    // Returns the events that draw a cap around `point`, starting on the left side of the
+    // outward-facing `direction` and ending on the right side.
+    fn cap_events(&self, point: &Point2D<f32>, direction: &Vector2D<f32>) -> Vec<PathEvent> {
+        let half_width = self.style.width * 0.5;
+        let direction = *direction * half_width;
+        let normal = Vector2D::new(-direction.y, direction.x);
+        match self.current_cap() {
+            LineCap::Butt => vec![PathEvent::LineTo(*point - normal)],
+            LineCap::Square => {
+                vec![
+                    PathEvent::LineTo(*point + normal + direction),
+                    PathEvent::LineTo(*point - normal + direction),
+                    PathEvent::LineTo(*point - normal),
+                ]
+            }
+            LineCap::Round => {
+                let arc = CenterArc {
+                    center: *point,
+                    radii: Vector2D::new(half_width, half_width),
+                    start_angle: Angle::radians(normal.y.atan2(normal.x)),
+                    sweep_angle: Angle::radians(-PI),
+                    x_rotation: Angle::radians(0.0),
+                };
+                let mut events = vec![];
+                arc.for_each_quadratic(self.style.width * ROUND_APPROX_TOLERANCE, |quadratic| {
+                    events.push(PathEvent::QuadraticTo(quadratic.ctrl, quadratic.to))
+                });
+                events
+            }
         }
     }
+
+    // Returns the events that join the left side of a segment ending at `from` to the left side
+    // of one starting at `to`, not including the final line to the start of the latter.
+    //
+    // Only corners that turn away from the left side need anything drawn; on the inside of a
+    // corner, the offset segments overlap, and the winding fill rule covers the overlap.
+    fn join_events(&self,
+                   from: &(Point2D<f32>, Vector2D<f32>),
+                   to: &(Point2D<f32>, Vector2D<f32>))
+                   -> Vec<PathEvent> {
+        let (pivot, from_tangent, to_tangent) = (to.0, from.1, to.1);
+        let cross = from_tangent.cross(to_tangent);
+        if cross > 0.0 || (cross == 0.0 && from_tangent.dot(to_tangent) >= 0.0) {
+            return vec![]
+        }
+
+        let half_width = self.style.width * 0.5;
+        let from_normal = Vector2D::new(-from_tangent.y, from_tangent.x) * half_width;
+        let to_normal = Vector2D::new(-to_tangent.y, to_tangent.x) * half_width;
+        match self.style.join {
+            LineJoin::Bevel => vec![],
+            LineJoin::Miter(miter_limit) => {
+                let bisector = from_normal + to_normal;
+                let bisector_square_length = bisector.square_length();
+                if bisector_square_length < f32::approx_epsilon() ||
+                        self.style.width > miter_limit * bisector_square_length.sqrt() {
+                    return vec![]
+                }
+                let miter = bisector * (2.0 * half_width * half_width / bisector_square_length);
+                vec![PathEvent::LineTo(pivot + miter)]
+            }
+            LineJoin::Round => {
+                let arc = CenterArc {
+                    center: pivot,
+                    radii: Vector2D::new(half_width, half_width),
+                    start_angle: Angle::radians(from_normal.y.atan2(from_normal.x)),
+                    // Sweep toward decreasing angle, which goes around the outside of the
+                    // corner, even if the path doubles back on itself.
+                    sweep_angle: Angle::radians(-from_normal.cross(to_normal)
+                                                            .abs()
+                                                            .atan2(from_normal.dot(to_normal))),
+                    x_rotation: Angle::radians(0.0),
+                };
+                let mut events = vec![];
+                arc.for_each_quadratic(self.style.width * ROUND_APPROX_TOLERANCE, |quadratic| {
+                    events.push(PathEvent::QuadraticTo(quadratic.ctrl, quadratic.to))
+                });
+                events
+            }
+        }
+    }
+
+    // Queues `events` so that they're returned in order, before anything already queued.
+    fn push_events(&mut self, events: Vec<PathEvent>) {
+        self.stack.extend(events.into_iter().rev())
+    }
 }
 
 impl<I> Iterator for StrokeToFillIter<I> where I: PathIterator {
     type Item = PathEvent;
 
-    // TODO(pcwalton): Support miter and round joins. This will probably require the inner iterator
-    // to be `Peekable`, I guess.
+    // Open subpaths are stroked by tracing the left side forward, capping the end, tracing the
+    // right side backward, and capping the start. Closed subpaths are stroked as two separate
+    // closed contours, one for each side, so no caps are drawn at the closure point; instead,
+    // both contours are joined there as at any other corner.
     fn next(&mut self) -> Option<PathEvent> {
         // If we have path events queued, return the latest.
         if let Some(path_event) = self.stack.pop() {
@@ -67,22 +228,51 @@ impl<I> Iterator for StrokeToFillIter<I> where I: PathIterator {
         let next_segment = match self.state {
             StrokeToFillState::Forward => {
                 match self.inner.next() {
+                    None if self.subpath.is_empty() => return None,
+                    Some(Segment::EndSubpath(_)) if self.subpath.is_empty() => {
+                        // Skip empty subpaths entirely.
+                        self.subpath_start = None;
+                        return self.next()
+                    }
                     None | Some(Segment::EndSubpath(false)) => {
-                        if self.subpath.is_empty() {
-                            return None
-                        }
                         self.state = StrokeToFillState::Backward;
+                        self.subpath_closed = false;
+
+                        // Cap the end of the subpath.
+                        let last_segment = *self.subpath.last().unwrap();
+                        if let Some((point, tangent)) = segment_end(&last_segment) {
+                            let events = self.cap_events(&point, &tangent);
+                            self.push_events(events);
+                            self.just_capped = true;
+                        }
+                        self.previous_end = None;
                         return self.next()
                     }
                     Some(Segment::EndSubpath(true)) => {
-                        if self.subpath.is_empty() {
-                            return None
-                        }
                         self.state = StrokeToFillState::Backward;
+                        self.subpath_closed = true;
                         self.first_point_in_subpath = true;
-                        return Some(PathEvent::Close)
+
+                        // Join the last segment to the first.
+                        self.subpath_end = self.previous_end.take();
+                        self.stack.push(PathEvent::Close);
+                        if let (Some(end), Some(start)) = (self.subpath_end, self.subpath_start) {
+                            let events = self.join_events(&end, &start);
+                            self.push_events(events);
+                        }
+                        return self.next()
                     }
                     Some(segment) => {
+                        // Degenerate segments have no direction to offset along, so drop them.
+                        // Notably, this includes the closing line of a subpath whose last point
+                        // is already its first point.
+                        let start = match segment_start(&segment) {
+                            None => return self.next(),
+                            Some(start) => start,
+                        };
+                        if self.subpath.is_empty() {
+                            self.subpath_start = Some(start)
+                        }
                         self.subpath.push(segment);
                         segment
                     }
@@ -93,13 +283,47 @@ impl<I> Iterator for StrokeToFillIter<I> where I: PathIterator {
                     None | Some(Segment::EndSubpath(_)) => {
                         self.state = StrokeToFillState::Forward;
                         self.first_point_in_subpath = true;
-                        return Some(PathEvent::Close)
+
+                        // Cap the start of the subpath, unless it was closed. The final line of
+                        // the cap is drawn by the close command.
+                        self.stack.push(PathEvent::Close);
+                        if self.subpath_closed {
+                            // Join the flipped first segment to the flipped last segment.
+                            if let (Some(end), Some((point, tangent))) = (self.previous_end,
+                                                                          self.subpath_end) {
+                                let events = self.join_events(&end, &(point, -tangent));
+                                self.push_events(events);
+                            }
+                        } else {
+                            if let Some((point, tangent)) = self.subpath_start {
+                                let mut events = self.cap_events(&point, &-tangent);
+                                if let Some(&PathEvent::LineTo(_)) = events.last() {
+                                    events.pop();
+                                }
+                                self.push_events(events);
+                            }
+                        }
+
+                        self.subpath_start = None;
+                        self.subpath_end = None;
+                        self.previous_end = None;
+                        self.subpath_index += 1;
+                        return self.next()
                     }
                     Some(segment) => segment.flip(),
                 }
             }
         };
 
+        let just_capped = self.just_capped;
+        self.just_capped = false;
+
+        let mut join_events = match (self.previous_end, segment_start(&next_segment)) {
+            (Some(ref end), Some(ref start)) if !just_capped => self.join_events(end, start),
+            _ => vec![],
+        };
+        self.previous_end = segment_end(&next_segment);
+
         next_segment.offset(self.style.width * 0.5, |offset_segment| {
             match *offset_segment {
                 Segment::EndSubpath(_) => unreachable!(),
@@ -107,7 +331,8 @@ impl<I> Iterator for StrokeToFillIter<I> where I: PathIterator {
                     if self.first_point_in_subpath {
                         self.first_point_in_subpath = false;
                         self.stack.push(PathEvent::MoveTo(offset_segment.from))
-                    } else if self.stack.is_empty() {
+                    } else if self.stack.is_empty() && !just_capped {
+                        self.stack.extend(join_events.drain(..));
                         self.stack.push(PathEvent::LineTo(offset_segment.from))
                     }
                     self.stack.push(PathEvent::LineTo(offset_segment.to))
@@ -116,7 +341,8 @@ impl<I> Iterator for StrokeToFillIter<I> where I: PathIterator {
                     if self.first_point_in_subpath {
                         self.first_point_in_subpath = false;
                         self.stack.push(PathEvent::MoveTo(offset_segment.from))
-                    } else if self.stack.is_empty() {
+                    } else if self.stack.is_empty() && !just_capped {
+                        self.stack.extend(join_events.drain(..));
                         self.stack.push(PathEvent::LineTo(offset_segment.from))
                     }
                     self.stack.push(PathEvent::QuadraticTo(offset_segment.ctrl, offset_segment.to))
@@ -125,7 +351,8 @@ impl<I> Iterator for StrokeToFillIter<I> where I: PathIterator {
                     if self.first_point_in_subpath {
                         self.first_point_in_subpath = false;
                         self.stack.push(PathEvent::MoveTo(offset_segment.from))
-                    } else if self.stack.is_empty() {
+                    } else if self.stack.is_empty() && !just_capped {
+                        self.stack.extend(join_events.drain(..));
                         self.stack.push(PathEvent::LineTo(offset_segment.from))
                     }
                     self.stack.push(PathEvent::CubicTo(offset_segment.ctrl1,
@@ -145,3 +372,115 @@ enum StrokeToFillState {
     Forward,
     Backward,
 }
+
+// Returns the start point of the segment and the unit tangent there, or `None` if the segment is
+// degenerate.
+fn segment_start(segment: &Segment) -> Option<(Point2D<f32>, Vector2D<f32>)> {
+    let (from, candidates) = match *segment {
+        Segment::EndSubpath(_) => return None,
+        Segment::Line(ref line) => (line.from, [line.to, line.to, line.to]),
+        Segment::Quadratic(ref quadratic) => {
+            (quadratic.from, [quadratic.ctrl, quadratic.to, quadratic.to])
+        }
+        Segment::Cubic(ref cubic) => (cubic.from, [cubic.ctrl1, cubic.ctrl2, cubic.to]),
+    };
+    first_tangent(&from, &candidates).map(|tangent| (from, tangent))
+}
+
+// Returns the end point of the segment and the unit tangent there, or `None` if the segment is
+// degenerate.
+fn segment_end(segment: &Segment) -> Option<(Point2D<f32>, Vector2D<f32>)> {
+    segment_start(&segment.flip()).map(|(point, tangent)| (point, -tangent))
+}
+
+fn first_tangent(from: &Point2D<f32>, candidates: &[Point2D<f32>]) -> Option<Vector2D<f32>> {
+    candidates.iter().map(|point| *point - *from).find(|vector| {
+        vector.square_length() > f32::approx_epsilon()
+    }).map(|vector| vector.normalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::Point2D;
+    use lyon_path::PathEvent;
+    use lyon_path::iterator::PathIter;
+    use std::f32;
+
+    use super::{LineCap, StrokeStyle, StrokeToFillIter};
+
+    fn stroke(path: &[PathEvent], cap: LineCap, subpath_caps: Vec<LineCap>) -> Vec<PathEvent> {
+        let mut style = StrokeStyle::new(2.0);
+        style.cap = cap;
+        let mut stroke = StrokeToFillIter::new(PathIter::new(path.iter().cloned()), style);
+        stroke.set_subpath_caps(subpath_caps);
+        stroke.collect()
+    }
+
+    // Returns the horizontal extent of the endpoints of `events`.
+    fn x_extent(events: &[PathEvent]) -> (f32, f32) {
+        events.iter().filter_map(|event| {
+            match *event {
+                PathEvent::MoveTo(to) | PathEvent::LineTo(to) | PathEvent::QuadraticTo(_, to) |
+                PathEvent::CubicTo(_, _, to) => Some(to.x),
+                PathEvent::Arc(..) | PathEvent::Close => None,
+            }
+        }).fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), x| (min.min(x), max.max(x)))
+    }
+
+    fn assert_x_extent(events: &[PathEvent], expected: (f32, f32)) {
+        let extent = x_extent(events);
+        assert!((extent.0 - expected.0).abs() < 1e-3 && (extent.1 - expected.1).abs() < 1e-3,
+                "{:?} != {:?}",
+                extent,
+                expected)
+    }
+
+    fn line() -> Vec<PathEvent> {
+        vec![PathEvent::MoveTo(Point2D::new(0.0, 0.0)), PathEvent::LineTo(Point2D::new(10.0, 0.0))]
+    }
+
+    fn square() -> Vec<PathEvent> {
+        vec![
+            PathEvent::MoveTo(Point2D::new(0.0, 0.0)),
+            PathEvent::LineTo(Point2D::new(10.0, 0.0)),
+            PathEvent::LineTo(Point2D::new(10.0, 10.0)),
+            PathEvent::LineTo(Point2D::new(0.0, 10.0)),
+            PathEvent::Close,
+        ]
+    }
+
+    #[test]
+    fn open_subpaths_are_capped_at_both_ends() {
+        let butt = stroke(&line(), LineCap::Butt, vec![]);
+        assert_x_extent(&butt, (0.0, 10.0));
+        assert_eq!(butt.iter().filter(|&&event| event == PathEvent::Close).count(), 1);
+        assert_x_extent(&stroke(&line(), LineCap::Square, vec![]), (-1.0, 11.0));
+        assert_x_extent(&stroke(&line(), LineCap::Round, vec![]), (-1.0, 11.0));
+    }
+
+    #[test]
+    fn closed_subpaths_are_never_capped() {
+        let butt = stroke(&square(), LineCap::Butt, vec![]);
+        assert_eq!(stroke(&square(), LineCap::Square, vec![]), butt);
+        assert_eq!(stroke(&square(), LineCap::Round, vec![]), butt);
+
+        // Each side of the stroke is its own closed contour.
+        assert_eq!(butt.iter().filter(|&&event| event == PathEvent::Close).count(), 2);
+        assert_x_extent(&butt, (-1.0, 11.0));
+    }
+
+    #[test]
+    fn subpath_caps_override_the_style_for_open_subpaths_only() {
+        let mut path = square();
+        path.extend(line());
+        let events = stroke(&path, LineCap::Butt, vec![LineCap::Square, LineCap::Square]);
+        let line_start = events.iter().rposition(|event| {
+            match *event {
+                PathEvent::MoveTo(_) => true,
+                _ => false,
+            }
+        }).unwrap();
+        assert_eq!(&events[..line_start], &stroke(&square(), LineCap::Butt, vec![])[..]);
+        assert_x_extent(&events[line_start..], (-1.0, 11.0));
+    }
+}
@@ -0,0 +1,206 @@
+// pathfinder/path-utils/src/text_on_path.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Lays out runs of glyphs along paths, as for labels that follow roads on a map.
+//!
+//! The path is measured by arc length, and each glyph is rotated to follow the path's tangent at
+//! the middle of its advance. Glyphs don't bend, so tight curves relative to the glyph size will
+//! make neighboring glyphs overlap or spread apart.
+//!
+//! The transforms are rigid, so glyph coordinates must already have the same orientation as the
+//! path. For example, if the path is in a Y-down space, flip the glyph outlines first.
+
+use euclid::{Angle, Point2D, Transform2D, Vector2D};
+use lyon_geom::QuadraticBezierSegment;
+use lyon_path::PathEvent;
+use std::cmp::Ordering;
+
+use arcs::ArcToQuadraticTransformer;
+use cubic_to_quadratic::CubicToQuadraticTransformer;
+use flatten::FlattenedQuadratic;
+
+/// A glyph to lay out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PathGlyph {
+    /// The glyph's origin relative to the start of the run. X is the distance along the path,
+    /// and Y is the distance away from it, toward the left of the path's direction.
+    pub position: Point2D<f32>,
+    /// The glyph's horizontal advance.
+    pub advance: f32,
+}
+
+/// How to position a run along its path.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathTextAlignment {
+    /// The run starts this far along the path.
+    Start(f32),
+    /// The run is centered on the middle of the path.
+    Center,
+    /// The run ends at the end of the path.
+    End,
+}
+
+/// A path flattened into a polyline and measured by arc length.
+#[derive(Clone, Debug)]
+pub struct ArcLengthPath {
+    points: Vec<Point2D<f32>>,
+    // The distance along the path to each point.
+    distances: Vec<f32>,
+}
+
+impl ArcLengthPath {
+    /// Measures the first subpath of the path described by `events`, approximating curves with
+    /// lines to within `tolerance`. If the subpath is closed, its closing line is included.
+    pub fn new<I>(events: I, tolerance: f32) -> ArcLengthPath where I: Iterator<Item = PathEvent> {
+        let events = CubicToQuadraticTransformer::new(ArcToQuadraticTransformer::new(events,
+                                                                                     tolerance),
+                                                      tolerance);
+
+        let mut path = ArcLengthPath {
+            points: vec![],
+            distances: vec![],
+        };
+        for event in events {
+            match event {
+                PathEvent::MoveTo(_) if !path.points.is_empty() => break,
+                PathEvent::MoveTo(to) => path.push_point(&to),
+                PathEvent::LineTo(to) => path.push_point(&to),
+                PathEvent::QuadraticTo(ctrl, to) => {
+                    let from = match path.points.last() {
+                        None => Point2D::zero(),
+                        Some(&from) => from,
+                    };
+                    let segment = QuadraticBezierSegment { from: from, ctrl: ctrl, to: to };
+                    for point in FlattenedQuadratic::new(&segment, tolerance) {
+                        path.push_point(&point)
+                    }
+                }
+                PathEvent::Close => {
+                    if let Some(&first_point) = path.points.first() {
+                        path.push_point(&first_point)
+                    }
+                    break
+                }
+                PathEvent::CubicTo(..) | PathEvent::Arc(..) => {
+                    unreachable!("Cubics and arcs should have been converted to quadratics!")
+                }
+            }
+        }
+        path
+    }
+
+    fn push_point(&mut self, point: &Point2D<f32>) {
+        let distance = match (self.points.last(), self.distances.last()) {
+            (Some(last_point), Some(&last_distance)) => {
+                last_distance + (*point - *last_point).length()
+            }
+            _ => 0.0,
+        };
+        self.points.push(*point);
+        self.distances.push(distance);
+    }
+
+    /// Returns the total length of the path.
+    #[inline]
+    pub fn length(&self) -> f32 {
+        self.distances.last().cloned().unwrap_or(0.0)
+    }
+
+    /// Returns the same path, running in the opposite direction. This is useful for keeping
+    /// labels upright on paths that run right to left.
+    pub fn reversed(&self) -> ArcLengthPath {
+        let length = self.length();
+        ArcLengthPath {
+            points: self.points.iter().rev().cloned().collect(),
+            distances: self.distances.iter().rev().map(|distance| length - distance).collect(),
+        }
+    }
+
+    /// Returns the point `distance` along the path and the unit tangent there, or `None` if the
+    /// distance is off either end of the path or is NaN.
+    pub fn sample(&self, distance: f32) -> Option<(Point2D<f32>, Vector2D<f32>)> {
+        if self.points.len() < 2 || !(distance >= 0.0 && distance <= self.length()) {
+            return None
+        }
+
+        // Find the line containing the distance, skipping any of zero length.
+        let mut line_index = match self.distances.binary_search_by(|point_distance| {
+            point_distance.partial_cmp(&distance).unwrap_or(Ordering::Equal)
+        }) {
+            Ok(point_index) | Err(point_index) => point_index.max(1) - 1,
+        };
+        line_index = line_index.min(self.points.len() - 2);
+        while line_index + 2 < self.points.len() &&
+                self.distances[line_index + 1] <= self.distances[line_index] {
+            line_index += 1
+        }
+
+        let (from, to) = (self.points[line_index], self.points[line_index + 1]);
+        let line_length = self.distances[line_index + 1] - self.distances[line_index];
+        if line_length <= 0.0 {
+            return None
+        }
+        let t = (distance - self.distances[line_index]) / line_length;
+        Some((from.lerp(to, t), (to - from) / line_length))
+    }
+
+    /// Computes the transform that places each glyph along the path, or `None` for glyphs that
+    /// would fall off either end of it. `run_advance` is the total advance of the run.
+    pub fn layout(&self,
+                  glyphs: &[PathGlyph],
+                  run_advance: f32,
+                  alignment: PathTextAlignment)
+                  -> Vec<Option<Transform2D<f32>>> {
+        let start = match alignment {
+            PathTextAlignment::Start(start) => start,
+            PathTextAlignment::Center => (self.length() - run_advance) * 0.5,
+            PathTextAlignment::End => self.length() - run_advance,
+        };
+
+        glyphs.iter().map(|glyph| {
+            let half_advance = glyph.advance * 0.5;
+            let (point, tangent) = match self.sample(start + glyph.position.x + half_advance) {
+                None => return None,
+                Some(sample) => sample,
+            };
+            Some(Transform2D::create_translation(-half_advance, glyph.position.y)
+                             .post_rotate(&Angle::radians(tangent.y.atan2(tangent.x)))
+                             .post_translate(point.to_vector()))
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::Point2D;
+    use lyon_path::PathEvent;
+    use std::f32;
+
+    use super::ArcLengthPath;
+
+    #[test]
+    fn nan_distances_and_points_are_not_sampled() {
+        let line = vec![
+            PathEvent::MoveTo(Point2D::new(0.0, 0.0)),
+            PathEvent::LineTo(Point2D::new(10.0, 0.0)),
+        ];
+        let path = ArcLengthPath::new(line.into_iter(), 0.1);
+        assert_eq!(path.sample(5.0).map(|(point, _)| point), Some(Point2D::new(5.0, 0.0)));
+        assert_eq!(path.sample(f32::NAN), None);
+
+        let bad_line = vec![
+            PathEvent::MoveTo(Point2D::new(0.0, 0.0)),
+            PathEvent::LineTo(Point2D::new(f32::NAN, 0.0)),
+            PathEvent::LineTo(Point2D::new(10.0, 0.0)),
+        ];
+        let path = ArcLengthPath::new(bad_line.into_iter(), 0.1);
+        assert_eq!(path.sample(5.0), None);
+    }
+}
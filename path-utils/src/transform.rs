@@ -58,3 +58,39 @@ impl<I> Iterator for Transform2DPathIter<I> where I: Iterator<Item = PathEvent>
         }
     }
 }
+
+/// Returns the largest factor by which `transform` stretches any vector.
+///
+/// This is the largest singular value of the linear part of the transform; translation is
+/// ignored.
+pub fn max_scale_factor(transform: &Transform2D<f32>) -> f32 {
+    let (a, b, c, d) = (transform.m11, transform.m12, transform.m21, transform.m22);
+    let sum_of_squares = a * a + b * b + c * c + d * d;
+    let determinant = a * d - b * c;
+    let discriminant = (sum_of_squares * sum_of_squares - 4.0 * determinant * determinant).max(0.0);
+    ((sum_of_squares + discriminant.sqrt()) * 0.5).sqrt()
+}
+
+/// Converts an approximation tolerance in device space to the local space of a path that is
+/// drawn with `transform`, so that approximations made in local space are accurate to
+/// `device_tolerance` on screen.
+///
+/// If `transform` is degenerate, `device_tolerance` is returned unchanged.
+pub fn local_tolerance(device_tolerance: f32, transform: &Transform2D<f32>) -> f32 {
+    let scale_factor = max_scale_factor(transform);
+    if scale_factor > 0.0 && scale_factor.is_finite() {
+        device_tolerance / scale_factor
+    } else {
+        device_tolerance
+    }
+}
+
+/// Like `local_tolerance()`, but for a chain of transforms applied in order from local space to
+/// device space.
+pub fn local_tolerance_for_chain<'a, I>(device_tolerance: f32, transforms: I) -> f32
+                                        where I: IntoIterator<Item = &'a Transform2D<f32>> {
+    let transform = transforms.into_iter().fold(Transform2D::identity(), |composed, transform| {
+        composed.post_mul(transform)
+    });
+    local_tolerance(device_tolerance, &transform)
+}
@@ -0,0 +1,120 @@
+// pathfinder/path-utils/src/units.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Converts paths authored in physical units (points, millimeters, inches) to device pixels.
+//!
+//! Build a `UnitContext` for the display, then transform each path with
+//! `transform::Transform2DPathIter` and the context's `transform()` before partitioning it. Use
+//! `transform::local_tolerance()` with the same transform to keep the approximation tolerance
+//! constant on screen.
+
+use euclid::Transform2D;
+
+/// The CSS reference resolution, at which one CSS pixel is 1/96 inch.
+pub const CSS_PIXELS_PER_INCH: f32 = 96.0;
+
+const POINTS_PER_INCH: f32 = 72.0;
+const MILLIMETERS_PER_INCH: f32 = 25.4;
+
+/// A unit of length.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum Unit {
+    /// Device pixels. These are never scaled.
+    DevicePixel,
+    /// CSS pixels: 1/96 inch, at the context's scale factor.
+    CssPixel,
+    /// Typographic points: 1/72 inch.
+    Point,
+    Millimeter,
+    Inch,
+}
+
+/// A distance in a particular unit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct Length {
+    pub value: f32,
+    pub unit: Unit,
+}
+
+impl Length {
+    #[inline]
+    pub fn new(value: f32, unit: Unit) -> Length {
+        Length {
+            value: value,
+            unit: unit,
+        }
+    }
+}
+
+/// Describes a display, so that physical lengths can be converted to its device pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct UnitContext {
+    /// The number of device pixels per physical inch.
+    pub device_pixels_per_inch: f32,
+    /// The number of device pixels per CSS pixel. This is usually the platform's scale factor
+    /// (`devicePixelRatio` on the Web) and need not match the physical resolution.
+    pub device_pixels_per_css_pixel: f32,
+}
+
+impl UnitContext {
+    /// Creates a context for a display of the given physical resolution, with CSS pixels sized
+    /// to match it.
+    #[inline]
+    pub fn from_dpi(device_pixels_per_inch: f32) -> UnitContext {
+        UnitContext {
+            device_pixels_per_inch: device_pixels_per_inch,
+            device_pixels_per_css_pixel: device_pixels_per_inch / CSS_PIXELS_PER_INCH,
+        }
+    }
+
+    /// Creates a context from a platform scale factor, assuming the nominal 96 CSS pixels per
+    /// inch. This is the best guess available when the display's physical size is unknown.
+    #[inline]
+    pub fn from_scale_factor(device_pixels_per_css_pixel: f32) -> UnitContext {
+        UnitContext {
+            device_pixels_per_inch: device_pixels_per_css_pixel * CSS_PIXELS_PER_INCH,
+            device_pixels_per_css_pixel: device_pixels_per_css_pixel,
+        }
+    }
+
+    /// Returns the number of device pixels in one `unit`.
+    pub fn device_pixels_per_unit(&self, unit: Unit) -> f32 {
+        match unit {
+            Unit::DevicePixel => 1.0,
+            Unit::CssPixel => self.device_pixels_per_css_pixel,
+            Unit::Point => self.device_pixels_per_inch / POINTS_PER_INCH,
+            Unit::Millimeter => self.device_pixels_per_inch / MILLIMETERS_PER_INCH,
+            Unit::Inch => self.device_pixels_per_inch,
+        }
+    }
+
+    /// Converts the length to device pixels.
+    #[inline]
+    pub fn to_device_pixels(&self, length: &Length) -> f32 {
+        length.value * self.device_pixels_per_unit(length.unit)
+    }
+
+    /// Converts a length in device pixels to `unit`.
+    #[inline]
+    pub fn from_device_pixels(&self, device_pixels: f32, unit: Unit) -> Length {
+        Length::new(device_pixels / self.device_pixels_per_unit(unit), unit)
+    }
+
+    /// Returns the transform that scales a path whose coordinates are in `unit` to device
+    /// pixels.
+    #[inline]
+    pub fn transform(&self, unit: Unit) -> Transform2D<f32> {
+        let scale = self.device_pixels_per_unit(unit);
+        Transform2D::create_scale(scale, scale)
+    }
+}
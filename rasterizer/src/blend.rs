@@ -0,0 +1,195 @@
+// pathfinder/rasterizer/src/blend.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Porter-Duff compositing operators and separable blend modes, as defined by the W3C
+//! Compositing and Blending specification and used by SVG and the HTML canvas.
+//!
+//! All colors here are premultiplied.
+
+use raster::Color;
+
+/// How a fill is combined with what's already on the canvas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    Clear,
+    Copy,
+    SourceOver,
+    DestinationOver,
+    SourceIn,
+    DestinationIn,
+    SourceOut,
+    DestinationOut,
+    SourceAtop,
+    DestinationAtop,
+    Xor,
+    /// Adds the source and destination, saturating.
+    Lighter,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+}
+
+impl Default for BlendMode {
+    #[inline]
+    fn default() -> BlendMode {
+        BlendMode::SourceOver
+    }
+}
+
+impl BlendMode {
+    /// Returns true if this mode only ever reads the canvas through the destination alpha and
+    /// color terms of Porter-Duff compositing. The others need the destination color itself,
+    /// which a GPU renderer can only get by drawing into an intermediate layer.
+    pub fn is_porter_duff(self) -> bool {
+        match self {
+            BlendMode::Clear | BlendMode::Copy | BlendMode::SourceOver |
+            BlendMode::DestinationOver | BlendMode::SourceIn | BlendMode::DestinationIn |
+            BlendMode::SourceOut | BlendMode::DestinationOut | BlendMode::SourceAtop |
+            BlendMode::DestinationAtop | BlendMode::Xor | BlendMode::Lighter => true,
+            BlendMode::Multiply | BlendMode::Screen | BlendMode::Overlay | BlendMode::Darken |
+            BlendMode::Lighten | BlendMode::ColorDodge | BlendMode::ColorBurn |
+            BlendMode::HardLight | BlendMode::SoftLight | BlendMode::Difference |
+            BlendMode::Exclusion => false,
+        }
+    }
+
+    /// Combines a premultiplied source color with a premultiplied destination color.
+    #[inline]
+    pub fn blend(self, source: Color, destination: Color) -> Color {
+        to_color(self.blend_float(to_float(source), to_float(destination)))
+    }
+
+    /// Like `blend()`, but on premultiplied colors with channels from 0 to 1.
+    pub fn blend_float(self, source: [f32; 4], destination: [f32; 4]) -> [f32; 4] {
+        let (source_alpha, destination_alpha) = (source[3], destination[3]);
+
+        let porter_duff = |source_factor: f32, destination_factor: f32| {
+            let mut result = [0.0; 4];
+            for channel in 0..4 {
+                result[channel] = source[channel] * source_factor +
+                    destination[channel] * destination_factor
+            }
+            result
+        };
+
+        match self {
+            BlendMode::Clear => [0.0; 4],
+            BlendMode::Copy => source,
+            BlendMode::SourceOver => porter_duff(1.0, 1.0 - source_alpha),
+            BlendMode::DestinationOver => porter_duff(1.0 - destination_alpha, 1.0),
+            BlendMode::SourceIn => porter_duff(destination_alpha, 0.0),
+            BlendMode::DestinationIn => porter_duff(0.0, source_alpha),
+            BlendMode::SourceOut => porter_duff(1.0 - destination_alpha, 0.0),
+            BlendMode::DestinationOut => porter_duff(0.0, 1.0 - source_alpha),
+            BlendMode::SourceAtop => porter_duff(destination_alpha, 1.0 - source_alpha),
+            BlendMode::DestinationAtop => porter_duff(1.0 - destination_alpha, source_alpha),
+            BlendMode::Xor => porter_duff(1.0 - destination_alpha, 1.0 - source_alpha),
+            BlendMode::Lighter => porter_duff(1.0, 1.0),
+            _ => {
+                // Separable blend modes, composited with source-over.
+                let mut result = [0.0; 4];
+                for channel in 0..3 {
+                    let source_color = unpremultiply(source[channel], source_alpha);
+                    let destination_color = unpremultiply(destination[channel],
+                                                          destination_alpha);
+                    let blended = self.blend_channel(source_color, destination_color);
+                    result[channel] = (1.0 - destination_alpha) * source[channel] +
+                        (1.0 - source_alpha) * destination[channel] +
+                        source_alpha * destination_alpha * blended
+                }
+                result[3] = source_alpha + destination_alpha - source_alpha * destination_alpha;
+                result
+            }
+        }
+    }
+
+    // The blend function B(Cs, Cd) of the specification, on unpremultiplied channels.
+    fn blend_channel(self, source: f32, destination: f32) -> f32 {
+        match self {
+            BlendMode::Multiply => source * destination,
+            BlendMode::Screen => screen(source, destination),
+            BlendMode::Overlay => hard_light(destination, source),
+            BlendMode::Darken => source.min(destination),
+            BlendMode::Lighten => source.max(destination),
+            BlendMode::ColorDodge => {
+                if destination <= 0.0 {
+                    0.0
+                } else if source >= 1.0 {
+                    1.0
+                } else {
+                    (destination / (1.0 - source)).min(1.0)
+                }
+            }
+            BlendMode::ColorBurn => {
+                if destination >= 1.0 {
+                    1.0
+                } else if source <= 0.0 {
+                    0.0
+                } else {
+                    1.0 - ((1.0 - destination) / source).min(1.0)
+                }
+            }
+            BlendMode::HardLight => hard_light(source, destination),
+            BlendMode::SoftLight => {
+                if source <= 0.5 {
+                    destination - (1.0 - 2.0 * source) * destination * (1.0 - destination)
+                } else {
+                    let d = if destination <= 0.25 {
+                        ((16.0 * destination - 12.0) * destination + 4.0) * destination
+                    } else {
+                        destination.sqrt()
+                    };
+                    destination + (2.0 * source - 1.0) * (d - destination)
+                }
+            }
+            BlendMode::Difference => (source - destination).abs(),
+            BlendMode::Exclusion => source + destination - 2.0 * source * destination,
+            _ => source,
+        }
+    }
+}
+
+fn screen(source: f32, destination: f32) -> f32 {
+    source + destination - source * destination
+}
+
+fn hard_light(source: f32, destination: f32) -> f32 {
+    if source <= 0.5 {
+        destination * 2.0 * source
+    } else {
+        screen(destination, 2.0 * source - 1.0)
+    }
+}
+
+fn unpremultiply(channel: f32, alpha: f32) -> f32 {
+    if alpha <= 0.0 { 0.0 } else { (channel / alpha).min(1.0) }
+}
+
+fn to_float(color: Color) -> [f32; 4] {
+    [
+        color[0] as f32 / 255.0,
+        color[1] as f32 / 255.0,
+        color[2] as f32 / 255.0,
+        color[3] as f32 / 255.0,
+    ]
+}
+
+fn to_color(color: [f32; 4]) -> Color {
+    let channel = |value: f32| (value.max(0.0).min(1.0) * 255.0).round() as u8;
+    [channel(color[0]), channel(color[1]), channel(color[2]), channel(color[3])]
+}
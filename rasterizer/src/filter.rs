@@ -0,0 +1,133 @@
+// pathfinder/rasterizer/src/filter.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Image filters for layers: Gaussian blur and drop shadows, as in CSS and SVG.
+//!
+//! Filters operate on premultiplied RGBA buffers. Pixels past the edges of the buffer are
+//! treated as transparent.
+
+use euclid::{Size2D, Vector2D};
+
+use raster::{self, Color};
+
+/// Kernels are cut off at this many standard deviations, past which the weights are negligible.
+const KERNEL_EXTENT: f32 = 3.0;
+
+/// An effect applied to a layer before it's composited.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    /// A Gaussian blur with the given standard deviation, in pixels.
+    Blur(f32),
+    /// A blurred, tinted copy of the layer's alpha, drawn underneath it.
+    DropShadow {
+        /// How far the shadow is shifted from the layer, in pixels.
+        offset: Vector2D<i32>,
+        /// The standard deviation of the shadow's blur, in pixels.
+        std_deviation: f32,
+        /// The color of the shadow, not premultiplied.
+        color: Color,
+    },
+}
+
+impl Filter {
+    /// Applies the filter to a premultiplied RGBA buffer of the given size.
+    pub fn apply(&self, pixels: &mut [u8], size: &Size2D<u32>) {
+        match *self {
+            Filter::Blur(std_deviation) => gaussian_blur(pixels, size, std_deviation),
+            Filter::DropShadow { offset, std_deviation, color } => {
+                let mut shadow = vec![0; pixels.len()];
+                let color = raster::premultiply(color);
+                let (width, height) = (size.width as i32, size.height as i32);
+                for y in 0..height {
+                    for x in 0..width {
+                        let (source_x, source_y) = (x - offset.x, y - offset.y);
+                        if source_x < 0 || source_x >= width || source_y < 0 ||
+                                source_y >= height {
+                            continue
+                        }
+                        let alpha = pixels[pixel_offset(source_x, source_y, size) + 3];
+                        let shadow_offset = pixel_offset(x, y, size);
+                        for channel in 0..4 {
+                            let value = raster::multiply(color[channel], alpha);
+                            shadow[shadow_offset + channel] = value
+                        }
+                    }
+                }
+                gaussian_blur(&mut shadow, size, std_deviation);
+
+                // Draw the layer over its shadow.
+                for (pixel, shadow) in pixels.chunks_mut(4).zip(shadow.chunks(4)) {
+                    let inverse_alpha = 255 - pixel[3];
+                    for channel in 0..4 {
+                        let shadow = raster::multiply(shadow[channel], inverse_alpha);
+                        pixel[channel] = pixel[channel].saturating_add(shadow)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Blurs a premultiplied RGBA buffer in place with a Gaussian of the given standard deviation,
+/// as a horizontal pass followed by a vertical one.
+pub fn gaussian_blur(pixels: &mut [u8], size: &Size2D<u32>, std_deviation: f32) {
+    if !(std_deviation > 0.0) {
+        return
+    }
+
+    let radius = (std_deviation * KERNEL_EXTENT).ceil() as i32;
+    let mut kernel: Vec<f32> = (-radius..(radius + 1)).map(|offset| {
+        let offset = offset as f32;
+        (-offset * offset / (2.0 * std_deviation * std_deviation)).exp()
+    }).collect();
+    let kernel_sum: f32 = kernel.iter().sum();
+    for weight in &mut kernel {
+        *weight /= kernel_sum
+    }
+
+    let mut scratch = vec![0; pixels.len()];
+    blur_pass(pixels, &mut scratch, size, &kernel, Vector2D::new(1, 0));
+    blur_pass(&scratch, pixels, size, &kernel, Vector2D::new(0, 1));
+}
+
+fn blur_pass(source: &[u8],
+             destination: &mut [u8],
+             size: &Size2D<u32>,
+             kernel: &[f32],
+             direction: Vector2D<i32>) {
+    let radius = (kernel.len() / 2) as i32;
+    let (width, height) = (size.width as i32, size.height as i32);
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0.0; 4];
+            for (tap, &weight) in kernel.iter().enumerate() {
+                let tap = tap as i32 - radius;
+                let (tap_x, tap_y) = (x + direction.x * tap, y + direction.y * tap);
+                if tap_x < 0 || tap_x >= width || tap_y < 0 || tap_y >= height {
+                    continue
+                }
+                let offset = pixel_offset(tap_x, tap_y, size);
+                for channel in 0..4 {
+                    sum[channel] += source[offset + channel] as f32 * weight
+                }
+            }
+
+            let offset = pixel_offset(x, y, size);
+            for channel in 0..4 {
+                destination[offset + channel] = sum[channel].round().max(0.0).min(255.0) as u8
+            }
+        }
+    }
+}
+
+#[inline]
+fn pixel_offset(x: i32, y: i32, size: &Size2D<u32>) -> usize {
+    (y as usize * size.width as usize + x as usize) * 4
+}
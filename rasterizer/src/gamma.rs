@@ -0,0 +1,104 @@
+// pathfinder/rasterizer/src/gamma.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Conversions between sRGB and linear light, and the coverage adjustment that makes
+//! antialiased text look as heavy as platform rasterizers draw it.
+//!
+//! Blending sRGB-encoded values directly, as most renderers do by default, makes antialiased
+//! edges too dark over light backgrounds and too light over dark ones. Blending in linear light
+//! fixes this but makes text look thinner than people are used to, which `TextGamma`
+//! compensates for.
+
+use raster::Color;
+
+/// Adjusts the coverage of solid-colored fills, to tune how heavy text looks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextGamma {
+    /// How much darker edges of dark fills, and lighter edges of light fills, get. 1 leaves
+    /// coverage alone; around 1.8 to 2.2 approximates platform text rendering.
+    pub gamma: f32,
+    /// Sharpens partially covered pixels, from 0 for no change to 1.
+    pub contrast: f32,
+}
+
+impl Default for TextGamma {
+    #[inline]
+    fn default() -> TextGamma {
+        TextGamma {
+            gamma: 1.8,
+            contrast: 0.2,
+        }
+    }
+}
+
+impl TextGamma {
+    /// Adjusts `coverage` for a fill whose (unpremultiplied, sRGB) color is `color`.
+    pub fn adjust(&self, coverage: u8, color: Color) -> u8 {
+        if coverage == 0 || coverage == 255 {
+            return coverage
+        }
+
+        let coverage = coverage as f32 / 255.0;
+        let coverage = coverage + self.contrast * coverage * (1.0 - coverage);
+
+        // Dark fills get heavier and light fills get lighter, scaling smoothly in between.
+        let luminance = luminance(color);
+        let gamma = self.gamma.max(1.0);
+        let exponent = (1.0 / gamma) + (gamma - 1.0 / gamma) * luminance;
+        (coverage.max(0.0).min(1.0).powf(exponent) * 255.0).round() as u8
+    }
+}
+
+/// Converts an sRGB-encoded channel from 0 to 1 to linear light.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a linear-light channel from 0 to 1 to sRGB encoding.
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Converts a premultiplied sRGB color to premultiplied linear light, with channels from 0 to 1.
+pub fn premultiplied_to_linear(color: Color) -> [f32; 4] {
+    let alpha = color[3] as f32 / 255.0;
+    if alpha <= 0.0 {
+        return [0.0; 4]
+    }
+    let channel = |value: u8| srgb_to_linear((value as f32 / 255.0 / alpha).min(1.0)) * alpha;
+    [channel(color[0]), channel(color[1]), channel(color[2]), alpha]
+}
+
+/// Converts a premultiplied linear-light color back to premultiplied sRGB.
+pub fn linear_to_premultiplied(color: [f32; 4]) -> Color {
+    let alpha = color[3].max(0.0).min(1.0);
+    if alpha <= 0.0 {
+        return [0; 4]
+    }
+    let channel = |value: f32| {
+        let value = linear_to_srgb((value / alpha).max(0.0).min(1.0)) * alpha;
+        (value * 255.0).round() as u8
+    };
+    [channel(color[0]), channel(color[1]), channel(color[2]), (alpha * 255.0).round() as u8]
+}
+
+// Returns the relative luminance of an sRGB color, from 0 to 1.
+fn luminance(color: Color) -> f32 {
+    let channel = |value: u8| srgb_to_linear(value as f32 / 255.0);
+    0.2126 * channel(color[0]) + 0.7152 * channel(color[1]) + 0.0722 * channel(color[2])
+}
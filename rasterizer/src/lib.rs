@@ -0,0 +1,29 @@
+// pathfinder/rasterizer/src/lib.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Draws meshes from `pathfinder_partitioner` into RGBA buffers on the CPU.
+//!
+//! `raster::Canvas` is the rasterizer itself. The other modules describe what it fills with and
+//! how: paints, blend modes, layer filters, text gamma, and gradient meshes that become image
+//! patterns. `overlay` draws partitioner debug views over a finished canvas.
+
+extern crate euclid;
+extern crate lyon_path;
+extern crate pathfinder_partitioner;
+
+use lyon_path::geom as lyon_geom;
+
+pub mod blend;
+pub mod filter;
+pub mod gamma;
+pub mod overlay;
+pub mod paint;
+pub mod patch;
+pub mod raster;
@@ -0,0 +1,279 @@
+// pathfinder/rasterizer/src/overlay.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Draws the B-quads of the meshes in a frame over a software canvas, for seeing how paths were
+//! partitioned without exporting them. `pathfinder_partitioner::debug`, behind that crate's
+//! `debug-export` feature, writes the same geometry to files.
+
+use euclid::{Point2D, Rect, Size2D, Vector2D};
+use lyon_geom::QuadraticBezierSegment;
+use pathfinder_partitioner::BQuadVertexPositions;
+use pathfinder_partitioner::mesh::Mesh;
+use std::cmp;
+use std::f32;
+
+use raster::{Canvas, Color};
+
+/// The width and height of the tiles that the heatmap counts B-quads in, in pixels.
+pub const DEBUG_TILE_SIZE: u32 = 16;
+
+/// How many line segments each curve of a B-quad is flattened into for the wireframe.
+const WIREFRAME_CURVE_SEGMENTS: u32 = 8;
+
+const WIREFRAME_COLOR: Color = [0, 192, 0, 255];
+const CONTROL_POLYGON_COLOR: Color = [96, 96, 96, 128];
+const VERTEX_COLOR: Color = [255, 255, 255, 255];
+const CONTROL_POINT_COLOR: Color = [255, 0, 255, 255];
+/// The opacity of the heatmap, from 0 to 255.
+const HEATMAP_ALPHA: u8 = 96;
+
+/// Which views `DebugOverlay` draws.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DebugOverlayViews {
+    /// The outline of each B-quad: its upper and lower curves and the vertical lines joining
+    /// them.
+    pub wireframe: bool,
+    /// The vertices of each B-quad in white and its control points in magenta, joined by its
+    /// control polygons.
+    pub control_points: bool,
+    /// A tint over each `DEBUG_TILE_SIZE` square, from transparent blue where few B-quads touch
+    /// it to red where the most do.
+    pub tile_heatmap: bool,
+}
+
+/// Collects the B-quads of the meshes drawn in a frame and draws them over the finished frame.
+///
+/// Push each mesh as it's filled, then call `draw()` once drawing is done. The overlay is drawn
+/// straight into the canvas's pixels, ignoring its clip, blend mode, viewport, and scissor rect,
+/// so the canvas shouldn't have any layers open.
+#[derive(Clone, Debug, Default)]
+pub struct DebugOverlay {
+    views: DebugOverlayViews,
+    // The positions of the B-quads pushed so far, in canvas pixels.
+    b_quads: Vec<BQuadVertexPositions>,
+}
+
+impl DebugOverlay {
+    #[inline]
+    pub fn new(views: DebugOverlayViews) -> DebugOverlay {
+        DebugOverlay {
+            views: views,
+            b_quads: vec![],
+        }
+    }
+
+    #[inline]
+    pub fn views(&self) -> DebugOverlayViews {
+        self.views
+    }
+
+    #[inline]
+    pub fn set_views(&mut self, views: DebugOverlayViews) {
+        self.views = views
+    }
+
+    /// Records the B-quads of a mesh that was filled at `offset`, as `Canvas::fill_mesh()` takes
+    /// it.
+    pub fn push_mesh(&mut self, mesh: &Mesh, offset: &Vector2D<i32>) {
+        let offset = offset.to_f32();
+        self.b_quads.extend(mesh.b_quad_vertex_positions.iter().map(|positions| {
+            BQuadVertexPositions {
+                upper_left_vertex_position: positions.upper_left_vertex_position + offset,
+                upper_control_point_position: positions.upper_control_point_position + offset,
+                upper_right_vertex_position: positions.upper_right_vertex_position + offset,
+                lower_right_vertex_position: positions.lower_right_vertex_position + offset,
+                lower_control_point_position: positions.lower_control_point_position + offset,
+                lower_left_vertex_position: positions.lower_left_vertex_position + offset,
+            }
+        }))
+    }
+
+    /// Returns the number of B-quads pushed since the last `clear()`.
+    #[inline]
+    pub fn b_quad_count(&self) -> usize {
+        self.b_quads.len()
+    }
+
+    /// Forgets the meshes pushed so far, to start the next frame.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.b_quads.clear()
+    }
+
+    /// Draws the enabled views over the contents of `canvas`.
+    pub fn draw(&self, canvas: &mut Canvas) {
+        if self.views.tile_heatmap {
+            self.draw_tile_heatmap(canvas)
+        }
+        if self.views.control_points {
+            for positions in &self.b_quads {
+                let (ul, uc, ur) = (positions.upper_left_vertex_position,
+                                    positions.upper_control_point_position,
+                                    positions.upper_right_vertex_position);
+                let (lr, lc, ll) = (positions.lower_right_vertex_position,
+                                    positions.lower_control_point_position,
+                                    positions.lower_left_vertex_position);
+                for &(from, to) in &[(ul, uc), (uc, ur), (lr, lc), (lc, ll)] {
+                    draw_line(canvas, &from, &to, CONTROL_POLYGON_COLOR)
+                }
+            }
+        }
+        if self.views.wireframe {
+            for positions in &self.b_quads {
+                draw_curve(canvas,
+                           &positions.upper_left_vertex_position,
+                           &positions.upper_control_point_position,
+                           &positions.upper_right_vertex_position);
+                draw_curve(canvas,
+                           &positions.lower_left_vertex_position,
+                           &positions.lower_control_point_position,
+                           &positions.lower_right_vertex_position);
+                draw_line(canvas,
+                          &positions.upper_left_vertex_position,
+                          &positions.lower_left_vertex_position,
+                          WIREFRAME_COLOR);
+                draw_line(canvas,
+                          &positions.upper_right_vertex_position,
+                          &positions.lower_right_vertex_position,
+                          WIREFRAME_COLOR);
+            }
+        }
+        if self.views.control_points {
+            for positions in &self.b_quads {
+                for point in &[
+                    positions.upper_left_vertex_position,
+                    positions.upper_right_vertex_position,
+                    positions.lower_right_vertex_position,
+                    positions.lower_left_vertex_position,
+                ] {
+                    draw_dot(canvas, point, VERTEX_COLOR)
+                }
+                draw_dot(canvas, &positions.upper_control_point_position, CONTROL_POINT_COLOR);
+                draw_dot(canvas, &positions.lower_control_point_position, CONTROL_POINT_COLOR);
+            }
+        }
+    }
+
+    fn draw_tile_heatmap(&self, canvas: &mut Canvas) {
+        let size = canvas.size();
+        let tiles_across = (size.width + DEBUG_TILE_SIZE - 1) / DEBUG_TILE_SIZE;
+        let tiles_down = (size.height + DEBUG_TILE_SIZE - 1) / DEBUG_TILE_SIZE;
+        if tiles_across == 0 || tiles_down == 0 {
+            return
+        }
+
+        // Count each B-quad in every tile its bounding box touches.
+        let mut counts = vec![0u32; tiles_across as usize * tiles_down as usize];
+        let tile_size = DEBUG_TILE_SIZE as f32;
+        for positions in &self.b_quads {
+            let bounds = Rect::from_points([
+                positions.upper_left_vertex_position,
+                positions.upper_control_point_position,
+                positions.upper_right_vertex_position,
+                positions.lower_right_vertex_position,
+                positions.lower_control_point_position,
+                positions.lower_left_vertex_position,
+            ].iter());
+            let (min, max) = (bounds.origin, bounds.bottom_right());
+            if max.x < 0.0 || max.y < 0.0 || min.x >= size.width as f32 ||
+                    min.y >= size.height as f32 {
+                continue
+            }
+            let first_column = (min.x / tile_size).floor().max(0.0) as u32;
+            let first_row = (min.y / tile_size).floor().max(0.0) as u32;
+            let last_column = cmp::min((max.x / tile_size).floor() as u32, tiles_across - 1);
+            let last_row = cmp::min((max.y / tile_size).floor() as u32, tiles_down - 1);
+            for row in first_row..(last_row + 1) {
+                for column in first_column..(last_column + 1) {
+                    counts[(row * tiles_across + column) as usize] += 1
+                }
+            }
+        }
+
+        let max_count = counts.iter().cloned().max().unwrap_or(0);
+        if max_count == 0 {
+            return
+        }
+        for row in 0..tiles_down {
+            for column in 0..tiles_across {
+                let count = counts[(row * tiles_across + column) as usize];
+                if count == 0 {
+                    continue
+                }
+                let heat = count as f32 / max_count as f32;
+                let color = [(heat * 255.0) as u8, 0, ((1.0 - heat) * 255.0) as u8, HEATMAP_ALPHA];
+                let origin = Point2D::new(column * DEBUG_TILE_SIZE, row * DEBUG_TILE_SIZE);
+                let tile_size = Size2D::new(cmp::min(DEBUG_TILE_SIZE, size.width - origin.x),
+                                            cmp::min(DEBUG_TILE_SIZE, size.height - origin.y));
+                for y in origin.y..(origin.y + tile_size.height) {
+                    for x in origin.x..(origin.x + tile_size.width) {
+                        blend_pixel(canvas, x as i32, y as i32, color)
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn draw_curve(canvas: &mut Canvas,
+              from: &Point2D<f32>,
+              ctrl: &Point2D<f32>,
+              to: &Point2D<f32>) {
+    let curve = QuadraticBezierSegment {
+        from: *from,
+        ctrl: *ctrl,
+        to: *to,
+    };
+    let mut last_point = *from;
+    for segment_index in 1..(WIREFRAME_CURVE_SEGMENTS + 1) {
+        let point = curve.sample(segment_index as f32 / WIREFRAME_CURVE_SEGMENTS as f32);
+        draw_line(canvas, &last_point, &point, WIREFRAME_COLOR);
+        last_point = point;
+    }
+}
+
+// Draws a line one pixel wide by stepping along its longer axis.
+fn draw_line(canvas: &mut Canvas, from: &Point2D<f32>, to: &Point2D<f32>, color: Color) {
+    let vector = *to - *from;
+    let step_count = f32::max(vector.x.abs(), vector.y.abs()).ceil().max(1.0);
+    if !step_count.is_finite() {
+        return
+    }
+    for step in 0..(step_count as u32 + 1) {
+        let point = *from + vector * (step as f32 / step_count);
+        blend_pixel(canvas, point.x.floor() as i32, point.y.floor() as i32, color)
+    }
+}
+
+// Draws a 3×3 pixel square centered on the point.
+fn draw_dot(canvas: &mut Canvas, point: &Point2D<f32>, color: Color) {
+    let (x, y) = (point.x.floor() as i32, point.y.floor() as i32);
+    for offset_y in -1..2 {
+        for offset_x in -1..2 {
+            blend_pixel(canvas, x + offset_x, y + offset_y, color)
+        }
+    }
+}
+
+// Composites an unpremultiplied color over one pixel, if it's inside the canvas.
+fn blend_pixel(canvas: &mut Canvas, x: i32, y: i32, color: Color) {
+    let size = canvas.size();
+    if x < 0 || y < 0 || x >= size.width as i32 || y >= size.height as i32 {
+        return
+    }
+    let index = (y as usize * size.width as usize + x as usize) * 4;
+    let pixel = &mut canvas.pixels_mut()[index..(index + 4)];
+    let alpha = color[3] as u32;
+    for channel in 0..3 {
+        let source = color[channel] as u32 * alpha / 255;
+        pixel[channel] = (source + pixel[channel] as u32 * (255 - alpha) / 255) as u8;
+    }
+    pixel[3] = (alpha + pixel[3] as u32 * (255 - alpha) / 255) as u8;
+}
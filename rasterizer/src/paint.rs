@@ -0,0 +1,306 @@
+// pathfinder/rasterizer/src/paint.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Paints that meshes can be filled with: flat colors, gradients, and image patterns.
+//!
+//! Gradients are evaluated through a *ramp*, a one-dimensional table of premultiplied colors
+//! sampled evenly from the stops. Only the software canvas in `raster` draws paints; the demo
+//! client's WebGL renderer still fills with flat colors.
+
+use euclid::{Point2D, Rect, Size2D, Transform2D};
+use std::f32;
+use std::sync::Arc;
+
+use raster::Color;
+
+/// The number of entries in the ramps the software canvas builds for gradients.
+pub const GRADIENT_RAMP_SIZE: usize = 256;
+
+/// What to fill a mesh with.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Paint {
+    Color(Color),
+    Gradient(Gradient),
+    Pattern(Pattern),
+}
+
+/// How a gradient continues past the ends of its stops.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpreadMode {
+    /// The end colors extend forever.
+    Pad,
+    /// The gradient starts over.
+    Repeat,
+    /// The gradient runs back and forth.
+    Reflect,
+}
+
+/// The shape of a gradient, in canvas pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientGeometry {
+    /// The gradient runs along the line from `from` to `to`.
+    Linear {
+        from: Point2D<f32>,
+        to: Point2D<f32>,
+    },
+    /// The gradient runs outward from `center` to the circle of `radius` around it.
+    Radial {
+        center: Point2D<f32>,
+        radius: f32,
+    },
+    /// The gradient sweeps once clockwise around `center`, starting from the direction `angle`
+    /// radians clockwise from straight up, as in CSS `conic-gradient()`.
+    ///
+    /// `center` and `angle` are in gradient space, which `inverse_transform` maps canvas pixels
+    /// into. It's the identity for a gradient defined in canvas pixels; otherwise it keeps the
+    /// sweep correct under skews and non-uniform scales, which no single center and angle can.
+    Conic {
+        center: Point2D<f32>,
+        angle: f32,
+        inverse_transform: Transform2D<f32>,
+    },
+}
+
+/// One color in a gradient.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GradientStop {
+    /// The position of the stop along the gradient, from 0 to 1.
+    pub offset: f32,
+    pub color: Color,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub geometry: GradientGeometry,
+    /// The stops, sorted by offset.
+    pub stops: Vec<GradientStop>,
+    pub spread: SpreadMode,
+}
+
+impl Gradient {
+    /// Returns the position along the gradient of `point`, from 0 to 1, with the spread mode
+    /// applied.
+    pub fn position_at(&self, point: &Point2D<f32>) -> f32 {
+        let position = match self.geometry {
+            GradientGeometry::Linear { from, to } => {
+                let direction = to - from;
+                let length_squared = direction.square_length();
+                if length_squared == 0.0 {
+                    0.0
+                } else {
+                    (*point - from).dot(direction) / length_squared
+                }
+            }
+            GradientGeometry::Radial { center, radius } => {
+                if radius <= 0.0 {
+                    1.0
+                } else {
+                    (*point - center).length() / radius
+                }
+            }
+            GradientGeometry::Conic { center, angle, ref inverse_transform } => {
+                // Y points down, so "up" is -Y and clockwise runs from there toward +X.
+                let vector = inverse_transform.transform_point(point) - center;
+                let turns = (vector.x.atan2(-vector.y) - angle) / (f32::consts::PI * 2.0);
+                turns - turns.floor()
+            }
+        };
+
+        match self.spread {
+            SpreadMode::Pad => position.max(0.0).min(1.0),
+            SpreadMode::Repeat => position - position.floor(),
+            SpreadMode::Reflect => {
+                let position = (position * 0.5 - (position * 0.5).floor()) * 2.0;
+                if position > 1.0 { 2.0 - position } else { position }
+            }
+        }
+    }
+
+    /// Renders the gradient into an image covering `bounds`, one pixel per canvas pixel, for
+    /// output formats that can't describe the geometry themselves.
+    pub fn to_image(&self, bounds: &Rect<i32>) -> Image {
+        let ramp = self.ramp(GRADIENT_RAMP_SIZE);
+        let size = Size2D::new(bounds.size.width.max(0) as u32, bounds.size.height.max(0) as u32);
+        let mut pixels = Vec::with_capacity(size.width as usize * size.height as usize * 4);
+        for y in 0..size.height {
+            for x in 0..size.width {
+                let point = Point2D::new((bounds.origin.x + x as i32) as f32 + 0.5,
+                                         (bounds.origin.y + y as i32) as f32 + 0.5);
+                let position = self.position_at(&point);
+                pixels.extend_from_slice(&ramp[(position * (ramp.len() - 1) as f32).round() as
+                                               usize]);
+            }
+        }
+        Image {
+            size: size,
+            pixels: pixels,
+        }
+    }
+
+    /// Samples the stops at `size` evenly spaced positions from 0 to 1, producing premultiplied
+    /// colors. Colors are interpolated in premultiplied space, so transparent stops don't darken
+    /// their neighbors.
+    pub fn ramp(&self, size: usize) -> Vec<Color> {
+        let stops: Vec<(f32, [f32; 4])> = self.stops.iter().map(|stop| {
+            let alpha = stop.color[3] as f32 / 255.0;
+            (stop.offset, [
+                stop.color[0] as f32 * alpha,
+                stop.color[1] as f32 * alpha,
+                stop.color[2] as f32 * alpha,
+                stop.color[3] as f32,
+            ])
+        }).collect();
+
+        (0..size).map(|index| {
+            let position = if size > 1 { index as f32 / (size - 1) as f32 } else { 0.0 };
+            let next_stop_index = stops.iter()
+                                       .position(|&(offset, _)| offset > position)
+                                       .unwrap_or(stops.len());
+            let color = match (next_stop_index.checked_sub(1), stops.get(next_stop_index)) {
+                (None, None) => [0.0; 4],
+                (Some(prev_index), None) => stops[prev_index].1,
+                (None, Some(&(_, next_color))) => next_color,
+                (Some(prev_index), Some(&(next_offset, next_color))) => {
+                    let (prev_offset, prev_color) = stops[prev_index];
+                    let t = (position - prev_offset) / (next_offset - prev_offset);
+                    let mut color = [0.0; 4];
+                    for channel in 0..4 {
+                        color[channel] = prev_color[channel] +
+                            (next_color[channel] - prev_color[channel]) * t
+                    }
+                    color
+                }
+            };
+            [
+                color[0].round() as u8,
+                color[1].round() as u8,
+                color[2].round() as u8,
+                color[3].round() as u8,
+            ]
+        }).collect()
+    }
+}
+
+/// An RGBA image to fill with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    size: Size2D<u32>,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    /// Creates an image from premultiplied RGBA pixels, stored row by row. If the buffer isn't
+    /// exactly `size` pixels, it's handed back unchanged.
+    pub fn new(size: &Size2D<u32>, pixels: Vec<u8>) -> Result<Image, Vec<u8>> {
+        if pixels.len() != size.width as usize * size.height as usize * 4 {
+            return Err(pixels)
+        }
+        Ok(Image {
+            size: *size,
+            pixels: pixels,
+        })
+    }
+
+    #[inline]
+    pub fn size(&self) -> Size2D<u32> {
+        self.size
+    }
+
+    #[inline]
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Returns the premultiplied color of the pixel at `x`, `y`, which must be in bounds.
+    #[inline]
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        let offset = (y as usize * self.size.width as usize + x as usize) * 4;
+        [
+            self.pixels[offset],
+            self.pixels[offset + 1],
+            self.pixels[offset + 2],
+            self.pixels[offset + 3],
+        ]
+    }
+}
+
+/// Which directions a pattern tiles in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PatternRepeat {
+    Repeat,
+    RepeatX,
+    RepeatY,
+    /// The image is drawn once, and the area around it is transparent.
+    NoRepeat,
+}
+
+/// An image tiled across the fill.
+///
+/// The image is shared, so the same one can be used by many paints without copying its pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    pub image: Arc<Image>,
+    /// The transform from image pixels to canvas pixels.
+    pub transform: Transform2D<f32>,
+    pub repeat: PatternRepeat,
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::{Point2D, Transform2D};
+    use std::f32;
+
+    use super::{Gradient, GradientGeometry, SpreadMode};
+
+    fn conic(angle: f32, inverse_transform: Transform2D<f32>) -> Gradient {
+        Gradient {
+            geometry: GradientGeometry::Conic {
+                center: Point2D::zero(),
+                angle: angle,
+                inverse_transform: inverse_transform,
+            },
+            stops: vec![],
+            spread: SpreadMode::Repeat,
+        }
+    }
+
+    fn assert_position(gradient: &Gradient, x: f32, y: f32, expected: f32) {
+        let position = gradient.position_at(&Point2D::new(x, y));
+        assert!((position - expected).abs() < 0.0001, "{} at ({}, {})", position, x, y);
+    }
+
+    #[test]
+    fn conic_gradients_start_at_the_top_and_run_clockwise() {
+        let gradient = conic(0.0, Transform2D::identity());
+        assert_position(&gradient, 0.0, -1.0, 0.0);
+        assert_position(&gradient, 1.0, 0.0, 0.25);
+        assert_position(&gradient, 0.0, 1.0, 0.5);
+        assert_position(&gradient, -1.0, 0.0, 0.75);
+
+        let gradient = conic(f32::consts::FRAC_PI_2, Transform2D::identity());
+        assert_position(&gradient, 1.0, 0.0, 0.0);
+        assert_position(&gradient, 0.0, -1.0, 0.75);
+    }
+
+    #[test]
+    fn conic_gradients_are_evaluated_in_gradient_space() {
+        // Stretched twice as wide, the diagonal of gradient space runs through (2, -1).
+        let transform = Transform2D::create_scale(2.0, 1.0);
+        let gradient = conic(0.0, transform.inverse().unwrap());
+        assert_position(&gradient, 2.0, -1.0, 0.125);
+        assert_position(&gradient, 2.0, 1.0, 0.375);
+
+        // Skewed, the vertical of gradient space leans right.
+        let transform = Transform2D::row_major(1.0, 0.0, 1.0, 1.0, 0.0, 0.0);
+        let gradient = conic(0.0, transform.inverse().unwrap());
+        assert_position(&gradient, -1.0, -1.0, 0.0);
+        assert_position(&gradient, 1.0, 0.0, 0.25);
+    }
+}
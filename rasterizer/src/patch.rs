@@ -0,0 +1,264 @@
+// pathfinder/rasterizer/src/patch.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Coons patches, the building block of gradient meshes in illustration tools and of PDF type 6
+//! shadings.
+//!
+//! A patch is a surface bounded by four cubic curves, with a color at each corner that blends
+//! smoothly across it. It's drawn by filling its outline with an image pattern of its colors, so
+//! the edges are antialiased with the same coverage as any other fill and the result composites,
+//! clips, and records like any other paint.
+
+use euclid::{Point2D, Rect, Size2D, Transform2D, Vector2D};
+use lyon_geom::CubicBezierSegment;
+use lyon_path::PathEvent;
+use std::sync::Arc;
+
+use paint::{Image, Pattern, PatternRepeat};
+use raster::Color;
+
+/// The most rows and columns of cells that a patch is divided into for rasterization.
+const MAX_PATCH_SUBDIVISIONS: u32 = 64;
+
+/// How many pixels along the patch's control polygon each cell should span, at most.
+const PIXELS_PER_SUBDIVISION: f32 = 4.0;
+
+/// A Coons patch.
+///
+/// The edges run clockwise: `top` from the top left corner to the top right, `right` down to the
+/// bottom right, `bottom` back to the bottom left, and `left` up to the top left, each starting
+/// where the previous one ends. "Top" and the rest only name the edges; the patch can be any
+/// shape.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CoonsPatch {
+    pub top: CubicBezierSegment<f32>,
+    pub right: CubicBezierSegment<f32>,
+    pub bottom: CubicBezierSegment<f32>,
+    pub left: CubicBezierSegment<f32>,
+    /// The colors at the top left, top right, bottom right, and bottom left corners, not
+    /// premultiplied.
+    pub colors: [Color; 4],
+}
+
+impl CoonsPatch {
+    /// Returns the patch with all of its control points transformed.
+    pub fn transform(&self, transform: &Transform2D<f32>) -> CoonsPatch {
+        let transform_curve = |curve: &CubicBezierSegment<f32>| {
+            CubicBezierSegment {
+                from: transform.transform_point(&curve.from),
+                ctrl1: transform.transform_point(&curve.ctrl1),
+                ctrl2: transform.transform_point(&curve.ctrl2),
+                to: transform.transform_point(&curve.to),
+            }
+        };
+        CoonsPatch {
+            top: transform_curve(&self.top),
+            right: transform_curve(&self.right),
+            bottom: transform_curve(&self.bottom),
+            left: transform_curve(&self.left),
+            colors: self.colors,
+        }
+    }
+
+    /// Returns the boundary of the patch as a closed path.
+    pub fn outline(&self) -> Vec<PathEvent> {
+        let mut events = vec![PathEvent::MoveTo(self.top.from)];
+        for edge in &[self.top, self.right, self.bottom, self.left] {
+            events.push(PathEvent::CubicTo(edge.ctrl1, edge.ctrl2, edge.to))
+        }
+        events.push(PathEvent::Close);
+        events
+    }
+
+    /// Returns the point of the surface at `u` from left to right and `v` from top to bottom,
+    /// both from 0 to 1.
+    pub fn sample(&self, u: f32, v: f32) -> Point2D<f32> {
+        // The bottom and left edges run backward.
+        let (top, bottom) = (self.top.sample(u).to_vector(),
+                             self.bottom.sample(1.0 - u).to_vector());
+        let (left, right) = (self.left.sample(1.0 - v).to_vector(),
+                             self.right.sample(v).to_vector());
+        let (top_left, top_right) = (self.top.from.to_vector(), self.top.to.to_vector());
+        let (bottom_right, bottom_left) = (self.bottom.from.to_vector(),
+                                           self.bottom.to.to_vector());
+
+        let ruled_u = top * (1.0 - v) + bottom * v;
+        let ruled_v = left * (1.0 - u) + right * u;
+        let corners = top_left * ((1.0 - u) * (1.0 - v)) + top_right * (u * (1.0 - v)) +
+            bottom_right * (u * v) + bottom_left * ((1.0 - u) * v);
+        (ruled_u + ruled_v - corners).to_point()
+    }
+
+    /// Returns the premultiplied color at `u`, `v`, blended bilinearly from the corners in
+    /// premultiplied space.
+    pub fn color_at(&self, u: f32, v: f32) -> Color {
+        let weights = [(1.0 - u) * (1.0 - v), u * (1.0 - v), u * v, (1.0 - u) * v];
+        let mut color = [0.0; 4];
+        for (corner_color, weight) in self.colors.iter().zip(weights.iter()) {
+            let alpha = corner_color[3] as f32 / 255.0;
+            for channel in 0..3 {
+                color[channel] += corner_color[channel] as f32 * alpha * weight
+            }
+            color[3] += corner_color[3] as f32 * weight
+        }
+        [
+            color[0].round().max(0.0).min(255.0) as u8,
+            color[1].round().max(0.0).min(255.0) as u8,
+            color[2].round().max(0.0).min(255.0) as u8,
+            color[3].round().max(0.0).min(255.0) as u8,
+        ]
+    }
+
+    /// Rasterizes the colors of the patch into a pattern, in the patch's coordinate space, to
+    /// fill its `outline()` with.
+    ///
+    /// Only the part of the patch inside `clip_rect` is rasterized. Returns `None` if none of it
+    /// is.
+    pub fn to_pattern(&self, clip_rect: &Rect<i32>) -> Option<Pattern> {
+        let mut control_points = Vec::with_capacity(12);
+        for edge in &[self.top, self.right, self.bottom, self.left] {
+            control_points.extend_from_slice(&[edge.from, edge.ctrl1, edge.ctrl2])
+        }
+        let bounds = Rect::from_points(&control_points).round_out().to_i32();
+        let bounds = match bounds.intersection(clip_rect) {
+            Some(ref bounds) if bounds.size.width > 0 && bounds.size.height > 0 => *bounds,
+            _ => return None,
+        };
+
+        // Split the patch into a grid of cells fine enough that they're nearly flat.
+        let perimeter = control_points.iter().enumerate().fold(0.0, |perimeter, (index, point)| {
+            let next = control_points[(index + 1) % control_points.len()];
+            perimeter + (next - *point).length()
+        });
+        let subdivisions = ((perimeter * 0.25 / PIXELS_PER_SUBDIVISION).ceil() as u32)
+            .max(1)
+            .min(MAX_PATCH_SUBDIVISIONS);
+
+        let mut rasterizer = PatchRasterizer::new(self, &bounds);
+        let step = 1.0 / subdivisions as f32;
+        for row in 0..subdivisions {
+            for column in 0..subdivisions {
+                let (u0, v0) = (column as f32 * step, row as f32 * step);
+                let (u1, v1) = (u0 + step, v0 + step);
+                let corners = [
+                    (self.sample(u0, v0), u0, v0),
+                    (self.sample(u1, v0), u1, v0),
+                    (self.sample(u1, v1), u1, v1),
+                    (self.sample(u0, v1), u0, v1),
+                ];
+                rasterizer.fill_triangle(&corners[0], &corners[1], &corners[2]);
+                rasterizer.fill_triangle(&corners[0], &corners[2], &corners[3]);
+            }
+        }
+        Some(rasterizer.finish())
+    }
+}
+
+// Draws the colors of a patch into an image, one triangle of its grid at a time.
+struct PatchRasterizer<'a> {
+    patch: &'a CoonsPatch,
+    bounds: Rect<i32>,
+    pixels: Vec<u8>,
+    // Whether each pixel has been written, so that gaps along the outline can be filled in.
+    written: Vec<bool>,
+}
+
+impl<'a> PatchRasterizer<'a> {
+    fn new(patch: &'a CoonsPatch, bounds: &Rect<i32>) -> PatchRasterizer<'a> {
+        let pixel_count = bounds.size.width as usize * bounds.size.height as usize;
+        PatchRasterizer {
+            patch: patch,
+            bounds: *bounds,
+            pixels: vec![0; pixel_count * 4],
+            written: vec![false; pixel_count],
+        }
+    }
+
+    // Fills the pixels whose centers are inside the triangle with the patch color, interpolating
+    // the surface parameters of the corners.
+    fn fill_triangle(&mut self,
+                     a: &(Point2D<f32>, f32, f32),
+                     b: &(Point2D<f32>, f32, f32),
+                     c: &(Point2D<f32>, f32, f32)) {
+        let area = (b.0 - a.0).cross(c.0 - a.0);
+        if area == 0.0 || !area.is_finite() {
+            return
+        }
+
+        let triangle_bounds = Rect::from_points(&[a.0, b.0, c.0]).round_out().to_i32();
+        let triangle_bounds = match triangle_bounds.intersection(&self.bounds) {
+            None => return,
+            Some(triangle_bounds) => triangle_bounds,
+        };
+        for y in triangle_bounds.min_y()..triangle_bounds.max_y() {
+            for x in triangle_bounds.min_x()..triangle_bounds.max_x() {
+                let point = Point2D::new(x as f32 + 0.5, y as f32 + 0.5);
+                let weight_a = (c.0 - b.0).cross(point - b.0) / area;
+                let weight_b = (a.0 - c.0).cross(point - c.0) / area;
+                let weight_c = 1.0 - weight_a - weight_b;
+                if weight_a < 0.0 || weight_b < 0.0 || weight_c < 0.0 {
+                    continue
+                }
+
+                // Clamp to keep rounding from pushing the color past the corners.
+                let u = (a.1 * weight_a + b.1 * weight_b + c.1 * weight_c).max(0.0).min(1.0);
+                let v = (a.2 * weight_a + b.2 * weight_b + c.2 * weight_c).max(0.0).min(1.0);
+                let index = self.pixel_index(x, y);
+                self.pixels[(index * 4)..(index * 4 + 4)]
+                    .copy_from_slice(&self.patch.color_at(u, v));
+                self.written[index] = true;
+            }
+        }
+    }
+
+    #[inline]
+    fn pixel_index(&self, x: i32, y: i32) -> usize {
+        (y - self.bounds.origin.y) as usize * self.bounds.size.width as usize +
+            (x - self.bounds.origin.x) as usize
+    }
+
+    // Copies written pixels into the unwritten pixels next to them, so that the antialiased edge
+    // of the outline, whose pixel centers can fall just outside every triangle, isn't faded
+    // toward transparent.
+    fn finish(mut self) -> Pattern {
+        let neighbors = [Vector2D::new(-1, 0), Vector2D::new(1, 0),
+                         Vector2D::new(0, -1), Vector2D::new(0, 1)];
+        for y in self.bounds.min_y()..self.bounds.max_y() {
+            for x in self.bounds.min_x()..self.bounds.max_x() {
+                let index = self.pixel_index(x, y);
+                if self.written[index] {
+                    continue
+                }
+                for neighbor in &neighbors {
+                    let (neighbor_x, neighbor_y) = (x + neighbor.x, y + neighbor.y);
+                    if !self.bounds.contains(&Point2D::new(neighbor_x, neighbor_y)) {
+                        continue
+                    }
+                    let neighbor_index = self.pixel_index(neighbor_x, neighbor_y);
+                    if self.written[neighbor_index] {
+                        for channel in 0..4 {
+                            self.pixels[index * 4 + channel] =
+                                self.pixels[neighbor_index * 4 + channel]
+                        }
+                        break
+                    }
+                }
+            }
+        }
+
+        let size = Size2D::new(self.bounds.size.width as u32, self.bounds.size.height as u32);
+        Pattern {
+            image: Arc::new(Image::new(&size, self.pixels).unwrap()),
+            transform: Transform2D::create_translation(self.bounds.origin.x as f32,
+                                                       self.bounds.origin.y as f32),
+            repeat: PatternRepeat::NoRepeat,
+        }
+    }
+}
@@ -0,0 +1,842 @@
+// pathfinder/rasterizer/src/raster.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A software rasterizer that draws partitioned meshes into RGBA buffers on the CPU.
+//!
+//! This is meant for headless servers and machines without a usable GPU, and as a reference to
+//! compare GPU output against in regression tests. Coverage comes from
+//! `pathfinder_partitioner::spans::mesh_spans()`, so antialiasing is close to, but not
+//! bit-identical with, the GPU path.
+
+use euclid::{Point2D, Rect, Size2D, Transform2D, Vector2D};
+use lyon_path::PathEvent;
+use pathfinder_partitioner::FillRule;
+use pathfinder_partitioner::batch;
+use pathfinder_partitioner::mesh::Mesh;
+use pathfinder_partitioner::mesh_pack::MeshPack;
+use pathfinder_partitioner::spans::{self, Span};
+use std::mem;
+
+use blend::BlendMode;
+use filter::Filter;
+use gamma::{self, TextGamma};
+use paint::{GRADIENT_RAMP_SIZE, Gradient, Paint, Pattern, PatternRepeat};
+
+const BYTES_PER_PIXEL: usize = 4;
+
+/// An RGBA color with 8 bits per channel, not premultiplied.
+pub type Color = [u8; 4];
+
+/// How the canvas antialiases the edges of fills and clips.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Antialiasing {
+    /// Pixels are drawn fully if at least half covered and not at all otherwise.
+    None,
+    /// Coverage is computed exactly in Y and from this many samples per pixel in X.
+    Analytic(u32),
+}
+
+impl Default for Antialiasing {
+    #[inline]
+    fn default() -> Antialiasing {
+        Antialiasing::Analytic(spans::DEFAULT_HORIZONTAL_SAMPLES)
+    }
+}
+
+/// An RGBA image that meshes can be drawn into.
+///
+/// Pixels are stored premultiplied, row by row. Row *y* of the image is scanline *y* of the
+/// mesh, so paths in a Y-up space should be flipped before partitioning.
+///
+/// Drawing goes to the topmost layer pushed with `push_layer()`, or to the canvas itself if
+/// there are none. It's restricted to the viewport and scissor rectangle, if set, and to the clip
+/// region built up with `push_clip()`.
+#[derive(Clone, Debug)]
+pub struct Canvas {
+    size: Size2D<u32>,
+    pixels: Vec<u8>,
+    blend_mode: BlendMode,
+    layers: Vec<Layer>,
+    // The coverage of each clip region, intersected with the regions under it that were pushed
+    // since the topmost layer. Clips pushed before a layer apply when it's composited, not to
+    // the fills inside it, so that their edges aren't applied twice.
+    clip_masks: Vec<Vec<u8>>,
+    viewport: Option<Rect<i32>>,
+    scissor_rect: Option<Rect<i32>>,
+    linear_blending: bool,
+    text_gamma: Option<TextGamma>,
+    antialiasing: Antialiasing,
+    fill_opacity: u8,
+}
+
+// The state saved when a layer is pushed.
+#[derive(Clone, Debug)]
+struct Layer {
+    // The pixels underneath the layer.
+    parent_pixels: Vec<u8>,
+    opacity: u8,
+    // The blend mode to composite the layer with, which is restored when it's popped.
+    blend_mode: BlendMode,
+    filter: Option<Filter>,
+    // The number of clip masks that were pushed when the layer was.
+    clip_depth: usize,
+}
+
+impl Canvas {
+    /// Creates a canvas of the given size, cleared to transparent black.
+    pub fn new(size: &Size2D<u32>) -> Canvas {
+        Canvas {
+            size: *size,
+            pixels: vec![0; size.width as usize * size.height as usize * BYTES_PER_PIXEL],
+            blend_mode: BlendMode::SourceOver,
+            layers: vec![],
+            clip_masks: vec![],
+            viewport: None,
+            scissor_rect: None,
+            linear_blending: false,
+            text_gamma: None,
+            antialiasing: Antialiasing::default(),
+            fill_opacity: 255,
+        }
+    }
+
+    /// Wraps an existing premultiplied RGBA buffer, such as a frame an embedder is compositing,
+    /// so that meshes are drawn over its contents.
+    ///
+    /// If the buffer isn't exactly `size` pixels, it's handed back unchanged.
+    pub fn from_pixels(size: &Size2D<u32>, pixels: Vec<u8>) -> Result<Canvas, Vec<u8>> {
+        if pixels.len() != size.width as usize * size.height as usize * BYTES_PER_PIXEL {
+            return Err(pixels)
+        }
+        Ok(Canvas {
+            size: *size,
+            pixels: pixels,
+            blend_mode: BlendMode::SourceOver,
+            layers: vec![],
+            clip_masks: vec![],
+            viewport: None,
+            scissor_rect: None,
+            linear_blending: false,
+            text_gamma: None,
+            antialiasing: Antialiasing::default(),
+            fill_opacity: 255,
+        })
+    }
+
+    #[inline]
+    pub fn size(&self) -> Size2D<u32> {
+        self.size
+    }
+
+    /// Returns the premultiplied RGBA pixels of the current layer.
+    #[inline]
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    #[inline]
+    pub fn pixels_mut(&mut self) -> &mut [u8] {
+        &mut self.pixels
+    }
+
+    /// Pops any open layers and returns the premultiplied RGBA pixels.
+    pub fn into_pixels(mut self) -> Vec<u8> {
+        while self.pop_layer() {}
+        self.pixels
+    }
+
+    /// Starts a group of fills that are drawn into a transparent intermediate layer and then
+    /// composited onto what's underneath as a unit, with `opacity` from 0 to 1 and the current
+    /// blend mode.
+    ///
+    /// This keeps overlapping fills in a translucent group from showing through one another.
+    /// The blend mode is reset to source-over inside the layer.
+    pub fn push_layer(&mut self, opacity: f32) {
+        self.push_layer_with_filter(opacity, None)
+    }
+
+    /// Like `push_layer()`, but `filter`, if present, is applied to the layer's contents before
+    /// they're composited.
+    pub fn push_layer_with_filter(&mut self, opacity: f32, filter: Option<Filter>) {
+        let layer_pixels = vec![0; self.pixels.len()];
+        self.layers.push(Layer {
+            parent_pixels: mem::replace(&mut self.pixels, layer_pixels),
+            opacity: (opacity.max(0.0).min(1.0) * 255.0).round() as u8,
+            blend_mode: self.blend_mode,
+            filter: filter,
+            clip_depth: self.clip_masks.len(),
+        });
+        self.blend_mode = BlendMode::SourceOver;
+    }
+
+    /// Composites the topmost layer onto the one underneath it, restoring the blend mode that
+    /// was current when it was pushed. Returns false if there was no layer to pop.
+    pub fn pop_layer(&mut self) -> bool {
+        let layer = match self.layers.pop() {
+            None => return false,
+            Some(layer) => layer,
+        };
+        let mut layer_pixels = mem::replace(&mut self.pixels, layer.parent_pixels);
+        if let Some(ref filter) = layer.filter {
+            filter.apply(&mut layer_pixels, &self.size)
+        }
+        let bounds = self.drawing_bounds();
+        let width = self.size.width as usize;
+        // The layer is clipped by the clips that were in effect when it was pushed.
+        let clip_depth = layer.clip_depth.min(self.clip_masks.len());
+        let clip_mask = self.clip_masks[..clip_depth].last();
+        for (pixel_index, (pixel, source)) in
+                self.pixels.chunks_mut(BYTES_PER_PIXEL)
+                           .zip(layer_pixels.chunks(BYTES_PER_PIXEL))
+                           .enumerate() {
+            let (x, y) = ((pixel_index % width) as i32, (pixel_index / width) as i32);
+            if !bounds.map_or(false, |bounds| bounds.contains(&Point2D::new(x, y))) {
+                continue
+            }
+            let source = [source[0], source[1], source[2], source[3]];
+            let coverage = match clip_mask {
+                None => layer.opacity,
+                Some(clip_mask) => multiply(layer.opacity, clip_mask[pixel_index]),
+            };
+            composite(pixel, source, coverage, layer.blend_mode, self.linear_blending)
+        }
+        self.blend_mode = layer.blend_mode;
+        true
+    }
+
+    /// Returns the number of layers currently pushed.
+    #[inline]
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Sets the rectangle of the canvas to draw into, in canvas pixels, or `None` for the whole
+    /// canvas.
+    ///
+    /// Fill offsets, paints, and clip paths are relative to the viewport's origin, and nothing
+    /// outside the viewport is touched, so a part of the canvas can be redrawn as if it were a
+    /// canvas of its own.
+    #[inline]
+    pub fn set_viewport(&mut self, viewport: Option<Rect<i32>>) {
+        self.viewport = viewport
+    }
+
+    #[inline]
+    pub fn viewport(&self) -> Option<Rect<i32>> {
+        self.viewport
+    }
+
+    /// Sets a rectangle, in canvas pixels, outside of which drawing has no effect, or `None` to
+    /// remove it. This applies to clearing as well as to fills and layers.
+    #[inline]
+    pub fn set_scissor_rect(&mut self, scissor_rect: Option<Rect<i32>>) {
+        self.scissor_rect = scissor_rect
+    }
+
+    #[inline]
+    pub fn scissor_rect(&self) -> Option<Rect<i32>> {
+        self.scissor_rect
+    }
+
+    /// Sets whether colors are blended in linear light rather than on their sRGB-encoded values.
+    /// Either way, the pixels are stored sRGB-encoded. This is off by default, which matches
+    /// what most GPU renderers do without an sRGB framebuffer.
+    #[inline]
+    pub fn set_linear_blending(&mut self, linear_blending: bool) {
+        self.linear_blending = linear_blending
+    }
+
+    #[inline]
+    pub fn linear_blending(&self) -> bool {
+        self.linear_blending
+    }
+
+    /// Sets the coverage adjustment for solid-colored fills, such as text, or `None` to leave
+    /// coverage as it is.
+    #[inline]
+    pub fn set_text_gamma(&mut self, text_gamma: Option<TextGamma>) {
+        self.text_gamma = text_gamma
+    }
+
+    #[inline]
+    pub fn text_gamma(&self) -> Option<TextGamma> {
+        self.text_gamma
+    }
+
+    #[inline]
+    pub fn set_antialiasing(&mut self, antialiasing: Antialiasing) {
+        self.antialiasing = antialiasing
+    }
+
+    #[inline]
+    pub fn antialiasing(&self) -> Antialiasing {
+        self.antialiasing
+    }
+
+    /// Sets the opacity, from 0 to 1, that the coverage of subsequent fills is multiplied by.
+    /// The default is 1.
+    ///
+    /// This is the same as drawing each fill into its own layer with this opacity, without the
+    /// cost of the layer. Use `push_layer()` instead to fade a group of overlapping fills as a
+    /// unit.
+    #[inline]
+    pub fn set_fill_opacity(&mut self, opacity: f32) {
+        self.fill_opacity = (opacity.max(0.0).min(1.0) * 255.0).round() as u8
+    }
+
+    #[inline]
+    pub fn fill_opacity(&self) -> f32 {
+        self.fill_opacity as f32 / 255.0
+    }
+
+    // Computes the coverage of a mesh with the current antialiasing setting.
+    fn mesh_spans(&self, mesh: &Mesh) -> Vec<Span> {
+        match self.antialiasing {
+            Antialiasing::Analytic(samples) => spans::mesh_spans_with_samples(mesh, samples),
+            Antialiasing::None => {
+                let mut spans = spans::mesh_spans(mesh);
+                spans.retain(|span| span.coverage >= 128);
+                for span in &mut spans {
+                    span.coverage = 255
+                }
+                spans
+            }
+        }
+    }
+
+    // Returns the origin of the viewport, which drawing coordinates are relative to.
+    fn viewport_origin(&self) -> Vector2D<i32> {
+        self.viewport.map_or(Vector2D::zero(), |viewport| viewport.origin.to_vector())
+    }
+
+    // Returns the part of the canvas that drawing can touch, or `None` if there's none.
+    fn drawing_bounds(&self) -> Option<Rect<i32>> {
+        let mut bounds = Rect::new(Point2D::zero(), self.size.to_i32());
+        for rect in self.viewport.iter().chain(self.scissor_rect.iter()) {
+            bounds = match bounds.intersection(rect) {
+                None => return None,
+                Some(bounds) => bounds,
+            }
+        }
+        Some(bounds)
+    }
+
+    /// Restricts subsequent drawing, including compositing layers, to the inside of `path`,
+    /// intersected with the current clip region. The path is relative to the viewport.
+    pub fn push_clip(&mut self, path: &[PathEvent], fill_rule: FillRule) {
+        let mesh = batch::partition_path(path, fill_rule, None);
+        self.push_clip_mesh(&mesh, &Vector2D::zero())
+    }
+
+    /// Like `push_clip()`, but the region is the union of the insides of `paths`, each with its
+    /// own fill rule. It's empty if there are no paths.
+    pub fn push_clip_union(&mut self, paths: &[(&[PathEvent], FillRule)]) {
+        let mut clip_mask = vec![0; self.size.width as usize * self.size.height as usize];
+        for &(path, fill_rule) in paths {
+            let mesh = batch::partition_path(path, fill_rule, None);
+            self.cover_clip_mask(&mut clip_mask, &mesh, &Vector2D::zero())
+        }
+        self.push_clip_mask(clip_mask)
+    }
+
+    /// Like `push_clip()`, but for a path that's already been partitioned, with its origin at
+    /// `offset` pixels.
+    pub fn push_clip_mesh(&mut self, mesh: &Mesh, offset: &Vector2D<i32>) {
+        let mut clip_mask = vec![0; self.size.width as usize * self.size.height as usize];
+        self.cover_clip_mask(&mut clip_mask, mesh, offset);
+        self.push_clip_mask(clip_mask)
+    }
+
+    // Raises the coverage in `clip_mask` to that of `mesh`, with its origin at `offset` pixels,
+    // wherever the mesh covers more.
+    fn cover_clip_mask(&self, clip_mask: &mut [u8], mesh: &Mesh, offset: &Vector2D<i32>) {
+        let offset = *offset + self.viewport_origin();
+        let (width, height) = (self.size.width as i32, self.size.height as i32);
+        for span in self.mesh_spans(mesh) {
+            let y = span.y + offset.y;
+            let left = (span.x + offset.x).max(0);
+            let right = (span.x + offset.x + span.len as i32).min(width);
+            if y < 0 || y >= height || left >= right {
+                continue
+            }
+            let row_start = y as usize * width as usize;
+            for coverage in &mut clip_mask[(row_start + left as usize)..
+                                           (row_start + right as usize)] {
+                *coverage = (*coverage).max(span.coverage)
+            }
+        }
+    }
+
+    // Intersects `clip_mask` with the current clip region and pushes it.
+    fn push_clip_mask(&mut self, mut clip_mask: Vec<u8>) {
+        if let Some(parent_clip_mask) = current_clip_mask(&self.layers, &self.clip_masks) {
+            for (coverage, &parent_coverage) in clip_mask.iter_mut().zip(parent_clip_mask) {
+                *coverage = multiply(*coverage, parent_coverage)
+            }
+        }
+        self.clip_masks.push(clip_mask)
+    }
+
+    /// Removes the most recently pushed clip region. Returns false if there was none.
+    pub fn pop_clip(&mut self) -> bool {
+        self.clip_masks.pop().is_some()
+    }
+
+    #[inline]
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Sets how subsequent fills combine with the canvas. The default is source-over.
+    ///
+    /// Only pixels that a fill covers are affected, so operators like `SourceIn` leave the rest
+    /// of the canvas alone.
+    #[inline]
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode
+    }
+
+    /// Fills the canvas with `color`, replacing what was there. Only the viewport and scissor
+    /// rectangle are cleared, if they're set.
+    pub fn clear(&mut self, color: Color) {
+        let color = premultiply(color);
+        let bounds = match self.drawing_bounds() {
+            None => return,
+            Some(bounds) => bounds,
+        };
+        for y in bounds.min_y()..bounds.max_y() {
+            let row_start = (y as usize * self.size.width as usize + bounds.min_x() as usize) *
+                BYTES_PER_PIXEL;
+            let row_end = row_start + bounds.size.width as usize * BYTES_PER_PIXEL;
+            for pixel in self.pixels[row_start..row_end].chunks_mut(BYTES_PER_PIXEL) {
+                pixel.copy_from_slice(&color)
+            }
+        }
+    }
+
+    /// Composites `mesh` over the canvas, filled with `paint`, with its origin at `offset`
+    /// pixels.
+    ///
+    /// One mesh unit is one pixel. Parts of the mesh outside the canvas are clipped. Gradients
+    /// and patterns are positioned relative to the viewport, regardless of `offset`.
+    pub fn fill_mesh(&mut self, mesh: &Mesh, offset: &Vector2D<i32>, paint: &Paint) {
+        let spans = self.mesh_spans(mesh);
+        self.fill_spans(&spans, offset, paint)
+    }
+
+    /// Composites every mesh in `mesh_pack` over the canvas in order, each with its own offset
+    /// and paint. Meshes without an entry in `paints` are skipped.
+    pub fn fill_mesh_pack(&mut self, mesh_pack: &MeshPack, paints: &[(Vector2D<i32>, Paint)]) {
+        for (mesh, &(ref offset, ref paint)) in mesh_pack.meshes.iter().zip(paints.iter()) {
+            self.fill_mesh(mesh, offset, paint)
+        }
+    }
+
+    /// Composites `mesh` once per instance, each with its own offset and paint, in order.
+    ///
+    /// The mesh's coverage is computed only once, so this is much cheaper than calling
+    /// `fill_mesh()` for each instance, as for repeated glyphs. Instances can only be translated
+    /// by whole pixels; a mesh that's scaled, rotated, or subpixel-positioned has different
+    /// coverage and needs to be partitioned separately.
+    pub fn fill_mesh_instances(&mut self, mesh: &Mesh, instances: &[(Vector2D<i32>, Paint)]) {
+        let spans = self.mesh_spans(mesh);
+        for &(ref offset, ref paint) in instances {
+            self.fill_spans(&spans, offset, paint)
+        }
+    }
+
+    /// Composites coverage spans over the canvas, filled with `paint` and offset by `offset`
+    /// pixels.
+    pub fn fill_spans(&mut self, spans: &[Span], offset: &Vector2D<i32>, paint: &Paint) {
+        let shader = Shader::new(paint);
+        let blend_mode = self.blend_mode;
+        let linear_blending = self.linear_blending;
+        let fill_opacity = self.fill_opacity;
+        let text_gamma = match (paint, self.text_gamma) {
+            (&Paint::Color(color), Some(text_gamma)) => Some((text_gamma, color)),
+            _ => None,
+        };
+        let width = self.size.width as i32;
+        let origin = self.viewport_origin();
+        let offset = *offset + origin;
+        let bounds = match self.drawing_bounds() {
+            None => return,
+            Some(bounds) => bounds,
+        };
+
+        for span in spans {
+            let y = span.y + offset.y;
+            if y < bounds.min_y() || y >= bounds.max_y() {
+                continue
+            }
+            let left = (span.x + offset.x).max(bounds.min_x());
+            let right = (span.x + offset.x + span.len as i32).min(bounds.max_x());
+            if left >= right {
+                continue
+            }
+
+            let row_start = (y as usize * self.size.width as usize + left as usize) *
+                BYTES_PER_PIXEL;
+            let row_end = row_start + (right - left) as usize * BYTES_PER_PIXEL;
+            let pixels = self.pixels[row_start..row_end].chunks_mut(BYTES_PER_PIXEL);
+            let clip_mask = current_clip_mask(&self.layers, &self.clip_masks);
+            let span_coverage = match text_gamma {
+                None => span.coverage,
+                Some((text_gamma, color)) => text_gamma.adjust(span.coverage, color),
+            };
+            let span_coverage = multiply(span_coverage, fill_opacity);
+            for (x, pixel) in (left..right).zip(pixels) {
+                let coverage = match clip_mask {
+                    None => span_coverage,
+                    Some(clip_mask) => {
+                        multiply(span_coverage, clip_mask[(y * width + x) as usize])
+                    }
+                };
+                if coverage > 0 {
+                    let source = shader.sample(x - origin.x, y - origin.y);
+                    composite(pixel, source, coverage, blend_mode, linear_blending)
+                }
+            }
+        }
+    }
+}
+
+// A paint prepared for sampling per pixel.
+enum Shader<'a> {
+    Solid(Color),
+    Gradient(&'a Gradient, Vec<Color>),
+    // The pattern and the transform from canvas pixels to image pixels, if it's invertible.
+    Pattern(&'a Pattern, Option<Transform2D<f32>>),
+}
+
+impl<'a> Shader<'a> {
+    fn new(paint: &'a Paint) -> Shader<'a> {
+        match *paint {
+            Paint::Color(color) => Shader::Solid(premultiply(color)),
+            Paint::Gradient(ref gradient) => {
+                Shader::Gradient(gradient, gradient.ramp(GRADIENT_RAMP_SIZE))
+            }
+            Paint::Pattern(ref pattern) => Shader::Pattern(pattern, pattern.transform.inverse()),
+        }
+    }
+
+    // Returns the premultiplied color at the center of the pixel.
+    fn sample(&self, x: i32, y: i32) -> Color {
+        let point = Point2D::new(x as f32 + 0.5, y as f32 + 0.5);
+        match *self {
+            Shader::Solid(color) => color,
+            Shader::Gradient(gradient, ref ramp) => {
+                let position = gradient.position_at(&point);
+                ramp[(position * (ramp.len() - 1) as f32).round() as usize]
+            }
+            Shader::Pattern(_, None) => [0; 4],
+            Shader::Pattern(pattern, Some(ref inverse_transform)) => {
+                // Patterns are sampled with the nearest image pixel.
+                let point = inverse_transform.transform_point(&point);
+                let size = pattern.image.size();
+                let (repeat_x, repeat_y) = match pattern.repeat {
+                    PatternRepeat::Repeat => (true, true),
+                    PatternRepeat::RepeatX => (true, false),
+                    PatternRepeat::RepeatY => (false, true),
+                    PatternRepeat::NoRepeat => (false, false),
+                };
+                match (wrap(point.x.floor() as i64, size.width, repeat_x),
+                       wrap(point.y.floor() as i64, size.height, repeat_y)) {
+                    (Some(x), Some(y)) => pattern.image.pixel(x, y),
+                    _ => [0; 4],
+                }
+            }
+        }
+    }
+}
+
+// Blends a premultiplied source color into a pixel, with partial coverage interpolating between
+// the old pixel and the fully blended result.
+// Returns the mask that fills into the topmost layer are clipped by, if any clips have been
+// pushed since it was.
+fn current_clip_mask<'a>(layers: &[Layer], clip_masks: &'a [Vec<u8>]) -> Option<&'a Vec<u8>> {
+    let layer_clip_depth = layers.last().map_or(0, |layer| layer.clip_depth);
+    if clip_masks.len() > layer_clip_depth {
+        clip_masks.last()
+    } else {
+        None
+    }
+}
+
+fn composite(pixel: &mut [u8],
+             source: Color,
+             coverage: u8,
+             blend_mode: BlendMode,
+             linear_blending: bool) {
+    if linear_blending {
+        let destination = gamma::premultiplied_to_linear([pixel[0], pixel[1], pixel[2], pixel[3]]);
+        let blended = blend_mode.blend_float(gamma::premultiplied_to_linear(source), destination);
+        let coverage = coverage as f32 / 255.0;
+        let mut result = [0.0; 4];
+        for channel in 0..BYTES_PER_PIXEL {
+            result[channel] = destination[channel] +
+                (blended[channel] - destination[channel]) * coverage
+        }
+        pixel.copy_from_slice(&gamma::linear_to_premultiplied(result));
+        return
+    }
+
+    if blend_mode == BlendMode::SourceOver {
+        // This is equivalent to the general case, but much cheaper.
+        let source = scale(source, coverage);
+        let inverse_alpha = 255 - source[3];
+        for channel in 0..BYTES_PER_PIXEL {
+            let destination = multiply(pixel[channel], inverse_alpha);
+            pixel[channel] = source[channel].saturating_add(destination)
+        }
+        return
+    }
+
+    let destination = [pixel[0], pixel[1], pixel[2], pixel[3]];
+    let blended = blend_mode.blend(source, destination);
+    for channel in 0..BYTES_PER_PIXEL {
+        pixel[channel] = multiply(destination[channel], 255 - coverage)
+            .saturating_add(multiply(blended[channel], coverage))
+    }
+}
+
+// Maps a pixel coordinate into an image of `length` pixels, tiling if `repeat` is set.
+fn wrap(coordinate: i64, length: u32, repeat: bool) -> Option<u32> {
+    if length == 0 {
+        None
+    } else if repeat {
+        Some((((coordinate % length as i64) + length as i64) % length as i64) as u32)
+    } else if coordinate >= 0 && coordinate < length as i64 {
+        Some(coordinate as u32)
+    } else {
+        None
+    }
+}
+
+/// Converts a straight color to a premultiplied pixel, as a `Canvas` stores them.
+pub fn premultiply(color: Color) -> Color {
+    let alpha = color[3];
+    [multiply(color[0], alpha), multiply(color[1], alpha), multiply(color[2], alpha), alpha]
+}
+
+/// Converts a premultiplied pixel back to a straight color, as image formats like PNG store them.
+pub fn unpremultiply(pixel: Color) -> Color {
+    let alpha = pixel[3] as u32;
+    if alpha == 0 {
+        return [0; 4]
+    }
+    let channel = |value: u8| ((value as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+    [channel(pixel[0]), channel(pixel[1]), channel(pixel[2]), pixel[3]]
+}
+
+fn scale(color: Color, coverage: u8) -> Color {
+    [
+        multiply(color[0], coverage),
+        multiply(color[1], coverage),
+        multiply(color[2], coverage),
+        multiply(color[3], coverage),
+    ]
+}
+
+// Multiplies two values in 0..255 as if they were fractions, rounding to nearest.
+#[inline]
+pub(crate) fn multiply(a: u8, b: u8) -> u8 {
+    let product = a as u32 * b as u32 + 128;
+    ((product + (product >> 8)) >> 8) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::{Point2D, Size2D, Vector2D};
+    use lyon_path::PathEvent;
+    use pathfinder_partitioner::FillRule;
+    use pathfinder_partitioner::batch;
+
+    use blend::BlendMode;
+    use paint::Paint;
+    use super::{Antialiasing, Canvas, Color};
+
+    const RED: Color = [255, 0, 0, 255];
+    const BLUE: Color = [0, 0, 255, 255];
+
+    // The premultiplied colors that the characters of a golden image stand for.
+    const PALETTE: [(char, Color); 6] = [
+        ('.', [0, 0, 0, 0]),
+        ('R', [255, 0, 0, 255]),
+        ('r', [128, 0, 0, 128]),
+        ('B', [0, 0, 255, 255]),
+        ('M', [255, 0, 255, 255]),
+        ('K', [0, 0, 0, 255]),
+    ];
+
+    // A rectangle from `(left, top)` to `(right, bottom)`, wound clockwise on screen.
+    fn rect(left: f32, top: f32, right: f32, bottom: f32) -> Vec<PathEvent> {
+        vec![
+            PathEvent::MoveTo(Point2D::new(left, top)),
+            PathEvent::LineTo(Point2D::new(right, top)),
+            PathEvent::LineTo(Point2D::new(right, bottom)),
+            PathEvent::LineTo(Point2D::new(left, bottom)),
+            PathEvent::Close,
+        ]
+    }
+
+    // Creates a canvas without antialiasing, so that pixel-aligned shapes cover whole pixels
+    // and the results are exact.
+    fn canvas(width: u32, height: u32) -> Canvas {
+        let mut canvas = Canvas::new(&Size2D::new(width, height));
+        canvas.set_antialiasing(Antialiasing::None);
+        canvas
+    }
+
+    fn fill(canvas: &mut Canvas, path: &[PathEvent], fill_rule: FillRule, color: Color) {
+        let mesh = batch::partition_path(path, fill_rule, None);
+        canvas.fill_mesh(&mesh, &Vector2D::zero(), &Paint::Color(color))
+    }
+
+    // Checks every pixel of `canvas` against `golden`, one string per row and one character per
+    // pixel, allowing for rounding by one step in each channel.
+    fn assert_matches_golden(canvas: &Canvas, golden: &[&str]) {
+        let size = canvas.size();
+        assert_eq!(golden.len(), size.height as usize);
+        for (y, row) in golden.iter().enumerate() {
+            assert_eq!(row.len(), size.width as usize);
+            for (x, character) in row.chars().enumerate() {
+                let expected = PALETTE.iter()
+                                      .find(|&&(palette_character, _)| {
+                                          palette_character == character
+                                      })
+                                      .unwrap()
+                                      .1;
+                let index = (y * size.width as usize + x) * 4;
+                let actual = &canvas.pixels()[index..(index + 4)];
+                for channel in 0..4 {
+                    assert!((actual[channel] as i32 - expected[channel] as i32).abs() <= 1,
+                            "pixel ({}, {}) is {:?}, not {:?} ('{}')",
+                            x,
+                            y,
+                            actual,
+                            expected,
+                            character);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fills_match_golden_images() {
+        let mut canvas = canvas(8, 6);
+        fill(&mut canvas, &rect(1.0, 1.0, 4.0, 5.0), FillRule::Winding, RED);
+        fill(&mut canvas, &rect(3.0, 2.0, 7.0, 4.0), FillRule::Winding, BLUE);
+        assert_matches_golden(&canvas, &[
+            "........",
+            ".RRR....",
+            ".RRBBBB.",
+            ".RRBBBB.",
+            ".RRR....",
+            "........",
+        ]);
+    }
+
+    #[test]
+    fn fill_rules_match_golden_images() {
+        // Two squares wound the same way. The inner one is inside twice, so it's a hole under
+        // the even-odd rule and filled under the nonzero winding rule.
+        let mut path = rect(1.0, 1.0, 7.0, 7.0);
+        path.extend(rect(3.0, 3.0, 5.0, 5.0));
+
+        let mut even_odd = canvas(8, 8);
+        fill(&mut even_odd, &path, FillRule::EvenOdd, RED);
+        assert_matches_golden(&even_odd, &[
+            "........",
+            ".RRRRRR.",
+            ".RRRRRR.",
+            ".RR..RR.",
+            ".RR..RR.",
+            ".RRRRRR.",
+            ".RRRRRR.",
+            "........",
+        ]);
+
+        let mut winding = canvas(8, 8);
+        fill(&mut winding, &path, FillRule::Winding, RED);
+        assert_matches_golden(&winding, &[
+            "........",
+            ".RRRRRR.",
+            ".RRRRRR.",
+            ".RRRRRR.",
+            ".RRRRRR.",
+            ".RRRRRR.",
+            ".RRRRRR.",
+            "........",
+        ]);
+    }
+
+    #[test]
+    fn blend_modes_match_golden_images() {
+        // Red is drawn over the left half of a blue row. Pixels the fill doesn't cover are left
+        // alone whatever the mode.
+        let cases = [
+            (BlendMode::SourceOver, "RRBB"),
+            (BlendMode::DestinationOver, "BBBB"),
+            (BlendMode::SourceIn, "RRBB"),
+            (BlendMode::DestinationOut, "..BB"),
+            (BlendMode::Xor, "..BB"),
+            (BlendMode::Lighter, "MMBB"),
+            (BlendMode::Multiply, "KKBB"),
+            (BlendMode::Screen, "MMBB"),
+            (BlendMode::Darken, "KKBB"),
+            (BlendMode::Lighten, "MMBB"),
+            (BlendMode::Difference, "MMBB"),
+        ];
+        for &(blend_mode, golden) in &cases {
+            let mut canvas = canvas(4, 1);
+            canvas.clear(BLUE);
+            canvas.set_blend_mode(blend_mode);
+            fill(&mut canvas, &rect(0.0, 0.0, 2.0, 1.0), FillRule::Winding, RED);
+            assert_matches_golden(&canvas, &[golden]);
+        }
+    }
+
+    #[test]
+    fn translucent_source_over_matches_formula() {
+        let mut canvas = canvas(1, 1);
+        canvas.clear(BLUE);
+        fill(&mut canvas, &rect(0.0, 0.0, 1.0, 1.0), FillRule::Winding, [255, 0, 0, 128]);
+        // Premultiplied red at half opacity plus half of the blue underneath.
+        let pixel = canvas.pixels();
+        for (&actual, &expected) in pixel.iter().zip([128, 0, 127, 255].iter()) {
+            assert!((actual as i32 - expected).abs() <= 1, "{:?}", pixel);
+        }
+    }
+
+    #[test]
+    fn clips_pushed_before_a_layer_apply_once() {
+        // The clip ends halfway across the second pixel, so that pixel is half covered. Were the
+        // clip applied to the fill inside the layer and again to the layer, it'd be a quarter.
+        let mut canvas = Canvas::new(&Size2D::new(3, 1));
+        canvas.push_clip(&rect(0.0, 0.0, 1.5, 1.0), FillRule::Winding);
+        canvas.push_layer(1.0);
+        fill(&mut canvas, &rect(0.0, 0.0, 3.0, 1.0), FillRule::Winding, RED);
+        assert!(canvas.pop_layer());
+        assert!(canvas.pop_clip());
+        assert_matches_golden(&canvas, &["Rr."]);
+    }
+
+    #[test]
+    fn clip_unions_cover_overlaps() {
+        // As one even-odd path, the two rectangles would leave out the pixel where they overlap.
+        let mut canvas = canvas(4, 1);
+        let (left, right) = (rect(0.0, 0.0, 2.0, 1.0), rect(1.0, 0.0, 3.0, 1.0));
+        canvas.push_clip_union(&[(&left[..], FillRule::EvenOdd), (&right[..], FillRule::EvenOdd)]);
+        fill(&mut canvas, &rect(0.0, 0.0, 4.0, 1.0), FillRule::Winding, RED);
+        assert!(canvas.pop_clip());
+        assert_matches_golden(&canvas, &["RRR."]);
+    }
+}
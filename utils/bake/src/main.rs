@@ -0,0 +1,270 @@
+// pathfinder/utils/bake/src/main.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Bakes fonts and SVG documents into mesh libraries ahead of time, so that apps with a fixed set
+//! of glyphs and vector art never pay the cost of partitioning at runtime.
+//!
+//! A font produces one mesh per glyph, indexed by glyph ID. An SVG document produces one mesh
+//! per fill, in drawing order, with strokes already converted to fills. Only geometry is baked;
+//! the app supplies the paints when it renders the meshes.
+
+extern crate clap;
+extern crate euclid;
+extern crate font_kit;
+extern crate lyon_path;
+extern crate pathfinder_canvas;
+extern crate pathfinder_font_utils;
+extern crate pathfinder_partitioner;
+extern crate pathfinder_path_utils;
+
+use clap::{App, Arg};
+use euclid::Size2D;
+use font_kit::font::Font;
+use lyon_path::builder::{FlatPathBuilder, PathBuilder};
+use lyon_path::default::Path as LyonPath;
+use pathfinder_canvas::CanvasRenderingContext2D;
+use pathfinder_canvas::svg::SvgDocument;
+use pathfinder_font_utils::hinting::HintingLevel;
+use pathfinder_font_utils::woff;
+use pathfinder_partitioner::FillRule;
+use pathfinder_partitioner::batch;
+use pathfinder_partitioner::mesh::Mesh;
+use pathfinder_partitioner::mesh_pack::{MeshPack, WriteOptions};
+use pathfinder_path_utils::cubic_to_quadratic::CubicToQuadraticTransformer;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+
+/// The tolerance, in font units, that cubic curves in CFF outlines are approximated to for the
+/// stencil segments when `--tolerance` isn't given.
+const DEFAULT_FONT_STENCIL_APPROX_TOLERANCE: f32 = 0.5;
+
+/// Options that apply to every kind of input.
+struct BakeOptions {
+    /// The tolerance that curves are approximated to, in font units for fonts and in pixels at
+    /// the chosen scale for SVG documents. `None` uses the partitioner's default.
+    approx_tolerance: Option<f32>,
+    write_options: WriteOptions,
+}
+
+/// Options that only apply to fonts.
+struct FontOptions {
+    font_index: u32,
+    fill_rule: FillRule,
+    hinting: HintingLevel,
+    point_size: f32,
+}
+
+fn bake_font(font_path: &Path, font_options: &FontOptions, options: &BakeOptions)
+             -> Result<MeshPack, String> {
+    let font_data = try!(fs::read(font_path).map_err(|error| error.to_string()));
+    let font_data = if woff::is_woff(&font_data) {
+        try!(woff::decode(&font_data).map_err(|_| "Failed to decode the WOFF file".to_owned()))
+    } else {
+        font_data
+    };
+    let font = try!(Font::from_bytes(Arc::new(font_data), font_options.font_index).map_err(|_| {
+        "Failed to load the font".to_owned()
+    }));
+
+    let hinting_options = font_options.hinting.to_hinting_options(font_options.point_size);
+    let mut mesh_pack = MeshPack::new();
+    for glyph_index in 0..font.glyph_count() {
+        let mut path_builder = LyonPath::builder();
+        if font.outline(glyph_index, hinting_options, &mut path_builder).is_err() {
+            // Keep the mesh indices in sync with the glyph IDs.
+            mesh_pack.push(Mesh::new());
+            continue
+        }
+        let path: Vec<_> = path_builder.build().iter().collect();
+
+        let mut mesh = batch::partition_path(&path,
+                                             font_options.fill_rule,
+                                             options.approx_tolerance);
+        // Stencil segments can only be quadratic, and CFF outlines are made of cubics.
+        let stencil_tolerance = options.approx_tolerance
+                                       .unwrap_or(DEFAULT_FONT_STENCIL_APPROX_TOLERANCE);
+        mesh.push_stencil_segments(CubicToQuadraticTransformer::new(path.iter().cloned(),
+                                                                    stencil_tolerance));
+        mesh_pack.push(mesh);
+    }
+    Ok(mesh_pack)
+}
+
+fn bake_svg(svg_path: &Path, scale: f32, options: &BakeOptions) -> Result<MeshPack, String> {
+    let svg_data = try!(fs::read(svg_path).map_err(|error| error.to_string()));
+    let document = try!(SvgDocument::from_bytes(&svg_data).map_err(|error| {
+        format!("Failed to parse the SVG document: {:?}", error)
+    }));
+
+    // Recording draws nothing into the canvas, so it needn't be the size of the document. A
+    // canvas that big could be too large to allocate at a high scale.
+    let mut context = CanvasRenderingContext2D::new(&Size2D::new(1, 1));
+    context.scale(scale, scale);
+    context.begin_recording();
+    document.draw(&mut context);
+    let picture = context.end_recording().unwrap();
+    if document.uses_clip_paths() {
+        eprintln!("warning: Clip paths aren't supported in mesh libraries and were discarded");
+    }
+    if document.uses_group_opacity() {
+        eprintln!("warning: Group opacity isn't supported in mesh libraries; translucent \
+                   elements were baked opaque");
+    }
+    Ok(picture.to_mesh_pack(options.approx_tolerance))
+}
+
+// Parses the value of an option, exiting if it's invalid.
+fn parse_or_exit<T>(value: &str, name: &str) -> T where T: ::std::str::FromStr {
+    match value.parse() {
+        Ok(value) => value,
+        Err(_) => {
+            eprintln!("error: Invalid {}", name);
+            process::exit(1)
+        }
+    }
+}
+
+pub fn main() {
+    let app = App::new("Pathfinder Mesh Baker")
+        .version("0.1")
+        .author("The Pathfinder Project Developers")
+        .about("Bakes fonts and SVG documents into mesh libraries for use with Pathfinder")
+        .arg(Arg::with_name("INPUT").help("The `.ttf`, `.otf`, `.ttc`, `.woff`, or `.svg` file \
+                                           to bake")
+                                    .required(true)
+                                    .index(1))
+        .arg(Arg::with_name("OUTPUT-PATH").help("The `.pfml` mesh library to produce").index(2))
+        .arg(Arg::with_name("svg").long("svg")
+                                  .help("Treat the input as an SVG document, whatever its \
+                                         extension"))
+        .arg(Arg::with_name("tolerance").long("tolerance")
+                                        .takes_value(true)
+                                        .help("The tolerance to approximate curves to, in font \
+                                               units or SVG pixels"))
+        .arg(Arg::with_name("quantize").long("quantize")
+                                       .help("Store vertex positions in the file as 16-bit \
+                                              coordinates, to make it smaller"))
+        .arg(Arg::with_name("scale").long("scale")
+                                    .takes_value(true)
+                                    .default_value("1")
+                                    .help("The scale to bake an SVG document at"))
+        .arg(Arg::with_name("font-index").long("font-index")
+                                         .takes_value(true)
+                                         .default_value("0")
+                                         .help("The face to use, if the font is a collection"))
+        .arg(Arg::with_name("fill-rule").long("fill-rule")
+                                        .takes_value(true)
+                                        .possible_values(&["winding", "evenodd"])
+                                        .default_value("winding")
+                                        .help("The fill rule to partition glyph outlines with"))
+        .arg(Arg::with_name("hinting").long("hinting")
+                                      .takes_value(true)
+                                      .possible_values(&["none", "vertical", "full"])
+                                      .default_value("none")
+                                      .help("How much to hint glyph outlines"))
+        .arg(Arg::with_name("point-size").long("point-size")
+                                         .takes_value(true)
+                                         .default_value("16")
+                                         .help("The size in pixels per em to hint glyphs for"));
+    let matches = app.get_matches();
+
+    let input_path = Path::new(matches.value_of("INPUT").unwrap());
+    let is_svg = matches.is_present("svg") ||
+        input_path.extension().map_or(false, |extension| extension == "svg");
+
+    let options = BakeOptions {
+        approx_tolerance: matches.value_of("tolerance").map(|tolerance| {
+            parse_or_exit(tolerance, "tolerance")
+        }),
+        write_options: WriteOptions {
+            quantize_vertex_positions: matches.is_present("quantize"),
+        },
+    };
+
+    let mesh_pack = if is_svg {
+        let scale = parse_or_exit(matches.value_of("scale").unwrap(), "scale");
+        bake_svg(input_path, scale, &options)
+    } else {
+        let font_options = FontOptions {
+            font_index: parse_or_exit(matches.value_of("font-index").unwrap(), "font index"),
+            fill_rule: match matches.value_of("fill-rule") {
+                Some("evenodd") => FillRule::EvenOdd,
+                _ => FillRule::Winding,
+            },
+            hinting: match matches.value_of("hinting") {
+                Some("vertical") => HintingLevel::Vertical,
+                Some("full") => HintingLevel::Full,
+                _ => HintingLevel::None,
+            },
+            point_size: parse_or_exit(matches.value_of("point-size").unwrap(), "point size"),
+        };
+        bake_font(input_path, &font_options, &options)
+    };
+    let mesh_pack = match mesh_pack {
+        Ok(mesh_pack) => mesh_pack,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            process::exit(1)
+        }
+    };
+
+    let output_path = match matches.value_of("OUTPUT-PATH") {
+        Some(output_path) => PathBuf::from(output_path),
+        None => input_path.with_extension("pfml"),
+    };
+    let result = File::create(&output_path).and_then(|mut output_file| {
+        mesh_pack.write_to_with_options(&mut output_file, &options.write_options)
+    });
+    if let Err(error) = result {
+        eprintln!("error: Failed to write `{}`: {}", output_path.display(), error);
+        process::exit(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use font_kit::font::Font;
+    use pathfinder_font_utils::hinting::HintingLevel;
+    use pathfinder_partitioner::FillRule;
+    use pathfinder_partitioner::mesh_pack::WriteOptions;
+    use std::fs;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use super::{BakeOptions, FontOptions, bake_font};
+
+    #[test]
+    fn cff_fonts_bake() {
+        // An `OTTO` font, whose outlines are cubic.
+        let font_path = Path::new(concat!(env!("CARGO_MANIFEST_DIR"),
+                                          "/../../resources/fonts/eb-garamond/\
+                                           EBGaramond12-Regular.otf"));
+        let font_options = FontOptions {
+            font_index: 0,
+            fill_rule: FillRule::Winding,
+            hinting: HintingLevel::None,
+            point_size: 16.0,
+        };
+        let options = BakeOptions {
+            approx_tolerance: None,
+            write_options: WriteOptions::default(),
+        };
+        let mesh_pack = bake_font(font_path, &font_options, &options).unwrap();
+
+        let font = Font::from_bytes(Arc::new(fs::read(font_path).unwrap()), 0).unwrap();
+        assert_eq!(mesh_pack.meshes.len(), font.glyph_count() as usize);
+        let glyph_id = font.glyph_for_char('a').unwrap();
+        let mesh = &mesh_pack.meshes[glyph_id as usize];
+        assert!(!mesh.b_quads.is_empty());
+        assert!(!mesh.stencil_segments.is_empty());
+    }
+}
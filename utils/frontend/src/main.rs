@@ -26,32 +26,47 @@
 extern crate clap;
 extern crate font_kit;
 extern crate lyon_path;
+extern crate pathfinder_font_utils;
 extern crate pathfinder_partitioner;
 extern crate pathfinder_path_utils;
 
 use clap::{App, Arg};
+use font_kit::family_name::FamilyName;
 use font_kit::font::Font;
-use font_kit::hinting::HintingOptions;
+use font_kit::properties::{Properties, Style, Weight};
+use font_kit::source::SystemSource;
 use lyon_path::PathEvent;
 use lyon_path::builder::{FlatPathBuilder, PathBuilder};
 use lyon_path::default::Path as LyonPath;
+use pathfinder_font_utils::hinting::HintingLevel;
+use pathfinder_font_utils::{sfnt, woff};
 use pathfinder_partitioner::FillRule;
-use pathfinder_partitioner::mesh_pack::MeshPack;
+use pathfinder_partitioner::mesh::Mesh;
+use pathfinder_partitioner::mesh_pack::{MeshPack, WriteOptions};
 use pathfinder_partitioner::partitioner::Partitioner;
-use std::fs::File;
+use std::fs::{self, File};
 use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::Arc;
 
-fn convert_font(font_path: &Path, output_path: &Path) -> Result<(), ()> {
-    let font = try!(Font::from_path(font_path, 0).map_err(drop));
+fn convert_font(font: &Font,
+                output_path: &Path,
+                fill_rule: FillRule,
+                hinting: HintingLevel,
+                point_size: f32,
+                write_options: &WriteOptions)
+                -> Result<(), ()> {
     let glyph_count = font.glyph_count();
+    let hinting_options = hinting.to_hinting_options(point_size);
 
     let mut paths: Vec<(u16, Vec<PathEvent>)> = vec![];
     let mut mesh_pack = MeshPack::new();
 
     for glyph_index in 0..glyph_count {
         let mut path_builder = LyonPath::builder();
-        if font.outline(glyph_index, HintingOptions::None, &mut path_builder).is_err() {
+        if font.outline(glyph_index, hinting_options, &mut path_builder).is_err() {
+            // Keep the mesh indices in sync with the glyph IDs.
+            mesh_pack.push(Mesh::new());
             continue
         }
         let path = path_builder.build();
@@ -61,7 +76,7 @@ fn convert_font(font_path: &Path, output_path: &Path) -> Result<(), ()> {
         let path_index = (glyph_index + 1) as u16;
         partitioner.mesh_mut().push_stencil_segments(path.iter());
         path.iter().for_each(|event| partitioner.builder_mut().path_event(event));
-        partitioner.partition(FillRule::Winding);
+        partitioner.partition(fill_rule);
         partitioner.builder_mut().build_and_reset();
 
         paths.push((path_index, path.iter().collect()));
@@ -69,7 +84,53 @@ fn convert_font(font_path: &Path, output_path: &Path) -> Result<(), ()> {
     }
 
     let mut output_file = try!(File::create(output_path).map_err(drop));
-    mesh_pack.serialize_into(&mut output_file).map_err(drop)
+    mesh_pack.write_to_with_options(&mut output_file, write_options).map_err(drop)
+}
+
+// Reads the font file, decoding it first if it's a WOFF file.
+fn load_font_data(font_path: &Path) -> Result<Arc<Vec<u8>>, ()> {
+    let font_data = try!(fs::read(font_path).map_err(drop));
+    if woff::is_woff(&font_data) {
+        return woff::decode(&font_data).map(Arc::new).map_err(drop)
+    }
+    Ok(Arc::new(font_data))
+}
+
+// Loads a face from the font file, or the best match for the family installed on the system.
+fn load_font(font: &str, system: Option<&Properties>, font_index: u32) -> Result<Font, ()> {
+    match system {
+        Some(properties) => {
+            let family_names = [FamilyName::Title(font.to_owned())];
+            let handle = try!(SystemSource::new().select_best_match(&family_names, properties)
+                                                 .map_err(drop));
+            handle.load().map_err(drop)
+        }
+        None => {
+            let font_data = try!(load_font_data(Path::new(font)));
+            Font::from_bytes(font_data, font_index).map_err(drop)
+        }
+    }
+}
+
+// Prints the index and name of each face in the font file, which may be a collection, or each
+// installed face of the family.
+fn list_faces(font: &str, system: bool) -> Result<(), ()> {
+    if system {
+        let family = try!(SystemSource::new().select_family_by_name(font).map_err(drop));
+        for handle in family.fonts() {
+            let font = try!(handle.load().map_err(drop));
+            println!("{}", font.full_name());
+        }
+        return Ok(())
+    }
+
+    let font_data = try!(load_font_data(Path::new(font)));
+    let face_count = try!(sfnt::font_count(&font_data).map_err(drop));
+    for font_index in 0..face_count {
+        let font = try!(Font::from_bytes(font_data.clone(), font_index).map_err(drop));
+        println!("{}: {}", font_index, font.full_name());
+    }
+    Ok(())
 }
 
 pub fn main() {
@@ -77,19 +138,91 @@ pub fn main() {
         .version("0.1")
         .author("The Pathfinder Project Developers")
         .about("Builds meshes from fonts for use with Pathfinder")
-        .arg(Arg::with_name("FONT-PATH").help("The `.ttf` or `.otf` font file to use")
-                                        .required(true)
-                                        .index(1))
-        .arg(Arg::with_name("OUTPUT-PATH").help("The `.pfml` mesh library to produce").index(2));
+        .arg(Arg::with_name("FONT").help("The `.ttf`, `.otf`, `.ttc`, or `.woff` font file to use, \
+                                           or the family name with `--system`")
+                                   .required(true)
+                                   .index(1))
+        .arg(Arg::with_name("OUTPUT-PATH").help("The `.pfml` mesh library to produce").index(2))
+        .arg(Arg::with_name("font-index").long("font-index")
+                                         .takes_value(true)
+                                         .default_value("0")
+                                         .help("The face to use, if the font is a collection"))
+        .arg(Arg::with_name("list-faces").long("list-faces")
+                                         .help("List the faces in the font and exit"))
+        .arg(Arg::with_name("system").long("system")
+                                     .help("Find an installed font by family name"))
+        .arg(Arg::with_name("weight").long("weight")
+                                     .takes_value(true)
+                                     .requires("system")
+                                     .help("The weight to match with `--system`, from 100 to 900"))
+        .arg(Arg::with_name("italic").long("italic")
+                                     .requires("system")
+                                     .help("Match an italic face with `--system`"))
+        .arg(Arg::with_name("fill-rule").long("fill-rule")
+                                        .takes_value(true)
+                                        .possible_values(&["winding", "evenodd"])
+                                        .default_value("winding")
+                                        .help("The fill rule to partition glyph outlines with"))
+        .arg(Arg::with_name("hinting").long("hinting")
+                                      .takes_value(true)
+                                      .possible_values(&["none", "vertical", "full"])
+                                      .default_value("none")
+                                      .help("How much to hint glyph outlines"))
+        .arg(Arg::with_name("point-size").long("point-size")
+                                         .takes_value(true)
+                                         .default_value("16")
+                                         .help("The size in pixels per em to hint glyphs for"))
+        .arg(Arg::with_name("quantize").long("quantize")
+                                       .help("Store vertex positions in the file as 16-bit \
+                                              coordinates, to make it smaller"));
     let matches = app.get_matches();
 
-    let font_path = matches.value_of("FONT-PATH").unwrap();
-    let font_path = Path::new(font_path);
+    let font_name = matches.value_of("FONT").unwrap();
+    let system = matches.is_present("system");
+
+    if matches.is_present("list-faces") {
+        if list_faces(font_name, system).is_err() {
+            eprintln!("error: Failed to read the font");
+            process::exit(1)
+        }
+        return
+    }
+
+    let font_index = match matches.value_of("font-index").unwrap().parse() {
+        Ok(font_index) => font_index,
+        Err(_) => {
+            eprintln!("error: Invalid font index");
+            process::exit(1)
+        }
+    };
+
+    let mut properties = Properties::new();
+    if let Some(weight) = matches.value_of("weight") {
+        match weight.parse() {
+            Ok(weight) => properties.weight(Weight(weight)),
+            Err(_) => {
+                eprintln!("error: Invalid weight");
+                process::exit(1)
+            }
+        };
+    }
+    if matches.is_present("italic") {
+        properties.style(Style::Italic);
+    }
+
+    let system_properties = if system { Some(&properties) } else { None };
+    let font = match load_font(font_name, system_properties, font_index) {
+        Ok(font) => font,
+        Err(()) => {
+            eprintln!("error: Failed to load the font");
+            process::exit(1)
+        }
+    };
 
     let output_path = match matches.value_of("OUTPUT-PATH") {
         Some(output_path) => PathBuf::from(output_path),
         None => {
-            match font_path.file_stem() {
+            match Path::new(font_name).file_stem() {
                 None => {
                     eprintln!("error: No valid input path specified");
                     process::exit(1)
@@ -103,7 +236,34 @@ pub fn main() {
         }
     };
 
-    if convert_font(font_path, &output_path).is_err() {
+    let fill_rule = match matches.value_of("fill-rule") {
+        Some("evenodd") => FillRule::EvenOdd,
+        _ => FillRule::Winding,
+    };
+
+    let hinting = match matches.value_of("hinting") {
+        Some("vertical") => HintingLevel::Vertical,
+        Some("full") => HintingLevel::Full,
+        _ => HintingLevel::None,
+    };
+    let point_size = match matches.value_of("point-size").unwrap().parse() {
+        Ok(point_size) => point_size,
+        Err(_) => {
+            eprintln!("error: Invalid point size");
+            process::exit(1)
+        }
+    };
+
+    let write_options = WriteOptions {
+        quantize_vertex_positions: matches.is_present("quantize"),
+    };
+
+    if convert_font(&font,
+                    &output_path,
+                    fill_rule,
+                    hinting,
+                    point_size,
+                    &write_options).is_err() {
         // TODO(pcwalton): Better error handling.
         eprintln!("error: Failed");
         process::exit(1)
@@ -0,0 +1,29 @@
+// pathfinder/utils/partition-server/src/main.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Partitions paths sent over standard input and writes the meshes to standard output, using the
+//! protocol in `pathfinder_partitioner::remote`.
+//!
+//! This is meant to be spawned by `RemotePartitioner`, inside whatever sandbox the embedder
+//! applies, so that malicious paths can't affect the process that renders them.
+
+extern crate pathfinder_partitioner;
+
+use pathfinder_partitioner::remote;
+use std::io;
+use std::process;
+
+pub fn main() {
+    let (stdin, stdout) = (io::stdin(), io::stdout());
+    if let Err(error) = remote::serve(&mut stdin.lock(), &mut stdout.lock()) {
+        eprintln!("error: {}", error);
+        process::exit(1)
+    }
+}